@@ -162,6 +162,13 @@ func SinglePackageGraph(rootPackageJSON *fs.PackageJSON, packageManagerName stri
 
 // BuildPackageGraph constructs a Context instance with information about the package dependency graph
 func BuildPackageGraph(repoRoot turbopath.AbsoluteSystemPath, rootPackageJSON *fs.PackageJSON, packageManagerName string) (*Context, error) {
+	return BuildPackageGraphFromWorkspaces(repoRoot, rootPackageJSON, packageManagerName, nil)
+}
+
+// BuildPackageGraphFromWorkspaces is BuildPackageGraph for when the absolute paths to the
+// package.json of each workspace are already known, such as from the daemon. If workspaces
+// is nil, they are discovered using the package manager's workspace globs.
+func BuildPackageGraphFromWorkspaces(repoRoot turbopath.AbsoluteSystemPath, rootPackageJSON *fs.PackageJSON, packageManagerName string, workspaces []string) (*Context, error) {
 	c := &Context{}
 	rootpath := repoRoot.ToStringDuringMigration()
 	c.WorkspaceInfos = workspace.Catalog{
@@ -185,10 +192,12 @@ func BuildPackageGraph(repoRoot turbopath.AbsoluteSystemPath, rootPackageJSON *f
 
 	// Get the workspaces from the package manager.
 	// workspaces are absolute paths
-	workspaces, err := c.PackageManager.GetWorkspaces(repoRoot)
+	if workspaces == nil {
+		workspaces, err = c.PackageManager.GetWorkspaces(repoRoot)
 
-	if err != nil {
-		return nil, fmt.Errorf("workspace configuration error: %w", err)
+		if err != nil {
+			return nil, fmt.Errorf("workspace configuration error: %w", err)
+		}
 	}
 
 	// We will parse all package.json's simultaneously. We use a
@@ -408,47 +417,28 @@ func (c *Context) ChangedPackages(previousLockfile lockfile.Lockfile) ([]string,
 		return nil, fmt.Errorf("Cannot detect changed packages without previous and current lockfile")
 	}
 
-	closures, err := lockfile.AllTransitiveClosures(c.externalWorkspaceDeps(), previousLockfile)
+	affectedWorkspaces, err := lockfile.AffectedWorkspaces(previousLockfile, c.Lockfile, c.externalWorkspaceDeps())
 	if err != nil {
 		return nil, err
 	}
-
-	didPackageChange := func(pkgName string, pkg *fs.PackageJSON) bool {
-		previousDeps, ok := closures[pkg.Dir.ToUnixPath()]
-		if !ok || previousDeps.Cardinality() != len(pkg.TransitiveDeps) {
-			return true
-		}
-
-		prevExternalDeps := make([]lockfile.Package, 0, previousDeps.Cardinality())
-		for _, d := range previousDeps.ToSlice() {
-			prevExternalDeps = append(prevExternalDeps, d.(lockfile.Package))
-		}
-		sort.Sort(lockfile.ByKey(prevExternalDeps))
-
-		for i := range prevExternalDeps {
-			if prevExternalDeps[i] != pkg.TransitiveDeps[i] {
-				return true
-			}
-		}
-		return false
+	affected := make(map[turbopath.AnchoredUnixPath]struct{}, len(affectedWorkspaces))
+	for _, workspace := range affectedWorkspaces {
+		affected[workspace] = struct{}{}
 	}
 
 	changedPkgs := make([]string, 0, len(c.WorkspaceInfos.PackageJSONs))
-
-	// check if prev and current have "global" changes e.g. lockfile bump
-	globalChange := c.Lockfile.GlobalChange(previousLockfile)
+	globalChange := false
 
 	for pkgName, pkg := range c.WorkspaceInfos.PackageJSONs {
-		if globalChange {
-			break
+		if _, ok := affected[pkg.Dir.ToUnixPath()]; !ok {
+			continue
 		}
-		if didPackageChange(pkgName, pkg) {
-			if pkgName == util.RootPkgName {
-				globalChange = true
-			} else {
-				changedPkgs = append(changedPkgs, pkgName)
-			}
+		// a change to the root's external deps could affect every package
+		if pkgName == util.RootPkgName {
+			globalChange = true
+			break
 		}
+		changedPkgs = append(changedPkgs, pkgName)
 	}
 
 	if globalChange {
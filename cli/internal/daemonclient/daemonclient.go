@@ -4,6 +4,7 @@ package daemonclient
 
 import (
 	"context"
+	"fmt"
 
 	"github.com/vercel/turbo/cli/internal/daemon/connector"
 	"github.com/vercel/turbo/cli/internal/fs"
@@ -54,6 +55,47 @@ func (d *DaemonClient) NotifyOutputsWritten(ctx context.Context, hash string, re
 	return err
 }
 
+// GetPackageFileHashes implements taskhash.PackageFileHasher.GetPackageFileHashes
+func (d *DaemonClient) GetPackageFileHashes(ctx context.Context, packagePath turbopath.AnchoredSystemPath, inputs []string) (map[turbopath.AnchoredUnixPath]string, error) {
+	resp, err := d.client.GetFileHashes(ctx, &turbodprotocol.GetFileHashesRequest{
+		PackagePath: packagePath.ToUnixPath().ToString(),
+		Inputs:      inputs,
+	})
+	if err != nil {
+		return nil, err
+	}
+
+	hashes := make(map[turbopath.AnchoredUnixPath]string, len(resp.FileHashes))
+	for path, hash := range resp.FileHashes {
+		hashes[turbopath.AnchoredUnixPath(path)] = hash
+	}
+	return hashes, nil
+}
+
+// GetWorkspacePackageJSONs returns the absolute paths to the package.json of every
+// workspace other than the root, from the daemon's package graph. An error is returned
+// if the daemon detected a different package manager than packageManagerName.
+func (d *DaemonClient) GetWorkspacePackageJSONs(ctx context.Context, repoRoot turbopath.AbsoluteSystemPath, packageManagerName string) ([]string, error) {
+	resp, err := d.client.GetPackageGraph(ctx, &turbodprotocol.GetPackageGraphRequest{})
+	if err != nil {
+		return nil, err
+	}
+	if resp.PackageManager != packageManagerName {
+		return nil, fmt.Errorf("daemon detected package manager %v, expected %v", resp.PackageManager, packageManagerName)
+	}
+
+	workspaces := make([]string, 0, len(resp.Packages))
+	for _, pkg := range resp.Packages {
+		// the root workspace is read separately
+		if pkg.Path == "" {
+			continue
+		}
+		workspaceDir := turbopath.AnchoredUnixPath(pkg.Path).ToSystemPath().RestoreAnchor(repoRoot)
+		workspaces = append(workspaces, workspaceDir.UntypedJoin("package.json").ToString())
+	}
+	return workspaces, nil
+}
+
 // Status returns the DaemonStatus from the daemon
 func (d *DaemonClient) Status(ctx context.Context) (*Status, error) {
 	resp, err := d.client.Status(ctx, &turbodprotocol.StatusRequest{})
@@ -27,3 +27,5 @@ struct Buffer subgraph(struct Buffer buf);
 struct Buffer patches(struct Buffer buf);
 
 struct Buffer global_change(struct Buffer buf);
+
+struct Buffer lockfile_diff(struct Buffer buf);
@@ -174,23 +174,10 @@ func TransitiveDeps(content []byte, packageManager string, workspaces map[string
 	if resolutions != nil {
 		additionalData = &ffi_proto.AdditionalBerryData{Resolutions: resolutions}
 	}
-	flatWorkspaces := make(map[string]*ffi_proto.PackageDependencyList)
-	for workspace, deps := range workspaces {
-		packageDependencyList := make([]*ffi_proto.PackageDependency, len(deps))
-		i := 0
-		for name, version := range deps {
-			packageDependencyList[i] = &ffi_proto.PackageDependency{
-				Name:  name,
-				Range: version,
-			}
-			i++
-		}
-		flatWorkspaces[workspace] = &ffi_proto.PackageDependencyList{List: packageDependencyList}
-	}
 	req := ffi_proto.TransitiveDepsRequest{
 		Contents:       content,
 		PackageManager: toPackageManager(packageManager),
-		Workspaces:     flatWorkspaces,
+		Workspaces:     toPackageDependencyLists(workspaces),
 		Resolutions:    additionalData,
 	}
 	reqBuf := Marshal(&req)
@@ -210,12 +197,33 @@ func TransitiveDeps(content []byte, packageManager string, workspaces map[string
 	return dependencies.GetDependencies(), nil
 }
 
+func toPackageDependencyLists(workspaces map[string]map[string]string) map[string]*ffi_proto.PackageDependencyList {
+	flatWorkspaces := make(map[string]*ffi_proto.PackageDependencyList)
+	for workspace, deps := range workspaces {
+		packageDependencyList := make([]*ffi_proto.PackageDependency, len(deps))
+		i := 0
+		for name, version := range deps {
+			packageDependencyList[i] = &ffi_proto.PackageDependency{
+				Name:  name,
+				Range: version,
+			}
+			i++
+		}
+		flatWorkspaces[workspace] = &ffi_proto.PackageDependencyList{List: packageDependencyList}
+	}
+	return flatWorkspaces
+}
+
 func toPackageManager(packageManager string) ffi_proto.PackageManager {
 	switch packageManager {
 	case "npm":
 		return ffi_proto.PackageManager_NPM
 	case "berry":
 		return ffi_proto.PackageManager_BERRY
+	case "pnpm":
+		return ffi_proto.PackageManager_PNPM
+	case "yarn":
+		return ffi_proto.PackageManager_YARN
 	default:
 		panic(fmt.Sprintf("Invalid package manager string: %s", packageManager))
 	}
@@ -303,7 +311,7 @@ func GlobalChange(packageManager string, prevContents []byte, currContents []byt
 		CurrContents:   currContents,
 	}
 	reqBuf := Marshal(&req)
-	resBuf := C.patches(reqBuf)
+	resBuf := C.global_change(reqBuf)
 	reqBuf.Free()
 
 	resp := ffi_proto.GlobalChangeResponse{}
@@ -314,6 +322,37 @@ func GlobalChange(packageManager string, prevContents []byte, currContents []byt
 	return resp.GetGlobalChange()
 }
 
+// LockfileDiff returns the packages that differ between two lockfiles along with
+// the workspaces whose transitive external deps are affected by the difference.
+func LockfileDiff(packageManager string, prevContents []byte, currContents []byte, workspaces map[string]map[string]string, prevResolutions map[string]string, currResolutions map[string]string) (*ffi_proto.LockfileDiff, error) {
+	req := ffi_proto.LockfileDiffRequest{
+		PackageManager: toPackageManager(packageManager),
+		PrevContents:   prevContents,
+		CurrContents:   currContents,
+		Workspaces:     toPackageDependencyLists(workspaces),
+	}
+	if prevResolutions != nil {
+		req.PrevResolutions = &ffi_proto.AdditionalBerryData{Resolutions: prevResolutions}
+	}
+	if currResolutions != nil {
+		req.CurrResolutions = &ffi_proto.AdditionalBerryData{Resolutions: currResolutions}
+	}
+	reqBuf := Marshal(&req)
+	resBuf := C.lockfile_diff(reqBuf)
+	reqBuf.Free()
+
+	resp := ffi_proto.LockfileDiffResponse{}
+	if err := Unmarshal(resBuf, resp.ProtoReflect().Interface()); err != nil {
+		panic(err)
+	}
+
+	if err := resp.GetError(); err != "" {
+		return nil, errors.New(err)
+	}
+
+	return resp.GetDiff(), nil
+}
+
 // GetPackageFileHashesFromGitIndex proxies to rust to use git to hash the files in a package.
 // It does not support additional files, it just hashes the non-ignored files in the package.
 func GetPackageFileHashesFromGitIndex(rootPath string, packagePath string) (map[string]string, error) {
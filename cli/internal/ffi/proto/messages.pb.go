@@ -25,6 +25,8 @@ type PackageManager int32
 const (
 	PackageManager_NPM   PackageManager = 0
 	PackageManager_BERRY PackageManager = 1
+	PackageManager_PNPM  PackageManager = 2
+	PackageManager_YARN  PackageManager = 3
 )
 
 // Enum value maps for PackageManager.
@@ -32,10 +34,14 @@ var (
 	PackageManager_name = map[int32]string{
 		0: "NPM",
 		1: "BERRY",
+		2: "PNPM",
+		3: "YARN",
 	}
 	PackageManager_value = map[string]int32{
 		"NPM":   0,
 		"BERRY": 1,
+		"PNPM":  2,
+		"YARN":  3,
 	}
 )
 
@@ -1560,6 +1566,316 @@ func (x *GlobalChangeResponse) GetGlobalChange() bool {
 	return false
 }
 
+type LockfileDiffRequest struct {
+	state         protoimpl.MessageState
+	sizeCache     protoimpl.SizeCache
+	unknownFields protoimpl.UnknownFields
+
+	PackageManager  PackageManager                    `protobuf:"varint,1,opt,name=package_manager,json=packageManager,proto3,enum=PackageManager" json:"package_manager,omitempty"`
+	PrevContents    []byte                            `protobuf:"bytes,2,opt,name=prev_contents,json=prevContents,proto3" json:"prev_contents,omitempty"`
+	CurrContents    []byte                            `protobuf:"bytes,3,opt,name=curr_contents,json=currContents,proto3" json:"curr_contents,omitempty"`
+	Workspaces      map[string]*PackageDependencyList `protobuf:"bytes,4,rep,name=workspaces,proto3" json:"workspaces,omitempty" protobuf_key:"bytes,1,opt,name=key,proto3" protobuf_val:"bytes,2,opt,name=value,proto3"`
+	PrevResolutions *AdditionalBerryData              `protobuf:"bytes,5,opt,name=prev_resolutions,json=prevResolutions,proto3,oneof" json:"prev_resolutions,omitempty"`
+	CurrResolutions *AdditionalBerryData              `protobuf:"bytes,6,opt,name=curr_resolutions,json=currResolutions,proto3,oneof" json:"curr_resolutions,omitempty"`
+}
+
+func (x *LockfileDiffRequest) Reset() {
+	*x = LockfileDiffRequest{}
+	if protoimpl.UnsafeEnabled {
+		mi := &file_turborepo_ffi_messages_proto_msgTypes[24]
+		ms := protoimpl.X.MessageStateOf(protoimpl.Pointer(x))
+		ms.StoreMessageInfo(mi)
+	}
+}
+
+func (x *LockfileDiffRequest) String() string {
+	return protoimpl.X.MessageStringOf(x)
+}
+
+func (*LockfileDiffRequest) ProtoMessage() {}
+
+func (x *LockfileDiffRequest) ProtoReflect() protoreflect.Message {
+	mi := &file_turborepo_ffi_messages_proto_msgTypes[24]
+	if protoimpl.UnsafeEnabled && x != nil {
+		ms := protoimpl.X.MessageStateOf(protoimpl.Pointer(x))
+		if ms.LoadMessageInfo() == nil {
+			ms.StoreMessageInfo(mi)
+		}
+		return ms
+	}
+	return mi.MessageOf(x)
+}
+
+// Deprecated: Use LockfileDiffRequest.ProtoReflect.Descriptor instead.
+func (*LockfileDiffRequest) Descriptor() ([]byte, []int) {
+	return file_turborepo_ffi_messages_proto_rawDescGZIP(), []int{24}
+}
+
+func (x *LockfileDiffRequest) GetPackageManager() PackageManager {
+	if x != nil {
+		return x.PackageManager
+	}
+	return PackageManager_NPM
+}
+
+func (x *LockfileDiffRequest) GetPrevContents() []byte {
+	if x != nil {
+		return x.PrevContents
+	}
+	return nil
+}
+
+func (x *LockfileDiffRequest) GetCurrContents() []byte {
+	if x != nil {
+		return x.CurrContents
+	}
+	return nil
+}
+
+func (x *LockfileDiffRequest) GetWorkspaces() map[string]*PackageDependencyList {
+	if x != nil {
+		return x.Workspaces
+	}
+	return nil
+}
+
+func (x *LockfileDiffRequest) GetPrevResolutions() *AdditionalBerryData {
+	if x != nil {
+		return x.PrevResolutions
+	}
+	return nil
+}
+
+func (x *LockfileDiffRequest) GetCurrResolutions() *AdditionalBerryData {
+	if x != nil {
+		return x.CurrResolutions
+	}
+	return nil
+}
+
+type LockfilePackageChange struct {
+	state         protoimpl.MessageState
+	sizeCache     protoimpl.SizeCache
+	unknownFields protoimpl.UnknownFields
+
+	Name     string           `protobuf:"bytes,1,opt,name=name,proto3" json:"name,omitempty"`
+	Previous *LockfilePackage `protobuf:"bytes,2,opt,name=previous,proto3" json:"previous,omitempty"`
+	Current  *LockfilePackage `protobuf:"bytes,3,opt,name=current,proto3" json:"current,omitempty"`
+}
+
+func (x *LockfilePackageChange) Reset() {
+	*x = LockfilePackageChange{}
+	if protoimpl.UnsafeEnabled {
+		mi := &file_turborepo_ffi_messages_proto_msgTypes[25]
+		ms := protoimpl.X.MessageStateOf(protoimpl.Pointer(x))
+		ms.StoreMessageInfo(mi)
+	}
+}
+
+func (x *LockfilePackageChange) String() string {
+	return protoimpl.X.MessageStringOf(x)
+}
+
+func (*LockfilePackageChange) ProtoMessage() {}
+
+func (x *LockfilePackageChange) ProtoReflect() protoreflect.Message {
+	mi := &file_turborepo_ffi_messages_proto_msgTypes[25]
+	if protoimpl.UnsafeEnabled && x != nil {
+		ms := protoimpl.X.MessageStateOf(protoimpl.Pointer(x))
+		if ms.LoadMessageInfo() == nil {
+			ms.StoreMessageInfo(mi)
+		}
+		return ms
+	}
+	return mi.MessageOf(x)
+}
+
+// Deprecated: Use LockfilePackageChange.ProtoReflect.Descriptor instead.
+func (*LockfilePackageChange) Descriptor() ([]byte, []int) {
+	return file_turborepo_ffi_messages_proto_rawDescGZIP(), []int{25}
+}
+
+func (x *LockfilePackageChange) GetName() string {
+	if x != nil {
+		return x.Name
+	}
+	return ""
+}
+
+func (x *LockfilePackageChange) GetPrevious() *LockfilePackage {
+	if x != nil {
+		return x.Previous
+	}
+	return nil
+}
+
+func (x *LockfilePackageChange) GetCurrent() *LockfilePackage {
+	if x != nil {
+		return x.Current
+	}
+	return nil
+}
+
+type LockfileDiff struct {
+	state         protoimpl.MessageState
+	sizeCache     protoimpl.SizeCache
+	unknownFields protoimpl.UnknownFields
+
+	Added   []*LockfilePackage       `protobuf:"bytes,1,rep,name=added,proto3" json:"added,omitempty"`
+	Removed []*LockfilePackage       `protobuf:"bytes,2,rep,name=removed,proto3" json:"removed,omitempty"`
+	Changed []*LockfilePackageChange `protobuf:"bytes,3,rep,name=changed,proto3" json:"changed,omitempty"`
+	// Names of lockfile settings that apply to every package and changed
+	GlobalChanges      []string `protobuf:"bytes,4,rep,name=global_changes,json=globalChanges,proto3" json:"global_changes,omitempty"`
+	AffectedWorkspaces []string `protobuf:"bytes,5,rep,name=affected_workspaces,json=affectedWorkspaces,proto3" json:"affected_workspaces,omitempty"`
+}
+
+func (x *LockfileDiff) Reset() {
+	*x = LockfileDiff{}
+	if protoimpl.UnsafeEnabled {
+		mi := &file_turborepo_ffi_messages_proto_msgTypes[26]
+		ms := protoimpl.X.MessageStateOf(protoimpl.Pointer(x))
+		ms.StoreMessageInfo(mi)
+	}
+}
+
+func (x *LockfileDiff) String() string {
+	return protoimpl.X.MessageStringOf(x)
+}
+
+func (*LockfileDiff) ProtoMessage() {}
+
+func (x *LockfileDiff) ProtoReflect() protoreflect.Message {
+	mi := &file_turborepo_ffi_messages_proto_msgTypes[26]
+	if protoimpl.UnsafeEnabled && x != nil {
+		ms := protoimpl.X.MessageStateOf(protoimpl.Pointer(x))
+		if ms.LoadMessageInfo() == nil {
+			ms.StoreMessageInfo(mi)
+		}
+		return ms
+	}
+	return mi.MessageOf(x)
+}
+
+// Deprecated: Use LockfileDiff.ProtoReflect.Descriptor instead.
+func (*LockfileDiff) Descriptor() ([]byte, []int) {
+	return file_turborepo_ffi_messages_proto_rawDescGZIP(), []int{26}
+}
+
+func (x *LockfileDiff) GetAdded() []*LockfilePackage {
+	if x != nil {
+		return x.Added
+	}
+	return nil
+}
+
+func (x *LockfileDiff) GetRemoved() []*LockfilePackage {
+	if x != nil {
+		return x.Removed
+	}
+	return nil
+}
+
+func (x *LockfileDiff) GetChanged() []*LockfilePackageChange {
+	if x != nil {
+		return x.Changed
+	}
+	return nil
+}
+
+func (x *LockfileDiff) GetGlobalChanges() []string {
+	if x != nil {
+		return x.GlobalChanges
+	}
+	return nil
+}
+
+func (x *LockfileDiff) GetAffectedWorkspaces() []string {
+	if x != nil {
+		return x.AffectedWorkspaces
+	}
+	return nil
+}
+
+type LockfileDiffResponse struct {
+	state         protoimpl.MessageState
+	sizeCache     protoimpl.SizeCache
+	unknownFields protoimpl.UnknownFields
+
+	// Types that are assignable to Response:
+	//	*LockfileDiffResponse_Diff
+	//	*LockfileDiffResponse_Error
+	Response isLockfileDiffResponse_Response `protobuf_oneof:"response"`
+}
+
+func (x *LockfileDiffResponse) Reset() {
+	*x = LockfileDiffResponse{}
+	if protoimpl.UnsafeEnabled {
+		mi := &file_turborepo_ffi_messages_proto_msgTypes[27]
+		ms := protoimpl.X.MessageStateOf(protoimpl.Pointer(x))
+		ms.StoreMessageInfo(mi)
+	}
+}
+
+func (x *LockfileDiffResponse) String() string {
+	return protoimpl.X.MessageStringOf(x)
+}
+
+func (*LockfileDiffResponse) ProtoMessage() {}
+
+func (x *LockfileDiffResponse) ProtoReflect() protoreflect.Message {
+	mi := &file_turborepo_ffi_messages_proto_msgTypes[27]
+	if protoimpl.UnsafeEnabled && x != nil {
+		ms := protoimpl.X.MessageStateOf(protoimpl.Pointer(x))
+		if ms.LoadMessageInfo() == nil {
+			ms.StoreMessageInfo(mi)
+		}
+		return ms
+	}
+	return mi.MessageOf(x)
+}
+
+// Deprecated: Use LockfileDiffResponse.ProtoReflect.Descriptor instead.
+func (*LockfileDiffResponse) Descriptor() ([]byte, []int) {
+	return file_turborepo_ffi_messages_proto_rawDescGZIP(), []int{27}
+}
+
+func (m *LockfileDiffResponse) GetResponse() isLockfileDiffResponse_Response {
+	if m != nil {
+		return m.Response
+	}
+	return nil
+}
+
+func (x *LockfileDiffResponse) GetDiff() *LockfileDiff {
+	if x, ok := x.GetResponse().(*LockfileDiffResponse_Diff); ok {
+		return x.Diff
+	}
+	return nil
+}
+
+func (x *LockfileDiffResponse) GetError() string {
+	if x, ok := x.GetResponse().(*LockfileDiffResponse_Error); ok {
+		return x.Error
+	}
+	return ""
+}
+
+type isLockfileDiffResponse_Response interface {
+	isLockfileDiffResponse_Response()
+}
+
+type LockfileDiffResponse_Diff struct {
+	Diff *LockfileDiff `protobuf:"bytes,1,opt,name=diff,proto3,oneof"`
+}
+
+type LockfileDiffResponse_Error struct {
+	Error string `protobuf:"bytes,2,opt,name=error,proto3,oneof"`
+}
+
+func (*LockfileDiffResponse_Diff) isLockfileDiffResponse_Response() {}
+
+func (*LockfileDiffResponse_Error) isLockfileDiffResponse_Response() {}
+
 type RecursiveCopyRequest struct {
 	state         protoimpl.MessageState
 	sizeCache     protoimpl.SizeCache
@@ -1572,7 +1888,7 @@ type RecursiveCopyRequest struct {
 func (x *RecursiveCopyRequest) Reset() {
 	*x = RecursiveCopyRequest{}
 	if protoimpl.UnsafeEnabled {
-		mi := &file_turborepo_ffi_messages_proto_msgTypes[24]
+		mi := &file_turborepo_ffi_messages_proto_msgTypes[28]
 		ms := protoimpl.X.MessageStateOf(protoimpl.Pointer(x))
 		ms.StoreMessageInfo(mi)
 	}
@@ -1585,7 +1901,7 @@ func (x *RecursiveCopyRequest) String() string {
 func (*RecursiveCopyRequest) ProtoMessage() {}
 
 func (x *RecursiveCopyRequest) ProtoReflect() protoreflect.Message {
-	mi := &file_turborepo_ffi_messages_proto_msgTypes[24]
+	mi := &file_turborepo_ffi_messages_proto_msgTypes[28]
 	if protoimpl.UnsafeEnabled && x != nil {
 		ms := protoimpl.X.MessageStateOf(protoimpl.Pointer(x))
 		if ms.LoadMessageInfo() == nil {
@@ -1598,7 +1914,7 @@ func (x *RecursiveCopyRequest) ProtoReflect() protoreflect.Message {
 
 // Deprecated: Use RecursiveCopyRequest.ProtoReflect.Descriptor instead.
 func (*RecursiveCopyRequest) Descriptor() ([]byte, []int) {
-	return file_turborepo_ffi_messages_proto_rawDescGZIP(), []int{24}
+	return file_turborepo_ffi_messages_proto_rawDescGZIP(), []int{28}
 }
 
 func (x *RecursiveCopyRequest) GetSrc() string {
@@ -1626,7 +1942,7 @@ type RecursiveCopyResponse struct {
 func (x *RecursiveCopyResponse) Reset() {
 	*x = RecursiveCopyResponse{}
 	if protoimpl.UnsafeEnabled {
-		mi := &file_turborepo_ffi_messages_proto_msgTypes[25]
+		mi := &file_turborepo_ffi_messages_proto_msgTypes[29]
 		ms := protoimpl.X.MessageStateOf(protoimpl.Pointer(x))
 		ms.StoreMessageInfo(mi)
 	}
@@ -1639,7 +1955,7 @@ func (x *RecursiveCopyResponse) String() string {
 func (*RecursiveCopyResponse) ProtoMessage() {}
 
 func (x *RecursiveCopyResponse) ProtoReflect() protoreflect.Message {
-	mi := &file_turborepo_ffi_messages_proto_msgTypes[25]
+	mi := &file_turborepo_ffi_messages_proto_msgTypes[29]
 	if protoimpl.UnsafeEnabled && x != nil {
 		ms := protoimpl.X.MessageStateOf(protoimpl.Pointer(x))
 		if ms.LoadMessageInfo() == nil {
@@ -1652,7 +1968,7 @@ func (x *RecursiveCopyResponse) ProtoReflect() protoreflect.Message {
 
 // Deprecated: Use RecursiveCopyResponse.ProtoReflect.Descriptor instead.
 func (*RecursiveCopyResponse) Descriptor() ([]byte, []int) {
-	return file_turborepo_ffi_messages_proto_rawDescGZIP(), []int{25}
+	return file_turborepo_ffi_messages_proto_rawDescGZIP(), []int{29}
 }
 
 func (x *RecursiveCopyResponse) GetError() string {
@@ -1674,7 +1990,7 @@ type GetPackageFileHashesFromGitIndexRequest struct {
 func (x *GetPackageFileHashesFromGitIndexRequest) Reset() {
 	*x = GetPackageFileHashesFromGitIndexRequest{}
 	if protoimpl.UnsafeEnabled {
-		mi := &file_turborepo_ffi_messages_proto_msgTypes[26]
+		mi := &file_turborepo_ffi_messages_proto_msgTypes[30]
 		ms := protoimpl.X.MessageStateOf(protoimpl.Pointer(x))
 		ms.StoreMessageInfo(mi)
 	}
@@ -1687,7 +2003,7 @@ func (x *GetPackageFileHashesFromGitIndexRequest) String() string {
 func (*GetPackageFileHashesFromGitIndexRequest) ProtoMessage() {}
 
 func (x *GetPackageFileHashesFromGitIndexRequest) ProtoReflect() protoreflect.Message {
-	mi := &file_turborepo_ffi_messages_proto_msgTypes[26]
+	mi := &file_turborepo_ffi_messages_proto_msgTypes[30]
 	if protoimpl.UnsafeEnabled && x != nil {
 		ms := protoimpl.X.MessageStateOf(protoimpl.Pointer(x))
 		if ms.LoadMessageInfo() == nil {
@@ -1700,7 +2016,7 @@ func (x *GetPackageFileHashesFromGitIndexRequest) ProtoReflect() protoreflect.Me
 
 // Deprecated: Use GetPackageFileHashesFromGitIndexRequest.ProtoReflect.Descriptor instead.
 func (*GetPackageFileHashesFromGitIndexRequest) Descriptor() ([]byte, []int) {
-	return file_turborepo_ffi_messages_proto_rawDescGZIP(), []int{26}
+	return file_turborepo_ffi_messages_proto_rawDescGZIP(), []int{30}
 }
 
 func (x *GetPackageFileHashesFromGitIndexRequest) GetTurboRoot() string {
@@ -1728,7 +2044,7 @@ type FileHashes struct {
 func (x *FileHashes) Reset() {
 	*x = FileHashes{}
 	if protoimpl.UnsafeEnabled {
-		mi := &file_turborepo_ffi_messages_proto_msgTypes[27]
+		mi := &file_turborepo_ffi_messages_proto_msgTypes[31]
 		ms := protoimpl.X.MessageStateOf(protoimpl.Pointer(x))
 		ms.StoreMessageInfo(mi)
 	}
@@ -1741,7 +2057,7 @@ func (x *FileHashes) String() string {
 func (*FileHashes) ProtoMessage() {}
 
 func (x *FileHashes) ProtoReflect() protoreflect.Message {
-	mi := &file_turborepo_ffi_messages_proto_msgTypes[27]
+	mi := &file_turborepo_ffi_messages_proto_msgTypes[31]
 	if protoimpl.UnsafeEnabled && x != nil {
 		ms := protoimpl.X.MessageStateOf(protoimpl.Pointer(x))
 		if ms.LoadMessageInfo() == nil {
@@ -1754,7 +2070,7 @@ func (x *FileHashes) ProtoReflect() protoreflect.Message {
 
 // Deprecated: Use FileHashes.ProtoReflect.Descriptor instead.
 func (*FileHashes) Descriptor() ([]byte, []int) {
-	return file_turborepo_ffi_messages_proto_rawDescGZIP(), []int{27}
+	return file_turborepo_ffi_messages_proto_rawDescGZIP(), []int{31}
 }
 
 func (x *FileHashes) GetHashes() map[string]string {
@@ -1778,7 +2094,7 @@ type GetPackageFileHashesFromGitIndexResponse struct {
 func (x *GetPackageFileHashesFromGitIndexResponse) Reset() {
 	*x = GetPackageFileHashesFromGitIndexResponse{}
 	if protoimpl.UnsafeEnabled {
-		mi := &file_turborepo_ffi_messages_proto_msgTypes[28]
+		mi := &file_turborepo_ffi_messages_proto_msgTypes[32]
 		ms := protoimpl.X.MessageStateOf(protoimpl.Pointer(x))
 		ms.StoreMessageInfo(mi)
 	}
@@ -1791,7 +2107,7 @@ func (x *GetPackageFileHashesFromGitIndexResponse) String() string {
 func (*GetPackageFileHashesFromGitIndexResponse) ProtoMessage() {}
 
 func (x *GetPackageFileHashesFromGitIndexResponse) ProtoReflect() protoreflect.Message {
-	mi := &file_turborepo_ffi_messages_proto_msgTypes[28]
+	mi := &file_turborepo_ffi_messages_proto_msgTypes[32]
 	if protoimpl.UnsafeEnabled && x != nil {
 		ms := protoimpl.X.MessageStateOf(protoimpl.Pointer(x))
 		if ms.LoadMessageInfo() == nil {
@@ -1804,7 +2120,7 @@ func (x *GetPackageFileHashesFromGitIndexResponse) ProtoReflect() protoreflect.M
 
 // Deprecated: Use GetPackageFileHashesFromGitIndexResponse.ProtoReflect.Descriptor instead.
 func (*GetPackageFileHashesFromGitIndexResponse) Descriptor() ([]byte, []int) {
-	return file_turborepo_ffi_messages_proto_rawDescGZIP(), []int{28}
+	return file_turborepo_ffi_messages_proto_rawDescGZIP(), []int{32}
 }
 
 func (m *GetPackageFileHashesFromGitIndexResponse) GetResponse() isGetPackageFileHashesFromGitIndexResponse_Response {
@@ -2019,40 +2335,101 @@ var file_turborepo_ffi_messages_proto_rawDesc = []byte{
 	0x47, 0x6c, 0x6f, 0x62, 0x61, 0x6c, 0x43, 0x68, 0x61, 0x6e, 0x67, 0x65, 0x52, 0x65, 0x73, 0x70,
 	0x6f, 0x6e, 0x73, 0x65, 0x12, 0x23, 0x0a, 0x0d, 0x67, 0x6c, 0x6f, 0x62, 0x61, 0x6c, 0x5f, 0x63,
 	0x68, 0x61, 0x6e, 0x67, 0x65, 0x18, 0x01, 0x20, 0x01, 0x28, 0x08, 0x52, 0x0c, 0x67, 0x6c, 0x6f,
-	0x62, 0x61, 0x6c, 0x43, 0x68, 0x61, 0x6e, 0x67, 0x65, 0x22, 0x3a, 0x0a, 0x14, 0x52, 0x65, 0x63,
-	0x75, 0x72, 0x73, 0x69, 0x76, 0x65, 0x43, 0x6f, 0x70, 0x79, 0x52, 0x65, 0x71, 0x75, 0x65, 0x73,
-	0x74, 0x12, 0x10, 0x0a, 0x03, 0x73, 0x72, 0x63, 0x18, 0x01, 0x20, 0x01, 0x28, 0x09, 0x52, 0x03,
-	0x73, 0x72, 0x63, 0x12, 0x10, 0x0a, 0x03, 0x64, 0x73, 0x74, 0x18, 0x02, 0x20, 0x01, 0x28, 0x09,
-	0x52, 0x03, 0x64, 0x73, 0x74, 0x22, 0x3c, 0x0a, 0x15, 0x52, 0x65, 0x63, 0x75, 0x72, 0x73, 0x69,
-	0x76, 0x65, 0x43, 0x6f, 0x70, 0x79, 0x52, 0x65, 0x73, 0x70, 0x6f, 0x6e, 0x73, 0x65, 0x12, 0x19,
-	0x0a, 0x05, 0x65, 0x72, 0x72, 0x6f, 0x72, 0x18, 0x01, 0x20, 0x01, 0x28, 0x09, 0x48, 0x00, 0x52,
-	0x05, 0x65, 0x72, 0x72, 0x6f, 0x72, 0x88, 0x01, 0x01, 0x42, 0x08, 0x0a, 0x06, 0x5f, 0x65, 0x72,
-	0x72, 0x6f, 0x72, 0x22, 0x6b, 0x0a, 0x27, 0x47, 0x65, 0x74, 0x50, 0x61, 0x63, 0x6b, 0x61, 0x67,
-	0x65, 0x46, 0x69, 0x6c, 0x65, 0x48, 0x61, 0x73, 0x68, 0x65, 0x73, 0x46, 0x72, 0x6f, 0x6d, 0x47,
-	0x69, 0x74, 0x49, 0x6e, 0x64, 0x65, 0x78, 0x52, 0x65, 0x71, 0x75, 0x65, 0x73, 0x74, 0x12, 0x1d,
-	0x0a, 0x0a, 0x74, 0x75, 0x72, 0x62, 0x6f, 0x5f, 0x72, 0x6f, 0x6f, 0x74, 0x18, 0x01, 0x20, 0x01,
-	0x28, 0x09, 0x52, 0x09, 0x74, 0x75, 0x72, 0x62, 0x6f, 0x52, 0x6f, 0x6f, 0x74, 0x12, 0x21, 0x0a,
-	0x0c, 0x70, 0x61, 0x63, 0x6b, 0x61, 0x67, 0x65, 0x5f, 0x70, 0x61, 0x74, 0x68, 0x18, 0x02, 0x20,
-	0x01, 0x28, 0x09, 0x52, 0x0b, 0x70, 0x61, 0x63, 0x6b, 0x61, 0x67, 0x65, 0x50, 0x61, 0x74, 0x68,
-	0x22, 0x78, 0x0a, 0x0a, 0x46, 0x69, 0x6c, 0x65, 0x48, 0x61, 0x73, 0x68, 0x65, 0x73, 0x12, 0x2f,
-	0x0a, 0x06, 0x68, 0x61, 0x73, 0x68, 0x65, 0x73, 0x18, 0x01, 0x20, 0x03, 0x28, 0x0b, 0x32, 0x17,
-	0x2e, 0x46, 0x69, 0x6c, 0x65, 0x48, 0x61, 0x73, 0x68, 0x65, 0x73, 0x2e, 0x48, 0x61, 0x73, 0x68,
-	0x65, 0x73, 0x45, 0x6e, 0x74, 0x72, 0x79, 0x52, 0x06, 0x68, 0x61, 0x73, 0x68, 0x65, 0x73, 0x1a,
-	0x39, 0x0a, 0x0b, 0x48, 0x61, 0x73, 0x68, 0x65, 0x73, 0x45, 0x6e, 0x74, 0x72, 0x79, 0x12, 0x10,
-	0x0a, 0x03, 0x6b, 0x65, 0x79, 0x18, 0x01, 0x20, 0x01, 0x28, 0x09, 0x52, 0x03, 0x6b, 0x65, 0x79,
-	0x12, 0x14, 0x0a, 0x05, 0x76, 0x61, 0x6c, 0x75, 0x65, 0x18, 0x02, 0x20, 0x01, 0x28, 0x09, 0x52,
-	0x05, 0x76, 0x61, 0x6c, 0x75, 0x65, 0x3a, 0x02, 0x38, 0x01, 0x22, 0x75, 0x0a, 0x28, 0x47, 0x65,
+	0x62, 0x61, 0x6c, 0x43, 0x68, 0x61, 0x6e, 0x67, 0x65, 0x22, 0xec, 0x03, 0x0a, 0x13, 0x4c, 0x6f,
+	0x63, 0x6b, 0x66, 0x69, 0x6c, 0x65, 0x44, 0x69, 0x66, 0x66, 0x52, 0x65, 0x71, 0x75, 0x65, 0x73,
+	0x74, 0x12, 0x38, 0x0a, 0x0f, 0x70, 0x61, 0x63, 0x6b, 0x61, 0x67, 0x65, 0x5f, 0x6d, 0x61, 0x6e,
+	0x61, 0x67, 0x65, 0x72, 0x18, 0x01, 0x20, 0x01, 0x28, 0x0e, 0x32, 0x0f, 0x2e, 0x50, 0x61, 0x63,
+	0x6b, 0x61, 0x67, 0x65, 0x4d, 0x61, 0x6e, 0x61, 0x67, 0x65, 0x72, 0x52, 0x0e, 0x70, 0x61, 0x63,
+	0x6b, 0x61, 0x67, 0x65, 0x4d, 0x61, 0x6e, 0x61, 0x67, 0x65, 0x72, 0x12, 0x23, 0x0a, 0x0d, 0x70,
+	0x72, 0x65, 0x76, 0x5f, 0x63, 0x6f, 0x6e, 0x74, 0x65, 0x6e, 0x74, 0x73, 0x18, 0x02, 0x20, 0x01,
+	0x28, 0x0c, 0x52, 0x0c, 0x70, 0x72, 0x65, 0x76, 0x43, 0x6f, 0x6e, 0x74, 0x65, 0x6e, 0x74, 0x73,
+	0x12, 0x23, 0x0a, 0x0d, 0x63, 0x75, 0x72, 0x72, 0x5f, 0x63, 0x6f, 0x6e, 0x74, 0x65, 0x6e, 0x74,
+	0x73, 0x18, 0x03, 0x20, 0x01, 0x28, 0x0c, 0x52, 0x0c, 0x63, 0x75, 0x72, 0x72, 0x43, 0x6f, 0x6e,
+	0x74, 0x65, 0x6e, 0x74, 0x73, 0x12, 0x44, 0x0a, 0x0a, 0x77, 0x6f, 0x72, 0x6b, 0x73, 0x70, 0x61,
+	0x63, 0x65, 0x73, 0x18, 0x04, 0x20, 0x03, 0x28, 0x0b, 0x32, 0x24, 0x2e, 0x4c, 0x6f, 0x63, 0x6b,
+	0x66, 0x69, 0x6c, 0x65, 0x44, 0x69, 0x66, 0x66, 0x52, 0x65, 0x71, 0x75, 0x65, 0x73, 0x74, 0x2e,
+	0x57, 0x6f, 0x72, 0x6b, 0x73, 0x70, 0x61, 0x63, 0x65, 0x73, 0x45, 0x6e, 0x74, 0x72, 0x79, 0x52,
+	0x0a, 0x77, 0x6f, 0x72, 0x6b, 0x73, 0x70, 0x61, 0x63, 0x65, 0x73, 0x12, 0x44, 0x0a, 0x10, 0x70,
+	0x72, 0x65, 0x76, 0x5f, 0x72, 0x65, 0x73, 0x6f, 0x6c, 0x75, 0x74, 0x69, 0x6f, 0x6e, 0x73, 0x18,
+	0x05, 0x20, 0x01, 0x28, 0x0b, 0x32, 0x14, 0x2e, 0x41, 0x64, 0x64, 0x69, 0x74, 0x69, 0x6f, 0x6e,
+	0x61, 0x6c, 0x42, 0x65, 0x72, 0x72, 0x79, 0x44, 0x61, 0x74, 0x61, 0x48, 0x00, 0x52, 0x0f, 0x70,
+	0x72, 0x65, 0x76, 0x52, 0x65, 0x73, 0x6f, 0x6c, 0x75, 0x74, 0x69, 0x6f, 0x6e, 0x73, 0x88, 0x01,
+	0x01, 0x12, 0x44, 0x0a, 0x10, 0x63, 0x75, 0x72, 0x72, 0x5f, 0x72, 0x65, 0x73, 0x6f, 0x6c, 0x75,
+	0x74, 0x69, 0x6f, 0x6e, 0x73, 0x18, 0x06, 0x20, 0x01, 0x28, 0x0b, 0x32, 0x14, 0x2e, 0x41, 0x64,
+	0x64, 0x69, 0x74, 0x69, 0x6f, 0x6e, 0x61, 0x6c, 0x42, 0x65, 0x72, 0x72, 0x79, 0x44, 0x61, 0x74,
+	0x61, 0x48, 0x01, 0x52, 0x0f, 0x63, 0x75, 0x72, 0x72, 0x52, 0x65, 0x73, 0x6f, 0x6c, 0x75, 0x74,
+	0x69, 0x6f, 0x6e, 0x73, 0x88, 0x01, 0x01, 0x1a, 0x55, 0x0a, 0x0f, 0x57, 0x6f, 0x72, 0x6b, 0x73,
+	0x70, 0x61, 0x63, 0x65, 0x73, 0x45, 0x6e, 0x74, 0x72, 0x79, 0x12, 0x10, 0x0a, 0x03, 0x6b, 0x65,
+	0x79, 0x18, 0x01, 0x20, 0x01, 0x28, 0x09, 0x52, 0x03, 0x6b, 0x65, 0x79, 0x12, 0x2c, 0x0a, 0x05,
+	0x76, 0x61, 0x6c, 0x75, 0x65, 0x18, 0x02, 0x20, 0x01, 0x28, 0x0b, 0x32, 0x16, 0x2e, 0x50, 0x61,
+	0x63, 0x6b, 0x61, 0x67, 0x65, 0x44, 0x65, 0x70, 0x65, 0x6e, 0x64, 0x65, 0x6e, 0x63, 0x79, 0x4c,
+	0x69, 0x73, 0x74, 0x52, 0x05, 0x76, 0x61, 0x6c, 0x75, 0x65, 0x3a, 0x02, 0x38, 0x01, 0x42, 0x13,
+	0x0a, 0x11, 0x5f, 0x70, 0x72, 0x65, 0x76, 0x5f, 0x72, 0x65, 0x73, 0x6f, 0x6c, 0x75, 0x74, 0x69,
+	0x6f, 0x6e, 0x73, 0x42, 0x13, 0x0a, 0x11, 0x5f, 0x63, 0x75, 0x72, 0x72, 0x5f, 0x72, 0x65, 0x73,
+	0x6f, 0x6c, 0x75, 0x74, 0x69, 0x6f, 0x6e, 0x73, 0x22, 0x85, 0x01, 0x0a, 0x15, 0x4c, 0x6f, 0x63,
+	0x6b, 0x66, 0x69, 0x6c, 0x65, 0x50, 0x61, 0x63, 0x6b, 0x61, 0x67, 0x65, 0x43, 0x68, 0x61, 0x6e,
+	0x67, 0x65, 0x12, 0x12, 0x0a, 0x04, 0x6e, 0x61, 0x6d, 0x65, 0x18, 0x01, 0x20, 0x01, 0x28, 0x09,
+	0x52, 0x04, 0x6e, 0x61, 0x6d, 0x65, 0x12, 0x2c, 0x0a, 0x08, 0x70, 0x72, 0x65, 0x76, 0x69, 0x6f,
+	0x75, 0x73, 0x18, 0x02, 0x20, 0x01, 0x28, 0x0b, 0x32, 0x10, 0x2e, 0x4c, 0x6f, 0x63, 0x6b, 0x66,
+	0x69, 0x6c, 0x65, 0x50, 0x61, 0x63, 0x6b, 0x61, 0x67, 0x65, 0x52, 0x08, 0x70, 0x72, 0x65, 0x76,
+	0x69, 0x6f, 0x75, 0x73, 0x12, 0x2a, 0x0a, 0x07, 0x63, 0x75, 0x72, 0x72, 0x65, 0x6e, 0x74, 0x18,
+	0x03, 0x20, 0x01, 0x28, 0x0b, 0x32, 0x10, 0x2e, 0x4c, 0x6f, 0x63, 0x6b, 0x66, 0x69, 0x6c, 0x65,
+	0x50, 0x61, 0x63, 0x6b, 0x61, 0x67, 0x65, 0x52, 0x07, 0x63, 0x75, 0x72, 0x72, 0x65, 0x6e, 0x74,
+	0x22, 0xec, 0x01, 0x0a, 0x0c, 0x4c, 0x6f, 0x63, 0x6b, 0x66, 0x69, 0x6c, 0x65, 0x44, 0x69, 0x66,
+	0x66, 0x12, 0x26, 0x0a, 0x05, 0x61, 0x64, 0x64, 0x65, 0x64, 0x18, 0x01, 0x20, 0x03, 0x28, 0x0b,
+	0x32, 0x10, 0x2e, 0x4c, 0x6f, 0x63, 0x6b, 0x66, 0x69, 0x6c, 0x65, 0x50, 0x61, 0x63, 0x6b, 0x61,
+	0x67, 0x65, 0x52, 0x05, 0x61, 0x64, 0x64, 0x65, 0x64, 0x12, 0x2a, 0x0a, 0x07, 0x72, 0x65, 0x6d,
+	0x6f, 0x76, 0x65, 0x64, 0x18, 0x02, 0x20, 0x03, 0x28, 0x0b, 0x32, 0x10, 0x2e, 0x4c, 0x6f, 0x63,
+	0x6b, 0x66, 0x69, 0x6c, 0x65, 0x50, 0x61, 0x63, 0x6b, 0x61, 0x67, 0x65, 0x52, 0x07, 0x72, 0x65,
+	0x6d, 0x6f, 0x76, 0x65, 0x64, 0x12, 0x30, 0x0a, 0x07, 0x63, 0x68, 0x61, 0x6e, 0x67, 0x65, 0x64,
+	0x18, 0x03, 0x20, 0x03, 0x28, 0x0b, 0x32, 0x16, 0x2e, 0x4c, 0x6f, 0x63, 0x6b, 0x66, 0x69, 0x6c,
+	0x65, 0x50, 0x61, 0x63, 0x6b, 0x61, 0x67, 0x65, 0x43, 0x68, 0x61, 0x6e, 0x67, 0x65, 0x52, 0x07,
+	0x63, 0x68, 0x61, 0x6e, 0x67, 0x65, 0x64, 0x12, 0x25, 0x0a, 0x0e, 0x67, 0x6c, 0x6f, 0x62, 0x61,
+	0x6c, 0x5f, 0x63, 0x68, 0x61, 0x6e, 0x67, 0x65, 0x73, 0x18, 0x04, 0x20, 0x03, 0x28, 0x09, 0x52,
+	0x0d, 0x67, 0x6c, 0x6f, 0x62, 0x61, 0x6c, 0x43, 0x68, 0x61, 0x6e, 0x67, 0x65, 0x73, 0x12, 0x2f,
+	0x0a, 0x13, 0x61, 0x66, 0x66, 0x65, 0x63, 0x74, 0x65, 0x64, 0x5f, 0x77, 0x6f, 0x72, 0x6b, 0x73,
+	0x70, 0x61, 0x63, 0x65, 0x73, 0x18, 0x05, 0x20, 0x03, 0x28, 0x09, 0x52, 0x12, 0x61, 0x66, 0x66,
+	0x65, 0x63, 0x74, 0x65, 0x64, 0x57, 0x6f, 0x72, 0x6b, 0x73, 0x70, 0x61, 0x63, 0x65, 0x73, 0x22,
+	0x5f, 0x0a, 0x14, 0x4c, 0x6f, 0x63, 0x6b, 0x66, 0x69, 0x6c, 0x65, 0x44, 0x69, 0x66, 0x66, 0x52,
+	0x65, 0x73, 0x70, 0x6f, 0x6e, 0x73, 0x65, 0x12, 0x23, 0x0a, 0x04, 0x64, 0x69, 0x66, 0x66, 0x18,
+	0x01, 0x20, 0x01, 0x28, 0x0b, 0x32, 0x0d, 0x2e, 0x4c, 0x6f, 0x63, 0x6b, 0x66, 0x69, 0x6c, 0x65,
+	0x44, 0x69, 0x66, 0x66, 0x48, 0x00, 0x52, 0x04, 0x64, 0x69, 0x66, 0x66, 0x12, 0x16, 0x0a, 0x05,
+	0x65, 0x72, 0x72, 0x6f, 0x72, 0x18, 0x02, 0x20, 0x01, 0x28, 0x09, 0x48, 0x00, 0x52, 0x05, 0x65,
+	0x72, 0x72, 0x6f, 0x72, 0x42, 0x0a, 0x0a, 0x08, 0x72, 0x65, 0x73, 0x70, 0x6f, 0x6e, 0x73, 0x65,
+	0x22, 0x3a, 0x0a, 0x14, 0x52, 0x65, 0x63, 0x75, 0x72, 0x73, 0x69, 0x76, 0x65, 0x43, 0x6f, 0x70,
+	0x79, 0x52, 0x65, 0x71, 0x75, 0x65, 0x73, 0x74, 0x12, 0x10, 0x0a, 0x03, 0x73, 0x72, 0x63, 0x18,
+	0x01, 0x20, 0x01, 0x28, 0x09, 0x52, 0x03, 0x73, 0x72, 0x63, 0x12, 0x10, 0x0a, 0x03, 0x64, 0x73,
+	0x74, 0x18, 0x02, 0x20, 0x01, 0x28, 0x09, 0x52, 0x03, 0x64, 0x73, 0x74, 0x22, 0x3c, 0x0a, 0x15,
+	0x52, 0x65, 0x63, 0x75, 0x72, 0x73, 0x69, 0x76, 0x65, 0x43, 0x6f, 0x70, 0x79, 0x52, 0x65, 0x73,
+	0x70, 0x6f, 0x6e, 0x73, 0x65, 0x12, 0x19, 0x0a, 0x05, 0x65, 0x72, 0x72, 0x6f, 0x72, 0x18, 0x01,
+	0x20, 0x01, 0x28, 0x09, 0x48, 0x00, 0x52, 0x05, 0x65, 0x72, 0x72, 0x6f, 0x72, 0x88, 0x01, 0x01,
+	0x42, 0x08, 0x0a, 0x06, 0x5f, 0x65, 0x72, 0x72, 0x6f, 0x72, 0x22, 0x6b, 0x0a, 0x27, 0x47, 0x65,
 	0x74, 0x50, 0x61, 0x63, 0x6b, 0x61, 0x67, 0x65, 0x46, 0x69, 0x6c, 0x65, 0x48, 0x61, 0x73, 0x68,
 	0x65, 0x73, 0x46, 0x72, 0x6f, 0x6d, 0x47, 0x69, 0x74, 0x49, 0x6e, 0x64, 0x65, 0x78, 0x52, 0x65,
-	0x73, 0x70, 0x6f, 0x6e, 0x73, 0x65, 0x12, 0x25, 0x0a, 0x06, 0x68, 0x61, 0x73, 0x68, 0x65, 0x73,
-	0x18, 0x01, 0x20, 0x01, 0x28, 0x0b, 0x32, 0x0b, 0x2e, 0x46, 0x69, 0x6c, 0x65, 0x48, 0x61, 0x73,
-	0x68, 0x65, 0x73, 0x48, 0x00, 0x52, 0x06, 0x68, 0x61, 0x73, 0x68, 0x65, 0x73, 0x12, 0x16, 0x0a,
-	0x05, 0x65, 0x72, 0x72, 0x6f, 0x72, 0x18, 0x02, 0x20, 0x01, 0x28, 0x09, 0x48, 0x00, 0x52, 0x05,
-	0x65, 0x72, 0x72, 0x6f, 0x72, 0x42, 0x0a, 0x0a, 0x08, 0x72, 0x65, 0x73, 0x70, 0x6f, 0x6e, 0x73,
-	0x65, 0x2a, 0x24, 0x0a, 0x0e, 0x50, 0x61, 0x63, 0x6b, 0x61, 0x67, 0x65, 0x4d, 0x61, 0x6e, 0x61,
-	0x67, 0x65, 0x72, 0x12, 0x07, 0x0a, 0x03, 0x4e, 0x50, 0x4d, 0x10, 0x00, 0x12, 0x09, 0x0a, 0x05,
-	0x42, 0x45, 0x52, 0x52, 0x59, 0x10, 0x01, 0x42, 0x0b, 0x5a, 0x09, 0x66, 0x66, 0x69, 0x2f, 0x70,
-	0x72, 0x6f, 0x74, 0x6f, 0x62, 0x06, 0x70, 0x72, 0x6f, 0x74, 0x6f, 0x33,
+	0x71, 0x75, 0x65, 0x73, 0x74, 0x12, 0x1d, 0x0a, 0x0a, 0x74, 0x75, 0x72, 0x62, 0x6f, 0x5f, 0x72,
+	0x6f, 0x6f, 0x74, 0x18, 0x01, 0x20, 0x01, 0x28, 0x09, 0x52, 0x09, 0x74, 0x75, 0x72, 0x62, 0x6f,
+	0x52, 0x6f, 0x6f, 0x74, 0x12, 0x21, 0x0a, 0x0c, 0x70, 0x61, 0x63, 0x6b, 0x61, 0x67, 0x65, 0x5f,
+	0x70, 0x61, 0x74, 0x68, 0x18, 0x02, 0x20, 0x01, 0x28, 0x09, 0x52, 0x0b, 0x70, 0x61, 0x63, 0x6b,
+	0x61, 0x67, 0x65, 0x50, 0x61, 0x74, 0x68, 0x22, 0x78, 0x0a, 0x0a, 0x46, 0x69, 0x6c, 0x65, 0x48,
+	0x61, 0x73, 0x68, 0x65, 0x73, 0x12, 0x2f, 0x0a, 0x06, 0x68, 0x61, 0x73, 0x68, 0x65, 0x73, 0x18,
+	0x01, 0x20, 0x03, 0x28, 0x0b, 0x32, 0x17, 0x2e, 0x46, 0x69, 0x6c, 0x65, 0x48, 0x61, 0x73, 0x68,
+	0x65, 0x73, 0x2e, 0x48, 0x61, 0x73, 0x68, 0x65, 0x73, 0x45, 0x6e, 0x74, 0x72, 0x79, 0x52, 0x06,
+	0x68, 0x61, 0x73, 0x68, 0x65, 0x73, 0x1a, 0x39, 0x0a, 0x0b, 0x48, 0x61, 0x73, 0x68, 0x65, 0x73,
+	0x45, 0x6e, 0x74, 0x72, 0x79, 0x12, 0x10, 0x0a, 0x03, 0x6b, 0x65, 0x79, 0x18, 0x01, 0x20, 0x01,
+	0x28, 0x09, 0x52, 0x03, 0x6b, 0x65, 0x79, 0x12, 0x14, 0x0a, 0x05, 0x76, 0x61, 0x6c, 0x75, 0x65,
+	0x18, 0x02, 0x20, 0x01, 0x28, 0x09, 0x52, 0x05, 0x76, 0x61, 0x6c, 0x75, 0x65, 0x3a, 0x02, 0x38,
+	0x01, 0x22, 0x75, 0x0a, 0x28, 0x47, 0x65, 0x74, 0x50, 0x61, 0x63, 0x6b, 0x61, 0x67, 0x65, 0x46,
+	0x69, 0x6c, 0x65, 0x48, 0x61, 0x73, 0x68, 0x65, 0x73, 0x46, 0x72, 0x6f, 0x6d, 0x47, 0x69, 0x74,
+	0x49, 0x6e, 0x64, 0x65, 0x78, 0x52, 0x65, 0x73, 0x70, 0x6f, 0x6e, 0x73, 0x65, 0x12, 0x25, 0x0a,
+	0x06, 0x68, 0x61, 0x73, 0x68, 0x65, 0x73, 0x18, 0x01, 0x20, 0x01, 0x28, 0x0b, 0x32, 0x0b, 0x2e,
+	0x46, 0x69, 0x6c, 0x65, 0x48, 0x61, 0x73, 0x68, 0x65, 0x73, 0x48, 0x00, 0x52, 0x06, 0x68, 0x61,
+	0x73, 0x68, 0x65, 0x73, 0x12, 0x16, 0x0a, 0x05, 0x65, 0x72, 0x72, 0x6f, 0x72, 0x18, 0x02, 0x20,
+	0x01, 0x28, 0x09, 0x48, 0x00, 0x52, 0x05, 0x65, 0x72, 0x72, 0x6f, 0x72, 0x42, 0x0a, 0x0a, 0x08,
+	0x72, 0x65, 0x73, 0x70, 0x6f, 0x6e, 0x73, 0x65, 0x2a, 0x38, 0x0a, 0x0e, 0x50, 0x61, 0x63, 0x6b,
+	0x61, 0x67, 0x65, 0x4d, 0x61, 0x6e, 0x61, 0x67, 0x65, 0x72, 0x12, 0x07, 0x0a, 0x03, 0x4e, 0x50,
+	0x4d, 0x10, 0x00, 0x12, 0x09, 0x0a, 0x05, 0x42, 0x45, 0x52, 0x52, 0x59, 0x10, 0x01, 0x12, 0x08,
+	0x0a, 0x04, 0x50, 0x4e, 0x50, 0x4d, 0x10, 0x02, 0x12, 0x08, 0x0a, 0x04, 0x59, 0x41, 0x52, 0x4e,
+	0x10, 0x03, 0x62, 0x06, 0x70, 0x72, 0x6f, 0x74, 0x6f, 0x33,
 }
 
 var (
@@ -2068,7 +2445,7 @@ func file_turborepo_ffi_messages_proto_rawDescGZIP() []byte {
 }
 
 var file_turborepo_ffi_messages_proto_enumTypes = make([]protoimpl.EnumInfo, 1)
-var file_turborepo_ffi_messages_proto_msgTypes = make([]protoimpl.MessageInfo, 33)
+var file_turborepo_ffi_messages_proto_msgTypes = make([]protoimpl.MessageInfo, 38)
 var file_turborepo_ffi_messages_proto_goTypes = []interface{}{
 	(PackageManager)(0),                              // 0: PackageManager
 	(*TurboDataDirResp)(nil),                         // 1: TurboDataDirResp
@@ -2095,41 +2472,57 @@ var file_turborepo_ffi_messages_proto_goTypes = []interface{}{
 	(*Patches)(nil),                                  // 22: Patches
 	(*GlobalChangeRequest)(nil),                      // 23: GlobalChangeRequest
 	(*GlobalChangeResponse)(nil),                     // 24: GlobalChangeResponse
-	(*RecursiveCopyRequest)(nil),                     // 25: RecursiveCopyRequest
-	(*RecursiveCopyResponse)(nil),                    // 26: RecursiveCopyResponse
-	(*GetPackageFileHashesFromGitIndexRequest)(nil),  // 27: GetPackageFileHashesFromGitIndexRequest
-	(*FileHashes)(nil),                               // 28: FileHashes
-	(*GetPackageFileHashesFromGitIndexResponse)(nil), // 29: GetPackageFileHashesFromGitIndexResponse
-	nil, // 30: WorkspaceDependencies.DependenciesEntry
-	nil, // 31: TransitiveDepsRequest.WorkspacesEntry
-	nil, // 32: AdditionalBerryData.ResolutionsEntry
-	nil, // 33: FileHashes.HashesEntry
+	(*LockfileDiffRequest)(nil),                      // 25: LockfileDiffRequest
+	(*LockfilePackageChange)(nil),                    // 26: LockfilePackageChange
+	(*LockfileDiff)(nil),                             // 27: LockfileDiff
+	(*LockfileDiffResponse)(nil),                     // 28: LockfileDiffResponse
+	(*RecursiveCopyRequest)(nil),                     // 29: RecursiveCopyRequest
+	(*RecursiveCopyResponse)(nil),                    // 30: RecursiveCopyResponse
+	(*GetPackageFileHashesFromGitIndexRequest)(nil),  // 31: GetPackageFileHashesFromGitIndexRequest
+	(*FileHashes)(nil),                               // 32: FileHashes
+	(*GetPackageFileHashesFromGitIndexResponse)(nil), // 33: GetPackageFileHashesFromGitIndexResponse
+	nil, // 34: WorkspaceDependencies.DependenciesEntry
+	nil, // 35: TransitiveDepsRequest.WorkspacesEntry
+	nil, // 36: AdditionalBerryData.ResolutionsEntry
+	nil, // 37: LockfileDiffRequest.WorkspacesEntry
+	nil, // 38: FileHashes.HashesEntry
 }
 var file_turborepo_ffi_messages_proto_depIdxs = []int32{
 	4,  // 0: GlobResp.files:type_name -> GlobRespList
 	7,  // 1: ChangedFilesResp.files:type_name -> ChangedFilesList
 	10, // 2: PackageDependencyList.list:type_name -> PackageDependency
-	30, // 3: WorkspaceDependencies.dependencies:type_name -> WorkspaceDependencies.DependenciesEntry
+	34, // 3: WorkspaceDependencies.dependencies:type_name -> WorkspaceDependencies.DependenciesEntry
 	0,  // 4: TransitiveDepsRequest.package_manager:type_name -> PackageManager
-	31, // 5: TransitiveDepsRequest.workspaces:type_name -> TransitiveDepsRequest.WorkspacesEntry
+	35, // 5: TransitiveDepsRequest.workspaces:type_name -> TransitiveDepsRequest.WorkspacesEntry
 	15, // 6: TransitiveDepsRequest.resolutions:type_name -> AdditionalBerryData
 	12, // 7: TransitiveDepsResponse.dependencies:type_name -> WorkspaceDependencies
-	32, // 8: AdditionalBerryData.resolutions:type_name -> AdditionalBerryData.ResolutionsEntry
+	36, // 8: AdditionalBerryData.resolutions:type_name -> AdditionalBerryData.ResolutionsEntry
 	16, // 9: LockfilePackageList.list:type_name -> LockfilePackage
 	0,  // 10: SubgraphRequest.package_manager:type_name -> PackageManager
 	15, // 11: SubgraphRequest.resolutions:type_name -> AdditionalBerryData
 	0,  // 12: PatchesRequest.package_manager:type_name -> PackageManager
 	22, // 13: PatchesResponse.patches:type_name -> Patches
 	0,  // 14: GlobalChangeRequest.package_manager:type_name -> PackageManager
-	33, // 15: FileHashes.hashes:type_name -> FileHashes.HashesEntry
-	28, // 16: GetPackageFileHashesFromGitIndexResponse.hashes:type_name -> FileHashes
-	17, // 17: WorkspaceDependencies.DependenciesEntry.value:type_name -> LockfilePackageList
-	11, // 18: TransitiveDepsRequest.WorkspacesEntry.value:type_name -> PackageDependencyList
-	19, // [19:19] is the sub-list for method output_type
-	19, // [19:19] is the sub-list for method input_type
-	19, // [19:19] is the sub-list for extension type_name
-	19, // [19:19] is the sub-list for extension extendee
-	0,  // [0:19] is the sub-list for field type_name
+	0,  // 15: LockfileDiffRequest.package_manager:type_name -> PackageManager
+	37, // 16: LockfileDiffRequest.workspaces:type_name -> LockfileDiffRequest.WorkspacesEntry
+	15, // 17: LockfileDiffRequest.prev_resolutions:type_name -> AdditionalBerryData
+	15, // 18: LockfileDiffRequest.curr_resolutions:type_name -> AdditionalBerryData
+	16, // 19: LockfilePackageChange.previous:type_name -> LockfilePackage
+	16, // 20: LockfilePackageChange.current:type_name -> LockfilePackage
+	16, // 21: LockfileDiff.added:type_name -> LockfilePackage
+	16, // 22: LockfileDiff.removed:type_name -> LockfilePackage
+	26, // 23: LockfileDiff.changed:type_name -> LockfilePackageChange
+	27, // 24: LockfileDiffResponse.diff:type_name -> LockfileDiff
+	38, // 25: FileHashes.hashes:type_name -> FileHashes.HashesEntry
+	32, // 26: GetPackageFileHashesFromGitIndexResponse.hashes:type_name -> FileHashes
+	17, // 27: WorkspaceDependencies.DependenciesEntry.value:type_name -> LockfilePackageList
+	11, // 28: TransitiveDepsRequest.WorkspacesEntry.value:type_name -> PackageDependencyList
+	11, // 29: LockfileDiffRequest.WorkspacesEntry.value:type_name -> PackageDependencyList
+	30, // [30:30] is the sub-list for method output_type
+	30, // [30:30] is the sub-list for method input_type
+	30, // [30:30] is the sub-list for extension type_name
+	30, // [30:30] is the sub-list for extension extendee
+	0,  // [0:30] is the sub-list for field type_name
 }
 
 func init() { file_turborepo_ffi_messages_proto_init() }
@@ -2427,7 +2820,7 @@ func file_turborepo_ffi_messages_proto_init() {
 			}
 		}
 		file_turborepo_ffi_messages_proto_msgTypes[24].Exporter = func(v interface{}, i int) interface{} {
-			switch v := v.(*RecursiveCopyRequest); i {
+			switch v := v.(*LockfileDiffRequest); i {
 			case 0:
 				return &v.state
 			case 1:
@@ -2439,7 +2832,7 @@ func file_turborepo_ffi_messages_proto_init() {
 			}
 		}
 		file_turborepo_ffi_messages_proto_msgTypes[25].Exporter = func(v interface{}, i int) interface{} {
-			switch v := v.(*RecursiveCopyResponse); i {
+			switch v := v.(*LockfilePackageChange); i {
 			case 0:
 				return &v.state
 			case 1:
@@ -2451,7 +2844,7 @@ func file_turborepo_ffi_messages_proto_init() {
 			}
 		}
 		file_turborepo_ffi_messages_proto_msgTypes[26].Exporter = func(v interface{}, i int) interface{} {
-			switch v := v.(*GetPackageFileHashesFromGitIndexRequest); i {
+			switch v := v.(*LockfileDiff); i {
 			case 0:
 				return &v.state
 			case 1:
@@ -2463,7 +2856,7 @@ func file_turborepo_ffi_messages_proto_init() {
 			}
 		}
 		file_turborepo_ffi_messages_proto_msgTypes[27].Exporter = func(v interface{}, i int) interface{} {
-			switch v := v.(*FileHashes); i {
+			switch v := v.(*LockfileDiffResponse); i {
 			case 0:
 				return &v.state
 			case 1:
@@ -2475,6 +2868,54 @@ func file_turborepo_ffi_messages_proto_init() {
 			}
 		}
 		file_turborepo_ffi_messages_proto_msgTypes[28].Exporter = func(v interface{}, i int) interface{} {
+			switch v := v.(*RecursiveCopyRequest); i {
+			case 0:
+				return &v.state
+			case 1:
+				return &v.sizeCache
+			case 2:
+				return &v.unknownFields
+			default:
+				return nil
+			}
+		}
+		file_turborepo_ffi_messages_proto_msgTypes[29].Exporter = func(v interface{}, i int) interface{} {
+			switch v := v.(*RecursiveCopyResponse); i {
+			case 0:
+				return &v.state
+			case 1:
+				return &v.sizeCache
+			case 2:
+				return &v.unknownFields
+			default:
+				return nil
+			}
+		}
+		file_turborepo_ffi_messages_proto_msgTypes[30].Exporter = func(v interface{}, i int) interface{} {
+			switch v := v.(*GetPackageFileHashesFromGitIndexRequest); i {
+			case 0:
+				return &v.state
+			case 1:
+				return &v.sizeCache
+			case 2:
+				return &v.unknownFields
+			default:
+				return nil
+			}
+		}
+		file_turborepo_ffi_messages_proto_msgTypes[31].Exporter = func(v interface{}, i int) interface{} {
+			switch v := v.(*FileHashes); i {
+			case 0:
+				return &v.state
+			case 1:
+				return &v.sizeCache
+			case 2:
+				return &v.unknownFields
+			default:
+				return nil
+			}
+		}
+		file_turborepo_ffi_messages_proto_msgTypes[32].Exporter = func(v interface{}, i int) interface{} {
 			switch v := v.(*GetPackageFileHashesFromGitIndexResponse); i {
 			case 0:
 				return &v.state
@@ -2514,8 +2955,13 @@ func file_turborepo_ffi_messages_proto_init() {
 		(*PatchesResponse_Patches)(nil),
 		(*PatchesResponse_Error)(nil),
 	}
-	file_turborepo_ffi_messages_proto_msgTypes[25].OneofWrappers = []interface{}{}
-	file_turborepo_ffi_messages_proto_msgTypes[28].OneofWrappers = []interface{}{
+	file_turborepo_ffi_messages_proto_msgTypes[24].OneofWrappers = []interface{}{}
+	file_turborepo_ffi_messages_proto_msgTypes[27].OneofWrappers = []interface{}{
+		(*LockfileDiffResponse_Diff)(nil),
+		(*LockfileDiffResponse_Error)(nil),
+	}
+	file_turborepo_ffi_messages_proto_msgTypes[29].OneofWrappers = []interface{}{}
+	file_turborepo_ffi_messages_proto_msgTypes[32].OneofWrappers = []interface{}{
 		(*GetPackageFileHashesFromGitIndexResponse_Hashes)(nil),
 		(*GetPackageFileHashesFromGitIndexResponse_Error)(nil),
 	}
@@ -2525,7 +2971,7 @@ func file_turborepo_ffi_messages_proto_init() {
 			GoPackagePath: reflect.TypeOf(x{}).PkgPath(),
 			RawDescriptor: file_turborepo_ffi_messages_proto_rawDesc,
 			NumEnums:      1,
-			NumMessages:   33,
+			NumMessages:   38,
 			NumExtensions: 0,
 			NumServices:   0,
 		},
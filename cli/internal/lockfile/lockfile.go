@@ -79,6 +79,12 @@ func AllTransitiveClosures(
 	if lf, ok := lockFile.(*BerryLockfile); ok {
 		return rustTransitiveDeps(lf.contents, "berry", workspaces, lf.resolutions)
 	}
+	if lf, ok := lockFile.(*PnpmLockfile); ok {
+		return rustTransitiveDeps(lf.contents, "pnpm", workspaces, nil)
+	}
+	if lf, ok := lockFile.(*YarnLockfile); ok {
+		return rustTransitiveDeps(lf.contents, "yarn", workspaces, nil)
+	}
 
 	g := new(errgroup.Group)
 	c := make(chan closureMsg, len(workspaces))
@@ -166,6 +172,56 @@ func transitiveClosureHelper(
 	}
 }
 
+// AffectedWorkspaces returns the workspaces whose transitive external dependencies differ between
+// the previous and current lockfile. A change to a setting that applies to every package, or a
+// change of package manager, affects every workspace.
+func AffectedWorkspaces(
+	previous Lockfile,
+	current Lockfile,
+	workspaces map[turbopath.AnchoredUnixPath]map[string]string,
+) ([]turbopath.AnchoredUnixPath, error) {
+	prevPackageManager, prevContents, prevResolutions := rustLockfile(previous)
+	currPackageManager, currContents, currResolutions := rustLockfile(current)
+	if prevPackageManager == "" || prevPackageManager != currPackageManager {
+		affected := make([]turbopath.AnchoredUnixPath, 0, len(workspaces))
+		for workspace := range workspaces {
+			affected = append(affected, workspace)
+		}
+		return affected, nil
+	}
+
+	processedWorkspaces := make(map[string]map[string]string, len(workspaces))
+	for workspacePath, workspace := range workspaces {
+		processedWorkspaces[workspacePath.ToString()] = workspace
+	}
+	diff, err := ffi.LockfileDiff(currPackageManager, prevContents, currContents, processedWorkspaces, prevResolutions, currResolutions)
+	if err != nil {
+		return nil, err
+	}
+	affected := make([]turbopath.AnchoredUnixPath, len(diff.GetAffectedWorkspaces()))
+	for i, workspace := range diff.GetAffectedWorkspaces() {
+		affected[i] = turbopath.AnchoredUnixPath(workspace)
+	}
+	return affected, nil
+}
+
+// rustLockfile returns the package manager name, raw contents, and berry resolutions
+// of a lockfile that is backed by the Rust implementation
+func rustLockfile(l Lockfile) (string, []byte, map[string]string) {
+	switch lf := l.(type) {
+	case *NpmLockfile:
+		return "npm", lf.contents, nil
+	case *BerryLockfile:
+		return "berry", lf.contents, lf.resolutions
+	case *PnpmLockfile:
+		return "pnpm", lf.contents, nil
+	case *YarnLockfile:
+		return "yarn", lf.contents, nil
+	default:
+		return "", nil, nil
+	}
+}
+
 func rustTransitiveDeps(content []byte, packageManager string, workspaces map[turbopath.AnchoredUnixPath]map[string]string, resolutions map[string]string) (map[turbopath.AnchoredUnixPath]mapset.Set, error) {
 	processedWorkspaces := make(map[string]map[string]string, len(workspaces))
 	for workspacePath, workspace := range workspaces {
@@ -3,11 +3,11 @@ package lockfile
 import (
 	"fmt"
 	"io"
-	"reflect"
 	"sort"
 	"strings"
 
 	"github.com/pkg/errors"
+	"github.com/vercel/turbo/cli/internal/ffi"
 	"github.com/vercel/turbo/cli/internal/turbopath"
 	"github.com/vercel/turbo/cli/internal/yaml"
 )
@@ -16,6 +16,9 @@ import (
 // Reference https://github.com/pnpm/pnpm/blob/main/packages/lockfile-types/src/index.ts
 type PnpmLockfile struct {
 	isV6 bool
+	// The raw lockfile is kept around as the Rust implementation handles
+	// closures, pruning, and serialization
+	contents []byte
 	// Before 6.0 version was stored as a float, but as of 6.0+ it's a string
 	Version                   interface{}                `yaml:"lockfileVersion"`
 	NeverBuiltDependencies    []string                   `yaml:"neverBuiltDependencies,omitempty"`
@@ -200,6 +203,7 @@ func DecodePnpmLockfile(contents []byte) (*PnpmLockfile, error) {
 	default:
 		return nil, fmt.Errorf("Unexpected type of lockfileVersion: '%T', expected float64 or string", lockfile.Version)
 	}
+	lockfile.contents = contents
 	return &lockfile, nil
 }
 
@@ -263,100 +267,15 @@ func (p *PnpmLockfile) AllDependencies(key string) (map[string]string, bool) {
 
 // Subgraph Given a list of lockfile keys returns a Lockfile based off the original one that only contains the packages given
 func (p *PnpmLockfile) Subgraph(workspacePackages []turbopath.AnchoredSystemPath, packages []string) (Lockfile, error) {
-	lockfilePackages := make(map[string]PackageSnapshot, len(packages))
-	for _, key := range packages {
-		entry, ok := p.Packages[key]
-		if ok {
-			lockfilePackages[key] = entry
-		} else {
-			return nil, fmt.Errorf("Unable to find lockfile entry for %s", key)
-		}
+	workspaces := make([]string, len(workspacePackages))
+	for i, workspace := range workspacePackages {
+		workspaces[i] = workspace.ToUnixPath().ToString()
 	}
-
-	importers, err := pruneImporters(p.Importers, workspacePackages)
+	contents, err := ffi.Subgraph("pnpm", p.contents, workspaces, packages, nil)
 	if err != nil {
 		return nil, err
 	}
-
-	for _, importer := range importers {
-		for dependency, meta := range importer.DependenciesMeta {
-			if meta.Injected {
-				resolution, ok, err := importer.findResolution(dependency)
-				if err != nil {
-					return nil, errors.Wrapf(err, "Unable to decode reference to %s", dependency)
-				}
-				if !ok {
-					return nil, fmt.Errorf("Unable to find %s other than reference in dependenciesMeta", dependency)
-				}
-				entry, ok := p.Packages[resolution.Version]
-				if !ok {
-					return nil, fmt.Errorf("Unable to find package entry for %s", resolution)
-				}
-
-				lockfilePackages[resolution.Version] = entry
-			}
-		}
-	}
-
-	lockfile := PnpmLockfile{
-		Version:                   p.Version,
-		Packages:                  lockfilePackages,
-		NeverBuiltDependencies:    p.NeverBuiltDependencies,
-		OnlyBuiltDependencies:     p.OnlyBuiltDependencies,
-		Overrides:                 p.Overrides,
-		PackageExtensionsChecksum: p.PackageExtensionsChecksum,
-		PatchedDependencies:       p.prunePatches(p.PatchedDependencies, lockfilePackages),
-		Importers:                 importers,
-	}
-
-	return &lockfile, nil
-}
-
-// Prune imports to only those have all of their dependencies in the packages list
-func pruneImporters(importers map[string]ProjectSnapshot, workspacePackages []turbopath.AnchoredSystemPath) (map[string]ProjectSnapshot, error) {
-	prunedImporters := map[string]ProjectSnapshot{}
-
-	// Copy over root level importer
-	if root, ok := importers["."]; ok {
-		prunedImporters["."] = root
-	}
-
-	for _, workspacePath := range workspacePackages {
-		workspace := workspacePath.ToUnixPath().ToString()
-		importer, ok := importers[workspace]
-
-		// If a workspace has no dependencies *and* it is only depended on by the
-		// workspace root it will not show up as an importer.
-		if ok {
-			prunedImporters[workspace] = importer
-		}
-
-	}
-
-	return prunedImporters, nil
-}
-
-func (p *PnpmLockfile) prunePatches(patches map[string]PatchFile, packages map[string]PackageSnapshot) map[string]PatchFile {
-	if len(patches) == 0 {
-		return nil
-	}
-
-	patchPackages := make(map[string]PatchFile, len(patches))
-	for dependency := range packages {
-		if p.isV6 {
-			// Internally pnpm partially converts the new path format to the old
-			// format in order for existing parsing logic to work.
-			dependency = convertNewToOldDepPath(dependency)
-		}
-		dp := parseDepPath(dependency)
-		patchKey := fmt.Sprintf("%s@%s", dp.name, dp.version)
-
-		if patch, ok := patches[patchKey]; ok && patch.Hash == dp.patchHash() {
-			patchPackages[patchKey] = patch
-		}
-	}
-
-	return patchPackages
+	return DecodePnpmLockfile(contents)
 }
 
 // Encode encode the lockfile representation and write it to the given writer
@@ -365,13 +284,8 @@ func (p *PnpmLockfile) Encode(w io.Writer) error {
 		return err
 	}
 
-	encoder := yaml.NewEncoder(w)
-	encoder.SetIndent(2)
-
-	if err := encoder.Encode(p); err != nil {
-		return errors.Wrap(err, "unable to encode pnpm lockfile")
-	}
-	return nil
+	_, err := w.Write(p.contents)
+	return err
 }
 
 // Patches return a list of patches used in the lockfile
@@ -398,11 +312,11 @@ func (p *PnpmLockfile) Patches() []turbopath.AnchoredUnixPath {
 // the cache.
 func (p *PnpmLockfile) GlobalChange(other Lockfile) bool {
 	o, ok := other.(*PnpmLockfile)
-	return !ok ||
-		p.Version != o.Version ||
-		p.PackageExtensionsChecksum != o.PackageExtensionsChecksum ||
-		!reflect.DeepEqual(p.Overrides, o.Overrides) ||
-		!reflect.DeepEqual(p.PatchedDependencies, o.PatchedDependencies)
+	if !ok {
+		return true
+	}
+
+	return ffi.GlobalChange("pnpm", o.contents, p.contents)
 }
 
 func (p *PnpmLockfile) resolveSpecifier(workspacePath turbopath.AnchoredUnixPath, name string, specifier string) (string, bool, error) {
@@ -541,29 +455,6 @@ func parseDepPath(dependency string) depPath {
 	return dp
 }
 
-var _patchHashKey = "patch_hash="
-
-func (d depPath) patchHash() string {
-	if strings.HasPrefix(d.peerSuffix, "(") && strings.HasSuffix(d.peerSuffix, ")") {
-		for _, part := range strings.Split(d.peerSuffix, "(") {
-			if strings.HasPrefix(part, _patchHashKey) {
-				// drop the enclosing ')'
-				return part[len(_patchHashKey) : len(part)-1]
-			}
-		}
-		// no patch entry found
-		return ""
-	}
-
-	sepIndex := strings.Index(d.peerSuffix, "_")
-	if sepIndex != -1 {
-		return d.peerSuffix[:sepIndex]
-	}
-	// if a dependency just has a single suffix we can't tell if it's a patch or peer hash
-	// return it in case it's a patch hash
-	return d.peerSuffix
-}
-
 // Used to convert v6's dep path of /name@version to v5's /name/version
 // See https://github.com/pnpm/pnpm/blob/185ab01adfc927ea23d2db08a14723bf51d0025f/lockfile/lockfile-file/src/experiments/inlineSpecifiersLockfileConverters.ts#L162
 func convertNewToOldDepPath(newPath string) string {
@@ -1,22 +1,21 @@
 package lockfile
 
 import (
-	"bytes"
 	"fmt"
 	"io"
 
-	"github.com/andybalholm/crlf"
 	"github.com/iseki0/go-yarnlock"
 	"github.com/pkg/errors"
+	"github.com/vercel/turbo/cli/internal/ffi"
 	"github.com/vercel/turbo/cli/internal/turbopath"
 )
 
-var _crlfLiteral = []byte("\r\n")
-
 // YarnLockfile representation of yarn lockfile
 type YarnLockfile struct {
-	inner   yarnlock.LockFile
-	hasCRLF bool
+	inner yarnlock.LockFile
+	// The raw lockfile is kept around as the Rust implementation handles
+	// closures, pruning, and serialization
+	contents []byte
 }
 
 var _ Lockfile = (*YarnLockfile)(nil)
@@ -55,28 +54,22 @@ func (l *YarnLockfile) AllDependencies(key string) (map[string]string, bool) {
 }
 
 // Subgraph Given a list of lockfile keys returns a Lockfile based off the original one that only contains the packages given
-func (l *YarnLockfile) Subgraph(_ []turbopath.AnchoredSystemPath, packages []string) (Lockfile, error) {
-	lockfile := make(map[string]yarnlock.LockFileEntry, len(packages))
-	for _, key := range packages {
-		entry, ok := (l.inner)[key]
-		if ok {
-			lockfile[key] = entry
-		}
+func (l *YarnLockfile) Subgraph(workspacePackages []turbopath.AnchoredSystemPath, packages []string) (Lockfile, error) {
+	workspaces := make([]string, len(workspacePackages))
+	for i, workspace := range workspacePackages {
+		workspaces[i] = workspace.ToUnixPath().ToString()
 	}
-
-	return &YarnLockfile{lockfile, l.hasCRLF}, nil
+	contents, err := ffi.Subgraph("yarn", l.contents, workspaces, packages, nil)
+	if err != nil {
+		return nil, err
+	}
+	return DecodeYarnLockfile(contents)
 }
 
 // Encode encode the lockfile representation and write it to the given writer
 func (l *YarnLockfile) Encode(w io.Writer) error {
-	writer := w
-	if l.hasCRLF {
-		writer = crlf.NewWriter(w)
-	}
-	if err := l.inner.Encode(writer); err != nil {
-		return errors.Wrap(err, "Unable to encode yarn.lock")
-	}
-	return nil
+	_, err := w.Write(l.contents)
+	return err
 }
 
 // Patches return a list of patches used in the lockfile
@@ -87,23 +80,11 @@ func (l *YarnLockfile) Patches() []turbopath.AnchoredUnixPath {
 // DecodeYarnLockfile Takes the contents of a yarn lockfile and returns a struct representation
 func DecodeYarnLockfile(contents []byte) (*YarnLockfile, error) {
 	lockfile, err := yarnlock.ParseLockFileData(contents)
-	hasCRLF := bytes.HasSuffix(contents, _crlfLiteral)
-	newline := []byte("\n")
-
-	// there's no trailing newline for this file, need to inspect more to see newline style
-	if !hasCRLF && !bytes.HasSuffix(contents, newline) {
-		firstNewline := bytes.IndexByte(contents, newline[0])
-		if firstNewline != -1 && firstNewline != 0 {
-			byteBeforeNewline := contents[firstNewline-1]
-			hasCRLF = byteBeforeNewline == '\r'
-		}
-	}
-
 	if err != nil {
 		return nil, errors.Wrap(err, "Unable to decode yarn.lock")
 	}
 
-	return &YarnLockfile{lockfile, hasCRLF}, nil
+	return &YarnLockfile{lockfile, contents}, nil
 }
 
 // GlobalChange checks if there are any differences between lockfiles that would completely invalidate
@@ -156,11 +156,38 @@ func (r *run) run(ctx gocontext.Context, targets []string, executionState *turbo
 
 	isStructuredOutput := r.opts.runOpts.GraphDot || r.opts.runOpts.DryRunJSON
 
+	var daemonClient *daemonclient.DaemonClient
+	var fileHasher taskhash.PackageFileHasher
+	if ui.IsCI && !r.opts.runOpts.NoDaemon {
+		r.base.Logger.Info("skipping turbod since we appear to be in a non-interactive context")
+	} else if !r.opts.runOpts.NoDaemon {
+		turbodClient, err := daemon.GetClient(ctx, r.base.RepoRoot, r.base.Logger, r.base.TurboVersion, daemon.ClientOpts{})
+		if err != nil {
+			r.base.LogWarning("", errors.Wrap(err, "failed to contact turbod. Continuing in standalone mode"))
+		} else {
+			defer func() { _ = turbodClient.Close() }()
+			r.base.Logger.Debug("running in daemon mode")
+			daemonClient = daemonclient.New(turbodClient)
+			r.opts.runcacheOpts.OutputWatcher = daemonClient
+			fileHasher = daemonClient
+		}
+	}
+
 	var pkgDepGraph *context.Context
 	if r.opts.runOpts.SinglePackage {
 		pkgDepGraph, err = context.SinglePackageGraph(rootPackageJSON, executionState.PackageManager)
 	} else {
-		pkgDepGraph, err = context.BuildPackageGraph(r.base.RepoRoot, rootPackageJSON, executionState.PackageManager)
+		// the daemon keeps the list of workspaces up to date, so we don't have to
+		// discover them unless it isn't available
+		var workspaces []string
+		if daemonClient != nil {
+			workspaces, err = daemonClient.GetWorkspacePackageJSONs(ctx, r.base.RepoRoot, executionState.PackageManager)
+			if err != nil {
+				r.base.Logger.Debug("failed to get workspaces from turbod, discovering them", "error", err)
+				workspaces = nil
+			}
+		}
+		pkgDepGraph, err = context.BuildPackageGraphFromWorkspaces(r.base.RepoRoot, rootPackageJSON, executionState.PackageManager, workspaces)
 	}
 	if err != nil {
 		var warnings *context.Warnings
@@ -171,20 +198,6 @@ func (r *run) run(ctx gocontext.Context, targets []string, executionState *turbo
 		}
 	}
 
-	if ui.IsCI && !r.opts.runOpts.NoDaemon {
-		r.base.Logger.Info("skipping turbod since we appear to be in a non-interactive context")
-	} else if !r.opts.runOpts.NoDaemon {
-		turbodClient, err := daemon.GetClient(ctx, r.base.RepoRoot, r.base.Logger, r.base.TurboVersion, daemon.ClientOpts{})
-		if err != nil {
-			r.base.LogWarning("", errors.Wrap(err, "failed to contact turbod. Continuing in standalone mode"))
-		} else {
-			defer func() { _ = turbodClient.Close() }()
-			r.base.Logger.Debug("running in daemon mode")
-			daemonClient := daemonclient.New(turbodClient)
-			r.opts.runcacheOpts.OutputWatcher = daemonClient
-		}
-	}
-
 	if err := util.ValidateGraph(&pkgDepGraph.WorkspaceGraph); err != nil {
 		return errors.Wrap(err, "Invalid package dependency graph")
 	}
@@ -296,11 +309,13 @@ func (r *run) run(ctx gocontext.Context, targets []string, executionState *turbo
 
 	// CalculateFileHashes assigns PackageInputsExpandedHashes as a side-effect
 	err = taskHashTracker.CalculateFileHashes(
+		ctx,
 		engine.TaskGraph.Vertices(),
 		rs.Opts.runOpts.Concurrency,
 		g.WorkspaceInfos,
 		g.TaskDefinitions,
 		r.base.RepoRoot,
+		fileHasher,
 	)
 
 	if err != nil {
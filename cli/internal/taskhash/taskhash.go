@@ -2,6 +2,7 @@
 package taskhash
 
 import (
+	"context"
 	"fmt"
 	"sort"
 	"strings"
@@ -21,6 +22,12 @@ import (
 	"golang.org/x/sync/errgroup"
 )
 
+// PackageFileHasher returns the hashes of the files in a package from a cache
+// that is kept up to date as files change, such as the one kept by the daemon.
+type PackageFileHasher interface {
+	GetPackageFileHashes(ctx context.Context, packagePath turbopath.AnchoredSystemPath, inputs []string) (map[turbopath.AnchoredUnixPath]string, error)
+}
+
 // Tracker caches package-inputs hashes, as well as package-task hashes.
 // package-inputs hashes must be calculated before package-task hashes,
 // and package-task hashes must be calculated in topographical order.
@@ -70,13 +77,16 @@ type packageFileHashInputs struct {
 }
 
 // CalculateFileHashes hashes each unique package-inputs combination that is present
-// in the task graph. Must be called before calculating task hashes.
+// in the task graph. Must be called before calculating task hashes. Hashes come from
+// fileHasher when one is given, falling back to hashing the files directly.
 func (th *Tracker) CalculateFileHashes(
+	ctx context.Context,
 	allTasks []dag.Vertex,
 	workerCount int,
 	workspaceInfos workspace.Catalog,
 	taskDefinitions map[string]*fs.TaskDefinition,
 	repoRoot turbopath.AbsoluteSystemPath,
+	fileHasher PackageFileHasher,
 ) error {
 	hashTasks := make(util.Set)
 
@@ -122,7 +132,15 @@ func (th *Tracker) CalculateFileHashes(
 				}
 
 				// Get the hashes of each file, keyed by the path.
-				hashObject, err := hashing.GetPackageFileHashes(repoRoot, pkg.Dir, packageFileHashInputs.taskDefinition.Inputs)
+				inputs := packageFileHashInputs.taskDefinition.Inputs
+				var hashObject map[turbopath.AnchoredUnixPath]string
+				var err error
+				if fileHasher != nil {
+					hashObject, err = fileHasher.GetPackageFileHashes(ctx, pkg.Dir, inputs)
+				}
+				if fileHasher == nil || err != nil {
+					hashObject, err = hashing.GetPackageFileHashes(repoRoot, pkg.Dir, inputs)
+				}
 				if err != nil {
 					return err
 				}
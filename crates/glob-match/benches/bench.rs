@@ -22,6 +22,34 @@ fn glob_match_crate(b: &mut Criterion) {
   });
 }
 
+fn compiled_glob(b: &mut Criterion) {
+  let glob = CompiledGlob::new(GLOB);
+  b.bench_function("compiled", |b| {
+    b.iter(|| assert!(glob.is_match(PATH).unwrap_or_default()))
+  });
+}
+
+fn glob_set(b: &mut Criterion) {
+  let globs = [
+    "other/**",
+    "some/*/needle.txt",
+    "some/**/haystack.txt",
+    GLOB,
+    "!some/**/*.md",
+  ];
+  b.bench_function("glob_set", |b| {
+    let set = GlobSet::new(globs);
+    b.iter(|| assert!(set.is_match(PATH)))
+  });
+  b.bench_function("glob_match_each", |b| {
+    b.iter(|| {
+      assert!(globs
+        .iter()
+        .any(|glob| glob_match(glob, PATH).unwrap_or_default()))
+    })
+  });
+}
+
 fn glob_crate(b: &mut Criterion) {
   b.bench_function("glob_crate", |b| b.iter(|| assert!(glob(GLOB, PATH))));
 }
@@ -30,5 +58,12 @@ fn globset_crate(b: &mut Criterion) {
   b.bench_function("globset_crate", |b| b.iter(|| assert!(globset(GLOB, PATH))));
 }
 
-criterion_group!(benches, globset_crate, glob_crate, glob_match_crate);
+criterion_group!(
+  benches,
+  globset_crate,
+  glob_crate,
+  glob_match_crate,
+  compiled_glob,
+  glob_set
+);
 criterion_main!(benches);
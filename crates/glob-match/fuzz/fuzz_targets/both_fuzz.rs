@@ -1,9 +1,10 @@
 #![no_main]
 
 use fuzz_local::Data;
-use glob_match::glob_match;
+use glob_match::{glob_match, CompiledGlob};
 use libfuzzer_sys::fuzz_target;
 
 fuzz_target!(|data: Data<'_>| {
-  _ = glob_match(data.pat, data.input);
+  let expected = glob_match(data.pat, data.input);
+  assert_eq!(CompiledGlob::new(data.pat).is_match(data.input), expected);
 });
@@ -0,0 +1,18 @@
+#![no_main]
+
+use fuzz_local::SetData;
+use glob_match::{glob_match, GlobSet};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: SetData<'_>| {
+  let expected = data
+    .pats
+    .iter()
+    .enumerate()
+    .filter(|(_, pat)| glob_match(pat, data.input) == Some(true))
+    .map(|(index, _)| index)
+    .collect::<Vec<_>>();
+  let set = GlobSet::new(&data.pats);
+  assert_eq!(set.matches(data.input), expected);
+  assert_eq!(set.is_match(data.input), !expected.is_empty());
+});
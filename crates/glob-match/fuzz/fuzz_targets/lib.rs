@@ -5,3 +5,9 @@ pub struct Data<'a> {
   pub pat: &'a str,
   pub input: &'a str,
 }
+
+#[derive(Debug, Arbitrary)]
+pub struct SetData<'a> {
+  pub pats: Vec<&'a str>,
+  pub input: &'a str,
+}
@@ -1,8 +1,9 @@
 #![no_main]
 
-use glob_match::glob_match;
+use glob_match::{glob_match, CompiledGlob};
 use libfuzzer_sys::fuzz_target;
 
 fuzz_target!(|data: &str| {
-  _ = glob_match(data, data);
+  let expected = glob_match(data, data);
+  assert_eq!(CompiledGlob::new(data).is_match(data), expected);
 });
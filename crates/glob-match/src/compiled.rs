@@ -0,0 +1,599 @@
+use std::path::is_separator;
+
+use crate::glob_match_internal;
+
+/// A glob that has been parsed ahead of time, so that it can cheaply be
+/// matched against many paths.
+///
+/// Matching behaves exactly like [`glob_match`](crate::glob_match). Paths that
+/// don't start with the literal prefix of the glob are rejected without
+/// running the matcher. Globs made of literal, `*`, `?` and `[...]` segments,
+/// or of literal segments and a single `**`, are compiled into a program that
+/// matches a path one segment at a time. Anything else, such as braces and
+/// escapes, is matched by `glob_match`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CompiledGlob {
+  glob: String,
+  negated: bool,
+  /// The byte range of the glob that must match the start of the path
+  /// literally.
+  prefix: std::ops::Range<usize>,
+  program: Option<Box<[Segment]>>,
+}
+
+/// A segment of a glob, matching the text between two path separators.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Segment {
+  /// `**`, matching any number of segments.
+  Globstar,
+  Literal(Box<[u8]>),
+  Pattern(Box<[Token]>),
+}
+
+/// Inclusive byte ranges of a character class.
+type Ranges = Box<[(u8, u8)]>;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+  Literal(u8),
+  /// `?`, matching any single byte.
+  Any,
+  /// `*`, matching any number of bytes.
+  Star,
+  /// `[...]`, matching a byte in any of the inclusive ranges.
+  Class(Ranges),
+}
+
+impl CompiledGlob {
+  pub fn new(glob: &str) -> Self {
+    let bytes = glob.as_bytes();
+    let start = bytes.iter().take_while(|c| **c == b'!').count();
+    // Escapes are not part of the prefix, as they map to different bytes.
+    let len = bytes[start..]
+      .iter()
+      .take_while(|c| !matches!(c, b'*' | b'?' | b'[' | b'{' | b'\\'))
+      .count();
+
+    Self {
+      glob: glob.to_string(),
+      negated: start % 2 == 1,
+      prefix: start..start + len,
+      program: compile(&bytes[start..], start > 0),
+    }
+  }
+
+  /// The glob this was compiled from.
+  pub fn as_str(&self) -> &str {
+    &self.glob
+  }
+
+  /// Whether the glob is negated with leading `!` characters.
+  pub fn is_negated(&self) -> bool {
+    self.negated
+  }
+
+  /// The literal text that every path matched by the glob starts with, or
+  /// that every path starts with that isn't matched by a negated glob.
+  pub fn literal_prefix(&self) -> &str {
+    &self.glob[self.prefix.clone()]
+  }
+
+  /// Matches the glob against a path. Returns `None` if the glob is invalid.
+  pub fn is_match(&self, path: &str) -> Option<bool> {
+    self.is_match_split(path, &split(path))
+  }
+
+  /// Like `is_match`, with the path already split into segments by `split`.
+  fn is_match_split(&self, path: &str, segments: &Option<Vec<&[u8]>>) -> Option<bool> {
+    if !starts_with_literal(path.as_bytes(), self.literal_prefix().as_bytes()) {
+      // The matcher gives up on the first literal that doesn't match, before
+      // looking at the rest of the glob.
+      return Some(self.negated);
+    }
+    match (&self.program, segments) {
+      (Some(program), Some(segments)) => Some(match_segments(program, segments) != self.negated),
+      _ => glob_match_internal(&self.glob, path, None),
+    }
+  }
+}
+
+/// Parses a glob without its negation into segments, or returns `None` if it
+/// uses syntax that can't be matched a segment at a time.
+fn compile(glob: &[u8], negated: bool) -> Option<Box<[Segment]>> {
+  if glob.is_empty() || glob.iter().any(|c| matches!(c, b'{' | b'}' | b'\\')) {
+    return None;
+  }
+
+  let mut segments = Vec::new();
+  for segment in glob.split(|c| *c == b'/') {
+    let segment = match segment {
+      // Empty segments only match at the start or end of paths.
+      b"" => return None,
+      b"**" => {
+        // Consecutive globstars are the same as one.
+        if segments.last() == Some(&Segment::Globstar) {
+          continue;
+        }
+        Segment::Globstar
+      }
+      segment if !segment.iter().any(|c| matches!(c, b'*' | b'?' | b'[')) => {
+        Segment::Literal(segment.into())
+      }
+      // A ** that isn't a whole segment acts like a *, but not consistently.
+      segment if segment.windows(2).any(|window| window == b"**") => return None,
+      segment => Segment::Pattern(compile_pattern(segment)?),
+    };
+    segments.push(segment);
+  }
+
+  // glob_match backtracks into a ** greedily, so alongside other wildcards or
+  // a negation it misses some paths a segment matcher finds. Leave those to
+  // glob_match.
+  let globstars = segments
+    .iter()
+    .filter(|segment| **segment == Segment::Globstar)
+    .count();
+  let patterns = segments
+    .iter()
+    .any(|segment| matches!(segment, Segment::Pattern(_)));
+  if globstars > 1 || (globstars == 1 && (patterns || negated)) {
+    return None;
+  }
+  Some(segments.into())
+}
+
+fn compile_pattern(segment: &[u8]) -> Option<Box<[Token]>> {
+  let mut tokens = Vec::new();
+  let mut index = 0;
+  while index < segment.len() {
+    let token = match segment[index] {
+      b'*' => Token::Star,
+      // Without unicode segmentation, `?` and `[...]` match single bytes.
+      #[cfg(not(feature = "unic-segment"))]
+      b'?' => Token::Any,
+      #[cfg(not(feature = "unic-segment"))]
+      b'[' => {
+        index += 1;
+        let (ranges, end) = compile_class(segment, index)?;
+        index = end;
+        Token::Class(ranges)
+      }
+      #[cfg(feature = "unic-segment")]
+      b'?' | b'[' => return None,
+      c => Token::Literal(c),
+    };
+    tokens.push(token);
+    index += 1;
+  }
+  Some(tokens.into())
+}
+
+/// Parses the ranges of a character class starting after its `[`, returning
+/// them with the index of the closing `]`. Classes that could match a path
+/// separator are not compiled, as `glob_match` lets them.
+#[cfg(not(feature = "unic-segment"))]
+fn compile_class(segment: &[u8], mut index: usize) -> Option<(Ranges, usize)> {
+  if matches!(segment.get(index), Some(b'^' | b'!')) {
+    return None;
+  }
+
+  let mut ranges = Vec::new();
+  let mut first = true;
+  while index < segment.len() && (first || segment[index] != b']') {
+    let low = segment[index];
+    index += 1;
+    // If there is a - and the following character is not ], read the range
+    // end character.
+    let high = if index + 1 < segment.len() && segment[index] == b'-' && segment[index + 1] != b']'
+    {
+      index += 2;
+      segment[index - 1]
+    } else {
+      low
+    };
+    if (low..=high).any(|c| is_separator(c as char)) {
+      return None;
+    }
+    ranges.push((low, high));
+    first = false;
+  }
+  // An unclosed class is invalid, which only `glob_match` reports.
+  (index < segment.len()).then(|| (ranges.into(), index))
+}
+
+/// Splits a path into segments, or returns `None` if it has empty segments,
+/// which compiled globs don't handle.
+fn split(path: &str) -> Option<Vec<&[u8]>> {
+  let segments = path
+    .as_bytes()
+    .split(|c| is_separator(*c as char))
+    .collect::<Vec<_>>();
+  (!segments.iter().any(|segment| segment.is_empty())).then_some(segments)
+}
+
+/// Matches path segments against a compiled glob. A `**` matches any number
+/// of segments and every other segment matches exactly one, so only the last
+/// `**` needs to be backtracked to.
+fn match_segments(program: &[Segment], path: &[&[u8]]) -> bool {
+  let (mut program_index, mut path_index) = (0, 0);
+  let mut globstar: Option<(usize, usize)> = None;
+  while program_index < program.len() || path_index < path.len() {
+    match program.get(program_index) {
+      Some(Segment::Globstar) => {
+        globstar = Some((program_index, path_index));
+        program_index += 1;
+        continue;
+      }
+      Some(segment) if path_index < path.len() && match_segment(segment, path[path_index]) => {
+        program_index += 1;
+        path_index += 1;
+        continue;
+      }
+      _ => {}
+    }
+    match &mut globstar {
+      Some((globstar_index, globstar_path_index)) if *globstar_path_index < path.len() => {
+        *globstar_path_index += 1;
+        program_index = *globstar_index + 1;
+        path_index = *globstar_path_index;
+      }
+      _ => return false,
+    }
+  }
+  true
+}
+
+fn match_segment(segment: &Segment, path: &[u8]) -> bool {
+  match segment {
+    Segment::Globstar => true,
+    Segment::Literal(literal) => **literal == *path,
+    Segment::Pattern(tokens) => match_pattern(tokens, path),
+  }
+}
+
+/// Matches a segment of a path against the tokens of a glob segment, using
+/// the same backtracking to the last `*` as `glob_match`.
+fn match_pattern(tokens: &[Token], path: &[u8]) -> bool {
+  let (mut token_index, mut path_index) = (0, 0);
+  let mut star: Option<(usize, usize)> = None;
+  while token_index < tokens.len() || path_index < path.len() {
+    match tokens.get(token_index) {
+      Some(Token::Star) => {
+        star = Some((token_index, path_index));
+        token_index += 1;
+        continue;
+      }
+      Some(token) if path_index < path.len() => {
+        let c = path[path_index];
+        let is_match = match token {
+          Token::Literal(literal) => *literal == c,
+          Token::Any => true,
+          Token::Class(ranges) => ranges.iter().any(|(low, high)| (*low..=*high).contains(&c)),
+          Token::Star => unreachable!(),
+        };
+        if is_match {
+          token_index += 1;
+          path_index += 1;
+          continue;
+        }
+      }
+      _ => {}
+    }
+    match &mut star {
+      Some((star_index, star_path_index)) if *star_path_index < path.len() => {
+        *star_path_index += 1;
+        token_index = *star_index + 1;
+        path_index = *star_path_index;
+      }
+      _ => return false,
+    }
+  }
+  true
+}
+
+/// A set of globs that are matched against a path together.
+///
+/// Globs are indexed by their literal prefix, so only the globs that could
+/// match a path are run against it.
+#[derive(Clone, Debug, Default)]
+pub struct GlobSet {
+  globs: Vec<CompiledGlob>,
+  /// Indices of the globs that aren't negated, keyed by literal prefix.
+  prefixes: PrefixTrie,
+  /// Negated globs match every path that doesn't start with their prefix, so
+  /// they are always run.
+  negated: Vec<usize>,
+}
+
+impl GlobSet {
+  pub fn new<I, S>(globs: I) -> Self
+  where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+  {
+    let mut set = Self::default();
+    for glob in globs {
+      set.insert(glob.as_ref());
+    }
+    set
+  }
+
+  /// Adds a glob to the set, returning its index.
+  pub fn insert(&mut self, glob: &str) -> usize {
+    let glob = CompiledGlob::new(glob);
+    let index = self.globs.len();
+    if glob.is_negated() {
+      self.negated.push(index);
+    } else {
+      self
+        .prefixes
+        .insert(glob.literal_prefix().as_bytes(), index);
+    }
+    self.globs.push(glob);
+    index
+  }
+
+  pub fn len(&self) -> usize {
+    self.globs.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.globs.is_empty()
+  }
+
+  pub fn globs(&self) -> &[CompiledGlob] {
+    &self.globs
+  }
+
+  /// Returns the indices of every glob that matches the path, in the order
+  /// they were inserted. Invalid globs never match.
+  pub fn matches(&self, path: &str) -> Vec<usize> {
+    let segments = split(path);
+    let mut matches = self
+      .prefixes
+      .candidates(path.as_bytes())
+      .chain(self.negated.iter().copied())
+      .filter(|index| self.globs[*index].is_match_split(path, &segments) == Some(true))
+      .collect::<Vec<_>>();
+    matches.sort_unstable();
+    matches
+  }
+
+  /// Whether any glob in the set matches the path.
+  pub fn is_match(&self, path: &str) -> bool {
+    let segments = split(path);
+    self
+      .prefixes
+      .candidates(path.as_bytes())
+      .chain(self.negated.iter().copied())
+      .any(|index| self.globs[index].is_match_split(path, &segments) == Some(true))
+  }
+}
+
+impl<S: AsRef<str>> FromIterator<S> for GlobSet {
+  fn from_iter<I: IntoIterator<Item = S>>(iter: I) -> Self {
+    Self::new(iter)
+  }
+}
+
+/// Whether the path starts with a literal glob prefix. A `/` in the glob
+/// matches any path separator, as it does in the matcher.
+fn starts_with_literal(path: &[u8], prefix: &[u8]) -> bool {
+  path.len() >= prefix.len()
+    && prefix.iter().zip(path).all(|(glob, path)| {
+      if *glob == b'/' {
+        is_separator(*path as char)
+      } else {
+        glob == path
+      }
+    })
+}
+
+#[derive(Clone, Debug, Default)]
+struct PrefixTrie {
+  nodes: Vec<PrefixNode>,
+}
+
+#[derive(Clone, Debug, Default)]
+struct PrefixNode {
+  children: Vec<(u8, usize)>,
+  globs: Vec<usize>,
+}
+
+impl PrefixTrie {
+  fn insert(&mut self, prefix: &[u8], glob: usize) {
+    if self.nodes.is_empty() {
+      self.nodes.push(PrefixNode::default());
+    }
+    let mut node = 0;
+    for byte in prefix {
+      node = match self.nodes[node].children.iter().find(|(b, _)| b == byte) {
+        Some((_, child)) => *child,
+        None => {
+          let child = self.nodes.len();
+          self.nodes.push(PrefixNode::default());
+          self.nodes[node].children.push((*byte, child));
+          child
+        }
+      };
+    }
+    self.nodes[node].globs.push(glob);
+  }
+
+  /// Walks the path down the trie, yielding every glob whose prefix the path
+  /// starts with.
+  fn candidates<'a>(&'a self, path: &'a [u8]) -> impl Iterator<Item = usize> + 'a {
+    let mut node = (!self.nodes.is_empty()).then_some(0);
+    let mut path = path.iter();
+    std::iter::from_fn(move || {
+      let current = node?;
+      node = path.next().and_then(|byte| {
+        let byte = if is_separator(*byte as char) {
+          b'/'
+        } else {
+          *byte
+        };
+        self.nodes[current]
+          .children
+          .iter()
+          .find(|(b, _)| *b == byte)
+          .map(|(_, child)| *child)
+      });
+      Some(self.nodes[current].globs.iter().copied())
+    })
+    .flatten()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use test_case::test_case;
+
+  use super::{CompiledGlob, GlobSet};
+  use crate::glob_match;
+
+  const GLOBS: &[&str] = &[
+    "",
+    "a",
+    "a/b",
+    "a/*",
+    "a/**",
+    "a/**/b",
+    "**/b",
+    "a/{b,c}",
+    "a/{b,c/**}",
+    "a/[bc]",
+    "a\\/b",
+    "\\a/b",
+    "!a/**",
+    "!!a/b",
+    "!**/b",
+    "!*",
+    "a/b}",
+    "a/b,c",
+    "a[",
+    "{a",
+    "a/{b",
+    "packages/*/package.json",
+    "packages/ui/dist/**",
+    "packages/ui/dist/**/*.map",
+  ];
+
+  const PATHS: &[&str] = &[
+    "",
+    "a",
+    "ab",
+    "a/",
+    "a/b",
+    "a/c",
+    "a/c/d",
+    "a/b/b",
+    "b",
+    "a/b}",
+    "a/b,c",
+    "\x07/b",
+    "packages/ui/package.json",
+    "packages/ui/dist/index.js",
+    "packages/ui/dist/index.js.map",
+  ];
+
+  #[test_case("a/b/**", false, "a/b/" ; "literal")]
+  #[test_case("!a/*", true, "a/" ; "negated")]
+  #[test_case("!!a/*", false, "a/" ; "double negated")]
+  #[test_case("a{b,c}", false, "a" ; "braces")]
+  #[test_case("a\\*b", false, "a" ; "escape")]
+  #[test_case("a/!b", false, "a/!b" ; "inner exclamation")]
+  fn compile(glob: &str, negated: bool, prefix: &str) {
+    let compiled = CompiledGlob::new(glob);
+    assert_eq!(compiled.as_str(), glob);
+    assert_eq!(compiled.is_negated(), negated);
+    assert_eq!(compiled.literal_prefix(), prefix);
+  }
+
+  #[test]
+  fn compiled_matches_uncompiled() {
+    for glob in GLOBS {
+      let compiled = CompiledGlob::new(glob);
+      for path in PATHS {
+        assert_eq!(
+          compiled.is_match(path),
+          glob_match(glob, path),
+          "{:?} {:?}",
+          glob,
+          path
+        );
+      }
+    }
+  }
+
+  /// Every combination of a few segments, to compare the compiled matcher
+  /// against `glob_match` on more than the hand picked cases.
+  #[test]
+  fn compiled_matches_uncompiled_exhaustive() {
+    const GLOB_SEGMENTS: &[&str] = &[
+      "a", "b", "ab", "*", "**", "?", "a*", "*b", "*a*", "a?", "[ab]", "[a-c]*", "[]a]", "!a", "",
+    ];
+    const PATH_SEGMENTS: &[&str] = &["a", "b", "ab", "ba", "abc", "aab", "]", "!a", ""];
+
+    fn combinations(segments: &[&str], max: usize) -> Vec<String> {
+      let mut all = vec![String::new()];
+      let mut last = vec![String::new()];
+      for _ in 0..max {
+        last = last
+          .iter()
+          .flat_map(|prefix| {
+            segments.iter().map(move |segment| match prefix.as_str() {
+              "" => segment.to_string(),
+              prefix => format!("{}/{}", prefix, segment),
+            })
+          })
+          .collect();
+        all.extend(last.iter().cloned());
+      }
+      all
+    }
+
+    let paths = combinations(PATH_SEGMENTS, 3);
+    let globs = combinations(GLOB_SEGMENTS, 3);
+    let negated = globs
+      .iter()
+      .map(|glob| format!("!{}", glob))
+      .collect::<Vec<_>>();
+    for glob in globs.iter().chain(&negated) {
+      let compiled = CompiledGlob::new(glob);
+      for path in &paths {
+        assert_eq!(
+          compiled.is_match(path),
+          glob_match(glob, path),
+          "{:?} {:?}",
+          glob,
+          path
+        );
+      }
+    }
+  }
+
+  #[test]
+  fn set_matches_uncompiled() {
+    let set = GlobSet::new(GLOBS);
+    assert_eq!(set.len(), GLOBS.len());
+    for path in PATHS {
+      let expected = GLOBS
+        .iter()
+        .enumerate()
+        .filter(|(_, glob)| glob_match(glob, path) == Some(true))
+        .map(|(index, _)| index)
+        .collect::<Vec<_>>();
+      assert_eq!(set.matches(path), expected, "{:?}", path);
+      assert_eq!(set.is_match(path), !expected.is_empty(), "{:?}", path);
+    }
+  }
+
+  #[test]
+  fn empty_set() {
+    let set = GlobSet::default();
+    assert!(set.is_empty());
+    assert!(set.matches("a").is_empty());
+    assert!(!set.is_match("a"));
+  }
+}
@@ -1,5 +1,9 @@
+mod compiled;
+
 use std::{ops::Range, path::is_separator};
 
+pub use compiled::{CompiledGlob, GlobSet};
+
 #[derive(Clone, Copy, Debug, Default)]
 struct State {
   // These store character indices into the glob and path strings.
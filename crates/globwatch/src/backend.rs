@@ -0,0 +1,216 @@
+//! Selection of the notify backend used to receive filesystem events.
+
+use std::{fmt, path::Path, str::FromStr, time::Duration};
+
+use notify::{
+    Config, Error, EventHandler, PollWatcher, RecommendedWatcher, RecursiveMode, Watcher,
+    WatcherKind,
+};
+
+/// How often `Backend::Poll` scans the watched paths for changes by default.
+/// A flush takes up to one interval to be observed.
+pub const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How often `Backend::PollContents` scans the watched paths for changes by
+/// default. Every scan reads every watched file, so this is much longer than
+/// `POLL_INTERVAL`.
+pub const CONTENT_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The strategy used to receive filesystem events.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Backend {
+    /// Use the native backend, falling back to `Poll` if it turns out not to
+    /// deliver events for the watched filesystem.
+    #[default]
+    Auto,
+    /// The platform's native event API, e.g. inotify or FSEvents.
+    Native,
+    /// Periodically scan the watched paths, comparing file modification times
+    /// and sizes.
+    Poll,
+    /// Periodically scan the watched paths, comparing file contents. This is
+    /// slower than `Poll`, but notices changes that preserve modification
+    /// times.
+    PollContents,
+}
+
+impl Backend {
+    /// The interval used when polling with this backend, unless one is
+    /// configured. `Auto` falls back to `Poll`.
+    pub fn default_poll_interval(&self) -> Duration {
+        match self {
+            Backend::PollContents => CONTENT_POLL_INTERVAL,
+            Backend::Auto | Backend::Native | Backend::Poll => POLL_INTERVAL,
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Backend::Auto => "auto",
+            Backend::Native => "native",
+            Backend::Poll => "poll",
+            Backend::PollContents => "poll-contents",
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The string is not the name of a backend.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseBackendError(String);
+
+impl fmt::Display for ParseBackendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown watcher backend '{}', expected one of auto, native, poll or poll-contents",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for ParseBackendError {}
+
+impl FromStr for Backend {
+    type Err = ParseBackendError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [
+            Backend::Auto,
+            Backend::Native,
+            Backend::Poll,
+            Backend::PollContents,
+        ]
+        .into_iter()
+        .find(|backend| backend.as_str().eq_ignore_ascii_case(s.trim()))
+        .ok_or_else(|| ParseBackendError(s.to_string()))
+    }
+}
+
+/// A watcher whose backend is chosen at runtime.
+#[derive(Debug)]
+pub enum BackendWatcher {
+    /// The platform's native watcher.
+    Native(RecommendedWatcher),
+    /// A watcher that scans the filesystem on an interval.
+    Poll {
+        /// the underlying watcher
+        watcher: PollWatcher,
+        /// whether file contents are compared, rather than metadata
+        compare_contents: bool,
+    },
+}
+
+impl BackendWatcher {
+    /// Creates a watcher for the given backend. `Backend::Auto` starts out
+    /// with the native watcher. `poll_interval` is only used by the polling
+    /// backends.
+    pub fn with_backend<F: EventHandler>(
+        backend: Backend,
+        poll_interval: Duration,
+        event_handler: F,
+    ) -> Result<Self, Error> {
+        match backend {
+            Backend::Auto | Backend::Native => Ok(Self::Native(RecommendedWatcher::new(
+                event_handler,
+                Config::default(),
+            )?)),
+            Backend::Poll | Backend::PollContents => {
+                let compare_contents = backend == Backend::PollContents;
+                let config = Config::default()
+                    .with_poll_interval(poll_interval)
+                    .with_compare_contents(compare_contents);
+                Ok(Self::Poll {
+                    watcher: PollWatcher::new(event_handler, config)?,
+                    compare_contents,
+                })
+            }
+        }
+    }
+
+    /// The backend in use. This is never `Backend::Auto`.
+    pub fn backend(&self) -> Backend {
+        match self {
+            Self::Native(_) => Backend::Native,
+            Self::Poll {
+                compare_contents: false,
+                ..
+            } => Backend::Poll,
+            Self::Poll {
+                compare_contents: true,
+                ..
+            } => Backend::PollContents,
+        }
+    }
+}
+
+impl Watcher for BackendWatcher {
+    fn new<F: EventHandler>(event_handler: F, config: Config) -> Result<Self, Error> {
+        Ok(Self::Native(RecommendedWatcher::new(
+            event_handler,
+            config,
+        )?))
+    }
+
+    fn watch(&mut self, path: &Path, recursive_mode: RecursiveMode) -> Result<(), Error> {
+        match self {
+            Self::Native(watcher) => watcher.watch(path, recursive_mode),
+            Self::Poll { watcher, .. } => watcher.watch(path, recursive_mode),
+        }
+    }
+
+    fn unwatch(&mut self, path: &Path) -> Result<(), Error> {
+        match self {
+            Self::Native(watcher) => watcher.unwatch(path),
+            Self::Poll { watcher, .. } => watcher.unwatch(path),
+        }
+    }
+
+    fn configure(&mut self, option: Config) -> Result<bool, Error> {
+        match self {
+            Self::Native(watcher) => watcher.configure(option),
+            Self::Poll { watcher, .. } => watcher.configure(option),
+        }
+    }
+
+    /// The kind of the native watcher, as the backend isn't known statically.
+    /// Use `BackendWatcher::backend` to find out which one is in use.
+    fn kind() -> WatcherKind {
+        RecommendedWatcher::kind()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Backend, CONTENT_POLL_INTERVAL, POLL_INTERVAL};
+
+    #[test]
+    fn test_parse_backend() {
+        for backend in [
+            Backend::Auto,
+            Backend::Native,
+            Backend::Poll,
+            Backend::PollContents,
+        ] {
+            assert_eq!(backend.to_string().parse::<Backend>(), Ok(backend));
+        }
+        assert_eq!(" Poll ".parse::<Backend>(), Ok(Backend::Poll));
+        assert!("inotify".parse::<Backend>().is_err());
+    }
+
+    #[test]
+    fn test_default_poll_interval() {
+        assert_eq!(Backend::Auto.default_poll_interval(), POLL_INTERVAL);
+        assert_eq!(Backend::Poll.default_poll_interval(), POLL_INTERVAL);
+        assert!(Backend::PollContents.default_poll_interval() > POLL_INTERVAL);
+        assert_eq!(
+            Backend::PollContents.default_poll_interval(),
+            CONTENT_POLL_INTERVAL
+        );
+    }
+}
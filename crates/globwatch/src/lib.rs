@@ -8,6 +8,15 @@
 //! for a file if we are not 'up to date'. The flushing mechanism allows us to
 //! watch for a full round trip through the filesystem to ensure the watcher is
 //! up to date.
+//!
+//! ## Backends
+//!
+//! Native event APIs don't deliver events on every filesystem: network
+//! drives, Docker bind mounts and WSL-mounted drives commonly stay silent. The
+//! watcher can instead poll the filesystem, see [`Backend`]. With
+//! `Backend::Auto`, a native watcher whose flushes repeatedly fail to observe
+//! their own writes is replaced by a polling one. The flush directory has to
+//! be on the same filesystem as the watched paths for this to be detected.
 
 #![deny(
     missing_docs,
@@ -19,16 +28,23 @@
 )]
 #![feature(drain_filter)]
 
+mod backend;
+
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
+    fmt,
     fs::File,
     path::{Path, PathBuf},
     sync::{
-        atomic::{AtomicU64, Ordering},
+        atomic::{AtomicU64, AtomicUsize, Ordering},
         Arc, Mutex,
     },
+    time::Duration,
 };
 
+pub use backend::{
+    Backend, BackendWatcher, ParseBackendError, CONTENT_POLL_INTERVAL, POLL_INTERVAL,
+};
 use futures::{channel::oneshot, future::Either, Stream, StreamExt as _};
 use itertools::Itertools;
 use merge_streams::MergeStreams;
@@ -52,36 +68,49 @@ impl GlobWatcher {
     /// storage when flushing file events. For more information on flushing,
     /// see the module-level documentation.
     #[tracing::instrument]
-    pub fn new(
+    pub fn new(flush_dir: PathBuf) -> Result<(Self, WatchConfig<BackendWatcher>), Error> {
+        Self::with_backend(flush_dir, Backend::Auto, None)
+    }
+
+    /// Create a new watcher that receives events using the given backend. The
+    /// polling backends scan the filesystem every `poll_interval`, defaulting
+    /// to `Backend::default_poll_interval`.
+    #[tracing::instrument]
+    pub fn with_backend(
         flush_dir: PathBuf,
-    ) -> Result<(Self, WatchConfig<notify::RecommendedWatcher>), Error> {
+        backend: Backend,
+        poll_interval: Option<Duration>,
+    ) -> Result<(Self, WatchConfig<BackendWatcher>), Error> {
         let (send_event, receive_event) = tokio::sync::mpsc::unbounded_channel();
         let (send_config, receive_config) = tokio::sync::mpsc::unbounded_channel();
 
         // even if this fails, we may still be able to continue
         std::fs::create_dir_all(&flush_dir).ok();
         let flush_dir = flush_dir.canonicalize()?;
-
-        let watcher = notify::recommended_watcher(move |event: Result<Event, Error>| {
-            let span = span!(tracing::Level::TRACE, "watcher");
-            let _ = span.enter();
-
-            let result = event.map(|e| {
-                trace!(parent: &span, "sending event: {:?}", e);
-                let tx = send_event.clone();
-                futures::executor::block_on(async move { tx.send(e) })
-            });
-
-            match result {
-                Ok(Ok(_)) => {}
-                Ok(Err(e)) => {
-                    warn!(parent: &span, "watch server closed: {:?}", e);
-                }
-                Err(e) => {
-                    warn!(parent: &span, "error from notify: {:?}", e);
-                }
+        // flush files left behind by a previous watcher, e.g. one whose
+        // flushes were never observed
+        if let Ok(entries) = std::fs::read_dir(&flush_dir) {
+            for entry in entries.flatten() {
+                std::fs::remove_file(entry.path()).ok();
             }
-        })?;
+        }
+
+        let poll_interval = poll_interval.unwrap_or_else(|| backend.default_poll_interval());
+        let watcher = BackendWatcher::with_backend(
+            backend,
+            poll_interval,
+            event_handler(send_event.clone()),
+        )?;
+        let active_backend = watcher.backend();
+        let fallback = (backend == Backend::Auto).then(|| {
+            Arc::new(move || {
+                BackendWatcher::with_backend(
+                    Backend::Poll,
+                    poll_interval,
+                    event_handler(send_event.clone()),
+                )
+            }) as Fallback<BackendWatcher>
+        });
 
         let watcher = Arc::new(Mutex::new(watcher));
 
@@ -104,18 +133,50 @@ impl GlobWatcher {
 
         Ok((
             Self {
-                flush_dir,
+                flush_dir: flush_dir.clone(),
                 stream: receive_event,
                 config: receive_config,
             },
             WatchConfig {
                 flush: send_config,
                 watcher,
+                flush_dir,
+                watched: Default::default(),
+                backend: Arc::new(Mutex::new(active_backend)),
+                poll_interval,
+                fallback,
+                flush_timeouts: Default::default(),
             },
         ))
     }
 }
 
+/// builds the callback that forwards events from notify to the stream
+fn event_handler(
+    send_event: UnboundedSender<Event>,
+) -> impl FnMut(Result<Event, Error>) + Send + 'static {
+    move |event: Result<Event, Error>| {
+        let span = span!(tracing::Level::TRACE, "watcher");
+        let _ = span.enter();
+
+        let result = event.map(|e| {
+            trace!(parent: &span, "sending event: {:?}", e);
+            let tx = send_event.clone();
+            futures::executor::block_on(async move { tx.send(e) })
+        });
+
+        match result {
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => {
+                warn!(parent: &span, "watch server closed: {:?}", e);
+            }
+            Err(e) => {
+                warn!(parent: &span, "error from notify: {:?}", e);
+            }
+        }
+    }
+}
+
 impl GlobWatcher {
     /// Convert the watcher into a stream of events, handling config changes and
     /// flushing transparently.
@@ -171,6 +232,10 @@ impl GlobWatcher {
                                         .expect("only fails if holder panics")
                                         .remove(&flush_id)
                                     {
+                                        // the flush has been seen, so the file is no longer
+                                        // needed. the event for its removal is dropped above.
+                                        std::fs::remove_file(flush_dir.join(flush_id.to_string()))
+                                            .ok();
                                         // if this fails, it just means the requestor has gone away
                                         // and we can ignore it
                                         tx.send(()).ok();
@@ -221,11 +286,41 @@ pub enum WatcherCommand {
     Flush(oneshot::Sender<()>),
 }
 
+/// Builds the watcher to switch to when the current one isn't delivering
+/// events.
+type Fallback<T> = Arc<dyn Fn() -> Result<T, Error> + Send + Sync>;
+
+/// The number of consecutive flush timeouts after which a native watcher is
+/// assumed not to deliver events. A single timeout can be caused by a busy
+/// filesystem.
+const FALLBACK_AFTER_TIMEOUTS: usize = 3;
+
 /// A sender for watcher configuration changes.
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct WatchConfig<T: Watcher> {
     flush: UnboundedSender<WatcherCommand>,
     watcher: Arc<Mutex<T>>,
+    flush_dir: PathBuf,
+    /// the paths registered with the watcher, so that they can be moved to a
+    /// fallback watcher
+    watched: Arc<Mutex<HashSet<PathBuf>>>,
+    backend: Arc<Mutex<Backend>>,
+    /// how often the polling backends scan the filesystem
+    poll_interval: Duration,
+    fallback: Option<Fallback<T>>,
+    /// the number of flushes in a row that have timed out
+    flush_timeouts: Arc<AtomicUsize>,
+}
+
+impl<T: Watcher> fmt::Debug for WatchConfig<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WatchConfig")
+            .field("flush_dir", &self.flush_dir)
+            .field("backend", &self.backend())
+            .field("poll_interval", &self.poll_interval)
+            .field("fallback", &self.fallback.is_some())
+            .finish_non_exhaustive()
+    }
 }
 
 /// The server is no longer running.
@@ -235,6 +330,8 @@ pub enum ConfigError {
     ServerStopped,
     /// Watch error
     WatchError(Vec<notify::Error>),
+    /// The watcher didn't observe the flush in time.
+    FlushTimeout,
 }
 
 impl<T: Watcher> WatchConfig<T> {
@@ -248,10 +345,16 @@ impl<T: Watcher> WatchConfig<T> {
             .map(|p| relative_to.join(p))
             .map(|p| {
                 trace!("watching {:?}", p);
-                self.watcher
+                let result = self
+                    .watcher
                     .lock()
                     .expect("only fails if poisoned")
-                    .watch(&p, notify::RecursiveMode::Recursive)
+                    .watch(&p, notify::RecursiveMode::Recursive);
+                self.watched
+                    .lock()
+                    .expect("only fails if poisoned")
+                    .insert(p);
+                result
             })
             .map(|r| match r {
                 Ok(()) => Ok(()),
@@ -299,9 +402,18 @@ impl<T: Watcher> WatchConfig<T> {
                 .expect("only fails if poisoned")
                 .unwatch(&p)
                 .ok();
+            self.watched
+                .lock()
+                .expect("only fails if poisoned")
+                .remove(&p);
         }
     }
 
+    /// The backend that is currently delivering events.
+    pub fn backend(&self) -> Backend {
+        *self.backend.lock().expect("only fails if poisoned")
+    }
+
     /// Await a full filesystem flush from the watcher.
     pub async fn flush(&self) -> Result<(), ConfigError> {
         let (tx, rx) = oneshot::channel();
@@ -310,6 +422,75 @@ impl<T: Watcher> WatchConfig<T> {
             .map_err(|_| ConfigError::ServerStopped)?;
         rx.await.map_err(|_| ConfigError::ServerStopped)
     }
+
+    /// Await a full filesystem flush from the watcher, giving up after
+    /// `timeout`.
+    pub async fn flush_timeout(&self, timeout: Duration) -> Result<(), ConfigError> {
+        tokio::time::timeout(timeout, self.flush())
+            .await
+            .map_err(|_| ConfigError::FlushTimeout)?
+    }
+
+    /// How often the polling backends scan the filesystem.
+    pub fn poll_interval(&self) -> Duration {
+        self.poll_interval
+    }
+
+    /// Await a full filesystem flush like `flush_timeout`. A polling watcher
+    /// takes up to one poll interval to observe a flush, which is added to
+    /// `timeout`. If the backend was chosen automatically and the native
+    /// watcher keeps failing to observe flushes, it is replaced by a polling
+    /// watcher. Returns whether the watcher was replaced, in which case events
+    /// may have been missed.
+    pub async fn flush_or_fall_back(&self, timeout: Duration) -> Result<bool, ConfigError> {
+        let result = match self.backend() {
+            Backend::Native => self.flush_timeout(timeout).await,
+            _ => self.flush_timeout(timeout + self.poll_interval).await,
+        };
+        let fallback = match (&result, &self.fallback) {
+            (Err(ConfigError::FlushTimeout), Some(fallback))
+                if self.backend() == Backend::Native =>
+            {
+                fallback
+            }
+            _ => {
+                if result.is_ok() {
+                    self.flush_timeouts.store(0, Ordering::SeqCst);
+                }
+                return result.map(|_| false);
+            }
+        };
+
+        if self.flush_timeouts.fetch_add(1, Ordering::SeqCst) + 1 < FALLBACK_AFTER_TIMEOUTS {
+            return result.map(|_| false);
+        }
+
+        warn!(
+            "the native watcher isn't delivering events for {}, falling back to polling every {:?}",
+            self.flush_dir.display(),
+            self.poll_interval
+        );
+        let watcher = fallback().map_err(|e| ConfigError::WatchError(vec![e]))?;
+        self.replace_watcher(watcher, Backend::Poll);
+        self.flush_timeouts.store(0, Ordering::SeqCst);
+
+        // the polling watcher picks up the flush file on its first scan
+        self.flush_timeout(timeout + self.poll_interval).await?;
+        Ok(true)
+    }
+
+    /// registers every watched path with a new watcher and swaps it in
+    fn replace_watcher(&self, mut watcher: T, backend: Backend) {
+        let watched = self.watched.lock().expect("only fails if poisoned");
+        for path in std::iter::once(&self.flush_dir).chain(watched.iter()) {
+            // paths that don't exist are expected, see `include`
+            if let Err(e) = watcher.watch(path, notify::RecursiveMode::Recursive) {
+                trace!("failed to watch {:?}: {}", path, e);
+            }
+        }
+        *self.watcher.lock().expect("only fails if poisoned") = watcher;
+        *self.backend.lock().expect("only fails if poisoned") = backend;
+    }
 }
 
 #[derive(PartialEq, Eq, Debug)]
@@ -479,12 +660,94 @@ fn glob_to_symbols(glob: &str) -> impl Iterator<Item = GlobSymbol> {
 }
 
 #[cfg(test)]
+#[allow(clippy::unwrap_used)]
 mod test {
-    use std::path::PathBuf;
+    use std::{path::PathBuf, time::Duration};
 
+    use futures::StreamExt;
     use test_case::test_case;
 
-    use super::GlobSymbol::*;
+    use super::{Backend, GlobSymbol::*, GlobWatcher, StopSource, POLL_INTERVAL};
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_poll_backend() {
+        let dir = tempdir::TempDir::new("globwatch-poll").unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::create_dir_all(root.join("watched")).unwrap();
+
+        let (watcher, config) =
+            GlobWatcher::with_backend(root.join("flush"), Backend::Poll, None).unwrap();
+        assert_eq!(config.backend(), Backend::Poll);
+        assert_eq!(config.poll_interval(), POLL_INTERVAL);
+        let stop = StopSource::new();
+        let mut stream = watcher.into_stream(stop.token());
+        config.include(&root, "watched/**").await.unwrap();
+
+        // the stream has to be polled for flushes to complete
+        let file = root.join("watched/file");
+        let expected = file.clone();
+        let events = tokio::spawn(async move {
+            while let Some(Ok(event)) = stream.next().await {
+                if event.paths.contains(&expected) {
+                    return true;
+                }
+            }
+            false
+        });
+        // a flush completing means a scan has happened since `include`. the
+        // flush directory is registered in the background, so a flush file
+        // created before that is part of the first scan and never reported,
+        // and the flush has to be retried.
+        let mut flushed = false;
+        for _ in 0..10 {
+            if config.flush_timeout(POLL_INTERVAL * 4).await.is_ok() {
+                flushed = true;
+                break;
+            }
+        }
+        assert!(flushed, "the polling watcher never observed a flush");
+
+        std::fs::write(file, "contents").unwrap();
+        let seen = tokio::time::timeout(POLL_INTERVAL * 20, events)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(seen);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_flush_files_removed() {
+        let dir = tempdir::TempDir::new("globwatch-flush").unwrap();
+        let flush_dir = dir.path().canonicalize().unwrap().join("flush");
+        std::fs::create_dir_all(&flush_dir).unwrap();
+        std::fs::write(flush_dir.join("1"), "").unwrap();
+
+        let (watcher, config) =
+            GlobWatcher::with_backend(flush_dir.clone(), Backend::Native, None).unwrap();
+        // files from a previous watcher are cleaned up
+        assert_eq!(std::fs::read_dir(&flush_dir).unwrap().count(), 0);
+
+        let stop = StopSource::new();
+        let mut stream = watcher.into_stream(stop.token());
+        tokio::spawn(async move { while let Some(Ok(_)) = stream.next().await {} });
+        // the flush directory is registered in the background, so early
+        // flushes may be missed. flush ids count up from 1.
+        let observed = {
+            let mut observed = None;
+            for id in 1..=10 {
+                if config
+                    .flush_timeout(Duration::from_millis(500))
+                    .await
+                    .is_ok()
+                {
+                    observed = Some(id);
+                    break;
+                }
+            }
+            observed.expect("the watcher never observed a flush")
+        };
+        assert!(!flush_dir.join(observed.to_string()).exists());
+    }
 
     #[test_case("foo/**", vec!["foo"])]
     #[test_case("foo/{a,b}", vec!["foo"])]
@@ -1,9 +1,10 @@
 use std::{
     convert::TryInto,
-    fs,
-    io::{Read, Write},
-    path::PathBuf,
-    process,
+    fs, io,
+    io::{Read, Seek, Write},
+    path::{Path, PathBuf},
+    process, thread,
+    time::{Duration, Instant},
 };
 
 use log::warn;
@@ -21,6 +22,10 @@ pub enum PidlockError {
     /// The lock is already owned by a running process
     #[error("already owned")]
     AlreadyOwned,
+    /// The OS-level file lock could not be taken or the pid file could not be
+    /// written
+    #[error("unable to lock \"{0}\": {1}")]
+    LockFailed(PathBuf, String),
 }
 
 /// A result from a Pidlock operation
@@ -70,9 +75,215 @@ fn process_exists(pid: i32) -> bool {
     }
 }
 
+/// Returns an opaque value identifying when a process started, so that a
+/// recycled pid can be told apart from the process that wrote a pid file.
+/// Values are only comparable with each other on the same machine.
+fn process_start_time(pid: i32) -> Option<u64> {
+    #[cfg(target_os = "linux")]
+    {
+        let stat = fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+        // the command name may contain spaces and parentheses, so skip past
+        // it. starttime is the 22nd field, and the 20th after the name.
+        let (_, fields) = stat.rsplit_once(')')?;
+        fields.split_whitespace().nth(19)?.parse().ok()
+    }
+
+    #[cfg(target_os = "macos")]
+    unsafe {
+        let mut info: libc::proc_bsdinfo = std::mem::zeroed();
+        let size = std::mem::size_of::<libc::proc_bsdinfo>() as libc::c_int;
+        let written = libc::proc_pidinfo(
+            pid,
+            libc::PROC_PIDTBSDINFO,
+            0,
+            &mut info as *mut libc::proc_bsdinfo as *mut libc::c_void,
+            size,
+        );
+        (written == size).then(|| info.pbi_start_tvsec * 1_000_000 + info.pbi_start_tvusec)
+    }
+
+    #[cfg(target_os = "windows")]
+    unsafe {
+        use windows_sys::Win32::{
+            Foundation::{CloseHandle, FILETIME},
+            System::Threading::{GetProcessTimes, OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION},
+        };
+        let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid as u32);
+        if handle == 0 {
+            return None;
+        }
+        let mut times = [FILETIME {
+            dwLowDateTime: 0,
+            dwHighDateTime: 0,
+        }; 4];
+        let [creation, exit, kernel, user] = &mut times;
+        let result = GetProcessTimes(handle, creation, exit, kernel, user);
+        CloseHandle(handle);
+        (result != 0)
+            .then(|| (times[0].dwHighDateTime as u64) << 32 | times[0].dwLowDateTime as u64)
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+    {
+        let _ = pid;
+        None
+    }
+}
+
+/// Takes an OS-level advisory lock on the file without blocking. Returns
+/// false if another open file holds a conflicting lock. The lock is released
+/// when the file is closed.
+fn try_lock_file(file: &fs::File, exclusive: bool) -> io::Result<bool> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::io::AsRawFd;
+        let operation = if exclusive {
+            libc::LOCK_EX
+        } else {
+            libc::LOCK_SH
+        };
+        if unsafe { libc::flock(file.as_raw_fd(), operation | libc::LOCK_NB) } == 0 {
+            return Ok(true);
+        }
+        let error = io::Error::last_os_error();
+        if error.kind() == io::ErrorKind::WouldBlock {
+            Ok(false)
+        } else {
+            Err(error)
+        }
+    }
+
+    #[cfg(windows)]
+    unsafe {
+        use std::os::windows::io::AsRawHandle;
+
+        use windows_sys::Win32::{
+            Foundation::ERROR_LOCK_VIOLATION,
+            Storage::FileSystem::{LockFileEx, LOCKFILE_EXCLUSIVE_LOCK, LOCKFILE_FAIL_IMMEDIATELY},
+            System::IO::OVERLAPPED,
+        };
+        // locks on windows are mandatory, so lock a byte far past the end of
+        // the file to keep the pid readable by other processes
+        let mut overlapped: OVERLAPPED = std::mem::zeroed();
+        overlapped.Anonymous.Anonymous.OffsetHigh = u32::MAX;
+        let mut flags = LOCKFILE_FAIL_IMMEDIATELY;
+        if exclusive {
+            flags |= LOCKFILE_EXCLUSIVE_LOCK;
+        }
+        if LockFileEx(file.as_raw_handle() as _, flags, 0, 1, 0, &mut overlapped) != 0 {
+            return Ok(true);
+        }
+        let error = io::Error::last_os_error();
+        if error.raw_os_error() == Some(ERROR_LOCK_VIOLATION as i32) {
+            Ok(false)
+        } else {
+            Err(error)
+        }
+    }
+}
+
+/// Takes an exclusive lock on the file, retrying briefly in case the lock is
+/// only held by another process probing for the owner.
+fn lock_file_exclusive(file: &fs::File) -> io::Result<bool> {
+    for attempt in 1..=FILE_LOCK_PROBE_ATTEMPTS {
+        if try_lock_file(file, true)? {
+            return Ok(true);
+        }
+        if attempt < FILE_LOCK_PROBE_ATTEMPTS {
+            thread::sleep(FILE_LOCK_PROBE_DELAY);
+        }
+    }
+    Ok(false)
+}
+
+/// Whether an open file is still the one at `path`, rather than one that has
+/// since been removed or replaced.
+fn is_same_file(file: &fs::File, path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        match (file.metadata(), fs::metadata(path)) {
+            (Ok(open), Ok(current)) => open.dev() == current.dev() && open.ino() == current.ino(),
+            _ => false,
+        }
+    }
+
+    // files can't be replaced while they are open on windows
+    #[cfg(not(unix))]
+    {
+        let _ = (file, path);
+        true
+    }
+}
+
+/// The contents of a pid file: the pid, optionally followed by the start time
+/// of the process on the next line.
+#[derive(Debug, PartialEq)]
+struct PidFileContents {
+    pid: i32,
+    start_time: Option<u64>,
+}
+
+impl PidFileContents {
+    fn current() -> Self {
+        let pid = process::id() as i32;
+        PidFileContents {
+            pid,
+            start_time: process_start_time(pid),
+        }
+    }
+
+    fn parse(contents: &str) -> Option<Self> {
+        let mut lines = contents.lines();
+        let pid = lines.next()?.trim().parse().ok()?;
+        let start_time = match lines.next().map(str::trim) {
+            None | Some("") => None,
+            Some(start_time) => Some(start_time.parse().ok()?),
+        };
+        Some(PidFileContents { pid, start_time })
+    }
+
+    fn to_contents(&self) -> String {
+        match self.start_time {
+            Some(start_time) => format!("{}\n{}", self.pid, start_time),
+            None => format!("{}", self.pid),
+        }
+    }
+
+    /// Whether the process that wrote the file is still running, as opposed
+    /// to a different process that has been given the same pid.
+    fn is_running(&self) -> bool {
+        process_exists(self.pid)
+            && match (self.start_time, process_start_time(self.pid)) {
+                (Some(recorded), Some(actual)) => recorded == actual,
+                _ => true,
+            }
+    }
+}
+
+/// Number of times to retry opening the pid file when the previous owner
+/// removes it while we are waiting for its lock
+const FILE_LOCK_ATTEMPTS: usize = 3;
+
+/// Number of times to try taking the file lock before deciding it is owned.
+/// `Pidlock::get_owner` briefly holds a shared lock to probe for the owner,
+/// which must not make a concurrent `acquire` fail.
+const FILE_LOCK_PROBE_ATTEMPTS: usize = 5;
+
+/// Delay between attempts to take the file lock
+const FILE_LOCK_PROBE_DELAY: Duration = Duration::from_millis(2);
+
+/// Upper bound on the delay between attempts in `Pidlock::acquire_timeout`
+const MAX_RETRY_DELAY: Duration = Duration::from_millis(100);
+
 /// A pid-centered lock. A lock is considered "acquired" when a file exists on
 /// disk at the path specified, containing the process id of the locking
 /// process.
+///
+/// By default a lock is stale when its process no longer exists. That breaks
+/// down when pids are recycled, or when the lock file is shared between pid
+/// namespaces, e.g. containers. `with_file_lock` additionally holds an OS-level
+/// file lock on the pid file, which the OS releases when the owner exits.
 pub struct Pidlock {
     /// The current process id
     pid: u32,
@@ -80,6 +291,10 @@ pub struct Pidlock {
     path: PathBuf,
     /// Current state of the Pidlock
     state: PidlockState,
+    /// Whether the pid file is paired with an OS-level file lock
+    file_lock: bool,
+    /// The open pid file, which holds the file lock while acquired
+    file: Option<fs::File>,
 }
 
 impl Pidlock {
@@ -89,6 +304,39 @@ impl Pidlock {
             pid: process::id(),
             path,
             state: PidlockState::New,
+            file_lock: false,
+            file: None,
+        }
+    }
+
+    /// Pairs the pid file with an OS-level file lock (`flock`, or `LockFileEx`
+    /// on windows), and records the start time of the process next to its
+    /// pid. Ownership is then decided by the file lock rather than by
+    /// whether the pid exists, so stale lock files are recovered safely.
+    ///
+    /// Pid files without a start time were written without a file lock, and
+    /// are still considered owned while their process is running.
+    pub fn with_file_lock(mut self) -> Self {
+        self.file_lock = true;
+        self
+    }
+
+    /// Acquire a lock, retrying until `timeout` has passed if it is owned by
+    /// another process. Returns the last error if the lock could not be
+    /// acquired in time.
+    pub fn acquire_timeout(&mut self, timeout: Duration) -> PidlockResult {
+        let deadline = Instant::now() + timeout;
+        let mut delay = Duration::from_millis(10);
+        loop {
+            match self.acquire() {
+                Err(PidlockError::AlreadyOwned | PidlockError::LockExists(_))
+                    if Instant::now() < deadline =>
+                {
+                    thread::sleep(delay.min(deadline.saturating_duration_since(Instant::now())));
+                    delay = (delay * 2).min(MAX_RETRY_DELAY);
+                }
+                result => return result,
+            }
         }
     }
 
@@ -101,6 +349,10 @@ impl Pidlock {
             }
         }
 
+        if self.file_lock {
+            return self.acquire_file_lock();
+        }
+
         // acquiring something with a valid owner is an error
         if self.get_owner().is_some() {
             return Err(PidlockError::AlreadyOwned);
@@ -128,6 +380,64 @@ impl Pidlock {
         Ok(())
     }
 
+    fn acquire_file_lock(&mut self) -> PidlockResult {
+        if let Some(p) = self.path.parent() {
+            // even if this fails, the next call might not
+            std::fs::create_dir_all(p).ok();
+        }
+
+        for _ in 0..FILE_LOCK_ATTEMPTS {
+            let mut file = match fs::OpenOptions::new()
+                .create(true)
+                .read(true)
+                .write(true)
+                .open(&self.path)
+            {
+                Ok(file) => file,
+                Err(_) => {
+                    return Err(PidlockError::LockExists(self.path.clone()));
+                }
+            };
+            match lock_file_exclusive(&file) {
+                Ok(true) => {}
+                Ok(false) => return Err(PidlockError::AlreadyOwned),
+                Err(e) => return Err(PidlockError::LockFailed(self.path.clone(), e.to_string())),
+            }
+            // the previous owner removes the file before releasing its lock,
+            // so we may have locked a file that nobody else will open
+            if !is_same_file(&file, &self.path) {
+                continue;
+            }
+
+            // nobody holds the file lock, so any previous owner has exited,
+            // unless it didn't take a file lock at all
+            let mut contents = String::new();
+            file.read_to_string(&mut contents).ok();
+            match PidFileContents::parse(&contents) {
+                Some(previous) if previous.start_time.is_none() && previous.is_running() => {
+                    return Err(PidlockError::AlreadyOwned);
+                }
+                Some(_) => warn!("recovering stale pid file at {:?}", self.path),
+                None => {}
+            }
+
+            let contents = PidFileContents::current().to_contents();
+            if let Err(e) = file
+                .set_len(0)
+                .and_then(|_| file.rewind())
+                .and_then(|_| file.write_all(contents.as_bytes()))
+            {
+                return Err(PidlockError::LockFailed(self.path.clone(), e.to_string()));
+            }
+
+            self.file = Some(file);
+            self.state = PidlockState::Acquired;
+            return Ok(());
+        }
+
+        Err(PidlockError::AlreadyOwned)
+    }
+
     /// Returns true when the lock is in an acquired state.
     pub fn locked(&self) -> bool {
         self.state == PidlockState::Acquired
@@ -143,6 +453,9 @@ impl Pidlock {
         }
 
         fs::remove_file(self.path.clone()).unwrap();
+        // the file lock is released after removing the file, so anyone
+        // waiting on it notices that the file is gone
+        self.file = None;
 
         self.state = PidlockState::Released;
         Ok(())
@@ -150,7 +463,8 @@ impl Pidlock {
 
     /// Gets the owner of this lockfile, returning the pid. If the lock file
     /// doesn't exist, or the specified pid is not a valid process id on the
-    /// system, it clears it.
+    /// system, it clears it. When using a file lock, the pid is only returned
+    /// while the file lock is held.
     pub fn get_owner(&self) -> Option<u32> {
         let mut file = match fs::OpenOptions::new().read(true).open(self.path.clone()) {
             Ok(file) => file,
@@ -165,15 +479,30 @@ impl Pidlock {
             return None;
         }
 
-        match contents.trim().parse::<i32>() {
-            Ok(pid) if process_exists(pid) => {
-                Some(pid.try_into().expect("if a pid exists it is a valid u32"))
+        // probe for the file lock after reading, and close the file straight
+        // away to release the shared lock the probe may take. `acquire`
+        // retries while a probe holds it.
+        let lock_held = self.file_lock && matches!(try_lock_file(&file, false), Ok(false));
+        drop(file);
+
+        match PidFileContents::parse(&contents) {
+            Some(contents)
+                if lock_held
+                    || (!self.file_lock || contents.start_time.is_none())
+                        && contents.is_running() =>
+            {
+                Some(
+                    contents
+                        .pid
+                        .try_into()
+                        .expect("if a pid exists it is a valid u32"),
+                )
             }
-            Ok(_) => {
+            Some(_) => {
                 warn!("stale pid file at {:?}", self.path);
                 None
             }
-            Err(_) => {
+            None => {
                 warn!("nonnumeric pid file at {:?}", self.path);
                 None
             }
@@ -191,11 +520,17 @@ impl Drop for Pidlock {
 
 #[cfg(test)]
 mod tests {
-    use std::{fs, io::Write, path::PathBuf};
+    use std::{
+        fs,
+        io::Write,
+        path::PathBuf,
+        thread,
+        time::{Duration, Instant},
+    };
 
     use rand::{distributions::Alphanumeric, thread_rng, Rng};
 
-    use super::{Pidlock, PidlockError, PidlockState};
+    use super::{try_lock_file, PidFileContents, Pidlock, PidlockError, PidlockState};
 
     // This was removed from the library itself, but retained here
     // to assert backwards compatibility with std::process::id
@@ -355,4 +690,135 @@ mod tests {
         let mut pidfile = Pidlock::new(path.clone());
         assert_eq!(pidfile.acquire(), Err(PidlockError::LockExists(path)));
     }
+
+    #[test]
+    fn test_pid_file_contents() {
+        assert_eq!(
+            PidFileContents::parse("123"),
+            Some(PidFileContents {
+                pid: 123,
+                start_time: None
+            })
+        );
+        assert_eq!(
+            PidFileContents::parse("123\n456\n"),
+            Some(PidFileContents {
+                pid: 123,
+                start_time: Some(456)
+            })
+        );
+        assert_eq!(PidFileContents::parse("123\nabc"), None);
+        assert_eq!(PidFileContents::parse(""), None);
+
+        let current = PidFileContents::current();
+        assert_eq!(
+            PidFileContents::parse(&current.to_contents()),
+            Some(current)
+        );
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+    #[test]
+    fn test_recycled_pid() {
+        use super::process_start_time;
+
+        let (_tmp, path) = make_pid_path();
+        let start_time = process_start_time(getpid() as i32).unwrap();
+        fs::write(&path, format!("{}\n{}", getpid(), start_time + 1)).unwrap();
+
+        // our pid is running, but it isn't the process that wrote the file
+        let pidfile = Pidlock::new(path.clone());
+        assert_eq!(pidfile.get_owner(), None);
+
+        fs::write(&path, format!("{}\n{}", getpid(), start_time)).unwrap();
+        assert_eq!(pidfile.get_owner(), Some(getpid()));
+    }
+
+    #[test]
+    fn test_file_lock_acquire_and_release() {
+        let (_tmp, path) = make_pid_path();
+        let mut pidfile = Pidlock::new(path.clone()).with_file_lock();
+        pidfile.acquire().unwrap();
+        assert!(pidfile.locked());
+
+        let contents = PidFileContents::parse(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(contents, PidFileContents::current());
+
+        let mut other = Pidlock::new(path.clone()).with_file_lock();
+        assert_eq!(other.get_owner(), Some(getpid()));
+        assert_eq!(other.acquire(), Err(PidlockError::AlreadyOwned));
+
+        pidfile.release().unwrap();
+        assert!(!path.exists());
+        assert_eq!(other.get_owner(), None);
+        other.acquire().unwrap();
+    }
+
+    #[test]
+    fn test_file_lock_stale_pid() {
+        let (_tmp, path) = make_pid_path();
+        // the pid in the file is running, e.g. because it was recycled or the
+        // file was written in another pid namespace, but nothing holds the
+        // file lock
+        fs::write(&path, format!("{}\n{}", getpid(), u64::MAX)).unwrap();
+
+        let mut pidfile = Pidlock::new(path.clone()).with_file_lock();
+        assert_eq!(pidfile.get_owner(), None);
+        pidfile.acquire().unwrap();
+        assert_eq!(
+            PidFileContents::parse(&fs::read_to_string(&path).unwrap()),
+            Some(PidFileContents::current())
+        );
+    }
+
+    #[test]
+    fn test_file_lock_legacy_pid_file() {
+        let (_tmp, path) = make_pid_path();
+        // written without a file lock by a process that is still running
+        fs::write(&path, format!("{}", getpid())).unwrap();
+
+        let mut pidfile = Pidlock::new(path).with_file_lock();
+        assert_eq!(pidfile.get_owner(), Some(getpid()));
+        assert_eq!(pidfile.acquire(), Err(PidlockError::AlreadyOwned));
+    }
+
+    #[test]
+    fn test_file_lock_acquire_while_probed() {
+        let (_tmp, path) = make_pid_path();
+        fs::write(&path, "").unwrap();
+        // hold the shared lock that get_owner probes with for a moment
+        let probe = fs::File::open(&path).unwrap();
+        assert!(try_lock_file(&probe, false).unwrap());
+        let release = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(1));
+            drop(probe);
+        });
+
+        let mut pidfile = Pidlock::new(path).with_file_lock();
+        pidfile.acquire().unwrap();
+        release.join().unwrap();
+    }
+
+    #[test]
+    fn test_acquire_timeout() {
+        let (_tmp, path) = make_pid_path();
+        let mut owner = Pidlock::new(path.clone()).with_file_lock();
+        owner.acquire().unwrap();
+
+        let mut pidfile = Pidlock::new(path).with_file_lock();
+        let start = Instant::now();
+        assert_eq!(
+            pidfile.acquire_timeout(Duration::from_millis(50)),
+            Err(PidlockError::AlreadyOwned)
+        );
+        assert!(start.elapsed() >= Duration::from_millis(50));
+
+        let release = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100));
+            owner.release().unwrap();
+        });
+        pidfile.acquire_timeout(Duration::from_secs(5)).unwrap();
+        assert!(pidfile.locked());
+        release.join().unwrap();
+    }
 }
@@ -1,12 +1,24 @@
-use std::{env, future::Future};
+use std::{
+    env, fmt,
+    future::Future,
+    io,
+    path::{Path, PathBuf},
+};
 
 use anyhow::{anyhow, Result};
-use reqwest::StatusCode;
+use bytes::Bytes;
+use reqwest::{Body, RequestBuilder, StatusCode};
 use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio_util::io::ReaderStream;
 
 use crate::retry::retry_future;
+pub use crate::signature_authentication::{
+    ArtifactSignatureAuthenticator, SignatureError, StreamValidator, SIGNATURE_KEY_ENV_VAR,
+};
 
 mod retry;
+mod signature_authentication;
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct VerifiedSsoUser {
@@ -35,6 +47,229 @@ pub struct CachingStatusResponse {
     pub status: CachingStatus,
 }
 
+/// The error body returned by the API, e.g. when remote caching is disabled
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct APIError {
+    pub code: String,
+    pub message: String,
+}
+
+/// Returned when the remote cache refuses a request because caching isn't
+/// enabled for the team
+#[derive(Debug, Clone)]
+pub struct CacheDisabledError {
+    pub status: CachingStatus,
+    pub message: String,
+}
+
+impl fmt::Display for CacheDisabledError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for CacheDisabledError {}
+
+impl APIError {
+    fn into_cache_disabled(self) -> Result<CacheDisabledError> {
+        let status = match self.code.strip_prefix("remote_caching_") {
+            Some("disabled") => CachingStatus::Disabled,
+            Some("enabled") => CachingStatus::Enabled,
+            Some("over_limit") => CachingStatus::OverLimit,
+            Some("paused") => CachingStatus::Paused,
+            _ => return Err(anyhow!("unknown status {}: {}", self.code, self.message)),
+        };
+        Ok(CacheDisabledError {
+            status,
+            message: self.message,
+        })
+    }
+}
+
+/// Information about an artifact that is sent as headers alongside it
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ArtifactMetadata {
+    /// How long the task that produced the artifact took, in milliseconds,
+    /// from `x-artifact-duration`
+    pub duration: u64,
+    /// The signature of the artifact, from `x-artifact-tag`
+    pub tag: Option<String>,
+}
+
+impl ArtifactMetadata {
+    fn from_headers(headers: &reqwest::header::HeaderMap) -> Result<Self> {
+        let duration = match headers.get("x-artifact-duration") {
+            Some(duration) => duration
+                .to_str()
+                .ok()
+                .and_then(|duration| duration.parse().ok())
+                .ok_or_else(|| anyhow!("invalid x-artifact-duration header: {:?}", duration))?,
+            None => 0,
+        };
+        let tag = headers
+            .get("x-artifact-tag")
+            .and_then(|tag| tag.to_str().ok())
+            .map(|tag| tag.to_string());
+        Ok(Self { duration, tag })
+    }
+}
+
+/// The body of an artifact to upload. It is opened again for every attempt,
+/// so that uploads can be retried without holding the artifact in memory.
+#[derive(Debug, Clone)]
+pub enum ArtifactBody {
+    Bytes(Bytes),
+    /// A file that is streamed from disk
+    File(PathBuf),
+}
+
+impl ArtifactBody {
+    /// Opens the body for a request, returning it with its length
+    async fn open(&self) -> io::Result<(Body, u64)> {
+        match self {
+            ArtifactBody::Bytes(bytes) => Ok((Body::from(bytes.clone()), bytes.len() as u64)),
+            ArtifactBody::File(path) => {
+                let file = tokio::fs::File::open(path).await?;
+                let len = file.metadata().await?.len();
+                Ok((
+                    hyper::Body::wrap_stream(ReaderStream::new(file)).into(),
+                    len,
+                ))
+            }
+        }
+    }
+
+    /// Feeds the whole body into a validator, one chunk at a time
+    async fn sign(&self, mut validator: StreamValidator) -> io::Result<String> {
+        match self {
+            ArtifactBody::Bytes(bytes) => validator.update(bytes),
+            ArtifactBody::File(path) => {
+                let mut file = tokio::fs::File::open(path).await?;
+                let mut buffer = vec![0; 64 * 1024];
+                loop {
+                    let read = file.read(&mut buffer).await?;
+                    if read == 0 {
+                        break;
+                    }
+                    validator.update(&buffer[..read]);
+                }
+            }
+        }
+        Ok(validator.tag())
+    }
+}
+
+impl From<Bytes> for ArtifactBody {
+    fn from(bytes: Bytes) -> Self {
+        ArtifactBody::Bytes(bytes)
+    }
+}
+
+impl From<Vec<u8>> for ArtifactBody {
+    fn from(bytes: Vec<u8>) -> Self {
+        ArtifactBody::Bytes(bytes.into())
+    }
+}
+
+impl From<PathBuf> for ArtifactBody {
+    fn from(path: PathBuf) -> Self {
+        ArtifactBody::File(path)
+    }
+}
+
+/// An artifact fetched from the remote cache. The body hasn't been read yet,
+/// so it can be streamed to disk.
+///
+/// If the client verifies signatures, the tag is checked once the whole body
+/// has been read. No part of the body is handed out before that, either it is
+/// buffered by `bytes` or written to a temporary file by `download`.
+#[derive(Debug)]
+pub struct Artifact {
+    pub metadata: ArtifactMetadata,
+    response: reqwest::Response,
+    verification: Option<(StreamValidator, String)>,
+}
+
+impl Artifact {
+    /// Streams the body to `path`. It is written to a temporary file next to
+    /// `path` that is only moved into place once the whole body has been read
+    /// and verified.
+    pub async fn download(mut self, path: &Path) -> Result<()> {
+        let mut temp_name = path
+            .file_name()
+            .ok_or_else(|| anyhow!("{} is not a file path", path.display()))?
+            .to_os_string();
+        temp_name.push(".download");
+        let temp_path = path.with_file_name(temp_name);
+
+        if let Err(e) = self.download_unverified(&temp_path).await {
+            tokio::fs::remove_file(&temp_path).await.ok();
+            return Err(e);
+        }
+        tokio::fs::rename(&temp_path, path).await?;
+        Ok(())
+    }
+
+    async fn download_unverified(&mut self, temp_path: &Path) -> Result<()> {
+        let mut file = tokio::fs::File::create(temp_path).await?;
+        while let Some(chunk) = self.response.chunk().await? {
+            if let Some((validator, _)) = &mut self.verification {
+                validator.update(&chunk);
+            }
+            file.write_all(&chunk).await?;
+        }
+        file.flush().await?;
+        if let Some((validator, expected_tag)) = self.verification.take() {
+            Self::verify(validator, expected_tag)?;
+        }
+        Ok(())
+    }
+
+    pub async fn bytes(self) -> Result<Bytes> {
+        let body = self.response.bytes().await?;
+        if let Some((mut validator, expected_tag)) = self.verification {
+            validator.update(&body);
+            Self::verify(validator, expected_tag)?;
+        }
+        Ok(body)
+    }
+
+    fn verify(validator: StreamValidator, expected_tag: String) -> Result<(), SignatureError> {
+        if validator.validate(&expected_tag) {
+            Ok(())
+        } else {
+            Err(SignatureError::InvalidTag(expected_tag))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum CacheSource {
+    Local,
+    Remote,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum CacheEvent {
+    Hit,
+    Miss,
+}
+
+/// A cache hit or miss, recorded for the team's analytics
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnalyticsEvent {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    pub source: CacheSource,
+    pub event: CacheEvent,
+    pub hash: String,
+    /// How long the task that produced the artifact took, in milliseconds
+    pub duration: u64,
+}
+
 /// Membership is the relationship between the logged-in user and a particular
 /// team
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -111,6 +346,9 @@ pub struct APIClient {
     client: reqwest::Client,
     base_url: String,
     user_agent: String,
+    /// Set when artifacts are signed. A missing key is only reported once an
+    /// artifact is uploaded or downloaded.
+    signature_authenticator: Option<Result<ArtifactSignatureAuthenticator, SignatureError>>,
 }
 
 impl APIClient {
@@ -283,8 +521,215 @@ impl APIClient {
         })
     }
 
+    /// Checks whether an artifact exists in the remote cache, without
+    /// downloading it
+    pub async fn artifact_exists(
+        &self,
+        hash: &str,
+        token: &str,
+        team_id: &str,
+        team_slug: Option<&str>,
+    ) -> Result<Option<ArtifactMetadata>> {
+        let response = self
+            .send_retryable_request(|| {
+                let request_builder = self
+                    .client
+                    .head(self.make_url(&format!("/v8/artifacts/{}", hash)))
+                    .header("User-Agent", self.user_agent.clone())
+                    .header("Authorization", format!("Bearer {}", token));
+
+                Self::add_team_params(request_builder, team_id, team_slug)
+            })
+            .await?;
+
+        match Self::artifact_response(response).await? {
+            Some(response) => Ok(Some(ArtifactMetadata::from_headers(response.headers())?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Fetches an artifact from the remote cache, returning `None` if it
+    /// doesn't exist
+    pub async fn fetch_artifact(
+        &self,
+        hash: &str,
+        token: &str,
+        team_id: &str,
+        team_slug: Option<&str>,
+    ) -> Result<Option<Artifact>> {
+        let response = self
+            .send_retryable_request(|| {
+                let request_builder = self
+                    .client
+                    .get(self.make_url(&format!("/v8/artifacts/{}", hash)))
+                    .header("User-Agent", self.user_agent.clone())
+                    .header("Authorization", format!("Bearer {}", token));
+
+                Self::add_team_params(request_builder, team_id, team_slug)
+            })
+            .await?;
+
+        let Some(response) = Self::artifact_response(response).await? else {
+            return Ok(None);
+        };
+        let metadata = ArtifactMetadata::from_headers(response.headers())?;
+        let verification = match self.signature_authenticator()? {
+            Some(authenticator) => {
+                let expected_tag = metadata.tag.clone().ok_or(SignatureError::MissingTag)?;
+                Some((authenticator.validator(hash, team_id), expected_tag))
+            }
+            None => None,
+        };
+        Ok(Some(Artifact {
+            metadata,
+            response,
+            verification,
+        }))
+    }
+
+    /// Uploads an artifact to the remote cache. The body is streamed, and
+    /// opened again if the request is retried. If the client signs artifacts,
+    /// the tag in `metadata` is replaced with the artifact's signature.
+    pub async fn put_artifact(
+        &self,
+        hash: &str,
+        artifact_body: impl Into<ArtifactBody>,
+        metadata: &ArtifactMetadata,
+        token: &str,
+        team_id: &str,
+        team_slug: Option<&str>,
+    ) -> Result<()> {
+        let artifact_body = artifact_body.into();
+        let tag = match self.signature_authenticator()? {
+            Some(authenticator) => Some(
+                artifact_body
+                    .sign(authenticator.validator(hash, team_id))
+                    .await?,
+            ),
+            None => metadata.tag.clone(),
+        };
+        let artifact_body = &artifact_body;
+        let tag = &tag;
+        let response = retry_future(
+            Self::RETRY_MAX,
+            || async move {
+                let (body, len) = artifact_body.open().await?;
+                let mut request_builder = self
+                    .client
+                    .put(self.make_url(&format!("/v8/artifacts/{}", hash)))
+                    .header("User-Agent", self.user_agent.clone())
+                    .header("Content-Type", "application/octet-stream")
+                    .header("Content-Length", len)
+                    .header("x-artifact-duration", metadata.duration.to_string())
+                    .header("Authorization", format!("Bearer {}", token))
+                    .body(body);
+
+                if let Some(tag) = tag {
+                    request_builder = request_builder.header("x-artifact-tag", tag);
+                }
+
+                let response = Self::add_team_params(request_builder, team_id, team_slug)
+                    .send()
+                    .await?;
+                if Self::should_retry_status(response.status()) {
+                    response.error_for_status().map_err(Into::into)
+                } else {
+                    Ok(response)
+                }
+            },
+            |error: &anyhow::Error| {
+                error
+                    .downcast_ref::<reqwest::Error>()
+                    .map_or(false, Self::should_retry_request)
+            },
+        )
+        .await?;
+
+        if response.status() == StatusCode::FORBIDDEN {
+            return Err(Self::cache_disabled_error(response).await);
+        }
+        response.error_for_status()?;
+        Ok(())
+    }
+
+    /// Records cache hits and misses for the team's analytics
+    pub async fn record_cache_events(
+        &self,
+        token: &str,
+        team_id: &str,
+        team_slug: Option<&str>,
+        events: &[AnalyticsEvent],
+    ) -> Result<()> {
+        self.send_retryable_request(|| {
+            let request_builder = self
+                .client
+                .post(self.make_url("/v8/artifacts/events"))
+                .header("User-Agent", self.user_agent.clone())
+                .header("Authorization", format!("Bearer {}", token))
+                .json(events);
+
+            Self::add_team_params(request_builder, team_id, team_slug)
+        })
+        .await?
+        .error_for_status()?;
+
+        Ok(())
+    }
+
+    fn add_team_params(
+        mut request_builder: RequestBuilder,
+        team_id: &str,
+        team_slug: Option<&str>,
+    ) -> RequestBuilder {
+        if team_id.starts_with("team_") {
+            request_builder = request_builder.query(&[("teamId", team_id)]);
+        }
+        if let Some(slug) = team_slug {
+            request_builder = request_builder.query(&[("slug", slug)]);
+        }
+        request_builder
+    }
+
+    /// Handles the statuses shared by the artifact endpoints, returning `None`
+    /// if the artifact doesn't exist
+    async fn artifact_response(response: reqwest::Response) -> Result<Option<reqwest::Response>> {
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(None),
+            StatusCode::FORBIDDEN => Err(Self::cache_disabled_error(response).await),
+            _ => Ok(Some(response.error_for_status()?)),
+        }
+    }
+
+    async fn cache_disabled_error(response: reqwest::Response) -> anyhow::Error {
+        match response.json::<APIError>().await {
+            Ok(api_error) => match api_error.into_cache_disabled() {
+                Ok(error) => error.into(),
+                Err(error) => error,
+            },
+            Err(err) => anyhow!("failed to read response: {}", err),
+        }
+    }
+
     const RETRY_MAX: u32 = 2;
 
+    /// Sends a request, retrying if the server is overloaded or has an
+    /// internal error. Other error statuses are left to the caller.
+    async fn send_retryable_request(
+        &self,
+        request_builder: impl Fn() -> RequestBuilder,
+    ) -> Result<reqwest::Response> {
+        let request_builder = &request_builder;
+        self.make_retryable_request(move || async move {
+            let response = request_builder().send().await?;
+            if Self::should_retry_status(response.status()) {
+                response.error_for_status()
+            } else {
+                Ok(response)
+            }
+        })
+        .await
+    }
+
     async fn make_retryable_request<
         F: Future<Output = Result<reqwest::Response, reqwest::Error>>,
     >(
@@ -295,17 +740,12 @@ impl APIClient {
     }
 
     fn should_retry_request(error: &reqwest::Error) -> bool {
-        if let Some(status) = error.status() {
-            if status == StatusCode::TOO_MANY_REQUESTS {
-                return true;
-            }
-
-            if status.as_u16() >= 500 && status.as_u16() != 501 {
-                return true;
-            }
-        }
+        error.status().map_or(false, Self::should_retry_status)
+    }
 
-        false
+    fn should_retry_status(status: StatusCode) -> bool {
+        status == StatusCode::TOO_MANY_REQUESTS
+            || (status.as_u16() >= 500 && status.as_u16() != 501)
     }
 
     pub fn new(base_url: impl AsRef<str>, timeout: u64, version: &'static str) -> Result<Self> {
@@ -328,10 +768,343 @@ impl APIClient {
             client,
             base_url: base_url.as_ref().to_string(),
             user_agent,
+            signature_authenticator: None,
         })
     }
 
+    /// Signs uploaded artifacts and rejects downloaded artifacts whose
+    /// `x-artifact-tag` doesn't match their contents
+    pub fn with_signature_authenticator(
+        mut self,
+        signature_authenticator: Option<ArtifactSignatureAuthenticator>,
+    ) -> Self {
+        self.signature_authenticator = signature_authenticator.map(Ok);
+        self
+    }
+
+    /// Signs and verifies artifacts with the key in
+    /// `TURBO_REMOTE_CACHE_SIGNATURE_KEY`. Requests that don't involve
+    /// artifacts still work without the key.
+    pub fn with_signature_key_from_env(mut self) -> Self {
+        self.signature_authenticator = Some(ArtifactSignatureAuthenticator::from_env());
+        self
+    }
+
+    fn signature_authenticator(
+        &self,
+    ) -> Result<Option<&ArtifactSignatureAuthenticator>, SignatureError> {
+        match &self.signature_authenticator {
+            Some(Ok(authenticator)) => Ok(Some(authenticator)),
+            Some(Err(error)) => Err(error.clone()),
+            None => Ok(None),
+        }
+    }
+
     fn make_url(&self, endpoint: &str) -> String {
         format!("{}{}", self.base_url, endpoint)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+    use bytes::Bytes;
+    use vercel_api_mock::{start_test_server, EXPECTED_TEAM_ID, EXPECTED_TOKEN};
+
+    use crate::{
+        APIClient, AnalyticsEvent, ArtifactMetadata, ArtifactSignatureAuthenticator, CacheEvent,
+        CacheSource, SignatureError, SIGNATURE_KEY_ENV_VAR,
+    };
+
+    fn client(port: u16) -> APIClient {
+        APIClient::new(format!("http://localhost:{}", port), 0, "").unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_put_and_fetch_artifact() -> Result<()> {
+        let port = port_scanner::request_open_port().unwrap();
+        let handle = tokio::spawn(start_test_server(port));
+        let client = client(port);
+
+        let hash = "some_hash";
+        assert_eq!(
+            client
+                .artifact_exists(hash, EXPECTED_TOKEN, EXPECTED_TEAM_ID, None)
+                .await?,
+            None
+        );
+        assert!(client
+            .fetch_artifact(hash, EXPECTED_TOKEN, EXPECTED_TEAM_ID, None)
+            .await?
+            .is_none());
+
+        let metadata = ArtifactMetadata {
+            duration: 42,
+            tag: Some("some_tag".to_string()),
+        };
+        let body = Bytes::from_static(b"some artifact");
+        client
+            .put_artifact(
+                hash,
+                body.clone(),
+                &metadata,
+                EXPECTED_TOKEN,
+                EXPECTED_TEAM_ID,
+                None,
+            )
+            .await?;
+
+        assert_eq!(
+            client
+                .artifact_exists(hash, EXPECTED_TOKEN, EXPECTED_TEAM_ID, None)
+                .await?,
+            Some(metadata.clone())
+        );
+        let artifact = client
+            .fetch_artifact(hash, EXPECTED_TOKEN, EXPECTED_TEAM_ID, None)
+            .await?
+            .unwrap();
+        assert_eq!(artifact.metadata, metadata);
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("artifact.tar.zst");
+        artifact.download(&path).await?;
+        assert_eq!(std::fs::read(&path)?, body);
+
+        handle.abort();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_fetch_artifact_retries() -> Result<()> {
+        let port = port_scanner::request_open_port().unwrap();
+        let handle = tokio::spawn(start_test_server(port));
+        let client = client(port);
+
+        let hash = vercel_api_mock::FLAKY_ARTIFACT_HASH;
+        let body = Bytes::from_static(b"flaky artifact");
+        client
+            .put_artifact(
+                hash,
+                body.clone(),
+                &ArtifactMetadata::default(),
+                EXPECTED_TOKEN,
+                EXPECTED_TEAM_ID,
+                None,
+            )
+            .await?;
+
+        // the first request fails with a 503
+        let artifact = client
+            .fetch_artifact(hash, EXPECTED_TOKEN, EXPECTED_TEAM_ID, None)
+            .await?
+            .unwrap();
+        assert_eq!(artifact.bytes().await?, body);
+
+        handle.abort();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_put_artifact_from_file_retries() -> Result<()> {
+        let port = port_scanner::request_open_port().unwrap();
+        let handle = tokio::spawn(start_test_server(port));
+        let client = client(port);
+
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("artifact.tar.zst");
+        let body = b"artifact on disk".repeat(10_000);
+        std::fs::write(&path, &body)?;
+
+        // the first upload fails with a 503, so the file is streamed twice
+        let hash = vercel_api_mock::FLAKY_UPLOAD_HASH;
+        client
+            .put_artifact(
+                hash,
+                path,
+                &ArtifactMetadata::default(),
+                EXPECTED_TOKEN,
+                EXPECTED_TEAM_ID,
+                None,
+            )
+            .await?;
+
+        let artifact = client
+            .fetch_artifact(hash, EXPECTED_TOKEN, EXPECTED_TEAM_ID, None)
+            .await?
+            .unwrap();
+        assert_eq!(artifact.bytes().await?, body);
+
+        handle.abort();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_signed_artifacts() -> Result<()> {
+        let port = port_scanner::request_open_port().unwrap();
+        let handle = tokio::spawn(start_test_server(port));
+        let signing_client = client(port).with_signature_authenticator(Some(
+            ArtifactSignatureAuthenticator::new("secret").unwrap(),
+        ));
+
+        let body = Bytes::from_static(b"signed artifact");
+        signing_client
+            .put_artifact(
+                "signed",
+                body.clone(),
+                &ArtifactMetadata::default(),
+                EXPECTED_TOKEN,
+                EXPECTED_TEAM_ID,
+                None,
+            )
+            .await?;
+        let artifact = signing_client
+            .fetch_artifact("signed", EXPECTED_TOKEN, EXPECTED_TEAM_ID, None)
+            .await?
+            .unwrap();
+        assert!(artifact.metadata.tag.is_some());
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("signed.tar.zst");
+        artifact.download(&path).await?;
+        assert_eq!(std::fs::read(&path)?, body);
+
+        // an artifact uploaded with a tag that doesn't match its contents
+        let unsigned_client = client(port);
+        let tampered = ArtifactMetadata {
+            duration: 0,
+            tag: Some(
+                ArtifactSignatureAuthenticator::new("secret")
+                    .unwrap()
+                    .generate_tag("tampered", EXPECTED_TEAM_ID, b"original artifact"),
+            ),
+        };
+        unsigned_client
+            .put_artifact(
+                "tampered",
+                Bytes::from_static(b"tampered artifact"),
+                &tampered,
+                EXPECTED_TOKEN,
+                EXPECTED_TEAM_ID,
+                None,
+            )
+            .await?;
+        let err = signing_client
+            .fetch_artifact("tampered", EXPECTED_TOKEN, EXPECTED_TEAM_ID, None)
+            .await?
+            .unwrap()
+            .bytes()
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.downcast::<SignatureError>()?,
+            SignatureError::InvalidTag(tampered.tag.clone().unwrap())
+        );
+        // nothing is left on disk for a download that fails verification
+        let path = dir.path().join("tampered.tar.zst");
+        let err = signing_client
+            .fetch_artifact("tampered", EXPECTED_TOKEN, EXPECTED_TEAM_ID, None)
+            .await?
+            .unwrap()
+            .download(&path)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.downcast::<SignatureError>()?,
+            SignatureError::InvalidTag(tampered.tag.unwrap())
+        );
+        assert_eq!(std::fs::read_dir(dir.path())?.count(), 1);
+
+        // an artifact without a tag
+        unsigned_client
+            .put_artifact(
+                "unsigned",
+                body,
+                &ArtifactMetadata::default(),
+                EXPECTED_TOKEN,
+                EXPECTED_TEAM_ID,
+                None,
+            )
+            .await?;
+        let err = signing_client
+            .fetch_artifact("unsigned", EXPECTED_TOKEN, EXPECTED_TEAM_ID, None)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.downcast::<SignatureError>()?,
+            SignatureError::MissingTag
+        );
+
+        handle.abort();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_missing_signature_key() -> Result<()> {
+        let port = port_scanner::request_open_port().unwrap();
+        let handle = tokio::spawn(start_test_server(port));
+        std::env::remove_var(SIGNATURE_KEY_ENV_VAR);
+        let signing_client = client(port).with_signature_key_from_env();
+        let body = Bytes::from_static(b"some artifact");
+
+        let err = signing_client
+            .put_artifact(
+                "some_hash",
+                body.clone(),
+                &ArtifactMetadata::default(),
+                EXPECTED_TOKEN,
+                EXPECTED_TEAM_ID,
+                None,
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.downcast::<SignatureError>()?,
+            SignatureError::NoSignatureSecretKey
+        );
+
+        client(port)
+            .put_artifact(
+                "some_hash",
+                body,
+                &ArtifactMetadata::default(),
+                EXPECTED_TOKEN,
+                EXPECTED_TEAM_ID,
+                None,
+            )
+            .await?;
+        let err = signing_client
+            .fetch_artifact("some_hash", EXPECTED_TOKEN, EXPECTED_TEAM_ID, None)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.downcast::<SignatureError>()?,
+            SignatureError::NoSignatureSecretKey
+        );
+
+        handle.abort();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_record_cache_events() -> Result<()> {
+        let port = port_scanner::request_open_port().unwrap();
+        let handle = tokio::spawn(start_test_server(port));
+
+        client(port)
+            .record_cache_events(
+                EXPECTED_TOKEN,
+                EXPECTED_TEAM_ID,
+                None,
+                &[AnalyticsEvent {
+                    session_id: None,
+                    source: CacheSource::Remote,
+                    event: CacheEvent::Hit,
+                    hash: "some_hash".to_string(),
+                    duration: 42,
+                }],
+            )
+            .await?;
+
+        handle.abort();
+        Ok(())
+    }
+}
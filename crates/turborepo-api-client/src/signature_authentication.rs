@@ -0,0 +1,172 @@
+//! Signing of remote cache artifacts, matching
+//! `cli/internal/cache/cache_signature_authentication.go`.
+//!
+//! The `x-artifact-tag` of an artifact is the base64 encoded HMAC-SHA256 of
+//! `{"hash":"<hash>","teamId":"<team id>"}` followed by the artifact body,
+//! keyed with `TURBO_REMOTE_CACHE_SIGNATURE_KEY`.
+
+use std::{env, fmt};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use thiserror::Error;
+
+pub const SIGNATURE_KEY_ENV_VAR: &str = "TURBO_REMOTE_CACHE_SIGNATURE_KEY";
+
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+pub enum SignatureError {
+    #[error(
+        "signature secret key not found. You must specify a secret key in the \
+         TURBO_REMOTE_CACHE_SIGNATURE_KEY environment variable"
+    )]
+    NoSignatureSecretKey,
+    #[error(
+        "artifact verification failed: Downloaded artifact is missing required x-artifact-tag \
+         header"
+    )]
+    MissingTag,
+    #[error("artifact verification failed: artifact tag does not match expected tag {0}")]
+    InvalidTag(String),
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SignedMetadata<'a> {
+    hash: &'a str,
+    team_id: &'a str,
+}
+
+/// Produces and verifies artifact tags with a secret key
+#[derive(Clone)]
+pub struct ArtifactSignatureAuthenticator {
+    secret_key: Vec<u8>,
+}
+
+impl fmt::Debug for ArtifactSignatureAuthenticator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ArtifactSignatureAuthenticator")
+            .finish_non_exhaustive()
+    }
+}
+
+impl ArtifactSignatureAuthenticator {
+    pub fn new(secret_key: impl Into<Vec<u8>>) -> Result<Self, SignatureError> {
+        let secret_key = secret_key.into();
+        if secret_key.is_empty() {
+            return Err(SignatureError::NoSignatureSecretKey);
+        }
+        Ok(Self { secret_key })
+    }
+
+    /// Reads the secret key from `TURBO_REMOTE_CACHE_SIGNATURE_KEY`
+    pub fn from_env() -> Result<Self, SignatureError> {
+        Self::new(env::var(SIGNATURE_KEY_ENV_VAR).unwrap_or_default())
+    }
+
+    pub fn generate_tag(&self, hash: &str, team_id: &str, artifact_body: &[u8]) -> String {
+        let mut validator = self.validator(hash, team_id);
+        validator.update(artifact_body);
+        validator.tag()
+    }
+
+    /// Whether `expected_tag` is the tag of the artifact. The comparison is
+    /// constant time.
+    pub fn validate(
+        &self,
+        hash: &str,
+        team_id: &str,
+        artifact_body: &[u8],
+        expected_tag: &str,
+    ) -> bool {
+        let mut validator = self.validator(hash, team_id);
+        validator.update(artifact_body);
+        validator.validate(expected_tag)
+    }
+
+    /// Starts computing the tag of an artifact whose body is fed in
+    /// incrementally, e.g. while it is being downloaded
+    pub fn validator(&self, hash: &str, team_id: &str) -> StreamValidator {
+        let metadata = serde_json::to_vec(&SignedMetadata { hash, team_id })
+            .expect("metadata only contains strings");
+        let mut mac =
+            HmacSha256::new_from_slice(&self.secret_key).expect("HMAC takes keys of any size");
+        mac.update(&metadata);
+        StreamValidator { mac }
+    }
+}
+
+/// Computes the tag of an artifact one chunk at a time
+#[derive(Clone)]
+pub struct StreamValidator {
+    mac: HmacSha256,
+}
+
+impl fmt::Debug for StreamValidator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StreamValidator").finish_non_exhaustive()
+    }
+}
+
+impl StreamValidator {
+    pub fn update(&mut self, chunk: &[u8]) {
+        self.mac.update(chunk);
+    }
+
+    pub fn tag(self) -> String {
+        STANDARD.encode(self.mac.finalize().into_bytes())
+    }
+
+    /// Whether `expected_tag` is the tag of everything fed in so far. The
+    /// comparison is constant time.
+    pub fn validate(self, expected_tag: &str) -> bool {
+        match STANDARD.decode(expected_tag) {
+            Ok(expected) => self.mac.verify_slice(&expected).is_ok(),
+            Err(_) => false,
+        }
+    }
+}
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[cfg(test)]
+mod test {
+    use super::{ArtifactSignatureAuthenticator, SignatureError};
+
+    #[test]
+    fn test_generate_and_validate_tag() {
+        let authenticator = ArtifactSignatureAuthenticator::new("secret").unwrap();
+        let tag = authenticator.generate_tag("some_hash", "team_id", b"body");
+        // base64(HMAC-SHA256("secret", '{"hash":"some_hash","teamId":"team_id"}body'))
+        assert_eq!(tag, "Z59AKlnE5Ge7mjpwWewoaxGIXBrYe3s9kXpCI64hajw=");
+        assert!(authenticator.validate("some_hash", "team_id", b"body", &tag));
+
+        assert!(!authenticator.validate("some_hash", "team_id", b"tampered", &tag));
+        assert!(!authenticator.validate("other_hash", "team_id", b"body", &tag));
+        assert!(!authenticator.validate("some_hash", "other_team", b"body", &tag));
+        assert!(!authenticator.validate("some_hash", "team_id", b"body", "not base64"));
+
+        let other_key = ArtifactSignatureAuthenticator::new("other secret").unwrap();
+        assert!(!other_key.validate("some_hash", "team_id", b"body", &tag));
+    }
+
+    #[test]
+    fn test_stream_validator() {
+        let authenticator = ArtifactSignatureAuthenticator::new("secret").unwrap();
+        let tag = authenticator.generate_tag("some_hash", "team_id", b"some body");
+
+        let mut validator = authenticator.validator("some_hash", "team_id");
+        validator.update(b"some ");
+        validator.update(b"body");
+        assert!(validator.validate(&tag));
+    }
+
+    #[test]
+    fn test_empty_key() {
+        assert_eq!(
+            ArtifactSignatureAuthenticator::new("").unwrap_err(),
+            SignatureError::NoSignatureSecretKey
+        );
+    }
+}
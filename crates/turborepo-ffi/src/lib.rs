@@ -206,7 +206,7 @@ pub extern "C" fn get_package_file_hashes_from_git_index(buffer: Buffer) -> Buff
             return resp.into();
         }
     };
-    let response = match turborepo_scm::package_deps::get_package_file_hashes_from_git_index(
+    let response = match turborepo_scm::package_deps::get_package_file_hashes(
         &turbo_root,
         &package_path,
     ) {
@@ -4,7 +4,10 @@ use std::{
 };
 
 use thiserror::Error;
-use turborepo_lockfiles::{self, BerryLockfile, LockfileData, NpmLockfile, Package};
+use turborepo_lockfiles::{
+    self, BerryLockfile, LockfileData, LockfileDiff, NpmLockfile, Package, PnpmLockfile,
+    Yarn1Lockfile,
+};
 
 use super::{proto, Buffer};
 
@@ -27,6 +30,10 @@ enum Error {
     Protobuf(#[from] prost::DecodeError),
     #[error(transparent)]
     BerryParse(#[from] turborepo_lockfiles::BerryError),
+    #[error(transparent)]
+    Pnpm(#[from] turborepo_lockfiles::PnpmError),
+    #[error(transparent)]
+    Yarn1(#[from] turborepo_lockfiles::Yarn1Error),
     #[error("unsupported package manager {0}")]
     UnsupportedPackageManager(proto::PackageManager),
 }
@@ -50,6 +57,8 @@ fn transitive_closure_inner(buf: Buffer) -> Result<proto::WorkspaceDependencies,
     match request.package_manager() {
         proto::PackageManager::Npm => npm_transitive_closure_inner(request),
         proto::PackageManager::Berry => berry_transitive_closure_inner(request),
+        proto::PackageManager::Pnpm => pnpm_transitive_closure_inner(request),
+        proto::PackageManager::Yarn => yarn_transitive_closure_inner(request),
     }
 }
 
@@ -89,6 +98,38 @@ fn berry_transitive_closure_inner(
     Ok(dependencies.into())
 }
 
+fn pnpm_transitive_closure_inner(
+    request: proto::TransitiveDepsRequest,
+) -> Result<proto::WorkspaceDependencies, Error> {
+    let proto::TransitiveDepsRequest {
+        contents,
+        workspaces,
+        ..
+    } = request;
+    let lockfile = PnpmLockfile::load(contents.as_slice())?;
+    let dependencies = turborepo_lockfiles::all_transitive_closures(
+        &lockfile,
+        workspaces.into_iter().map(|(k, v)| (k, v.into())).collect(),
+    )?;
+    Ok(dependencies.into())
+}
+
+fn yarn_transitive_closure_inner(
+    request: proto::TransitiveDepsRequest,
+) -> Result<proto::WorkspaceDependencies, Error> {
+    let proto::TransitiveDepsRequest {
+        contents,
+        workspaces,
+        ..
+    } = request;
+    let lockfile = Yarn1Lockfile::from_bytes(contents.as_slice())?;
+    let dependencies = turborepo_lockfiles::all_transitive_closures(
+        &lockfile,
+        workspaces.into_iter().map(|(k, v)| (k, v.into())).collect(),
+    )?;
+    Ok(dependencies.into())
+}
+
 #[no_mangle]
 pub extern "C" fn subgraph(buf: Buffer) -> Buffer {
     use proto::subgraph_response::Response;
@@ -121,6 +162,10 @@ fn subgraph_inner(buf: Buffer) -> Result<Vec<u8>, Error> {
             &packages,
             resolutions.map(|res| res.resolutions),
         )?,
+        proto::PackageManager::Pnpm => {
+            turborepo_lockfiles::pnpm_subgraph(&contents, &workspaces, &packages)?
+        }
+        proto::PackageManager::Yarn => turborepo_lockfiles::yarn_subgraph(&contents, &packages)?,
     };
     Ok(contents)
 }
@@ -153,6 +198,14 @@ fn patches_internal(buf: Buffer) -> Result<proto::Patches, Error> {
                 })
                 .collect::<Vec<_>>())
         }
+        proto::PackageManager::Pnpm => {
+            let lockfile = PnpmLockfile::load(&request.contents)?;
+            Ok(lockfile
+                .patches()
+                .into_iter()
+                .map(|p| p.to_string())
+                .collect::<Vec<_>>())
+        }
         pm => Err(Error::UnsupportedPackageManager(pm)),
     }?;
     Ok(proto::Patches { patches })
@@ -177,6 +230,107 @@ fn global_change_inner(buf: Buffer) -> Result<bool, Error> {
             &request.prev_contents,
             &request.curr_contents,
         )?),
+        proto::PackageManager::Pnpm => Ok(turborepo_lockfiles::pnpm_global_change(
+            &request.prev_contents,
+            &request.curr_contents,
+        )?),
+        // Yarn 1 lockfiles don't contain any global settings
+        proto::PackageManager::Yarn => Ok(false),
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn lockfile_diff(buf: Buffer) -> Buffer {
+    use proto::lockfile_diff_response::Response;
+    let response = match lockfile_diff_inner(buf) {
+        Ok(diff) => Response::Diff(diff),
+        Err(err) => Response::Error(err.to_string()),
+    };
+    proto::LockfileDiffResponse {
+        response: Some(response),
+    }
+    .into()
+}
+
+fn lockfile_diff_inner(buf: Buffer) -> Result<proto::LockfileDiff, Error> {
+    let request: proto::LockfileDiffRequest = buf.into_proto()?;
+    let package_manager = request.package_manager();
+    let proto::LockfileDiffRequest {
+        prev_contents,
+        curr_contents,
+        workspaces,
+        prev_resolutions,
+        curr_resolutions,
+        ..
+    } = request;
+    let workspaces = workspaces.into_iter().map(|(k, v)| (k, v.into())).collect();
+
+    let diff = match package_manager {
+        proto::PackageManager::Npm => turborepo_lockfiles::lockfile_diff(
+            &NpmLockfile::load(&prev_contents)?,
+            &NpmLockfile::load(&curr_contents)?,
+            workspaces,
+        )?,
+        proto::PackageManager::Berry => {
+            let prev_resolutions = prev_resolutions
+                .map(|r| turborepo_lockfiles::BerryManifest::with_resolutions(r.resolutions));
+            let curr_resolutions = curr_resolutions
+                .map(|r| turborepo_lockfiles::BerryManifest::with_resolutions(r.resolutions));
+            let prev_data = LockfileData::from_bytes(&prev_contents)?;
+            let curr_data = LockfileData::from_bytes(&curr_contents)?;
+            turborepo_lockfiles::lockfile_diff(
+                &BerryLockfile::new(&prev_data, prev_resolutions.as_ref())?,
+                &BerryLockfile::new(&curr_data, curr_resolutions.as_ref())?,
+                workspaces,
+            )?
+        }
+        proto::PackageManager::Pnpm => turborepo_lockfiles::lockfile_diff(
+            &PnpmLockfile::load(&prev_contents)?,
+            &PnpmLockfile::load(&curr_contents)?,
+            workspaces,
+        )?,
+        proto::PackageManager::Yarn => turborepo_lockfiles::lockfile_diff(
+            &Yarn1Lockfile::from_bytes(&prev_contents)?,
+            &Yarn1Lockfile::from_bytes(&curr_contents)?,
+            workspaces,
+        )?,
+    };
+
+    Ok(diff.into())
+}
+
+impl From<LockfileDiff> for proto::LockfileDiff {
+    fn from(value: LockfileDiff) -> Self {
+        let LockfileDiff {
+            added,
+            removed,
+            changed,
+            global_changes,
+            affected_workspaces,
+        } = value;
+        proto::LockfileDiff {
+            added: added
+                .into_iter()
+                .map(proto::LockfilePackage::from)
+                .collect(),
+            removed: removed
+                .into_iter()
+                .map(proto::LockfilePackage::from)
+                .collect(),
+            changed: changed
+                .into_iter()
+                .map(|change| proto::LockfilePackageChange {
+                    name: change.name,
+                    previous: Some(change.previous.into()),
+                    current: Some(change.current.into()),
+                })
+                .collect(),
+            global_changes: global_changes
+                .into_iter()
+                .map(|change| change.setting)
+                .collect(),
+            affected_workspaces: affected_workspaces.into_iter().collect(),
+        }
     }
 }
 
@@ -219,6 +373,8 @@ impl fmt::Display for proto::PackageManager {
         f.write_str(match self {
             proto::PackageManager::Npm => "npm",
             proto::PackageManager::Berry => "berry",
+            proto::PackageManager::Pnpm => "pnpm",
+            proto::PackageManager::Yarn => "yarn",
         })
     }
 }
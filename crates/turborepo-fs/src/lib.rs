@@ -1,65 +1,185 @@
-use std::fs::{self, DirBuilder, Metadata};
+//! Filesystem helpers for copying files and directory trees.
+//!
+//! `recursive_copy` walks the source tree once, creating directories as it
+//! goes, and then copies files and symlinks in parallel. File contents are
+//! cloned rather than copied where the filesystem supports it, see
+//! `reflink`.
+
+mod reflink;
+
+use std::{
+    fs::{self, DirBuilder, Metadata},
+    ops::{Add, AddAssign},
+};
 
 use anyhow::Result;
-use turbopath::{AbsoluteSystemPath, AnchoredSystemPathBuf};
+use rayon::prelude::*;
+use turbopath::{AbsoluteSystemPath, AbsoluteSystemPathBuf, AnchoredSystemPathBuf};
 use walkdir::WalkDir;
 
+use crate::reflink::{copy_contents, CopyMethod};
+
+/// Options for `recursive_copy_with_options`. The defaults match
+/// `recursive_copy`.
+#[derive(Debug, Clone, Default)]
+pub struct CopyOptions {
+    preserve_symlinked_dirs: bool,
+    preserve_broken_links: bool,
+    threads: Option<usize>,
+}
+
+impl CopyOptions {
+    /// Copy symlinks to directories as symlinks. By default they are copied
+    /// as empty directories.
+    pub fn preserve_symlinked_dirs(mut self, preserve: bool) -> Self {
+        self.preserve_symlinked_dirs = preserve;
+        self
+    }
+
+    /// Copy symlinks whose target doesn't exist. By default they are skipped.
+    pub fn preserve_broken_links(mut self, preserve: bool) -> Self {
+        self.preserve_broken_links = preserve;
+        self
+    }
+
+    /// The number of threads to copy files with. Defaults to the global rayon
+    /// pool, which has one thread per core.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = Some(threads);
+        self
+    }
+}
+
+/// What a copy did. `bytes` counts the contents of every file, including
+/// files that were cloned.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CopySummary {
+    pub files: u64,
+    pub bytes: u64,
+    /// Files that share their data with the source, a subset of `files`
+    pub cloned_files: u64,
+    pub symlinks: u64,
+    pub directories: u64,
+}
+
+impl Add for CopySummary {
+    type Output = Self;
+
+    fn add(mut self, rhs: Self) -> Self {
+        self += rhs;
+        self
+    }
+}
+
+impl AddAssign for CopySummary {
+    fn add_assign(&mut self, rhs: Self) {
+        self.files += rhs.files;
+        self.bytes += rhs.bytes;
+        self.cloned_files += rhs.cloned_files;
+        self.symlinks += rhs.symlinks;
+        self.directories += rhs.directories;
+    }
+}
+
+/// A file or symlink found while walking the source, to be copied once every
+/// directory exists
+struct CopyJob {
+    from: AbsoluteSystemPathBuf,
+    from_type: fs::FileType,
+    to: AbsoluteSystemPathBuf,
+    /// whether a symlink points to a directory
+    symlink_to_dir: bool,
+}
+
 pub fn recursive_copy(
     src: impl AsRef<AbsoluteSystemPath>,
     dst: impl AsRef<AbsoluteSystemPath>,
 ) -> Result<()> {
+    recursive_copy_with_options(src, dst, &CopyOptions::default())?;
+    Ok(())
+}
+
+pub fn recursive_copy_with_options(
+    src: impl AsRef<AbsoluteSystemPath>,
+    dst: impl AsRef<AbsoluteSystemPath>,
+    options: &CopyOptions,
+) -> Result<CopySummary> {
     let src = src.as_ref();
     let dst = dst.as_ref();
     let src_metadata = src.symlink_metadata()?;
-    if src_metadata.is_dir() {
-        let walker = WalkDir::new(src.as_path()).follow_links(false);
-        for entry in walker.into_iter() {
-            match entry {
-                Err(e) => {
-                    if e.io_error().is_some() {
-                        // Matches go behavior where we translate path errors
-                        // into skipping the path we're currently walking
-                        continue;
-                    } else {
-                        return Err(e.into());
-                    }
-                }
-                Ok(entry) => {
-                    let path = entry.path();
-                    let path = AbsoluteSystemPath::new(path)?;
-                    let file_type = entry.file_type();
-                    // currently we support symlinked files, but not symlinked directories:
-                    // For copying, we Mkdir and bail if we encounter a symlink to a directoy
-                    // For finding packages, we enumerate the symlink, but don't follow inside
-                    // Note that we also don't currently copy broken symlinks
-                    let is_dir_or_symlink_to_dir = if file_type.is_dir() {
-                        true
-                    } else if file_type.is_symlink() {
-                        if let Ok(metadata) = path.stat() {
-                            metadata.is_dir()
-                        } else {
-                            // If we have a broken link, skip this entry
-                            continue;
-                        }
-                    } else {
-                        false
-                    };
-
-                    let suffix = AnchoredSystemPathBuf::new(src, &path)?;
-                    let target = dst.resolve(&suffix);
-                    if is_dir_or_symlink_to_dir {
-                        let src_metadata = entry.metadata()?;
-                        make_dir_copy(&target, &src_metadata)?;
-                    } else {
-                        copy_file_with_type(&path, file_type, &target)?;
-                    }
+    if !src_metadata.is_dir() {
+        return copy_entry(src, src_metadata.file_type(), dst, false);
+    }
+
+    let mut summary = CopySummary::default();
+    let mut jobs = Vec::new();
+    let walker = WalkDir::new(src.as_path()).follow_links(false);
+    for entry in walker.into_iter() {
+        let entry = match entry {
+            Err(e) => {
+                if e.io_error().is_some() {
+                    // Matches go behavior where we translate path errors
+                    // into skipping the path we're currently walking
+                    continue;
+                } else {
+                    return Err(e.into());
                 }
             }
+            Ok(entry) => entry,
+        };
+        let path = AbsoluteSystemPath::new(entry.path())?;
+        let file_type = entry.file_type();
+        // By default we support symlinked files, but not symlinked directories:
+        // For copying, we Mkdir and bail if we encounter a symlink to a directoy
+        // For finding packages, we enumerate the symlink, but don't follow inside
+        // Note that we also don't copy broken symlinks by default
+        let is_symlink_to_dir = if file_type.is_symlink() {
+            match path.stat() {
+                Ok(metadata) => metadata.is_dir(),
+                Err(_) if options.preserve_broken_links => false,
+                // If we have a broken link, skip this entry
+                Err(_) => continue,
+            }
+        } else {
+            false
+        };
+
+        let suffix = AnchoredSystemPathBuf::new(src, path)?;
+        let target = dst.resolve(&suffix);
+        if file_type.is_dir() || (is_symlink_to_dir && !options.preserve_symlinked_dirs) {
+            let src_metadata = entry.metadata()?;
+            make_dir_copy(&target, &src_metadata)?;
+            summary.directories += 1;
+        } else {
+            jobs.push(CopyJob {
+                from: path.to_owned(),
+                from_type: file_type,
+                to: target,
+                symlink_to_dir: is_symlink_to_dir,
+            });
         }
-        Ok(())
-    } else {
-        copy_file_with_type(src, src_metadata.file_type(), dst)
     }
+
+    let copy_all = || {
+        jobs.par_iter()
+            .map(|job| {
+                copy_entry(
+                    job.from.as_absolute_path(),
+                    job.from_type,
+                    job.to.as_absolute_path(),
+                    job.symlink_to_dir,
+                )
+            })
+            .try_reduce(CopySummary::default, |a, b| Ok(a + b))
+    };
+    summary += match options.threads {
+        Some(threads) => rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()?
+            .install(copy_all)?,
+        None => copy_all()?,
+    };
+    Ok(summary)
 }
 
 fn make_dir_copy(dir: impl AsRef<AbsoluteSystemPath>, src_metadata: &Metadata) -> Result<()> {
@@ -89,20 +209,40 @@ fn copy_file_with_type(
     from_type: fs::FileType,
     to: impl AsRef<AbsoluteSystemPath>,
 ) -> Result<()> {
-    let from = from.as_ref();
-    let to = to.as_ref();
+    copy_entry(from.as_ref(), from_type, to.as_ref(), false)?;
+    Ok(())
+}
+
+fn copy_entry(
+    from: &AbsoluteSystemPath,
+    from_type: fs::FileType,
+    to: &AbsoluteSystemPath,
+    symlink_to_dir: bool,
+) -> Result<CopySummary> {
+    to.ensure_dir()?;
     if from_type.is_symlink() {
         let target = from.read_link()?;
-        to.ensure_dir()?;
         if to.symlink_metadata().is_ok() {
             to.remove_file()?;
         }
-        to.symlink_to_file(target)?;
-        Ok(())
+        if symlink_to_dir {
+            to.symlink_to_dir(target)?;
+        } else {
+            to.symlink_to_file(target)?;
+        }
+        Ok(CopySummary {
+            symlinks: 1,
+            ..Default::default()
+        })
     } else {
-        to.ensure_dir()?;
-        fs::copy(from.as_path(), to.as_path())?;
-        Ok(())
+        let metadata = from.stat()?;
+        let (bytes, method) = copy_contents(from.as_path(), to.as_path(), &metadata)?;
+        Ok(CopySummary {
+            files: 1,
+            bytes,
+            cloned_files: (method == CopyMethod::Clone) as u64,
+            ..Default::default()
+        })
     }
 }
 
@@ -250,6 +390,95 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_recursive_copy_summary() -> Result<()> {
+        let (_src_tmp, src_dir) = tmp_dir()?;
+        let child_dir = src_dir.join_literal("child");
+        let a_path = child_dir.join_literal("a");
+        a_path.ensure_dir()?;
+        a_path.create_with_contents("hello")?;
+        src_dir.join_literal("b").create_with_contents("bFile")?;
+        child_dir.join_literal("link").symlink_to_file("../b")?;
+        child_dir
+            .join_literal("broken")
+            .symlink_to_file("missing")?;
+
+        let (_dst_tmp, dst_dir) = tmp_dir()?;
+        let summary = recursive_copy_with_options(&src_dir, dst_dir, &CopyOptions::default())?;
+        assert_eq!(summary.files, 2);
+        assert_eq!(summary.bytes, 10);
+        assert!(summary.cloned_files <= summary.files);
+        assert_eq!(summary.symlinks, 1);
+        // the root and child
+        assert_eq!(summary.directories, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_recursive_copy_preserve_links() -> Result<()> {
+        // Directory layout:
+        //
+        // <src>/
+        //   child/
+        //     a
+        //     broken -> missing
+        //     circle -> ../child
+        let (_src_tmp, src_dir) = tmp_dir()?;
+        let child_dir = src_dir.join_literal("child");
+        let a_path = child_dir.join_literal("a");
+        a_path.ensure_dir()?;
+        a_path.create_with_contents("hello")?;
+        child_dir
+            .join_literal("broken")
+            .symlink_to_file("missing")?;
+        child_dir
+            .join_literal("circle")
+            .symlink_to_dir("../child")?;
+
+        let (_dst_tmp, dst_dir) = tmp_dir()?;
+        let options = CopyOptions::default()
+            .preserve_symlinked_dirs(true)
+            .preserve_broken_links(true)
+            .threads(2);
+        let summary = recursive_copy_with_options(&src_dir, &dst_dir, &options)?;
+        assert_eq!(summary.files, 1);
+        assert_eq!(summary.symlinks, 2);
+
+        let dst_child_path = dst_dir.join_literal("child");
+        assert_file_matches(&a_path, dst_child_path.join_literal("a"));
+        assert_target_matches(dst_child_path.join_literal("broken"), "missing");
+        assert_target_matches(dst_child_path.join_literal("circle"), "../child");
+        let dst_circle_path = dst_child_path.join_literal("circle");
+        assert!(fs::metadata(dst_circle_path.as_path())?.is_dir());
+        Ok(())
+    }
+
+    #[test]
+    fn test_recursive_copy_many_files() -> Result<()> {
+        let (_src_tmp, src_dir) = tmp_dir()?;
+        for dir in 0..8 {
+            for file in 0..32 {
+                let path = src_dir.join_literal(&format!("{dir}/{file}"));
+                path.ensure_dir()?;
+                path.create_with_contents(&format!("{dir}-{file}").repeat(file))?;
+            }
+        }
+
+        let (_dst_tmp, dst_dir) = tmp_dir()?;
+        let summary = recursive_copy_with_options(&src_dir, &dst_dir, &CopyOptions::default())?;
+        assert_eq!(summary.files, 8 * 32);
+        let mut bytes = 0;
+        for dir in 0..8 {
+            for file in 0..32 {
+                let name = format!("{dir}/{file}");
+                assert_file_matches(src_dir.join_literal(&name), dst_dir.join_literal(&name));
+                bytes += fs::metadata(dst_dir.join_literal(&name).as_path())?.len();
+            }
+        }
+        assert_eq!(summary.bytes, bytes);
+        Ok(())
+    }
+
     fn assert_file_matches(a: impl AsRef<AbsoluteSystemPath>, b: impl AsRef<AbsoluteSystemPath>) {
         let a = a.as_ref();
         let b = b.as_ref();
@@ -0,0 +1,104 @@
+//! Copying the contents of a single file, as cheaply as the filesystem allows.
+//!
+//! On linux we first try to clone the file with `FICLONE`, which shares the
+//! underlying extents on copy-on-write filesystems like btrfs and XFS. If the
+//! filesystem doesn't support that we use `copy_file_range`, which still
+//! avoids copying through userspace, and finally a plain read/write loop.
+//! Elsewhere we rely on `std::fs::copy`, which already clones files on APFS.
+
+use std::{fs, io, path::Path};
+
+/// How the contents of a file were copied
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CopyMethod {
+    /// The file shares its data with the source until either is modified
+    Clone,
+    /// The data was copied
+    Copy,
+}
+
+/// Copies the contents and permissions of `from` to `to`, replacing `to` if
+/// it exists. Returns the number of bytes in the file and how it was copied.
+#[cfg(target_os = "linux")]
+pub(crate) fn copy_contents(
+    from: &Path,
+    to: &Path,
+    metadata: &fs::Metadata,
+) -> io::Result<(u64, CopyMethod)> {
+    use std::os::unix::{fs::OpenOptionsExt, io::AsRawFd};
+
+    let src = fs::File::open(from)?;
+    let mut dst = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(std::os::unix::fs::PermissionsExt::mode(
+            &metadata.permissions(),
+        ))
+        .open(to)?;
+
+    let method = if unsafe { libc::ioctl(dst.as_raw_fd(), FICLONE, src.as_raw_fd()) } == 0 {
+        CopyMethod::Clone
+    } else {
+        copy_file_range(&src, &mut dst, metadata.len())?;
+        CopyMethod::Copy
+    };
+
+    // an existing file keeps its permissions when opened
+    dst.set_permissions(metadata.permissions())?;
+    Ok((metadata.len(), method))
+}
+
+/// `_IOW(0x94, 9, int)`, which libc doesn't define yet
+#[cfg(target_os = "linux")]
+const FICLONE: libc::c_ulong = 0x40049409;
+
+#[cfg(target_os = "linux")]
+fn copy_file_range(src: &fs::File, dst: &mut fs::File, len: u64) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    let mut copied = 0;
+    while copied < len {
+        let chunk = (len - copied).min(isize::MAX as u64) as usize;
+        let result = unsafe {
+            libc::copy_file_range(
+                src.as_raw_fd(),
+                std::ptr::null_mut(),
+                dst.as_raw_fd(),
+                std::ptr::null_mut(),
+                chunk,
+                0,
+            )
+        };
+        match result {
+            // the file was truncated while we were copying it
+            0 => return Ok(()),
+            n if n > 0 => copied += n as u64,
+            _ => {
+                let error = io::Error::last_os_error();
+                return match error.raw_os_error() {
+                    // not supported by the kernel or between these filesystems, so
+                    // copy through userspace instead
+                    Some(libc::ENOSYS | libc::EXDEV | libc::EINVAL | libc::EOPNOTSUPP)
+                        if copied == 0 =>
+                    {
+                        io::copy(&mut &*src, dst).map(|_| ())
+                    }
+                    _ => Err(error),
+                };
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn copy_contents(
+    from: &Path,
+    to: &Path,
+    metadata: &fs::Metadata,
+) -> io::Result<(u64, CopyMethod)> {
+    let _ = metadata;
+    let len = fs::copy(from, to)?;
+    Ok((len, CopyMethod::Copy))
+}
@@ -0,0 +1,304 @@
+//! Expands include and exclude globs against the filesystem, the way
+//! `cli/internal/globby` does on the Go side.
+//!
+//! Directories are only descended into if they could contain a match for one
+//! of the include globs, based on the literal prefix of each glob, and
+//! directories matched by an exclude glob are skipped entirely.
+
+use std::collections::BTreeSet;
+
+use glob_match::GlobSet;
+use thiserror::Error;
+use turbopath::{AbsoluteSystemPath, AnchoredSystemPathBuf, PathError};
+use walkdir::WalkDir;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WalkType {
+    /// Only files, and symlinks to files
+    Files,
+    /// Files and directories, including symlinks to directories
+    All,
+}
+
+#[derive(Debug, Error)]
+pub enum WalkError {
+    #[error("the path you are attempting to specify ({0}) is outside of the root")]
+    OutsideRoot(String),
+    #[error(transparent)]
+    Walk(#[from] walkdir::Error),
+    #[error(transparent)]
+    Path(#[from] PathError),
+}
+
+/// Returns the paths under `base_path` that match any of the `include` globs
+/// and none of the `exclude` globs. Globs use unix separators and are
+/// relative to `base_path`. An exclude glob also excludes everything inside
+/// the directories it matches.
+///
+/// Symlinks are handled like `turborepo_fs::recursive_copy`: they are never
+/// followed, a symlink to a directory is treated as an empty directory, and
+/// broken symlinks are skipped.
+pub fn globwalk(
+    base_path: &AbsoluteSystemPath,
+    include: &[String],
+    exclude: &[String],
+    walk_type: WalkType,
+) -> Result<BTreeSet<AnchoredSystemPathBuf>, WalkError> {
+    let include = include
+        .iter()
+        .map(|glob| clean_glob(glob))
+        .collect::<Result<Vec<_>, _>>()?;
+    let exclude = exclude
+        .iter()
+        .map(|glob| clean_glob(glob))
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .flat_map(|glob| {
+            // excludes also apply to everything inside a matching directory
+            let children = if glob.is_empty() {
+                "**".to_string()
+            } else {
+                format!("{glob}/**")
+            };
+            let exact = (!glob.ends_with("/**")).then_some(glob);
+            exact.into_iter().chain(Some(children))
+        });
+
+    let include = GlobSet::new(include);
+    let exclude = GlobSet::new(exclude);
+    let mut results = BTreeSet::new();
+    if include.is_empty() {
+        return Ok(results);
+    }
+
+    let walker = WalkDir::new(base_path.as_path())
+        .follow_links(false)
+        .into_iter()
+        .filter_entry(|entry| {
+            if entry.depth() == 0 || !entry.file_type().is_dir() {
+                return true;
+            }
+            match relative_unix_path(base_path, entry.path()) {
+                Some(path) => !exclude.is_match(&path) && may_contain_matches(&include, &path),
+                None => false,
+            }
+        });
+
+    for entry in walker {
+        let entry = match entry {
+            Ok(entry) => entry,
+            // Matches go behavior where we translate path errors
+            // into skipping the path we're currently walking
+            Err(e) if e.io_error().is_some() => continue,
+            Err(e) => return Err(e.into()),
+        };
+        if entry.depth() == 0 {
+            continue;
+        }
+
+        let path = AbsoluteSystemPath::new(entry.path())?;
+        let file_type = entry.file_type();
+        let is_dir = if file_type.is_symlink() {
+            match path.stat() {
+                Ok(metadata) => metadata.is_dir(),
+                // If we have a broken link, skip this entry
+                Err(_) => continue,
+            }
+        } else {
+            file_type.is_dir()
+        };
+        if is_dir && walk_type == WalkType::Files {
+            continue;
+        }
+
+        // paths that aren't valid utf8 can't be matched
+        let Some(unix_path) = relative_unix_path(base_path, entry.path()) else {
+            continue;
+        };
+        if include.is_match(&unix_path) && !exclude.is_match(&unix_path) {
+            results.insert(AnchoredSystemPathBuf::new(base_path, path)?);
+        }
+    }
+
+    Ok(results)
+}
+
+/// Whether a directory, given as a unix path relative to the base, could
+/// contain a path matching one of the globs. A match has to start with the
+/// literal prefix of its glob, and everything in the directory starts with
+/// the directory, so one has to be a prefix of the other.
+fn may_contain_matches(globs: &GlobSet, dir: &str) -> bool {
+    let dir = format!("{dir}/");
+    globs.globs().iter().any(|glob| {
+        let prefix = glob.literal_prefix();
+        glob.is_negated() || prefix.starts_with(&dir) || dir.starts_with(prefix)
+    })
+}
+
+fn relative_unix_path(base_path: &AbsoluteSystemPath, path: &std::path::Path) -> Option<String> {
+    let relative = path.strip_prefix(base_path.as_path()).ok()?;
+    let components = relative
+        .components()
+        .map(|component| component.as_os_str().to_str())
+        .collect::<Option<Vec<_>>>()?;
+    Some(components.join("/"))
+}
+
+/// Normalizes `.` and `..` segments the same way joining the glob onto the
+/// base path would, and makes sure the glob can't match outside of the base.
+fn clean_glob(glob: &str) -> Result<String, WalkError> {
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in glob.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                if segments.pop().is_none() {
+                    return Err(WalkError::OutsideRoot(glob.to_string()));
+                }
+            }
+            segment => segments.push(segment),
+        }
+    }
+    Ok(segments.join("/"))
+}
+
+#[cfg(test)]
+mod test {
+    use std::{collections::BTreeSet, fs};
+
+    use turbopath::{AbsoluteSystemPathBuf, AnchoredSystemPathBuf};
+
+    use super::{clean_glob, globwalk, WalkError, WalkType};
+
+    fn setup() -> (tempfile::TempDir, AbsoluteSystemPathBuf) {
+        // Directory layout:
+        //
+        // <root>/
+        //   package.json
+        //   dist/
+        //     index.js
+        //     index.js.map
+        //     nested/
+        //       chunk.js
+        //   src/
+        //     index.ts
+        //     link.ts -> ../dist/index.js
+        //     broken -> missing
+        //     dist -> ../dist
+        //   node_modules/
+        //     dep/
+        //       index.js
+        let tmp = tempfile::tempdir().unwrap();
+        let root = AbsoluteSystemPathBuf::new(tmp.path()).unwrap();
+        for file in [
+            "package.json",
+            "dist/index.js",
+            "dist/index.js.map",
+            "dist/nested/chunk.js",
+            "src/index.ts",
+            "node_modules/dep/index.js",
+        ] {
+            let path = root.as_path().join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, file).unwrap();
+        }
+        let src = root.join_literal("src");
+        src.join_literal("link.ts")
+            .symlink_to_file("../dist/index.js")
+            .unwrap();
+        src.join_literal("broken")
+            .symlink_to_file("missing")
+            .unwrap();
+        src.join_literal("dist").symlink_to_dir("../dist").unwrap();
+        (tmp, root)
+    }
+
+    fn walk(
+        root: &AbsoluteSystemPathBuf,
+        include: &[&str],
+        exclude: &[&str],
+        walk_type: WalkType,
+    ) -> BTreeSet<AnchoredSystemPathBuf> {
+        let strings = |globs: &[&str]| globs.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        globwalk(
+            root.as_absolute_path(),
+            &strings(include),
+            &strings(exclude),
+            walk_type,
+        )
+        .unwrap()
+    }
+
+    fn paths(paths: &[&str]) -> BTreeSet<AnchoredSystemPathBuf> {
+        paths
+            .iter()
+            .map(|path| AnchoredSystemPathBuf::from_raw(path).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_files() {
+        let (_tmp, root) = setup();
+        assert_eq!(
+            walk(&root, &["dist/**"], &[], WalkType::Files),
+            paths(&["dist/index.js", "dist/index.js.map", "dist/nested/chunk.js"])
+        );
+        assert_eq!(
+            walk(&root, &["**/*.js"], &["node_modules"], WalkType::Files),
+            paths(&["dist/index.js", "dist/nested/chunk.js"])
+        );
+        assert_eq!(
+            walk(
+                &root,
+                &["./dist/**", "src/../package.json"],
+                &["**/*.map", "dist/nested/**"],
+                WalkType::Files
+            ),
+            paths(&["dist/index.js", "package.json"])
+        );
+    }
+
+    #[test]
+    fn test_all() {
+        let (_tmp, root) = setup();
+        assert_eq!(
+            walk(&root, &["dist/**"], &["dist/index.*"], WalkType::All),
+            paths(&["dist/nested", "dist/nested/chunk.js"])
+        );
+    }
+
+    #[test]
+    fn test_symlinks() {
+        let (_tmp, root) = setup();
+        // symlinked files are included, broken links are skipped and linked
+        // directories are not followed
+        assert_eq!(
+            walk(&root, &["src/**"], &[], WalkType::Files),
+            paths(&["src/index.ts", "src/link.ts"])
+        );
+        assert_eq!(
+            walk(&root, &["src/**"], &[], WalkType::All),
+            paths(&["src/dist", "src/index.ts", "src/link.ts"])
+        );
+    }
+
+    #[test]
+    fn test_outside_root() {
+        let (_tmp, root) = setup();
+        let result = globwalk(
+            root.as_absolute_path(),
+            &["../**".to_string()],
+            &[],
+            WalkType::Files,
+        );
+        assert!(matches!(result, Err(WalkError::OutsideRoot(_))));
+    }
+
+    #[test]
+    fn test_clean_glob() {
+        assert_eq!(clean_glob("./a/./b/").unwrap(), "a/b");
+        assert_eq!(clean_glob("/a/b/../c").unwrap(), "a/c");
+        assert_eq!(clean_glob("a/..").unwrap(), "");
+        assert!(clean_glob("a/../..").is_err());
+    }
+}
@@ -14,7 +14,7 @@ use tracing::{debug, error};
 use turbopath::AbsoluteSystemPathBuf;
 
 use crate::{
-    commands::{bin, daemon, generate, link, login, logout, unlink, CommandBase},
+    commands::{bin, config, daemon, generate, link, lockfile, login, logout, unlink, CommandBase},
     get_version,
     shim::{RepoMode, RepoState},
     tracing::TurboSubscriber,
@@ -180,6 +180,49 @@ pub enum DaemonCommand {
     Stop,
 }
 
+#[derive(Subcommand, Clone, Debug, Serialize, PartialEq)]
+#[serde(tag = "command")]
+pub enum ConfigCommand {
+    /// Prints the effective definition of a task in a workspace, and which
+    /// turbo.json each field comes from
+    Explain {
+        /// The task to explain, as <workspace>#<task>. Use // as the
+        /// workspace for the root workspace.
+        task: String,
+        /// Pass --json to print the definition in JSON format
+        #[clap(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand, Copy, Clone, Debug, Serialize, PartialEq)]
+#[serde(tag = "command")]
+pub enum LockfileCommand {
+    /// Verifies that the lockfile is consistent with the workspaces in the
+    /// repository
+    Check {
+        /// Pass --json to report problems in JSON format
+        #[clap(long)]
+        json: bool,
+    },
+    /// Converts the lockfile to the format used by another package manager
+    Convert {
+        /// The package manager whose lockfile format to convert to
+        #[clap(long, value_enum)]
+        to: LockfileFormat,
+        /// Convert even if integrity hashes can't be carried over from an
+        /// existing lockfile in the target format
+        #[clap(long)]
+        allow_lossy: bool,
+    },
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, ValueEnum)]
+pub enum LockfileFormat {
+    Npm,
+    Berry,
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Serialize, ValueEnum)]
 pub enum LinkTarget {
     RemoteCache,
@@ -244,6 +287,13 @@ pub enum Command {
     /// Generate the autocompletion script for the specified shell
     #[serde(skip)]
     Completion { shell: Shell },
+    /// Inspect the configuration of your monorepo. Prints each
+    /// configuration value and where it was set if no subcommand is given.
+    Config {
+        #[clap(subcommand)]
+        #[serde(flatten)]
+        command: Option<ConfigCommand>,
+    },
     /// Runs the Turborepo background daemon
     Daemon {
         /// Set the idle timeout for turbod
@@ -272,6 +322,12 @@ pub enum Command {
         #[serde(flatten)]
         command: GenerateCommand,
     },
+    /// Inspect the lockfile of your monorepo
+    Lockfile {
+        #[clap(subcommand)]
+        #[serde(flatten)]
+        command: LockfileCommand,
+    },
     /// Login to your Vercel account
     Login {
         #[clap(long = "sso-team")]
@@ -649,6 +705,26 @@ pub async fn run(
 
             Ok(Payload::Rust(Ok(0)))
         }
+        Command::Config { command } => {
+            let base = CommandBase::new(cli_args.clone(), repo_root, version, ui)?;
+            match command {
+                Some(ConfigCommand::Explain { task, json }) => config::explain(&base, task, *json)?,
+                None => config::print(&base)?,
+            }
+
+            Ok(Payload::Rust(Ok(0)))
+        }
+        Command::Lockfile { command } => {
+            let base = CommandBase::new(cli_args.clone(), repo_root, version, ui)?;
+            let exit_code = match command {
+                LockfileCommand::Check { json } => lockfile::check(&base, *json)?,
+                LockfileCommand::Convert { to, allow_lossy } => {
+                    lockfile::convert(&base, *to, *allow_lossy)?
+                }
+            };
+
+            Ok(Payload::Rust(Ok(exit_code)))
+        }
         Command::Run(args) => {
             if args.tasks.is_empty() {
                 return Err(anyhow!("at least one task must be specified"));
@@ -1391,6 +1467,109 @@ mod test {
         .test();
     }
 
+    #[test]
+    fn test_parse_lockfile_check() {
+        assert_eq!(
+            Args::try_parse_from(["turbo", "lockfile", "check"]).unwrap(),
+            Args {
+                command: Some(Command::Lockfile {
+                    command: crate::cli::LockfileCommand::Check { json: false }
+                }),
+                ..Args::default()
+            }
+        );
+
+        CommandTestCase {
+            command: "lockfile",
+            command_args: vec![vec!["check", "--json"]],
+            global_args: vec![vec!["--cwd", "../examples/with-yarn"]],
+            expected_output: Args {
+                command: Some(Command::Lockfile {
+                    command: crate::cli::LockfileCommand::Check { json: true },
+                }),
+                cwd: Some(PathBuf::from("../examples/with-yarn")),
+                ..Args::default()
+            },
+        }
+        .test();
+
+        assert_eq!(
+            Args::try_parse_from(["turbo", "lockfile", "convert", "--to", "berry"]).unwrap(),
+            Args {
+                command: Some(Command::Lockfile {
+                    command: crate::cli::LockfileCommand::Convert {
+                        to: crate::cli::LockfileFormat::Berry,
+                        allow_lossy: false,
+                    }
+                }),
+                ..Args::default()
+            }
+        );
+        assert_eq!(
+            Args::try_parse_from([
+                "turbo",
+                "lockfile",
+                "convert",
+                "--to",
+                "npm",
+                "--allow-lossy"
+            ])
+            .unwrap(),
+            Args {
+                command: Some(Command::Lockfile {
+                    command: crate::cli::LockfileCommand::Convert {
+                        to: crate::cli::LockfileFormat::Npm,
+                        allow_lossy: true,
+                    }
+                }),
+                ..Args::default()
+            }
+        );
+        assert!(Args::try_parse_from(["turbo", "lockfile", "convert", "--to", "pnpm"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_config_explain() {
+        assert_eq!(
+            Args::try_parse_from(["turbo", "config"]).unwrap(),
+            Args {
+                command: Some(Command::Config { command: None }),
+                ..Args::default()
+            }
+        );
+        assert_eq!(
+            Args::try_parse_from(["turbo", "config", "explain", "web#build"]).unwrap(),
+            Args {
+                command: Some(Command::Config {
+                    command: Some(crate::cli::ConfigCommand::Explain {
+                        task: "web#build".to_string(),
+                        json: false
+                    })
+                }),
+                ..Args::default()
+            }
+        );
+
+        CommandTestCase {
+            command: "config",
+            command_args: vec![vec!["explain", "//#lint", "--json"]],
+            global_args: vec![vec!["--cwd", "../examples/with-yarn"]],
+            expected_output: Args {
+                command: Some(Command::Config {
+                    command: Some(crate::cli::ConfigCommand::Explain {
+                        task: "//#lint".to_string(),
+                        json: true,
+                    }),
+                }),
+                cwd: Some(PathBuf::from("../examples/with-yarn")),
+                ..Args::default()
+            },
+        }
+        .test();
+
+        assert!(Args::try_parse_from(["turbo", "config", "explain"]).is_err());
+    }
+
     #[test]
     fn test_parse_prune() {
         let default_prune = Command::Prune {
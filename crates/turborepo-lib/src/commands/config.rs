@@ -0,0 +1,123 @@
+use anyhow::{anyhow, Result};
+use serde_json::{json, Map};
+
+use super::CommandBase;
+use crate::{
+    config::{TaskDefinitionResolver, TaskDefinitionSource},
+    package_graph::PackageGraph,
+    ui::{BOLD, GREY},
+};
+
+/// Prints every configuration value and where it was set
+pub fn print(base: &CommandBase) -> Result<()> {
+    let config = base.resolved_config()?;
+    let rows = config
+        .values()
+        .iter()
+        .map(|resolved| {
+            let value = match &resolved.value {
+                Some(_) if resolved.key.is_secret() => "********".to_string(),
+                Some(value) => value.clone(),
+                None => "-".to_string(),
+            };
+            let source = resolved
+                .source
+                .map_or_else(String::new, |source| source.to_string());
+            (resolved.key.name(), value, source)
+        })
+        .collect::<Vec<_>>();
+    let key_width = rows
+        .iter()
+        .map(|(key, ..)| key.len())
+        .max()
+        .unwrap_or(0)
+        .max("Key".len());
+    let value_width = rows
+        .iter()
+        .map(|(_, value, _)| value.len())
+        .max()
+        .unwrap_or(0)
+        .max("Value".len());
+
+    let header = format!("{:key_width$}  {:value_width$}  Source", "Key", "Value");
+    println!("{}", base.ui.apply(BOLD.apply_to(header.trim_end())));
+    for (key, value, source) in rows {
+        let line = format!("{key:key_width$}  {value:value_width$}  {source}");
+        println!("{}", line.trim_end());
+    }
+
+    Ok(())
+}
+
+/// Prints the effective definition of a task, given as `<workspace>#<task>`,
+/// and the `turbo.json` that set each of its fields
+pub fn explain(base: &CommandBase, task_id: &str, json: bool) -> Result<()> {
+    let Some((workspace, task)) = task_id.split_once('#') else {
+        return Err(anyhow!(
+            "expected a task in the form <workspace>#<task>, found \"{}\"",
+            task_id
+        ));
+    };
+
+    let package_graph = PackageGraph::build(&base.repo_root, &base.ui)?;
+    let resolver = TaskDefinitionResolver::load(
+        &base.repo_root,
+        package_graph
+            .workspaces()
+            .filter_map(|(path, package_json)| Some((package_json.name.as_deref()?, path))),
+    )?;
+    let resolved = resolver.resolved_task_definition(workspace, task)?;
+    let fields = resolved.fields()?;
+
+    if json {
+        let definition = fields
+            .iter()
+            .map(|field| (field.name.to_string(), field.value.clone()))
+            .collect::<Map<_, _>>();
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&json!({
+                "task": task_id,
+                "definition": definition,
+                "sources": resolved.sources,
+            }))?
+        );
+        return Ok(());
+    }
+
+    let describe_source = |source: &TaskDefinitionSource| match source.task == task {
+        true => source.turbo_json.clone(),
+        false => format!("{} ({})", source.turbo_json, source.task),
+    };
+    let rows = fields
+        .iter()
+        .map(|field| {
+            (
+                field.name,
+                field.value.to_string(),
+                field.source.map(describe_source),
+            )
+        })
+        .collect::<Vec<_>>();
+    let field_width = rows
+        .iter()
+        .map(|(field, ..)| field.len())
+        .max()
+        .unwrap_or(0);
+    let value_width = rows
+        .iter()
+        .map(|(_, value, _)| value.len())
+        .max()
+        .unwrap_or(0);
+
+    println!("{}", base.ui.apply(BOLD.apply_to(task_id)));
+    for (field, value, source) in rows {
+        let source = match source {
+            Some(source) => source,
+            None => base.ui.apply(GREY.apply_to("default")).to_string(),
+        };
+        println!("  {field:field_width$}  {value:value_width$}  {source}");
+    }
+
+    Ok(())
+}
@@ -1,7 +1,7 @@
 use std::{path::PathBuf, time::Duration};
 
 use pidlock::PidlockError::AlreadyOwned;
-use time::{format_description, OffsetDateTime};
+use time::{format_description, format_description::well_known::Rfc3339, OffsetDateTime};
 use tracing::{trace, warn};
 use turbopath::{AbsoluteSystemPathBuf, RelativeSystemPathBuf};
 
@@ -51,17 +51,34 @@ pub async fn daemon_client(command: &DaemonCommand, base: &CommandBase) -> Resul
                 log_file: log_file.into(),
                 pid_file: client.pid_file().to_owned(),
                 sock_file: client.sock_file().to_owned(),
+                watcher: status.watcher.map(|watcher| WatcherStatus {
+                    backend: watcher.backend,
+                    watched_globs: watcher.watched_globs,
+                    registered_hashes: watcher.registered_hashes,
+                    pending_flushes: watcher.pending_flushes,
+                    last_event: match watcher.last_event_unix_msec {
+                        0 => None,
+                        millis => Some(format_unix_millis(millis)),
+                    },
+                }),
+                timeout_ms: status.timeout_msec,
+                memory_bytes: match status.memory_bytes {
+                    0 => None,
+                    bytes => Some(bytes),
+                },
+                recent_errors: status
+                    .recent_errors
+                    .into_iter()
+                    .map(|error| DaemonErrorRecord {
+                        time: format_unix_millis(error.unix_msec),
+                        message: error.message,
+                    })
+                    .collect(),
             };
             if *json {
                 println!("{}", serde_json::to_string_pretty(&status)?);
             } else {
-                println!("Daemon log file: {}", status.log_file.to_string_lossy());
-                println!(
-                    "Daemon uptime: {}s",
-                    humantime::format_duration(Duration::from_millis(status.uptime_ms))
-                );
-                println!("Daemon pid file: {}", status.pid_file.to_string_lossy());
-                println!("Daemon socket file: {}", status.sock_file.to_string_lossy());
+                print_status(&status);
             }
         }
     };
@@ -69,6 +86,48 @@ pub async fn daemon_client(command: &DaemonCommand, base: &CommandBase) -> Resul
     Ok(())
 }
 
+fn print_status(status: &DaemonStatus) {
+    println!("Daemon log file: {}", status.log_file.to_string_lossy());
+    println!(
+        "Daemon uptime: {}s",
+        humantime::format_duration(Duration::from_millis(status.uptime_ms))
+    );
+    println!("Daemon pid file: {}", status.pid_file.to_string_lossy());
+    println!("Daemon socket file: {}", status.sock_file.to_string_lossy());
+    println!(
+        "Daemon idle timeout in: {}",
+        humantime::format_duration(Duration::from_secs(status.timeout_ms / 1000))
+    );
+    if let Some(memory_bytes) = status.memory_bytes {
+        println!("Daemon memory: {}MiB", memory_bytes / (1024 * 1024));
+    }
+    if let Some(watcher) = &status.watcher {
+        println!("Watcher backend: {}", watcher.backend);
+        println!("Watched globs: {}", watcher.watched_globs);
+        println!("Registered hashes: {}", watcher.registered_hashes);
+        println!("Pending flushes: {}", watcher.pending_flushes);
+        println!(
+            "Last filesystem event: {}",
+            watcher.last_event.as_deref().unwrap_or("never")
+        );
+    }
+    if !status.recent_errors.is_empty() {
+        println!("Recent errors:");
+        for error in &status.recent_errors {
+            println!("  {} {}", error.time, error.message);
+        }
+    }
+}
+
+/// Formats a timestamp from the daemon, falling back to the raw value if it's
+/// out of range
+fn format_unix_millis(millis: u64) -> String {
+    OffsetDateTime::from_unix_timestamp_nanos(millis as i128 * 1_000_000)
+        .ok()
+        .and_then(|time| time.format(&Rfc3339).ok())
+        .unwrap_or_else(|| millis.to_string())
+}
+
 // log_filename matches the algorithm used by tracing_appender::Rotation::DAILY
 // to generate the log filename. This is kind of a hack, but there didn't appear
 // to be a simple way to grab the generated filename.
@@ -144,4 +203,24 @@ pub struct DaemonStatus {
     pub log_file: PathBuf,
     pub pid_file: turbopath::AbsoluteSystemPathBuf,
     pub sock_file: turbopath::AbsoluteSystemPathBuf,
+    // missing if the daemon predates watcher diagnostics
+    pub watcher: Option<WatcherStatus>,
+    pub timeout_ms: u64,
+    pub memory_bytes: Option<u64>,
+    pub recent_errors: Vec<DaemonErrorRecord>,
+}
+
+#[derive(serde::Serialize)]
+pub struct WatcherStatus {
+    pub backend: String,
+    pub watched_globs: u64,
+    pub registered_hashes: u64,
+    pub pending_flushes: u64,
+    pub last_event: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+pub struct DaemonErrorRecord {
+    pub time: String,
+    pub message: String,
 }
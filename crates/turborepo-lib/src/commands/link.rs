@@ -16,6 +16,7 @@ use dialoguer::{theme::ColorfulTheme, Confirm};
 use dirs_next::home_dir;
 #[cfg(test)]
 use rand::Rng;
+use serde_json::{json, Value};
 use turbopath::RelativeSystemPathBuf;
 use turborepo_api_client::{APIClient, CachingStatus, Space, Team};
 
@@ -24,7 +25,6 @@ use crate::ui::CYAN;
 use crate::{
     cli::LinkTarget,
     commands::CommandBase,
-    config::{SpacesJson, TurboJson},
     ui::{BOLD, GREY, UNDERLINE},
 };
 
@@ -433,20 +433,19 @@ fn add_space_id_to_turbo_json(base: &CommandBase, space_id: &str) -> Result<()>
         return Err(anyhow!("turbo.json not found."));
     }
 
+    // edit the raw json so the rest of turbo.json is written back untouched
     let turbo_json_file = File::open(&turbo_json_path)?;
-    let mut turbo_json: TurboJson = serde_json::from_reader(turbo_json_file)?;
-    match turbo_json.experimental_spaces {
-        Some(mut spaces_config) => {
-            spaces_config.id = Some(space_id.to_string());
-            turbo_json.experimental_spaces = Some(spaces_config);
-        }
-        None => {
-            turbo_json.experimental_spaces = Some(SpacesJson {
-                id: Some(space_id.to_string()),
-                other: None,
-            });
-        }
+    let mut turbo_json: Value = serde_json::from_reader(turbo_json_file)?;
+    let turbo_json_object = turbo_json
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("turbo.json must be an object."))?;
+    let spaces_config = turbo_json_object
+        .entry("experimentalSpaces")
+        .or_insert_with(|| json!({}));
+    if !spaces_config.is_object() {
+        *spaces_config = json!({});
     }
+    spaces_config["id"] = json!(space_id);
 
     // write turbo_json back to file
     let config_file = File::create(&turbo_json_path)?;
@@ -0,0 +1,133 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use turbopath::RelativeSystemPathBuf;
+use turborepo_lockfiles::{LockfileReport, UnresolvedDependency};
+
+use super::CommandBase;
+use crate::{
+    cli::LockfileFormat, package_json::PackageJson, package_manager::PackageManager, ui::BOLD,
+};
+
+/// Verifies that the lockfile is consistent with the workspaces in the repo.
+/// Returns a non-zero exit code if any problems were found.
+pub fn check(base: &CommandBase, json: bool) -> Result<i32> {
+    let root_package_json = PackageJson::load(
+        &base
+            .repo_root
+            .join_relative(RelativeSystemPathBuf::new("package.json")?),
+    )?;
+    let package_manager = PackageManager::get_package_manager(base, Some(&root_package_json))?;
+
+    let mut package_jsons = package_manager.get_workspace_package_jsons(&base.repo_root)?;
+    package_jsons.insert(String::new(), root_package_json);
+
+    // Dependencies on other workspaces are resolved by the package manager
+    // without the lockfile so we exclude them.
+    let workspace_names = package_jsons
+        .values()
+        .filter_map(|package_json| package_json.name.clone())
+        .collect::<HashSet<_>>();
+    let workspaces = package_jsons
+        .iter()
+        .map(|(workspace, package_json)| {
+            let external_deps = package_json
+                .all_dependencies()
+                .filter(|(name, _)| !workspace_names.contains(*name))
+                .map(|(name, specifier)| (name.clone(), specifier.clone()))
+                .collect::<HashMap<_, _>>();
+            (workspace.clone(), external_deps)
+        })
+        .collect();
+
+    let report = package_manager.check_lockfile(&base.repo_root, workspaces)?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print_report(base, package_manager.lockfile_name(), &report);
+    }
+
+    Ok(if report.is_ok() { 0 } else { 1 })
+}
+
+/// Converts the lockfile of the repo to the lockfile format of another package
+/// manager. The original lockfile is left in place.
+pub fn convert(base: &CommandBase, to: LockfileFormat, allow_lossy: bool) -> Result<i32> {
+    let root_package_json = PackageJson::load(
+        &base
+            .repo_root
+            .join_relative(RelativeSystemPathBuf::new("package.json")?),
+    )?;
+    let package_manager = PackageManager::get_package_manager(base, Some(&root_package_json))?;
+    let target = match to {
+        LockfileFormat::Npm => PackageManager::Npm,
+        LockfileFormat::Berry => PackageManager::Berry,
+    };
+
+    package_manager.convert_lockfile(&base.repo_root, &root_package_json, &target, allow_lossy)?;
+    println!(
+        "Converted {} to {}",
+        package_manager.lockfile_name(),
+        base.ui.apply(BOLD.apply_to(target.lockfile_name()))
+    );
+
+    Ok(0)
+}
+
+fn print_report(base: &CommandBase, lockfile_name: &str, report: &LockfileReport) {
+    if report.is_ok() {
+        println!("{} has no problems", lockfile_name);
+        return;
+    }
+
+    let header = |title: &str, count: usize| {
+        println!("{} ({count})", base.ui.apply(BOLD.apply_to(title)));
+    };
+    let dependency = |dep: &UnresolvedDependency| {
+        println!("  {}: {}@{}", dep.dependent, dep.name, dep.specifier);
+    };
+
+    if !report.missing_workspaces.is_empty() {
+        header(
+            "Workspaces missing from lockfile",
+            report.missing_workspaces.len(),
+        );
+        for workspace in &report.missing_workspaces {
+            let workspace = match workspace.as_str() {
+                "" => "(root)",
+                workspace => workspace,
+            };
+            println!("  {workspace}");
+        }
+    }
+    if !report.unresolved.is_empty() {
+        header("Unresolved workspace dependencies", report.unresolved.len());
+        report.unresolved.iter().for_each(dependency);
+    }
+    if !report.dangling.is_empty() {
+        header("Dangling dependencies", report.dangling.len());
+        report.dangling.iter().for_each(dependency);
+    }
+    if !report.unreachable.is_empty() {
+        header("Unreachable entries", report.unreachable.len());
+        for key in &report.unreachable {
+            println!("  {key}");
+        }
+    }
+    if !report.missing_integrity.is_empty() {
+        header("Entries missing integrity", report.missing_integrity.len());
+        for key in &report.missing_integrity {
+            println!("  {key}");
+        }
+    }
+    if !report.errors.is_empty() {
+        header("Failed lookups", report.errors.len());
+        for error in &report.errors {
+            println!(
+                "  {}: {}@{}: {}",
+                error.dependent, error.name, error.specifier, error.error
+            );
+        }
+    }
+}
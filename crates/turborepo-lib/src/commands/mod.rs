@@ -3,22 +3,25 @@ use std::borrow::Borrow;
 use anyhow::Result;
 use sha2::{Digest, Sha256};
 use tokio::sync::OnceCell;
-use turbopath::AbsoluteSystemPathBuf;
+use turbopath::{AbsoluteSystemPathBuf, RelativeSystemPathBuf};
 use turborepo_api_client::APIClient;
 
 use crate::{
     config::{
         default_user_config_path, get_repo_config_path, ClientConfig, ClientConfigLoader,
-        RepoConfig, RepoConfigLoader, UserConfig, UserConfigLoader,
+        ConfigResolver, RepoConfig, RepoConfigLoader, ResolvedConfig, TurboJson, UserConfig,
+        UserConfigLoader,
     },
     ui::UI,
     Args,
 };
 
 pub(crate) mod bin;
+pub(crate) mod config;
 pub(crate) mod daemon;
 pub(crate) mod generate;
 pub(crate) mod link;
+pub(crate) mod lockfile;
 pub(crate) mod login;
 pub(crate) mod logout;
 pub(crate) mod unlink;
@@ -135,6 +138,20 @@ impl CommandBase {
         Ok(self.client_config.get().unwrap())
     }
 
+    /// Resolves every configuration value from the flags, the environment and
+    /// the config files, recording where each value came from
+    pub fn resolved_config(&self) -> Result<ResolvedConfig> {
+        ConfigResolver::new()
+            .with_repo_config(get_repo_config_path(self.repo_root.borrow()))
+            .with_user_config(default_user_config_path()?)
+            .with_api(self.args.api.clone())
+            .with_login(self.args.login.clone())
+            .with_team_slug(self.args.team.clone())
+            .with_token(self.args.token.clone())
+            .with_remote_cache_timeout(self.args.remote_cache_timeout)
+            .resolve()
+    }
+
     pub fn args(&self) -> &Args {
         &self.args
     }
@@ -145,7 +162,22 @@ impl CommandBase {
 
         let api_url = repo_config.api_url();
         let timeout = client_config.remote_cache_timeout();
-        APIClient::new(api_url, timeout, self.version)
+        let api_client = APIClient::new(api_url, timeout, self.version)?;
+        if self.remote_cache_signature() {
+            return Ok(api_client.with_signature_key_from_env());
+        }
+        Ok(api_client)
+    }
+
+    /// Whether the root `turbo.json` enables `remoteCache.signature`, in
+    /// which case artifacts are signed with `TURBO_REMOTE_CACHE_SIGNATURE_KEY`.
+    /// Only that key is read, so an otherwise invalid `turbo.json` doesn't
+    /// prevent commands that talk to the API from running.
+    fn remote_cache_signature(&self) -> bool {
+        let turbo_json_path = self
+            .repo_root
+            .join_relative(RelativeSystemPathBuf::new("turbo.json").expect("relative"));
+        TurboJson::read_remote_cache_signature(turbo_json_path.as_absolute_path())
     }
 
     pub fn daemon_file_root(&self) -> turbopath::AbsoluteSystemPathBuf {
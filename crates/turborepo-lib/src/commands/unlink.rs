@@ -1,9 +1,10 @@
 use std::fs::File;
 
 use anyhow::{Context, Result};
+use serde_json::Value;
 use turbopath::RelativeSystemPathBuf;
 
-use crate::{cli::LinkTarget, commands::CommandBase, config::TurboJson, ui::GREY};
+use crate::{cli::LinkTarget, commands::CommandBase, ui::GREY};
 
 enum UnlinkSpacesResult {
     Unlinked,
@@ -58,16 +59,18 @@ fn remove_spaces_from_turbo_json(base: &CommandBase) -> Result<UnlinkSpacesResul
         .repo_root
         .join_relative(RelativeSystemPathBuf::new("turbo.json").expect("relative"));
 
+    // edit the raw json so the rest of turbo.json is written back untouched
     let turbo_json_file = File::open(&turbo_json_path).context("unable to open turbo.json file")?;
-    let mut turbo_json: TurboJson = serde_json::from_reader(turbo_json_file)?;
-    let has_spaces_id = turbo_json
-        .experimental_spaces
-        .unwrap_or_default()
-        .id
-        .is_some();
+    let mut turbo_json: Value = serde_json::from_reader(turbo_json_file)?;
     // remove the spaces config
     // TODO: in the future unlink should possible just remove the spaces id
-    turbo_json.experimental_spaces = None;
+    let spaces_config = turbo_json
+        .as_object_mut()
+        .and_then(|turbo_json| turbo_json.remove("experimentalSpaces"));
+    let has_spaces_id = spaces_config
+        .as_ref()
+        .and_then(|spaces_config| spaces_config.get("id"))
+        .map_or(false, |id| !id.is_null());
 
     // write turbo_json back to file
     let config_file = File::create(&turbo_json_path)?;
@@ -1,10 +1,9 @@
 use std::collections::HashMap;
 
 use anyhow::Result;
-use config::{Config, ConfigError, Environment};
 use serde::{Deserialize, Serialize};
 
-const DEFAULT_TIMEOUT: u64 = 20;
+use super::ConfigResolver;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ClientConfig {
@@ -55,22 +54,16 @@ impl ClientConfigLoader {
             environment,
         } = self;
 
-        let config_attempt: Result<ClientConfigValue, ConfigError> = Config::builder()
-            .set_default("remote_cache_timeout", DEFAULT_TIMEOUT)?
-            .add_source(Environment::with_prefix("turbo").source(environment))
-            .set_override_option("remote_cache_timeout", remote_cache_timeout)?
-            .build()?
-            .try_deserialize();
-
-        // This goes wrong when TURBO_REMOTE_CACHE_TIMEOUT can't be deserialized to u64
-        match config_attempt {
-            Err(_) => Ok(ClientConfig {
-                config: ClientConfigValue {
-                    remote_cache_timeout: DEFAULT_TIMEOUT,
-                },
-            }),
-            Ok(config) => Ok(ClientConfig { config }),
-        }
+        let resolved = ConfigResolver::new()
+            .with_remote_cache_timeout(remote_cache_timeout)
+            .with_environment(environment)
+            .resolve()?;
+
+        Ok(ClientConfig {
+            config: ClientConfigValue {
+                remote_cache_timeout: resolved.remote_cache_timeout(),
+            },
+        })
     }
 }
 
@@ -79,6 +72,7 @@ mod test {
     use std::env::set_var;
 
     use super::*;
+    use crate::config::resolver::DEFAULT_TIMEOUT;
 
     // We group these test functions under one test to
     // avoid race conditions with environment variables
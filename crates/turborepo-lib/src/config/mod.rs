@@ -1,6 +1,7 @@
 mod client;
-mod env;
 mod repo;
+mod resolver;
+mod task_definition;
 mod turbo;
 mod user;
 
@@ -16,10 +17,17 @@ use dirs_next::config_dir;
 // We use cache_dir so we can find the config dir that the Go code uses
 #[cfg(windows)]
 use dirs_next::data_local_dir as config_dir;
-pub use env::MappedEnvironment;
 pub use repo::{get_repo_config_path, RepoConfig, RepoConfigLoader};
+pub use resolver::{ConfigKey, ConfigResolver, ConfigSource, ResolvedConfig, ResolvedValue};
 use serde::Serialize;
-pub use turbo::{SpacesJson, TurboJson};
+pub use task_definition::{
+    ResolveError, ResolvedField, ResolvedTaskDefinition, TaskDefinitionResolver,
+    TaskDefinitionSource,
+};
+pub use turbo::{
+    Diagnostic, OutputMode, Pipeline, RemoteCacheOptions, SpacesJson, TaskDefinition, TurboJson,
+    TurboJsonError,
+};
 pub use user::{UserConfig, UserConfigLoader};
 
 pub fn default_user_config_path() -> Result<PathBuf> {
@@ -1,14 +1,13 @@
-use std::{collections::HashMap, env};
+use std::collections::HashMap;
 
 use anyhow::Result;
-use config::Config;
 use serde::{Deserialize, Serialize};
 use turbopath::{AbsoluteSystemPath, AbsoluteSystemPathBuf, RelativeSystemPathBuf};
 
-use super::{write_to_disk, MappedEnvironment};
-
-const DEFAULT_API_URL: &str = "https://vercel.com/api";
-const DEFAULT_LOGIN_URL: &str = "https://vercel.com";
+use super::{
+    resolver::{read_config_file, DEFAULT_API_URL, DEFAULT_LOGIN_URL},
+    write_to_disk, ConfigKey, ConfigResolver,
+};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct RepoConfig {
@@ -27,6 +26,10 @@ struct RepoConfigValue {
     team_slug: Option<String>,
     #[serde(rename = "teamid")]
     team_id: Option<String>,
+    #[serde(rename = "watcherbackend")]
+    watcher_backend: Option<String>,
+    #[serde(rename = "watcherpollinterval")]
+    watcher_poll_interval: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -62,6 +65,18 @@ impl RepoConfig {
         self.config.team_id.as_deref()
     }
 
+    /// The backend the daemon uses to watch files, one of `auto`, `native`,
+    /// `poll` or `poll-contents`
+    pub fn watcher_backend(&self) -> Option<&str> {
+        self.config.watcher_backend.as_deref()
+    }
+
+    /// How often the daemon scans the filesystem when polling, in
+    /// milliseconds. Defaults to a longer interval for `poll-contents`.
+    pub fn watcher_poll_interval(&self) -> Option<&str> {
+        self.config.watcher_poll_interval.as_deref()
+    }
+
     /// Sets the team id and clears the team slug, since it may have been from
     /// an old team
     #[allow(dead_code)]
@@ -128,45 +143,27 @@ impl RepoConfigLoader {
             team_slug,
             environment,
         } = self;
-        let raw_disk_config = Config::builder()
-            .add_source(
-                config::File::with_name(path.to_string_lossy().as_ref())
-                    .format(config::FileFormat::Json)
-                    .required(false),
-            )
-            .build()?;
-
-        let has_team_slug_override = team_slug.is_some();
-
-        let mut config: RepoConfigValue = Config::builder()
-            .add_source(raw_disk_config.clone())
-            .add_source(
-                MappedEnvironment::with_prefix("turbo")
-                    .source(environment)
-                    .replace("api", "apiurl")
-                    .replace("login", "loginurl")
-                    .replace("team", "teamslug"),
-            )
-            .set_override_option("apiurl", api)?
-            .set_override_option("loginurl", login)?
-            .set_override_option("teamslug", team_slug)?
-            // set teamid to none if teamslug present
-            .build()?
-            .try_deserialize()?;
+        let raw_disk_config = read_config_file(path.to_string_lossy().as_ref())?;
+
+        let resolved = ConfigResolver::new()
+            .with_loaded_repo_config(raw_disk_config.clone())
+            .with_api(api)
+            .with_login(login)
+            .with_team_slug(team_slug)
+            .with_environment(environment)
+            .resolve()?;
+        let value = |key| resolved.get(key).map(|value| value.to_string());
+        let config = RepoConfigValue {
+            api_url: value(ConfigKey::ApiUrl),
+            login_url: value(ConfigKey::LoginUrl),
+            team_slug: value(ConfigKey::TeamSlug),
+            team_id: value(ConfigKey::TeamId),
+            watcher_backend: value(ConfigKey::WatcherBackend),
+            watcher_poll_interval: value(ConfigKey::WatcherPollInterval),
+        };
 
         let disk_config: RepoConfigValue = raw_disk_config.try_deserialize()?;
 
-        // If teamid was passed via command line flag we ignore team slug as it
-        // might not match.
-        if has_team_slug_override {
-            config.team_id = None;
-        }
-
-        // We don't set this above because it's specific to team_id
-        if let Ok(vercel_artifacts_owner) = env::var("VERCEL_ARTIFACTS_OWNER") {
-            config.team_id = Some(vercel_artifacts_owner);
-        }
-
         Ok(RepoConfig {
             disk_config,
             config,
@@ -232,6 +229,33 @@ mod test {
         assert_eq!(config.team_id(), None);
     }
 
+    #[test]
+    fn test_watcher_backend() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let config_path = AbsoluteSystemPathBuf::new(dir.path().join("config.json"))?;
+        std::fs::write(config_path.as_path(), r#"{"watcherbackend": "poll"}"#)?;
+
+        let config = RepoConfigLoader::new(config_path.clone()).load()?;
+        assert_eq!(config.watcher_backend(), Some("poll"));
+
+        let config = RepoConfigLoader::new(config_path.clone())
+            .with_environment(Some(HashMap::from([(
+                "TURBO_WATCHER_BACKEND".to_string(),
+                "poll-contents".to_string(),
+            )])))
+            .load()?;
+        assert_eq!(config.watcher_backend(), Some("poll-contents"));
+
+        std::fs::write(
+            config_path.as_path(),
+            r#"{"watcherbackend": "poll", "watcherpollinterval": "1000"}"#,
+        )?;
+        let config = RepoConfigLoader::new(config_path).load()?;
+        assert_eq!(config.watcher_poll_interval(), Some("1000"));
+
+        Ok(())
+    }
+
     #[test]
     fn test_team_override_clears_id() -> Result<()> {
         let mut config_file = NamedTempFile::new()?;
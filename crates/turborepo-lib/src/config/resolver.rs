@@ -0,0 +1,556 @@
+//! Resolves each configuration value from every place it can be set, and
+//! records which of them the value came from.
+//!
+//! From lowest to highest precedence, values come from defaults, the user
+//! config file, the repo config file at `.turbo/config.json`, environment
+//! variables and command line flags. Config file keys are case insensitive,
+//! like they are for viper on the Go side, and every environment variable
+//! named after a config file key, like `TURBO_APIURL`, is accepted alongside
+//! the documented one, like `TURBO_API`.
+
+use std::{collections::HashMap, env, fmt, path::PathBuf};
+
+use anyhow::Result;
+use config::Config;
+use serde::Serialize;
+use turbopath::AbsoluteSystemPathBuf;
+
+pub const DEFAULT_API_URL: &str = "https://vercel.com/api";
+pub const DEFAULT_LOGIN_URL: &str = "https://vercel.com";
+pub const DEFAULT_TIMEOUT: u64 = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ConfigKey {
+    ApiUrl,
+    LoginUrl,
+    TeamSlug,
+    TeamId,
+    Token,
+    RemoteCacheTimeout,
+    WatcherBackend,
+    WatcherPollInterval,
+}
+
+impl ConfigKey {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ConfigKey::ApiUrl => "apiUrl",
+            ConfigKey::LoginUrl => "loginUrl",
+            ConfigKey::TeamSlug => "teamSlug",
+            ConfigKey::TeamId => "teamId",
+            ConfigKey::Token => "token",
+            ConfigKey::RemoteCacheTimeout => "remoteCacheTimeout",
+            ConfigKey::WatcherBackend => "watcherBackend",
+            ConfigKey::WatcherPollInterval => "watcherPollInterval",
+        }
+    }
+
+    /// Whether the value shouldn't be printed
+    pub fn is_secret(&self) -> bool {
+        matches!(self, ConfigKey::Token)
+    }
+}
+
+/// Where a configuration value came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigSource {
+    Default,
+    UserConfig,
+    RepoConfig,
+    /// The name of the environment variable
+    Environment(&'static str),
+    /// The command line flag
+    Flag(&'static str),
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigSource::Default => write!(f, "default"),
+            ConfigSource::UserConfig => write!(f, "user config"),
+            ConfigSource::RepoConfig => write!(f, ".turbo/config.json"),
+            ConfigSource::Environment(name) => write!(f, "${}", name),
+            ConfigSource::Flag(flag) => write!(f, "{}", flag),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedValue {
+    pub key: ConfigKey,
+    pub value: Option<String>,
+    /// The source that set the value. A value that was cleared, like the team
+    /// id when `--team` is passed, has a source but no value.
+    pub source: Option<ConfigSource>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedConfig {
+    values: Vec<ResolvedValue>,
+}
+
+impl ResolvedConfig {
+    /// Every value, in a stable order
+    pub fn values(&self) -> &[ResolvedValue] {
+        &self.values
+    }
+
+    pub fn get(&self, key: ConfigKey) -> Option<&str> {
+        self.resolved(key).value.as_deref()
+    }
+
+    pub fn source(&self, key: ConfigKey) -> Option<ConfigSource> {
+        self.resolved(key).source
+    }
+
+    pub fn remote_cache_timeout(&self) -> u64 {
+        self.get(ConfigKey::RemoteCacheTimeout)
+            .and_then(|timeout| timeout.parse().ok())
+            .unwrap_or(DEFAULT_TIMEOUT)
+    }
+
+    fn resolved(&self, key: ConfigKey) -> &ResolvedValue {
+        self.values
+            .iter()
+            .find(|value| value.key == key)
+            .expect("every key is resolved")
+    }
+}
+
+/// A config file, either to be read when resolving or already read by the
+/// caller
+#[derive(Debug, Clone)]
+enum ConfigFile {
+    Path(String),
+    Loaded(Config),
+}
+
+impl ConfigFile {
+    fn load(&self) -> Result<Config> {
+        match self {
+            ConfigFile::Path(path) => read_config_file(path),
+            ConfigFile::Loaded(config) => Ok(config.clone()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ConfigResolver {
+    repo_config: Option<ConfigFile>,
+    user_config: Option<ConfigFile>,
+    api: Option<String>,
+    login: Option<String>,
+    team_slug: Option<String>,
+    token: Option<String>,
+    remote_cache_timeout: Option<u64>,
+    environment: Option<HashMap<String, String>>,
+}
+
+impl ConfigResolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_repo_config(mut self, path: AbsoluteSystemPathBuf) -> Self {
+        self.repo_config = Some(ConfigFile::Path(path.to_string_lossy().to_string()));
+        self
+    }
+
+    /// Use a repo config file that has already been read with
+    /// `read_config_file`, instead of reading it again
+    pub fn with_loaded_repo_config(mut self, config: Config) -> Self {
+        self.repo_config = Some(ConfigFile::Loaded(config));
+        self
+    }
+
+    pub fn with_user_config(mut self, path: PathBuf) -> Self {
+        self.user_config = Some(ConfigFile::Path(path.to_string_lossy().to_string()));
+        self
+    }
+
+    /// Use a user config file that has already been read with
+    /// `read_config_file`, instead of reading it again
+    pub fn with_loaded_user_config(mut self, config: Config) -> Self {
+        self.user_config = Some(ConfigFile::Loaded(config));
+        self
+    }
+
+    pub fn with_api(mut self, api: Option<String>) -> Self {
+        self.api = api;
+        self
+    }
+
+    pub fn with_login(mut self, login: Option<String>) -> Self {
+        self.login = login;
+        self
+    }
+
+    pub fn with_team_slug(mut self, team_slug: Option<String>) -> Self {
+        self.team_slug = team_slug;
+        self
+    }
+
+    pub fn with_token(mut self, token: Option<String>) -> Self {
+        self.token = token;
+        self
+    }
+
+    pub fn with_remote_cache_timeout(mut self, remote_cache_timeout: Option<u64>) -> Self {
+        self.remote_cache_timeout = remote_cache_timeout;
+        self
+    }
+
+    /// Use the given environment map instead of querying the process's
+    /// environment
+    pub fn with_environment(mut self, environment: Option<HashMap<String, String>>) -> Self {
+        self.environment = environment;
+        self
+    }
+
+    pub fn resolve(&self) -> Result<ResolvedConfig> {
+        let load = |file: &Option<ConfigFile>| match file {
+            Some(file) => file.load(),
+            None => Ok(Config::default()),
+        };
+        let repo_config = load(&self.repo_config)?;
+        let user_config = load(&self.user_config)?;
+
+        let default = |value: &str| (ConfigSource::Default, Some(value.to_string()));
+        let repo = |key: &str| (ConfigSource::RepoConfig, repo_config.get_string(key).ok());
+        let user = |key: &str| (ConfigSource::UserConfig, user_config.get_string(key).ok());
+        let env = |name: &'static str| (ConfigSource::Environment(name), self.env_var(name));
+        let flag =
+            |flag: &'static str, value: &Option<String>| (ConfigSource::Flag(flag), value.clone());
+
+        let mut team_id = resolve(ConfigKey::TeamId, [repo("teamid"), env("TURBO_TEAMID")]);
+        // the team id may not match a team slug passed on the command line
+        if self.team_slug.is_some() {
+            team_id.value = None;
+            team_id.source = Some(ConfigSource::Flag("--team"));
+        }
+        // Vercel sets this to the team that owns the build, so it always wins
+        if let (source, Some(owner)) = env("VERCEL_ARTIFACTS_OWNER") {
+            team_id.value = Some(owner);
+            team_id.source = Some(source);
+        }
+
+        let values = vec![
+            resolve(
+                ConfigKey::ApiUrl,
+                [
+                    default(DEFAULT_API_URL),
+                    repo("apiurl"),
+                    env("TURBO_APIURL"),
+                    env("TURBO_API"),
+                    flag("--api", &self.api),
+                ],
+            ),
+            resolve(
+                ConfigKey::LoginUrl,
+                [
+                    default(DEFAULT_LOGIN_URL),
+                    repo("loginurl"),
+                    env("TURBO_LOGINURL"),
+                    env("TURBO_LOGIN"),
+                    flag("--login", &self.login),
+                ],
+            ),
+            resolve(
+                ConfigKey::TeamSlug,
+                [
+                    repo("teamslug"),
+                    env("TURBO_TEAMSLUG"),
+                    env("TURBO_TEAM"),
+                    flag("--team", &self.team_slug),
+                ],
+            ),
+            team_id,
+            resolve(
+                ConfigKey::Token,
+                [
+                    user("token"),
+                    env("TURBO_TOKEN"),
+                    env("VERCEL_ARTIFACTS_TOKEN"),
+                    flag("--token", &self.token),
+                ],
+            ),
+            resolve(
+                ConfigKey::RemoteCacheTimeout,
+                [
+                    default(&DEFAULT_TIMEOUT.to_string()),
+                    // a timeout that isn't a number is ignored
+                    match env("TURBO_REMOTE_CACHE_TIMEOUT") {
+                        (source, Some(timeout)) if timeout.parse::<u64>().is_ok() => {
+                            (source, Some(timeout))
+                        }
+                        (source, _) => (source, None),
+                    },
+                    flag(
+                        "--remote-cache-timeout",
+                        &self.remote_cache_timeout.map(|timeout| timeout.to_string()),
+                    ),
+                ],
+            ),
+            resolve(
+                ConfigKey::WatcherBackend,
+                [
+                    repo("watcherbackend"),
+                    env("TURBO_WATCHERBACKEND"),
+                    env("TURBO_WATCHER_BACKEND"),
+                ],
+            ),
+            resolve(
+                ConfigKey::WatcherPollInterval,
+                [
+                    repo("watcherpollinterval"),
+                    env("TURBO_WATCHER_POLL_INTERVAL"),
+                ],
+            ),
+        ];
+
+        Ok(ResolvedConfig { values })
+    }
+
+    fn env_var(&self, name: &str) -> Option<String> {
+        match &self.environment {
+            Some(environment) => environment.get(name).cloned(),
+            None => env::var(name).ok(),
+        }
+    }
+}
+
+/// Takes the value from the last source that sets it
+fn resolve(
+    key: ConfigKey,
+    sources: impl IntoIterator<Item = (ConfigSource, Option<String>)>,
+) -> ResolvedValue {
+    sources
+        .into_iter()
+        .filter_map(|(source, value)| Some((source, value?)))
+        .last()
+        .map_or(
+            ResolvedValue {
+                key,
+                value: None,
+                source: None,
+            },
+            |(source, value)| ResolvedValue {
+                key,
+                value: Some(value),
+                source: Some(source),
+            },
+        )
+}
+
+/// Reads a JSON config file, which may not exist
+pub(crate) fn read_config_file(path: &str) -> Result<Config> {
+    Ok(Config::builder()
+        .add_source(
+            config::File::with_name(path)
+                .format(config::FileFormat::Json)
+                .required(false),
+        )
+        .build()?)
+}
+
+#[cfg(test)]
+mod test {
+    use std::{collections::HashMap, fs};
+
+    use anyhow::Result;
+    use turbopath::AbsoluteSystemPathBuf;
+
+    use super::{ConfigKey, ConfigResolver, ConfigSource, DEFAULT_API_URL};
+
+    fn resolver(
+        repo_config: &str,
+        user_config: &str,
+        environment: &[(&str, &str)],
+    ) -> Result<(tempfile::TempDir, ConfigResolver)> {
+        let dir = tempfile::tempdir()?;
+        let repo_config_path = dir.path().join("repo.json");
+        let user_config_path = dir.path().join("user.json");
+        fs::write(&repo_config_path, repo_config)?;
+        fs::write(&user_config_path, user_config)?;
+        let environment = environment
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect::<HashMap<_, _>>();
+
+        let resolver = ConfigResolver::new()
+            .with_repo_config(AbsoluteSystemPathBuf::new(repo_config_path)?)
+            .with_user_config(user_config_path)
+            .with_environment(Some(environment));
+        Ok((dir, resolver))
+    }
+
+    #[test]
+    fn test_precedence() -> Result<()> {
+        let (_dir, resolver) = resolver(
+            r#"{"apiUrl": "http://repo-api", "teamslug": "repo-team", "loginurl": "http://repo-login"}"#,
+            r#"{"token": "user-token"}"#,
+            &[("TURBO_API", "http://env-api"), ("TURBO_TEAM", "env-team")],
+        )?;
+        let config = resolver
+            .with_team_slug(Some("flag-team".into()))
+            .resolve()?;
+
+        let values = config
+            .values()
+            .iter()
+            .map(|value| (value.key, value.value.as_deref(), value.source))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            values,
+            vec![
+                (
+                    ConfigKey::ApiUrl,
+                    Some("http://env-api"),
+                    Some(ConfigSource::Environment("TURBO_API"))
+                ),
+                (
+                    ConfigKey::LoginUrl,
+                    Some("http://repo-login"),
+                    Some(ConfigSource::RepoConfig)
+                ),
+                (
+                    ConfigKey::TeamSlug,
+                    Some("flag-team"),
+                    Some(ConfigSource::Flag("--team"))
+                ),
+                (ConfigKey::TeamId, None, Some(ConfigSource::Flag("--team"))),
+                (
+                    ConfigKey::Token,
+                    Some("user-token"),
+                    Some(ConfigSource::UserConfig)
+                ),
+                (
+                    ConfigKey::RemoteCacheTimeout,
+                    Some("20"),
+                    Some(ConfigSource::Default)
+                ),
+                (ConfigKey::WatcherBackend, None, None),
+                (ConfigKey::WatcherPollInterval, None, None),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_defaults() -> Result<()> {
+        let config = ConfigResolver::new()
+            .with_environment(Some(HashMap::new()))
+            .resolve()?;
+        assert_eq!(config.get(ConfigKey::ApiUrl), Some(DEFAULT_API_URL));
+        assert_eq!(
+            config.source(ConfigKey::ApiUrl),
+            Some(ConfigSource::Default)
+        );
+        assert_eq!(config.get(ConfigKey::Token), None);
+        assert_eq!(config.source(ConfigKey::Token), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_env_aliases() -> Result<()> {
+        let (_dir, resolver) = resolver(
+            "{}",
+            "{}",
+            &[
+                ("TURBO_APIURL", "http://alias-api"),
+                ("TURBO_LOGINURL", "http://alias-login"),
+                ("TURBO_TEAMSLUG", "alias-team"),
+            ],
+        )?;
+        let config = resolver.resolve()?;
+        assert_eq!(config.get(ConfigKey::ApiUrl), Some("http://alias-api"));
+        assert_eq!(
+            config.source(ConfigKey::ApiUrl),
+            Some(ConfigSource::Environment("TURBO_APIURL"))
+        );
+        assert_eq!(config.get(ConfigKey::LoginUrl), Some("http://alias-login"));
+        assert_eq!(config.get(ConfigKey::TeamSlug), Some("alias-team"));
+
+        // the documented name wins over the alias
+        let config = resolver
+            .with_environment(Some(HashMap::from([
+                ("TURBO_APIURL".to_string(), "http://alias-api".to_string()),
+                ("TURBO_API".to_string(), "http://env-api".to_string()),
+            ])))
+            .resolve()?;
+        assert_eq!(config.get(ConfigKey::ApiUrl), Some("http://env-api"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_team_id() -> Result<()> {
+        let (_dir, resolver) = resolver(r#"{"teamid": "repo-id"}"#, "{}", &[])?;
+        let config = resolver.resolve()?;
+        assert_eq!(config.get(ConfigKey::TeamId), Some("repo-id"));
+
+        let config = resolver
+            .clone()
+            .with_environment(Some(HashMap::from([(
+                "TURBO_TEAMID".to_string(),
+                "env-id".to_string(),
+            )])))
+            .resolve()?;
+        assert_eq!(config.get(ConfigKey::TeamId), Some("env-id"));
+
+        let config = resolver
+            .with_team_slug(Some("flag-team".into()))
+            .with_environment(Some(HashMap::from([(
+                "VERCEL_ARTIFACTS_OWNER".to_string(),
+                "owner-id".to_string(),
+            )])))
+            .resolve()?;
+        assert_eq!(config.get(ConfigKey::TeamId), Some("owner-id"));
+        assert_eq!(
+            config.source(ConfigKey::TeamId),
+            Some(ConfigSource::Environment("VERCEL_ARTIFACTS_OWNER"))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_remote_cache_timeout() -> Result<()> {
+        let (_dir, resolver) = resolver("{}", "{}", &[("TURBO_REMOTE_CACHE_TIMEOUT", "garbage")])?;
+        let config = resolver.resolve()?;
+        assert_eq!(config.remote_cache_timeout(), 20);
+        assert_eq!(
+            config.source(ConfigKey::RemoteCacheTimeout),
+            Some(ConfigSource::Default)
+        );
+
+        let config = resolver.with_remote_cache_timeout(Some(0)).resolve()?;
+        assert_eq!(config.remote_cache_timeout(), 0);
+        assert_eq!(
+            config.source(ConfigKey::RemoteCacheTimeout),
+            Some(ConfigSource::Flag("--remote-cache-timeout"))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_token() -> Result<()> {
+        let (_dir, resolver) = resolver(
+            "{}",
+            r#"{"token": "user-token"}"#,
+            &[
+                ("TURBO_TOKEN", "turbo-token"),
+                ("VERCEL_ARTIFACTS_TOKEN", "vercel-token"),
+            ],
+        )?;
+        let config = resolver.resolve()?;
+        assert_eq!(config.get(ConfigKey::Token), Some("vercel-token"));
+        assert_eq!(
+            config.source(ConfigKey::Token),
+            Some(ConfigSource::Environment("VERCEL_ARTIFACTS_TOKEN"))
+        );
+
+        let config = resolver.with_token(Some("flag-token".into())).resolve()?;
+        assert_eq!(config.get(ConfigKey::Token), Some("flag-token"));
+        Ok(())
+    }
+}
@@ -0,0 +1,408 @@
+//! Resolves the definition of a task in a workspace from the root
+//! `turbo.json` and the workspace's own `turbo.json`, which extends it.
+//!
+//! Definitions are merged the way `MergeTaskDefinitions` does on the Go side:
+//! the root definition, either `<workspace>#<task>` or `<task>`, comes first
+//! and every field the workspace `turbo.json` sets replaces the root's value.
+//! Fields are replaced as a whole, so lists aren't concatenated.
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+use thiserror::Error;
+use turbopath::{AbsoluteSystemPathBuf, PathError};
+
+use super::{OutputMode, TaskDefinition, TurboJson, TurboJsonError};
+
+const TURBO_JSON: &str = "turbo.json";
+const ROOT_WORKSPACE: &str = "//";
+/// The fields of a task definition, as they are named in `turbo.json`
+const FIELDS: &[&str] = &[
+    "dependsOn",
+    "inputs",
+    "outputs",
+    "env",
+    "passThroughEnv",
+    "cache",
+    "persistent",
+    "outputMode",
+];
+
+#[derive(Debug, Error)]
+pub enum ResolveError {
+    #[error(
+        "Could not find turbo.json. Follow directions at https://turbo.build/repo/docs to create \
+         one"
+    )]
+    NoRootTurboJson,
+    #[error("Could not find workspace \"{0}\"")]
+    UnknownWorkspace(String),
+    #[error(
+        "Could not find \"{workspace}#{task}\" in root turbo.json or \"{workspace}\" workspace"
+    )]
+    UnknownTask { workspace: String, task: String },
+    #[error(transparent)]
+    TurboJson(#[from] TurboJsonError),
+    #[error(transparent)]
+    Path(#[from] PathError),
+    #[error("invalid task definition: {0}")]
+    Merge(#[from] serde_json::Error),
+}
+
+/// Where a field of a resolved task definition was set
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskDefinitionSource {
+    /// The unix path of the `turbo.json`, relative to the repo root
+    pub turbo_json: String,
+    /// The key of the definition in the `pipeline`
+    pub task: String,
+}
+
+/// A task definition merged from every `turbo.json` that defines the task
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ResolvedTaskDefinition {
+    /// Fields that no `turbo.json` sets are `None`, and take their default
+    /// value.
+    pub definition: TaskDefinition,
+    /// The source of every field that is set in `definition`, keyed by the
+    /// name of the field in `turbo.json`
+    pub sources: BTreeMap<String, TaskDefinitionSource>,
+}
+
+/// A field of a resolved task definition
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedField<'a> {
+    /// The name of the field in `turbo.json`
+    pub name: &'static str,
+    pub value: Value,
+    /// `None` if the field has its default value
+    pub source: Option<&'a TaskDefinitionSource>,
+}
+
+impl ResolvedTaskDefinition {
+    /// The value of every field, in the order they are declared in, with
+    /// defaults filled in for fields that aren't set. A field with no source
+    /// has its default value.
+    pub fn fields(&self) -> Result<Vec<ResolvedField>, ResolveError> {
+        let defaults = to_map(&TaskDefinition {
+            depends_on: Some(Vec::new()),
+            inputs: Some(Vec::new()),
+            outputs: Some(Vec::new()),
+            env: Some(Vec::new()),
+            // leaving passThroughEnv unset is different to setting it to an
+            // empty list, so it has no default
+            pass_through_env: None,
+            cache: Some(true),
+            persistent: Some(false),
+            output_mode: Some(OutputMode::Full),
+            other: Map::new(),
+        })?;
+        let fields = to_map(&self.definition)?;
+
+        Ok(FIELDS
+            .iter()
+            .map(|field| {
+                let value = fields
+                    .get(*field)
+                    .or_else(|| defaults.get(*field))
+                    .cloned()
+                    .unwrap_or(Value::Null);
+                ResolvedField {
+                    name: field,
+                    value,
+                    source: self.sources.get(*field),
+                }
+            })
+            .collect())
+    }
+}
+
+#[derive(Debug)]
+struct WorkspaceTurboJson {
+    /// The unix path of the `turbo.json`, relative to the repo root
+    path: String,
+    turbo_json: TurboJson,
+}
+
+/// The root `turbo.json` and the `turbo.json` of every workspace that has one
+#[derive(Debug)]
+pub struct TaskDefinitionResolver {
+    root: TurboJson,
+    /// Keyed by package name. Workspaces without a `turbo.json` are `None`.
+    workspaces: HashMap<String, Option<WorkspaceTurboJson>>,
+}
+
+impl TaskDefinitionResolver {
+    /// Reads and validates the root `turbo.json` and the `turbo.json` of each
+    /// workspace, given as its package name and the unix path of the
+    /// workspace relative to `repo_root`.
+    pub fn load<'a>(
+        repo_root: &AbsoluteSystemPathBuf,
+        workspaces: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> Result<Self, ResolveError> {
+        let root_path = repo_root.join_literal(TURBO_JSON);
+        if !root_path.exists() {
+            return Err(ResolveError::NoRootTurboJson);
+        }
+        let root = TurboJson::read(root_path.as_absolute_path())?;
+
+        let mut workspace_turbo_jsons = HashMap::new();
+        for (name, path) in workspaces {
+            if path.is_empty() {
+                continue;
+            }
+            let turbo_json_path = format!("{}/{}", path, TURBO_JSON);
+            let absolute_path = repo_root.join_unix_path_literal(&turbo_json_path)?;
+            // a workspace doesn't need its own turbo.json
+            let turbo_json = match absolute_path.exists() {
+                true => Some(WorkspaceTurboJson {
+                    path: turbo_json_path,
+                    turbo_json: TurboJson::read_workspace(absolute_path.as_absolute_path())?,
+                }),
+                false => None,
+            };
+            workspace_turbo_jsons.insert(name.to_string(), turbo_json);
+        }
+
+        Ok(Self {
+            root,
+            workspaces: workspace_turbo_jsons,
+        })
+    }
+
+    /// Merges the definitions of `task` in `workspace`, which is a package
+    /// name or `//` for the root workspace
+    pub fn resolved_task_definition(
+        &self,
+        workspace: &str,
+        task: &str,
+    ) -> Result<ResolvedTaskDefinition, ResolveError> {
+        let workspace_turbo_json = match workspace {
+            ROOT_WORKSPACE => None,
+            workspace => self
+                .workspaces
+                .get(workspace)
+                .ok_or_else(|| ResolveError::UnknownWorkspace(workspace.to_string()))?
+                .as_ref(),
+        };
+
+        let mut chain = Vec::new();
+        let package_task = format!("{}#{}", workspace, task);
+        if let Some((key, definition)) = self
+            .root
+            .pipeline
+            .get_key_value(&package_task)
+            .or_else(|| self.root.pipeline.get_key_value(task))
+        {
+            chain.push((TURBO_JSON, key, definition));
+        }
+        if let Some(WorkspaceTurboJson { path, turbo_json }) = workspace_turbo_json {
+            if let Some((key, definition)) = turbo_json.pipeline.get_key_value(task) {
+                chain.push((path.as_str(), key, definition));
+            }
+        }
+        if chain.is_empty() {
+            return Err(ResolveError::UnknownTask {
+                workspace: workspace.to_string(),
+                task: task.to_string(),
+            });
+        }
+
+        let mut merged = Map::new();
+        let mut sources = BTreeMap::new();
+        for (turbo_json, key, definition) in chain {
+            for (field, value) in to_map(definition)? {
+                // unknown keys aren't part of the definition
+                if !FIELDS.contains(&field.as_str()) {
+                    continue;
+                }
+                sources.insert(
+                    field.clone(),
+                    TaskDefinitionSource {
+                        turbo_json: turbo_json.to_string(),
+                        task: key.clone(),
+                    },
+                );
+                merged.insert(field, value);
+            }
+        }
+
+        Ok(ResolvedTaskDefinition {
+            definition: serde_json::from_value(Value::Object(merged))?,
+            sources,
+        })
+    }
+}
+
+/// The fields that are set in a definition, keyed by their name in
+/// `turbo.json`
+fn to_map(definition: &TaskDefinition) -> Result<Map<String, Value>, ResolveError> {
+    match serde_json::to_value(definition)? {
+        Value::Object(fields) => Ok(fields),
+        _ => unreachable!("task definitions serialize to objects"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+    use turbopath::AbsoluteSystemPathBuf;
+
+    use super::{ResolveError, TaskDefinitionResolver, TaskDefinitionSource};
+    use crate::config::{OutputMode, TurboJsonError};
+
+    const WORKSPACES: &[(&str, &str)] = &[("web", "apps/web"), ("docs", "apps/docs")];
+
+    fn setup(files: &[(&str, &str)]) -> (tempfile::TempDir, AbsoluteSystemPathBuf) {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = AbsoluteSystemPathBuf::new(tmp.path()).unwrap();
+        for (path, contents) in files {
+            let path = root.as_path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+        (tmp, root)
+    }
+
+    fn source(turbo_json: &str, task: &str) -> TaskDefinitionSource {
+        TaskDefinitionSource {
+            turbo_json: turbo_json.to_string(),
+            task: task.to_string(),
+        }
+    }
+
+    const ROOT_TURBO_JSON: &str = r#"{
+  "pipeline": {
+    "build": { "dependsOn": ["^build"], "outputs": ["dist/**"], "env": ["NODE_ENV"] },
+    "docs#build": { "outputs": ["out/**"], "cache": false },
+    "lint": {}
+  }
+}"#;
+
+    #[test]
+    fn test_workspace_overrides_root() {
+        let (_tmp, root) = setup(&[
+            ("turbo.json", ROOT_TURBO_JSON),
+            (
+                "apps/web/turbo.json",
+                r#"{
+  "extends": ["//"],
+  "pipeline": {
+    "build": { "outputs": [".next/**"], "outputMode": "new-only" }
+  }
+}"#,
+            ),
+        ]);
+        let resolver = TaskDefinitionResolver::load(&root, WORKSPACES.iter().copied()).unwrap();
+
+        let resolved = resolver.resolved_task_definition("web", "build").unwrap();
+        assert_eq!(resolved.definition.depends_on, Some(vec!["^build".into()]));
+        assert_eq!(resolved.definition.outputs, Some(vec![".next/**".into()]));
+        assert_eq!(resolved.definition.env, Some(vec!["NODE_ENV".into()]));
+        assert_eq!(resolved.definition.output_mode, Some(OutputMode::NewOnly));
+        assert_eq!(resolved.definition.cache, None);
+        assert_eq!(
+            resolved.sources.into_iter().collect::<Vec<_>>(),
+            vec![
+                ("dependsOn".to_string(), source("turbo.json", "build")),
+                ("env".to_string(), source("turbo.json", "build")),
+                (
+                    "outputMode".to_string(),
+                    source("apps/web/turbo.json", "build")
+                ),
+                (
+                    "outputs".to_string(),
+                    source("apps/web/turbo.json", "build")
+                ),
+            ]
+        );
+
+        // a workspace without a definition of its own uses the root's
+        let resolved = resolver.resolved_task_definition("web", "lint").unwrap();
+        assert!(resolved.sources.is_empty());
+    }
+
+    #[test]
+    fn test_package_task() {
+        let (_tmp, root) = setup(&[("turbo.json", ROOT_TURBO_JSON)]);
+        let resolver = TaskDefinitionResolver::load(&root, WORKSPACES.iter().copied()).unwrap();
+
+        // `docs#build` takes precedence over `build` and isn't merged with it
+        let resolved = resolver.resolved_task_definition("docs", "build").unwrap();
+        assert_eq!(resolved.definition.depends_on, None);
+        assert_eq!(resolved.definition.outputs, Some(vec!["out/**".into()]));
+        assert_eq!(
+            resolved.sources["cache"],
+            source("turbo.json", "docs#build")
+        );
+
+        let resolved = resolver.resolved_task_definition("//", "build").unwrap();
+        assert_eq!(resolved.definition.outputs, Some(vec!["dist/**".into()]));
+    }
+
+    #[test]
+    fn test_fields() {
+        let (_tmp, root) = setup(&[("turbo.json", ROOT_TURBO_JSON)]);
+        let resolver = TaskDefinitionResolver::load(&root, WORKSPACES.iter().copied()).unwrap();
+        let resolved = resolver.resolved_task_definition("docs", "build").unwrap();
+        let fields = resolved
+            .fields()
+            .unwrap()
+            .into_iter()
+            .map(|field| (field.name, field.value, field.source.is_some()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            fields,
+            vec![
+                ("dependsOn", json!([]), false),
+                ("inputs", json!([]), false),
+                ("outputs", json!(["out/**"]), true),
+                ("env", json!([]), false),
+                ("passThroughEnv", json!(null), false),
+                ("cache", json!(false), true),
+                ("persistent", json!(false), false),
+                ("outputMode", json!("full"), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let (_tmp, root) = setup(&[]);
+        assert!(matches!(
+            TaskDefinitionResolver::load(&root, WORKSPACES.iter().copied()),
+            Err(ResolveError::NoRootTurboJson)
+        ));
+
+        let (_tmp, root) = setup(&[
+            ("turbo.json", ROOT_TURBO_JSON),
+            ("apps/web/turbo.json", r#"{ "pipeline": {} }"#),
+        ]);
+        assert!(matches!(
+            TaskDefinitionResolver::load(&root, WORKSPACES.iter().copied()),
+            Err(ResolveError::TurboJson(TurboJsonError::Invalid { .. }))
+        ));
+
+        let (_tmp, root) = setup(&[("turbo.json", ROOT_TURBO_JSON)]);
+        let resolver = TaskDefinitionResolver::load(&root, WORKSPACES.iter().copied()).unwrap();
+        assert_eq!(
+            resolver
+                .resolved_task_definition("blog", "build")
+                .unwrap_err()
+                .to_string(),
+            "Could not find workspace \"blog\""
+        );
+        assert_eq!(
+            resolver
+                .resolved_task_definition("web", "test")
+                .unwrap_err()
+                .to_string(),
+            "Could not find \"web#test\" in root turbo.json or \"web\" workspace"
+        );
+    }
+}
@@ -1,6 +1,24 @@
+//! The `turbo.json` configuration file.
+//!
+//! `TurboJson::read` parses the file into typed structures and validates it.
+//! Every problem is reported as a `Diagnostic` pointing at the line and
+//! column it was found at, rather than surfacing as a failure later on.
+//! Like the Go implementation, comments are allowed in the file, and keys
+//! this version doesn't know about are warned about rather than rejected.
+
+use std::{collections::HashMap, fmt, fs, io};
+
+use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use thiserror::Error;
+use tracing::warn;
+use turbopath::AbsoluteSystemPath;
+
+const ENV_PIPELINE_DELIMITER: &str = "$";
+const ROOT_WORKSPACE: &str = "//";
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct SpacesJson {
     pub id: Option<String>,
@@ -8,11 +26,911 @@ pub struct SpacesJson {
     pub other: Option<serde_json::Value>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteCacheOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub team_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature: Option<bool>,
+    /// Keys this version doesn't know about, kept so that they are written
+    /// back unchanged
+    #[serde(flatten, skip_serializing_if = "Map::is_empty")]
+    pub other: Map<String, Value>,
+}
+
+/// How the output of a task is logged
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputMode {
+    Full,
+    None,
+    HashOnly,
+    NewOnly,
+    ErrorsOnly,
+}
+
+/// A task in the `pipeline`. Fields that are missing are `None`, so that
+/// they can be told apart from empty values when merging definitions.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskDefinition {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub depends_on: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inputs: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub outputs: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub env: Option<Vec<String>>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        alias = "experimentalPassThroughEnv",
+        alias = "experimentalPassthroughEnv"
+    )]
+    pub pass_through_env: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub persistent: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_mode: Option<OutputMode>,
+    /// Keys this version doesn't know about
+    #[serde(flatten, skip_serializing_if = "Map::is_empty")]
+    pub other: Map<String, Value>,
+}
+
+/// Task definitions keyed by task name, e.g. `build`, or by package task,
+/// e.g. `web#build`. Tasks are kept in the order they were written in.
+pub type Pipeline = IndexMap<String, TaskDefinition>;
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct TurboJson {
-    #[serde(flatten)]
-    other: serde_json::Value,
+    #[serde(rename = "$schema", skip_serializing_if = "Option::is_none")]
+    pub schema: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub global_dependencies: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub global_env: Option<Vec<String>>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        alias = "experimentalGlobalPassThroughEnv"
+    )]
+    pub global_pass_through_env: Option<Vec<String>>,
+    #[serde(default)]
+    pub pipeline: Pipeline,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remote_cache: Option<RemoteCacheOptions>,
+    /// The workspaces whose configuration a workspace `turbo.json` builds
+    /// on. Only the root workspace, `//`, can be extended.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extends: Option<Vec<String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub experimental_spaces: Option<SpacesJson>,
+    /// Keys this version doesn't know about
+    #[serde(flatten, skip_serializing_if = "Map::is_empty")]
+    pub other: Map<String, Value>,
+}
+
+/// A problem with a `turbo.json`, and where in the file it is
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub message: String,
+    /// 1-based line number
+    pub line: usize,
+    /// 1-based column number
+    pub column: usize,
+    source_line: String,
+}
+
+impl Diagnostic {
+    fn new(source: &str, line: usize, column: usize, message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            line,
+            column,
+            source_line: source
+                .lines()
+                .nth(line.saturating_sub(1))
+                .unwrap_or_default()
+                .to_string(),
+        }
+    }
+
+    fn at_offset(source: &str, offset: usize, message: impl Into<String>) -> Self {
+        let before = &source[..offset];
+        let line = before.matches('\n').count() + 1;
+        let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+        let column = before[line_start..].chars().count() + 1;
+        Self::new(source, line, column, message)
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let gutter = self.line.to_string().len();
+        writeln!(f, "{}:{}: {}", self.line, self.column, self.message)?;
+        writeln!(f, "{:>gutter$} | {}", self.line, self.source_line)?;
+        let indent: String = self
+            .source_line
+            .chars()
+            .take(self.column.saturating_sub(1))
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        write!(f, "{:gutter$} | {}^", "", indent)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum TurboJsonError {
+    #[error("unable to read {path}: {source}")]
+    Io {
+        path: String,
+        #[source]
+        source: io::Error,
+    },
+    #[error("{}", format_diagnostics(.path, .diagnostics))]
+    Invalid {
+        path: String,
+        diagnostics: Vec<Diagnostic>,
+    },
+}
+
+fn format_diagnostics(path: &str, diagnostics: &[Diagnostic]) -> String {
+    diagnostics
+        .iter()
+        .map(|diagnostic| format!("{}:{}", path, diagnostic))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+impl TurboJson {
+    /// Reads and validates the `turbo.json` at `path`
+    pub fn read(path: &AbsoluteSystemPath) -> Result<Self, TurboJsonError> {
+        let (source, path) = Self::read_source(path)?;
+        Self::parse(&source, &path)
+    }
+
+    /// Reads and validates the `turbo.json` of a workspace other than the
+    /// root, which has to extend the root `turbo.json`
+    pub fn read_workspace(path: &AbsoluteSystemPath) -> Result<Self, TurboJsonError> {
+        let (source, path) = Self::read_source(path)?;
+        Self::parse_workspace(&source, &path)
+    }
+
+    /// Parses and validates the contents of a `turbo.json`. `path` is only
+    /// used to describe where errors are. Warnings are logged.
+    pub fn parse(source: &str, path: &str) -> Result<Self, TurboJsonError> {
+        Self::parse_and_warn(source, path, false)
+    }
+
+    /// Parses and validates the contents of a workspace `turbo.json`
+    pub fn parse_workspace(source: &str, path: &str) -> Result<Self, TurboJsonError> {
+        Self::parse_and_warn(source, path, true)
+    }
+
+    fn parse_and_warn(source: &str, path: &str, workspace: bool) -> Result<Self, TurboJsonError> {
+        let (turbo_json, warnings) = Self::parse_as(source, path, workspace)?;
+        if !warnings.is_empty() {
+            warn!("{}", format_diagnostics(path, &warnings));
+        }
+        Ok(turbo_json)
+    }
+
+    /// Reads only `remoteCache.signature`, without validating the rest of
+    /// the file. A file that can't be read or parsed doesn't enable
+    /// signatures.
+    pub fn read_remote_cache_signature(path: &AbsoluteSystemPath) -> bool {
+        fs::read_to_string(path.as_path())
+            .map_or(false, |source| Self::parse_remote_cache_signature(&source))
+    }
+
+    fn parse_remote_cache_signature(source: &str) -> bool {
+        serde_json::from_str::<Value>(&strip_comments(source))
+            .ok()
+            .and_then(|json| json.pointer("/remoteCache/signature")?.as_bool())
+            .unwrap_or(false)
+    }
+
+    fn read_source(path: &AbsoluteSystemPath) -> Result<(String, String), TurboJsonError> {
+        let display_path = path.as_path().display().to_string();
+        match fs::read_to_string(path.as_path()) {
+            Ok(source) => Ok((source, display_path)),
+            Err(source) => Err(TurboJsonError::Io {
+                path: display_path,
+                source,
+            }),
+        }
+    }
+
+    /// Parses and validates a `turbo.json`, returning it with warnings about
+    /// unknown keys and deprecated syntax
+    fn parse_as(
+        source: &str,
+        path: &str,
+        workspace: bool,
+    ) -> Result<(Self, Vec<Diagnostic>), TurboJsonError> {
+        let invalid = |diagnostics| TurboJsonError::Invalid {
+            path: path.to_string(),
+            diagnostics,
+        };
+        // comments are replaced by whitespace, so positions are unchanged
+        let json = strip_comments(source);
+        let mut turbo_json: TurboJson = serde_json::from_str(&json).map_err(|err| {
+            let message = err.to_string();
+            let suffix = format!(" at line {} column {}", err.line(), err.column());
+            let message = message.strip_suffix(&suffix).unwrap_or(&message);
+            invalid(vec![Diagnostic::new(
+                source,
+                err.line(),
+                err.column(),
+                message,
+            )])
+        })?;
+
+        let spans = SpanIndex::new(&json);
+        let diagnostics = turbo_json.validate(source, &spans, workspace);
+        if !diagnostics.is_empty() {
+            return Err(invalid(diagnostics));
+        }
+        let mut warnings = turbo_json.unknown_keys(source, &spans);
+        warnings.extend(turbo_json.migrate_env_dependencies(source, &spans));
+        Ok((turbo_json, warnings))
+    }
+
+    /// Warns about keys this version doesn't know about, e.g. from a newer
+    /// version of turbo, which are ignored
+    fn unknown_keys(&self, source: &str, spans: &SpanIndex) -> Vec<Diagnostic> {
+        let mut warnings = Vec::new();
+        let mut warn = |pointer: &[&str], other: &Map<String, Value>| {
+            for key in other.keys() {
+                let pointer = [pointer, &[key.as_str()]].concat();
+                warnings.push(Diagnostic::at_offset(
+                    source,
+                    spans.offset(&pointer),
+                    format!("unknown key \"{}\" will be ignored", key),
+                ));
+            }
+        };
+        warn(&[], &self.other);
+        for (task_name, task) in &self.pipeline {
+            warn(&["pipeline", task_name], &task.other);
+        }
+        if let Some(remote_cache) = &self.remote_cache {
+            warn(&["remoteCache"], &remote_cache.other);
+        }
+        warnings
+    }
+
+    /// Moves environment variables declared with a `$` prefix in
+    /// `dependsOn` and `globalDependencies` to `env` and `globalEnv`, which
+    /// is deprecated but still supported, as on the Go side
+    fn migrate_env_dependencies(&mut self, source: &str, spans: &SpanIndex) -> Vec<Diagnostic> {
+        let mut warnings = Vec::new();
+        let mut migrate = |pointer: &[&str],
+                           key: &str,
+                           env_key: &str,
+                           dependencies: &mut Option<Vec<String>>,
+                           env: &mut Option<Vec<String>>| {
+            let Some(dependencies) = dependencies else {
+                return;
+            };
+            let mut index = 0;
+            dependencies.retain(|dependency| {
+                let var = dependency.strip_prefix(ENV_PIPELINE_DELIMITER);
+                if let Some(var) = var {
+                    let index = index.to_string();
+                    let pointer = [pointer, &[index.as_str()]].concat();
+                    warnings.push(Diagnostic::at_offset(
+                        source,
+                        spans.offset(&pointer),
+                        format!(
+                            "[DEPRECATED] Declaring an environment variable in \"{}\" is \
+                             deprecated, found {}. Use the \"{}\" key or use `npx @turbo/codemod \
+                             migrate-env-var-dependencies`.",
+                            key, dependency, env_key
+                        ),
+                    ));
+                    let env = env.get_or_insert_with(Vec::new);
+                    if !env.iter().any(|existing| existing == var) {
+                        env.push(var.to_string());
+                    }
+                }
+                index += 1;
+                var.is_none()
+            });
+        };
+        migrate(
+            &["globalDependencies"],
+            "globalDependencies",
+            "globalEnv",
+            &mut self.global_dependencies,
+            &mut self.global_env,
+        );
+        for (task_name, task) in &mut self.pipeline {
+            migrate(
+                &["pipeline", task_name, "dependsOn"],
+                "dependsOn",
+                "env",
+                &mut task.depends_on,
+                &mut task.env,
+            );
+        }
+        warnings
+    }
+
+    /// Checks for problems that the types can't express
+    fn validate(&self, source: &str, spans: &SpanIndex, workspace: bool) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        let mut error = |pointer: &[&str], message: String| {
+            diagnostics.push(Diagnostic::at_offset(
+                source,
+                spans.offset(pointer),
+                message,
+            ));
+        };
+
+        let mut check_env = |pointer: &[&str], key: &str, vars: &Option<Vec<String>>| {
+            for (index, var) in vars.iter().flatten().enumerate() {
+                if var.starts_with(ENV_PIPELINE_DELIMITER) {
+                    let index = index.to_string();
+                    let pointer = [pointer, &[index.as_str()]].concat();
+                    error(
+                        &pointer,
+                        format!(
+                            "You specified \"{}\" in the \"{}\" key. You should not prefix your \
+                             environment variables with \"{}\"",
+                            var, key, ENV_PIPELINE_DELIMITER
+                        ),
+                    );
+                }
+            }
+        };
+        check_env(&["globalEnv"], "globalEnv", &self.global_env);
+        check_env(
+            &["globalPassThroughEnv"],
+            "globalPassThroughEnv",
+            &self.global_pass_through_env,
+        );
+        for (task_name, task) in &self.pipeline {
+            check_env(&["pipeline", task_name, "env"], "env", &task.env);
+            check_env(
+                &["pipeline", task_name, "passThroughEnv"],
+                "passThroughEnv",
+                &task.pass_through_env,
+            );
+        }
+
+        match &self.extends {
+            Some(extends) => {
+                for (index, workspace) in extends.iter().enumerate() {
+                    if workspace != ROOT_WORKSPACE {
+                        let index = index.to_string();
+                        error(
+                            &["extends", &index],
+                            format!(
+                                "You can only extend from the root workspace, found \"{}\"",
+                                workspace
+                            ),
+                        );
+                    }
+                }
+            }
+            None if workspace => error(&[], "No \"extends\" key found".to_string()),
+            None => {}
+        }
+        if workspace || self.extends.is_some() {
+            for task_name in self.pipeline.keys() {
+                if task_name.contains('#') {
+                    error(
+                        &["pipeline", task_name],
+                        format!(
+                            "Package tasks (<package>#<task>) are not allowed in workspace \
+                             configurations, found \"{}\"",
+                            task_name
+                        ),
+                    );
+                }
+            }
+        }
+
+        diagnostics
+    }
+}
+
+/// Replaces `//` and `/* */` comments outside of strings with spaces, keeping
+/// newlines so that line and column numbers still match the original source.
+fn strip_comments(source: &str) -> String {
+    let mut stripped = String::with_capacity(source.len());
+    let mut chars = source.chars().peekable();
+    let mut in_string = false;
+    while let Some(c) = chars.next() {
+        if in_string {
+            stripped.push(c);
+            match c {
+                '\\' => stripped.extend(chars.next()),
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match (c, chars.peek()) {
+            ('"', _) => {
+                in_string = true;
+                stripped.push(c);
+            }
+            ('/', Some('/')) => {
+                while let Some(c) = chars.next_if(|c| *c != '\n') {
+                    stripped.extend(std::iter::repeat(' ').take(c.len_utf8()));
+                }
+                stripped.push(' ');
+            }
+            ('/', Some('*')) => {
+                chars.next();
+                stripped.push_str("  ");
+                let mut previous = None;
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        stripped.push('\n');
+                    } else {
+                        stripped.extend(std::iter::repeat(' ').take(c.len_utf8()));
+                    }
+                    if previous == Some('*') && c == '/' {
+                        break;
+                    }
+                    previous = Some(c);
+                }
+            }
+            _ => stripped.push(c),
+        }
+    }
+    stripped
+}
+
+/// The byte offset of every value in a JSON document, keyed by JSON pointer.
+/// Object members point at their key, so diagnostics about a member point at
+/// its name.
+struct SpanIndex {
+    offsets: HashMap<String, usize>,
+}
+
+impl SpanIndex {
+    /// Indexes a document that is already known to be valid JSON
+    fn new(json: &str) -> Self {
+        let mut index = Self {
+            offsets: HashMap::new(),
+        };
+        let mut pos = 0;
+        index.index_value(json.as_bytes(), &mut pos, String::new());
+        index
+    }
+
+    /// The offset of the value at `pointer`, or of its closest ancestor
+    fn offset(&self, pointer: &[&str]) -> usize {
+        (0..=pointer.len())
+            .rev()
+            .find_map(|len| self.offsets.get(&Self::pointer(&pointer[..len])))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    fn pointer(segments: &[&str]) -> String {
+        segments
+            .iter()
+            .map(|segment| format!("/{}", segment.replace('~', "~0").replace('/', "~1")))
+            .collect()
+    }
+
+    fn index_value(&mut self, json: &[u8], pos: &mut usize, pointer: String) {
+        skip_whitespace(json, pos);
+        match json.get(*pos) {
+            Some(b'{') => {
+                *pos += 1;
+                loop {
+                    skip_whitespace(json, pos);
+                    match json.get(*pos) {
+                        Some(b'}') | None => break,
+                        Some(b',') => *pos += 1,
+                        _ => {
+                            let key_start = *pos;
+                            skip_string(json, pos);
+                            let key: String =
+                                serde_json::from_slice(&json[key_start..*pos]).unwrap_or_default();
+                            skip_whitespace(json, pos);
+                            // the colon
+                            *pos += 1;
+                            let child = format!("{}{}", pointer, Self::pointer(&[&key]));
+                            self.offsets.insert(child.clone(), key_start);
+                            self.index_value(json, pos, child);
+                        }
+                    }
+                }
+                *pos += 1;
+            }
+            Some(b'[') => {
+                *pos += 1;
+                let mut index = 0;
+                loop {
+                    skip_whitespace(json, pos);
+                    match json.get(*pos) {
+                        Some(b']') | None => break,
+                        Some(b',') => *pos += 1,
+                        _ => {
+                            let child = format!("{}/{}", pointer, index);
+                            self.offsets.insert(child.clone(), *pos);
+                            self.index_value(json, pos, child);
+                            index += 1;
+                        }
+                    }
+                }
+                *pos += 1;
+            }
+            Some(b'"') => skip_string(json, pos),
+            Some(_) => {
+                while json.get(*pos).map_or(false, |c| {
+                    !matches!(c, b',' | b']' | b'}') && !c.is_ascii_whitespace()
+                }) {
+                    *pos += 1;
+                }
+            }
+            None => {}
+        }
+    }
+}
+
+fn skip_whitespace(json: &[u8], pos: &mut usize) {
+    while json.get(*pos).map_or(false, |c| c.is_ascii_whitespace()) {
+        *pos += 1;
+    }
+}
+
+fn skip_string(json: &[u8], pos: &mut usize) {
+    // the opening quote
+    *pos += 1;
+    while let Some(c) = json.get(*pos) {
+        *pos += 1;
+        match c {
+            b'\\' => *pos += 1,
+            b'"' => return,
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::{OutputMode, TaskDefinition, TurboJson, TurboJsonError};
+
+    fn warnings(source: &str) -> Vec<(usize, usize, String)> {
+        let (_, warnings) = TurboJson::parse_as(source, "turbo.json", false).unwrap();
+        warnings
+            .into_iter()
+            .map(|warning| (warning.line, warning.column, warning.message))
+            .collect()
+    }
+
+    fn diagnostics(source: &str) -> Vec<(usize, usize, String)> {
+        match TurboJson::parse(source, "turbo.json") {
+            Err(TurboJsonError::Invalid { diagnostics, .. }) => diagnostics
+                .into_iter()
+                .map(|diagnostic| (diagnostic.line, diagnostic.column, diagnostic.message))
+                .collect(),
+            other => panic!("expected diagnostics, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        let turbo_json = TurboJson::parse(
+            r#"{
+  "$schema": "https://turbo.build/schema.json",
+  // comments are allowed
+  "globalDependencies": ["tsconfig.json"],
+  "globalEnv": ["CI"],
+  "pipeline": {
+    "build": {
+      "dependsOn": ["^build", "codegen"],
+      "inputs": ["src/**"],
+      "outputs": ["dist/**", "!dist/cache/**"],
+      "env": ["NODE_ENV"],
+      "passThroughEnv": ["HOME"],
+      "outputMode": "new-only"
+    },
+    /* tasks are kept in order */
+    "dev": { "cache": false, "persistent": true },
+    "codegen": {}
+  },
+  "remoteCache": { "signature": true }
+}"#,
+            "turbo.json",
+        )
+        .unwrap();
+
+        assert_eq!(
+            turbo_json.global_dependencies,
+            Some(vec!["tsconfig.json".into()])
+        );
+        assert_eq!(turbo_json.global_env, Some(vec!["CI".into()]));
+        assert_eq!(
+            turbo_json.pipeline.keys().collect::<Vec<_>>(),
+            vec!["build", "dev", "codegen"]
+        );
+        let build = &turbo_json.pipeline["build"];
+        assert_eq!(
+            build.depends_on,
+            Some(vec!["^build".into(), "codegen".into()])
+        );
+        assert_eq!(build.pass_through_env, Some(vec!["HOME".into()]));
+        assert_eq!(build.output_mode, Some(OutputMode::NewOnly));
+        assert_eq!(
+            turbo_json.pipeline["dev"],
+            TaskDefinition {
+                cache: Some(false),
+                persistent: Some(true),
+                ..Default::default()
+            }
+        );
+        assert_eq!(turbo_json.pipeline["codegen"], TaskDefinition::default());
+        assert_eq!(turbo_json.remote_cache.unwrap().signature, Some(true));
+    }
+
+    #[test]
+    fn test_parse_remote_cache_signature() {
+        // the rest of the file isn't validated
+        assert!(TurboJson::parse_remote_cache_signature(
+            r#"{
+  // comments are allowed
+  "pipeline": { "build": { "outputMode": "not-a-mode" } },
+  "remoteCache": { "signature": true }
+}"#
+        ));
+        assert!(!TurboJson::parse_remote_cache_signature(
+            r#"{ "remoteCache": { "signature": false } }"#
+        ));
+        assert!(!TurboJson::parse_remote_cache_signature(
+            r#"{ "remoteCache": {} }"#
+        ));
+        assert!(!TurboJson::parse_remote_cache_signature("{ not json"));
+    }
+
+    #[test]
+    fn test_experimental_aliases() {
+        let turbo_json = TurboJson::parse(
+            r#"{
+  "experimentalGlobalPassThroughEnv": ["HOME"],
+  "pipeline": { "build": { "experimentalPassThroughEnv": ["PATH"] } }
+}"#,
+            "turbo.json",
+        )
+        .unwrap();
+        assert_eq!(
+            turbo_json.global_pass_through_env,
+            Some(vec!["HOME".into()])
+        );
+        assert_eq!(
+            turbo_json.pipeline["build"].pass_through_env,
+            Some(vec!["PATH".into()])
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let source = r#"{ "globalEnv": [], "pipeline": { "lint": {}, "build": { "cache": false } }, "experimentalSpaces": { "id": "space" } }"#;
+        let turbo_json = TurboJson::parse(source, "turbo.json").unwrap();
+        let serialized = serde_json::to_string(&turbo_json).unwrap();
+        assert_eq!(
+            serialized,
+            r#"{"globalEnv":[],"pipeline":{"lint":{},"build":{"cache":false}},"experimentalSpaces":{"id":"space"}}"#
+        );
+        assert_eq!(
+            TurboJson::parse(&serialized, "turbo.json").unwrap(),
+            turbo_json
+        );
+    }
+
+    #[test]
+    fn test_unknown_keys() {
+        let source = r#"{
+  "futureOption": true,
+  "pipeline": {
+    "build": {
+      // a typo
+      "dependOn": ["^build"]
+    }
+  },
+  "remoteCache": { "enabled": true }
+}"#;
+        assert_eq!(
+            warnings(source),
+            vec![
+                (
+                    2,
+                    3,
+                    "unknown key \"futureOption\" will be ignored".to_string()
+                ),
+                (6, 7, "unknown key \"dependOn\" will be ignored".to_string()),
+                (9, 20, "unknown key \"enabled\" will be ignored".to_string()),
+            ]
+        );
+
+        // unknown keys are written back as they were
+        let turbo_json = TurboJson::parse(source, "turbo.json").unwrap();
+        assert_eq!(turbo_json.pipeline["build"].depends_on, None);
+        assert_eq!(
+            serde_json::to_string(&turbo_json).unwrap(),
+            r#"{"pipeline":{"build":{"dependOn":["^build"]}},"remoteCache":{"enabled":true},"futureOption":true}"#
+        );
+    }
+
+    #[test]
+    fn test_env_dependencies() {
+        let source = r#"{
+  "globalDependencies": ["tsconfig.json", "$GLOBAL_TOKEN"],
+  "globalEnv": ["CI"],
+  "pipeline": {
+    "build": {
+      "dependsOn": ["^build", "$NODE_ENV"],
+      "env": ["NODE_ENV"]
+    },
+    "test": { "dependsOn": ["$API_URL"] }
+  }
+}"#;
+        let turbo_json = TurboJson::parse(source, "turbo.json").unwrap();
+        assert_eq!(
+            turbo_json.global_dependencies,
+            Some(vec!["tsconfig.json".into()])
+        );
+        assert_eq!(
+            turbo_json.global_env,
+            Some(vec!["CI".into(), "GLOBAL_TOKEN".into()])
+        );
+        let build = &turbo_json.pipeline["build"];
+        assert_eq!(build.depends_on, Some(vec!["^build".into()]));
+        assert_eq!(build.env, Some(vec!["NODE_ENV".into()]));
+        let test = &turbo_json.pipeline["test"];
+        assert_eq!(test.depends_on, Some(vec![]));
+        assert_eq!(test.env, Some(vec!["API_URL".into()]));
+
+        let warnings = warnings(source);
+        assert_eq!(
+            warnings
+                .iter()
+                .map(|(line, column, _)| (*line, *column))
+                .collect::<Vec<_>>(),
+            vec![(2, 43), (6, 31), (9, 29)]
+        );
+        assert_eq!(
+            warnings[1].2,
+            "[DEPRECATED] Declaring an environment variable in \"dependsOn\" is deprecated, found \
+             $NODE_ENV. Use the \"env\" key or use `npx @turbo/codemod \
+             migrate-env-var-dependencies`."
+        );
+    }
+
+    #[test]
+    fn test_invalid_output_mode() {
+        let diagnostics = diagnostics(
+            r#"{
+  "pipeline": { "build": { "outputMode": "quiet" } }
+}"#,
+        );
+        let (line, _, message) = &diagnostics[0];
+        assert_eq!(*line, 2);
+        assert!(
+            message.starts_with("unknown variant `quiet`"),
+            "{}",
+            message
+        );
+    }
+
+    #[test]
+    fn test_syntax_error() {
+        let diagnostics = diagnostics("{\n  \"pipeline\": {\n}");
+        assert_eq!(diagnostics[0].0, 3);
+    }
+
+    #[test]
+    fn test_env_prefix() {
+        assert_eq!(
+            diagnostics(
+                r#"{
+  "globalEnv": ["CI", "$TOKEN"],
+  "pipeline": {
+    "//#build": {
+      "env": [
+        "$NODE_ENV"
+      ]
+    }
+  }
+}"#
+            ),
+            vec![
+                (
+                    2,
+                    23,
+                    "You specified \"$TOKEN\" in the \"globalEnv\" key. You should not prefix \
+                     your environment variables with \"$\""
+                        .to_string()
+                ),
+                (
+                    6,
+                    9,
+                    "You specified \"$NODE_ENV\" in the \"env\" key. You should not prefix your \
+                     environment variables with \"$\""
+                        .to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extends() {
+        assert!(TurboJson::parse(
+            r#"{ "extends": ["//"], "pipeline": { "build": {} } }"#,
+            "turbo.json"
+        )
+        .is_ok());
+        assert_eq!(
+            diagnostics(
+                r#"{
+  "extends": ["//", "docs"],
+  "pipeline": {
+    "web#build": {}
+  }
+}"#
+            ),
+            vec![
+                (
+                    2,
+                    21,
+                    "You can only extend from the root workspace, found \"docs\"".to_string()
+                ),
+                (
+                    4,
+                    5,
+                    "Package tasks (<package>#<task>) are not allowed in workspace \
+                     configurations, found \"web#build\""
+                        .to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_workspace() {
+        assert!(TurboJson::parse_workspace(
+            r#"{ "extends": ["//"], "pipeline": { "build": {} } }"#,
+            "turbo.json"
+        )
+        .is_ok());
+        match TurboJson::parse_workspace(r#"{ "pipeline": { "web#build": {} } }"#, "turbo.json") {
+            Err(TurboJsonError::Invalid { diagnostics, .. }) => assert_eq!(
+                diagnostics
+                    .iter()
+                    .map(|diagnostic| diagnostic.message.as_str())
+                    .collect::<Vec<_>>(),
+                vec![
+                    "No \"extends\" key found",
+                    "Package tasks (<package>#<task>) are not allowed in workspace \
+                     configurations, found \"web#build\"",
+                ]
+            ),
+            other => panic!("expected diagnostics, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_display() {
+        let err =
+            TurboJson::parse("{\n  \"globalEnv\": [\"$CI\"]\n}", "/repo/turbo.json").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "/repo/turbo.json:2:17: You specified \"$CI\" in the \"globalEnv\" key. You should \
+             not prefix your environment variables with \"$\"\n2 |   \"globalEnv\": [\"$CI\"]\n  \
+             |                 ^"
+        );
+    }
 }
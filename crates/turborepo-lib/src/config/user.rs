@@ -1,10 +1,9 @@
 use std::{collections::HashMap, path::PathBuf};
 
 use anyhow::Result;
-use config::{Config, Environment};
 use serde::{Deserialize, Serialize};
 
-use super::write_to_disk;
+use super::{resolver::read_config_file, write_to_disk, ConfigKey, ConfigResolver};
 
 // Inner struct that matches the config file schema
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, Default)]
@@ -82,21 +81,18 @@ impl UserConfigLoader {
         } = self;
         // We load just the disk config to make sure we don't write a config
         // value that comes from a flag or environment variable.
-        let raw_disk_config = Config::builder()
-            .add_source(
-                config::File::with_name(path.to_string_lossy().as_ref())
-                    .format(config::FileFormat::Json)
-                    .required(false),
-            )
-            .build()?;
-
-        let config = Config::builder()
-            .add_source(raw_disk_config.clone())
-            .add_source(Environment::with_prefix("TURBO").source(environment.clone()))
-            .add_source(Environment::with_prefix("VERCEL_ARTIFACTS").source(environment))
-            .set_override_option("token", token)?
-            .build()?
-            .try_deserialize()?;
+        let raw_disk_config = read_config_file(path.to_string_lossy().as_ref())?;
+
+        let resolved = ConfigResolver::new()
+            .with_loaded_user_config(raw_disk_config.clone())
+            .with_token(token)
+            .with_environment(environment)
+            .resolve()?;
+        let config = UserConfigValue {
+            token: resolved
+                .get(ConfigKey::Token)
+                .map(|token| token.to_string()),
+        };
 
         let disk_config: UserConfigValue = raw_disk_config.try_deserialize()?;
 
@@ -5,12 +5,17 @@
 
 use std::sync::Arc;
 
-use tonic::transport::NamedService;
+use tonic::{codegen::http, transport::NamedService};
 use tower::{Layer, Service};
 
 use super::bump_timeout::BumpTimeout;
 
-/// A layer that resets a <BumpTimeout> when a request is received.
+/// Checking on the daemon isn't activity, and the status reports the time
+/// left before the timeout, so it must not reset it.
+const STATUS_PATH: &str = "/turbodprotocol.Turbod/Status";
+
+/// A layer that resets a <BumpTimeout> when a request other than a status
+/// check is received.
 pub struct BumpTimeoutLayer(Arc<BumpTimeout>);
 
 impl BumpTimeoutLayer {
@@ -36,9 +41,9 @@ pub struct BumpTimeoutService<S> {
     timeout: Arc<BumpTimeout>,
 }
 
-impl<S, Request> Service<Request> for BumpTimeoutService<S>
+impl<S, B> Service<http::Request<B>> for BumpTimeoutService<S>
 where
-    S: Service<Request>,
+    S: Service<http::Request<B>>,
 {
     type Response = S::Response;
     type Error = S::Error;
@@ -51,8 +56,10 @@ where
         self.inner.poll_ready(cx)
     }
 
-    fn call(&mut self, req: Request) -> Self::Future {
-        self.timeout.reset();
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        if req.uri().path() != STATUS_PATH {
+            self.timeout.reset();
+        }
         self.inner.call(req)
     }
 }
@@ -1,11 +1,16 @@
+use std::collections::HashMap;
+
 use thiserror::Error;
 use tonic::{Code, Status};
-use tracing::info;
+use tracing::{debug, info};
+use turbopath::{AbsoluteSystemPathBuf, AnchoredSystemPathBuf};
+use turborepo_scm::package_deps::get_package_file_hashes_from_git_index;
 
 use self::proto::turbod_client::TurbodClient;
 use super::{
     connector::{DaemonConnector, DaemonConnectorError},
     endpoint::SocketOpenError,
+    file_hashes::input_hashes,
 };
 use crate::get_version;
 
@@ -102,6 +107,56 @@ impl DaemonClient<DaemonConnector> {
         Ok(())
     }
 
+    /// Subscribe to file changes in the given workspaces. The stream yields
+    /// an event for each workspace with changed files.
+    #[allow(dead_code)]
+    pub async fn watch_package_changes(
+        &mut self,
+        workspaces: Vec<proto::WorkspaceDirectory>,
+    ) -> Result<tonic::Streaming<proto::PackageChangeEvent>, DaemonError> {
+        Ok(self
+            .client
+            .watch_package_changes(proto::WatchPackageChangesRequest { workspaces })
+            .await?
+            .into_inner())
+    }
+
+    /// Get the hashes of the files in a package that match `inputs`, keyed by
+    /// their unix path relative to the package.
+    #[allow(dead_code)]
+    pub async fn get_file_hashes(
+        &mut self,
+        package_path: &AnchoredSystemPathBuf,
+        inputs: Vec<String>,
+    ) -> Result<HashMap<String, String>, DaemonError> {
+        let package_path = package_path
+            .to_str()
+            .map_err(|_| DaemonError::InvalidPath)?
+            .to_string();
+        Ok(self
+            .client
+            .get_file_hashes(proto::GetFileHashesRequest {
+                package_path,
+                inputs,
+            })
+            .await?
+            .into_inner()
+            .file_hashes)
+    }
+
+    /// Get the workspaces of the repository and the dependencies between
+    /// them.
+    #[allow(dead_code)]
+    pub async fn get_package_graph(
+        &mut self,
+    ) -> Result<proto::GetPackageGraphResponse, DaemonError> {
+        Ok(self
+            .client
+            .get_package_graph(proto::GetPackageGraphRequest {})
+            .await?
+            .into_inner())
+    }
+
     /// Get the status of the daemon.
     pub async fn status(&mut self) -> Result<proto::DaemonStatus, DaemonError> {
         self.client
@@ -121,6 +176,26 @@ impl DaemonClient<DaemonConnector> {
     }
 }
 
+/// Get the hashes of the files in a package that match `inputs` from the
+/// daemon, hashing them locally if the daemon isn't available.
+#[allow(dead_code)]
+pub async fn get_file_hashes(
+    client: Option<&mut DaemonClient<DaemonConnector>>,
+    repo_root: &AbsoluteSystemPathBuf,
+    package_path: &AnchoredSystemPathBuf,
+    inputs: Vec<String>,
+) -> Result<HashMap<String, String>, turborepo_scm::Error> {
+    if let Some(client) = client {
+        match client.get_file_hashes(package_path, inputs.clone()).await {
+            Ok(hashes) => return Ok(hashes),
+            Err(e) => debug!("daemon failed to hash files, hashing locally: {}", e),
+        }
+    }
+
+    let hashes = get_package_file_hashes_from_git_index(repo_root, package_path)?;
+    input_hashes(repo_root, package_path, &hashes, &inputs)
+}
+
 #[derive(Error, Debug)]
 pub enum DaemonError {
     /// The server was connected but is now unavailable.
@@ -137,6 +212,9 @@ pub enum DaemonError {
     /// The daemon returned an unexpected status code.
     #[error("bad grpc status code: {0}")]
     GrpcFailure(tonic::Code),
+    /// A path couldn't be sent to the daemon.
+    #[error("path is not valid UTF-8")]
+    InvalidPath,
     /// The daemon returned a malformed response.
     #[error("malformed response")]
     MalformedResponse,
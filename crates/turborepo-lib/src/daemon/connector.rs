@@ -264,7 +264,7 @@ impl DaemonConnector {
     }
 
     fn pid_lock(&self) -> pidlock::Pidlock {
-        pidlock::Pidlock::new(self.pid_file.clone().into())
+        pidlock::Pidlock::new(self.pid_file.clone().into()).with_file_lock()
     }
 }
 
@@ -567,6 +567,9 @@ mod test {
 
     #[tonic::async_trait]
     impl proto::turbod_server::Turbod for DummyServer {
+        type WatchPackageChangesStream =
+            futures::stream::Empty<tonic::Result<proto::PackageChangeEvent>>;
+
         async fn shutdown(
             &self,
             req: tonic::Request<proto::ShutdownRequest>,
@@ -609,6 +612,27 @@ mod test {
         ) -> tonic::Result<tonic::Response<proto::GetChangedOutputsResponse>> {
             unimplemented!()
         }
+
+        async fn watch_package_changes(
+            &self,
+            _req: tonic::Request<proto::WatchPackageChangesRequest>,
+        ) -> tonic::Result<tonic::Response<Self::WatchPackageChangesStream>> {
+            unimplemented!()
+        }
+
+        async fn get_file_hashes(
+            &self,
+            _req: tonic::Request<proto::GetFileHashesRequest>,
+        ) -> tonic::Result<tonic::Response<proto::GetFileHashesResponse>> {
+            unimplemented!()
+        }
+
+        async fn get_package_graph(
+            &self,
+            _req: tonic::Request<proto::GetPackageGraphRequest>,
+        ) -> tonic::Result<tonic::Response<proto::GetPackageGraphResponse>> {
+            unimplemented!()
+        }
     }
 
     #[tokio::test]
@@ -1,6 +1,9 @@
-use std::sync::{atomic::AtomicBool, Arc};
 #[cfg(windows)]
-use std::{io::ErrorKind, sync::atomic::Ordering, time::Duration};
+use std::{io::ErrorKind, sync::atomic::Ordering};
+use std::{
+    sync::{atomic::AtomicBool, Arc},
+    time::Duration,
+};
 
 use futures::Stream;
 use tokio::io::{AsyncRead, AsyncWrite};
@@ -19,6 +22,10 @@ pub enum SocketOpenError {
     LockError(#[from] pidlock::PidlockError),
 }
 
+/// How long to wait for a daemon that is shutting down, or another daemon
+/// starting at the same time, to release the pid file.
+const PIDLOCK_TIMEOUT: Duration = Duration::from_secs(1);
+
 #[cfg(windows)]
 const WINDOWS_POLL_DURATION: Duration = Duration::from_millis(1);
 
@@ -41,11 +48,16 @@ pub async fn listen_socket(
 > {
     let pid_path = path.join_relative(RelativeSystemPathBuf::new("turbod.pid").unwrap());
     let sock_path = path.join_relative(RelativeSystemPathBuf::new("turbod.sock").unwrap());
-    let mut lock = pidlock::Pidlock::new(pid_path.as_path().to_owned());
+    let mut lock = pidlock::Pidlock::new(pid_path.as_path().to_owned()).with_file_lock();
 
     trace!("acquiring pidlock");
-    // this will fail if the pid is already owned
-    lock.acquire()?;
+    // this will fail if the pid is still owned once the timeout has passed
+    let lock = tokio::task::spawn_blocking(move || {
+        lock.acquire_timeout(PIDLOCK_TIMEOUT)?;
+        Ok::<_, pidlock::PidlockError>(lock)
+    })
+    .await
+    .expect("acquiring the pidlock does not panic")?;
     std::fs::remove_file(&sock_path).ok();
 
     debug!("pidlock acquired at {}", pid_path);
@@ -0,0 +1,373 @@
+//! File Hash Cache
+//!
+//! Keeps the git-compatible file hashes of packages in memory so that the
+//! daemon can answer hashing requests without touching git. Entries are
+//! computed with `turborepo-scm` and dropped when the file watcher reports a
+//! change that could affect them.
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use tokio::sync::broadcast::{self, error::TryRecvError};
+use tracing::trace;
+use turbopath::{AbsoluteSystemPathBuf, AnchoredSystemPathBuf};
+use turborepo_scm::package_deps::{
+    expand_package_inputs, get_hashes_for_files, get_package_file_hashes, GitHashes,
+};
+
+use crate::globwatcher::Changes;
+
+pub struct FileHashCache {
+    repo_root: AbsoluteSystemPathBuf,
+    state: Mutex<CacheState>,
+}
+
+struct CacheState {
+    changes: broadcast::Receiver<Changes>,
+    /// hashes of each package keyed by the unix path of the package relative
+    /// to the repo root
+    packages: HashMap<String, Arc<GitHashes>>,
+    /// packages that have had their files watched
+    watched: HashSet<String>,
+}
+
+impl FileHashCache {
+    pub fn new(repo_root: AbsoluteSystemPathBuf, changes: broadcast::Receiver<Changes>) -> Self {
+        Self {
+            repo_root,
+            state: Mutex::new(CacheState {
+                changes,
+                packages: HashMap::new(),
+                watched: HashSet::new(),
+            }),
+        }
+    }
+
+    /// The globs that need to be watched to notice every change to the hashes
+    /// of a package: the package itself, ignore files above it, and the index.
+    pub fn watch_globs(package_path: &str) -> Vec<String> {
+        let mut globs = vec![".git/index".to_string(), ".gitignore".to_string()];
+        let mut ancestor = String::new();
+        for component in package_path.split('/').filter(|c| !c.is_empty()) {
+            if !ancestor.is_empty() {
+                globs.push(format!("{}/.gitignore", ancestor));
+                ancestor.push('/');
+            }
+            ancestor.push_str(component);
+        }
+        globs.push(match package_path {
+            "" => "**".to_string(),
+            path => format!("{}/**", path),
+        });
+        globs
+    }
+
+    /// Records that a package is being watched, returning false if it already
+    /// was.
+    pub fn start_watching(&self, package_path: &str) -> bool {
+        self.lock().watched.insert(package_path.to_string())
+    }
+
+    pub fn stop_watching(&self, package_path: &str) {
+        self.lock().watched.remove(package_path);
+    }
+
+    /// Returns the hashes of every file in a package, hashing them if they
+    /// aren't cached. Results are only cached for packages that are watched.
+    pub fn package_hashes(
+        &self,
+        package_path: &str,
+    ) -> Result<Arc<GitHashes>, turborepo_scm::Error> {
+        {
+            let mut state = self.lock();
+            state.apply_changes(None);
+            if let Some(hashes) = state.packages.get(package_path) {
+                trace!("file hash cache hit for {}", package_path);
+                return Ok(hashes.clone());
+            }
+        }
+
+        // hashing can be slow, so we don't hold the lock while doing it
+        let hashes = Arc::new(get_package_file_hashes(
+            &self.repo_root,
+            &AnchoredSystemPathBuf::from_raw(package_path)?,
+        )?);
+
+        let mut state = self.lock();
+        // a change made while hashing may not be reflected in the result
+        let changed_while_hashing = state.apply_changes(Some(package_path));
+        if !changed_while_hashing && state.watched.contains(package_path) {
+            state
+                .packages
+                .insert(package_path.to_string(), hashes.clone());
+        }
+        Ok(hashes)
+    }
+
+    /// Returns the hashes of the files in a package matching `inputs`, see
+    /// `input_hashes`.
+    pub fn package_input_hashes(
+        &self,
+        package_path: &str,
+        inputs: &[String],
+    ) -> Result<HashMap<String, String>, turborepo_scm::Error> {
+        let hashes = self.package_hashes(package_path)?;
+        input_hashes(
+            &self.repo_root,
+            &AnchoredSystemPathBuf::from_raw(package_path)?,
+            &hashes,
+            inputs,
+        )
+    }
+
+    fn lock(&self) -> MutexGuard<CacheState> {
+        self.state.lock().expect("only fails if poisoned")
+    }
+}
+
+impl CacheState {
+    /// drops the hashes of every package affected by changes received since
+    /// the last call. returns whether the given package was affected.
+    fn apply_changes(&mut self, package_path: Option<&str>) -> bool {
+        let mut affected = false;
+        loop {
+            let changes = match self.changes.try_recv() {
+                Ok(changes) => changes,
+                Err(TryRecvError::Empty) | Err(TryRecvError::Closed) => return affected,
+                // we don't know what changed, so everything is suspect
+                Err(TryRecvError::Lagged(_)) => {
+                    self.packages.clear();
+                    affected = true;
+                    continue;
+                }
+            };
+
+            for path in changes.iter() {
+                self.packages
+                    .retain(|package, _| !is_affected_by(package, path));
+                affected |= package_path.map_or(false, |package| is_affected_by(package, path));
+            }
+        }
+    }
+}
+
+/// whether a change to `path`, relative to the repo root, can change the
+/// hashes of the package at `package_path`
+fn is_affected_by(package_path: &str, path: &Path) -> bool {
+    // paths that aren't valid utf8 can't be in a package we hash
+    let Some(path) = path.to_str() else {
+        return false;
+    };
+    let path = path.replace(std::path::MAIN_SEPARATOR, "/");
+
+    // the root itself changing means that anything may have changed
+    if path.is_empty() || path == ".git" || path.starts_with(".git/") {
+        return true;
+    }
+    if is_within(&path, package_path) {
+        return true;
+    }
+    // ignore files in parent directories change which files are hashed
+    match path.strip_suffix(".gitignore") {
+        Some("") => true,
+        Some(dir) => dir
+            .strip_suffix('/')
+            .map_or(false, |dir| is_within(package_path, dir)),
+        None => false,
+    }
+}
+
+fn is_within(path: &str, dir: &str) -> bool {
+    dir.is_empty()
+        || path == dir
+        || path
+            .strip_prefix(dir)
+            .map_or(false, |rest| rest.starts_with('/'))
+}
+
+/// Selects the hashes of the files matching `inputs`, see
+/// `expand_package_inputs`. Without any inputs every file is selected. Matching
+/// files that aren't in `hashes`, such as files ignored by git, are hashed from
+/// disk.
+pub fn input_hashes(
+    repo_root: &AbsoluteSystemPathBuf,
+    package_path: &AnchoredSystemPathBuf,
+    hashes: &GitHashes,
+    inputs: &[String],
+) -> Result<HashMap<String, String>, turborepo_scm::Error> {
+    if inputs.is_empty() {
+        return to_string_map(hashes.clone());
+    }
+
+    let files = expand_package_inputs(repo_root, package_path, inputs)?;
+    let (known, unknown): (Vec<_>, Vec<_>) = files
+        .into_iter()
+        .partition(|file| hashes.contains_key(file));
+    let mut selected = get_hashes_for_files(repo_root, package_path, &unknown)?;
+    selected.extend(known.into_iter().map(|file| {
+        let hash = hashes[&file].clone();
+        (file, hash)
+    }));
+    to_string_map(selected)
+}
+
+fn to_string_map(hashes: GitHashes) -> Result<HashMap<String, String>, turborepo_scm::Error> {
+    hashes
+        .into_iter()
+        .map(|(path, hash)| Ok((path.as_str()?.to_string(), hash)))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::{path::PathBuf, process::Command, sync::Arc};
+
+    use turbopath::{AbsoluteSystemPathBuf, AnchoredSystemPathBuf, RelativeUnixPathBuf};
+    use turborepo_scm::package_deps::GitHashes;
+
+    use super::{input_hashes, is_affected_by, FileHashCache};
+
+    fn git(dir: &std::path::Path, args: &[&str]) {
+        let output = Command::new("git")
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+    }
+
+    #[test]
+    fn test_watch_globs() {
+        assert_eq!(
+            FileHashCache::watch_globs("apps/web"),
+            vec![".git/index", ".gitignore", "apps/.gitignore", "apps/web/**"]
+        );
+        assert_eq!(
+            FileHashCache::watch_globs(""),
+            vec![".git/index", ".gitignore", "**"]
+        );
+    }
+
+    #[test]
+    fn test_is_affected_by() {
+        let cases = [
+            ("apps/web", "apps/web/src/index.ts", true),
+            ("apps/web", "apps/website/index.ts", false),
+            ("apps/web", "apps/.gitignore", true),
+            ("apps/web", ".gitignore", true),
+            ("apps/web", "packages/.gitignore", false),
+            ("apps/web", ".git/index", true),
+            ("apps/web", "", true),
+            ("", "packages/ui/index.ts", true),
+        ];
+        for (package, path, expected) in cases {
+            assert_eq!(
+                is_affected_by(package, &PathBuf::from(path)),
+                expected,
+                "{} {}",
+                package,
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn test_input_hashes() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo_root = AbsoluteSystemPathBuf::new(dir.path())
+            .unwrap()
+            .to_realpath()
+            .unwrap();
+        let package = repo_root.as_path().join("my-pkg");
+        std::fs::create_dir_all(package.join("src")).unwrap();
+        std::fs::write(package.join("package.json"), "{}").unwrap();
+        std::fs::write(package.join("src/index.ts"), "index").unwrap();
+        std::fs::write(package.join("src/index.test.ts"), "test").unwrap();
+        std::fs::write(package.join("README.md"), "readme").unwrap();
+        // gitignored, so it isn't in the cached hashes
+        std::fs::write(package.join(".env"), "SECRET=1").unwrap();
+
+        let hashes: GitHashes = [
+            "package.json",
+            "src/index.ts",
+            "src/index.test.ts",
+            "README.md",
+        ]
+        .into_iter()
+        .map(|path| (RelativeUnixPathBuf::new(path).unwrap(), path.to_string()))
+        .collect();
+        let package_path = AnchoredSystemPathBuf::from_raw("my-pkg").unwrap();
+
+        assert_eq!(
+            input_hashes(&repo_root, &package_path, &hashes, &[])
+                .unwrap()
+                .len(),
+            4
+        );
+
+        let mut selected = input_hashes(
+            &repo_root,
+            &package_path,
+            &hashes,
+            &[
+                "src/**".to_string(),
+                ".env".to_string(),
+                "!**/*.test.ts".to_string(),
+            ],
+        )
+        .unwrap()
+        .into_iter()
+        .collect::<Vec<_>>();
+        selected.sort();
+        assert_eq!(selected[0].0, ".env");
+        assert_ne!(selected[0].1, ".env");
+        assert_eq!(
+            selected[1..],
+            [
+                ("package.json".to_string(), "package.json".to_string()),
+                ("src/index.ts".to_string(), "src/index.ts".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalidation() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo_root = AbsoluteSystemPathBuf::new(dir.path())
+            .unwrap()
+            .to_realpath()
+            .unwrap();
+        let package = repo_root.as_path().join("my-pkg");
+        std::fs::create_dir_all(&package).unwrap();
+        std::fs::write(package.join("index.js"), "first").unwrap();
+        git(repo_root.as_path(), &["init", "."]);
+
+        let (tx, rx) = tokio::sync::broadcast::channel(16);
+        let cache = FileHashCache::new(repo_root, rx);
+        assert!(cache.start_watching("my-pkg"));
+        assert!(!cache.start_watching("my-pkg"));
+
+        let first = cache.package_hashes("my-pkg").unwrap();
+        assert!(Arc::ptr_eq(
+            &first,
+            &cache.package_hashes("my-pkg").unwrap()
+        ));
+
+        // a change elsewhere keeps the cached hashes
+        tx.send(Arc::new(vec![PathBuf::from("other-pkg/index.js")]))
+            .unwrap();
+        assert!(Arc::ptr_eq(
+            &first,
+            &cache.package_hashes("my-pkg").unwrap()
+        ));
+
+        std::fs::write(package.join("index.js"), "second").unwrap();
+        tx.send(Arc::new(vec![PathBuf::from("my-pkg/index.js")]))
+            .unwrap();
+        let second = cache.package_hashes("my-pkg").unwrap();
+        assert_ne!(first, second);
+    }
+}
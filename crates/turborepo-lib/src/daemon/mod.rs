@@ -3,9 +3,12 @@ mod bump_timeout_layer;
 mod client;
 mod connector;
 pub(crate) mod endpoint;
+mod file_hashes;
+mod package_graph;
+mod recent_errors;
 mod server;
 
-pub use client::{DaemonClient, DaemonError};
+pub use client::{get_file_hashes, DaemonClient, DaemonError};
 pub use connector::DaemonConnector;
 pub use server::{CloseReason, DaemonServer};
 
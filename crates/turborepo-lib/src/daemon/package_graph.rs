@@ -0,0 +1,193 @@
+//! Package Graph Cache
+//!
+//! Keeps the package graph of the repository in memory so that it doesn't
+//! have to be rediscovered for every run. The graph is updated from file
+//! watcher events, and rebuilt when the way workspaces are discovered changes.
+
+use anyhow::Result;
+use tokio::sync::broadcast::{self, error::TryRecvError};
+use tracing::debug;
+use turbopath::AbsoluteSystemPathBuf;
+
+use super::proto;
+use crate::{
+    globwatcher::Changes, package_graph::PackageGraph, package_manager::LOCKFILES, ui::UI,
+};
+
+pub struct PackageGraphCache {
+    repo_root: AbsoluteSystemPathBuf,
+    changes: broadcast::Receiver<Changes>,
+    graph: Option<PackageGraph>,
+    watching: bool,
+}
+
+impl PackageGraphCache {
+    pub fn new(repo_root: AbsoluteSystemPathBuf, changes: broadcast::Receiver<Changes>) -> Self {
+        Self {
+            repo_root,
+            changes,
+            graph: None,
+            watching: false,
+        }
+    }
+
+    /// The globs that need to be watched to notice every change to the graph
+    pub fn watch_globs() -> Vec<String> {
+        ["**/package.json", "pnpm-workspace.yaml"]
+            .into_iter()
+            .chain(LOCKFILES)
+            .map(String::from)
+            .collect()
+    }
+
+    /// Whether the files in `watch_globs` are being watched. The graph is only
+    /// kept between calls to `package_graph` once they are.
+    pub fn is_watching(&self) -> bool {
+        self.watching
+    }
+
+    pub fn set_watching(&mut self) {
+        self.watching = true;
+    }
+
+    /// Returns the package graph, applying any changes since the last call.
+    pub fn package_graph(&mut self) -> Result<&PackageGraph> {
+        self.apply_changes();
+        if !self.watching {
+            self.graph = None;
+        }
+
+        let graph = match self.graph.take() {
+            Some(graph) => graph,
+            None => {
+                debug!("building package graph");
+                PackageGraph::build(&self.repo_root, &UI::new(true))?
+            }
+        };
+        Ok(self.graph.insert(graph))
+    }
+
+    fn apply_changes(&mut self) {
+        loop {
+            let changes = match self.changes.try_recv() {
+                Ok(changes) => changes,
+                Err(TryRecvError::Empty) | Err(TryRecvError::Closed) => return,
+                // we don't know what changed, so start over
+                Err(TryRecvError::Lagged(_)) => {
+                    self.graph = None;
+                    continue;
+                }
+            };
+
+            let Some(graph) = &mut self.graph else {
+                continue;
+            };
+            for path in changes.iter() {
+                match graph.apply_change(path) {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        debug!("{} changed, rebuilding package graph", path.display());
+                        self.graph = None;
+                        break;
+                    }
+                    // the file may be mid-write, try again on the next request
+                    Err(e) => {
+                        debug!("unable to update package graph: {}", e);
+                        self.graph = None;
+                        break;
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl From<&PackageGraph> for proto::GetPackageGraphResponse {
+    fn from(graph: &PackageGraph) -> Self {
+        let mut dependencies = graph.dependencies();
+        Self {
+            package_manager: graph.package_manager().to_string(),
+            packages: graph
+                .workspaces()
+                .map(|(path, package_json)| proto::PackageNode {
+                    name: package_json.name.clone().unwrap_or_default(),
+                    path: path.to_string(),
+                    dependencies: dependencies
+                        .remove(path)
+                        .into_iter()
+                        .flatten()
+                        .map(|dependency| dependency.to_string())
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{path::PathBuf, sync::Arc};
+
+    use turbopath::AbsoluteSystemPathBuf;
+
+    use super::PackageGraphCache;
+    use crate::daemon::proto;
+
+    #[test]
+    fn test_package_graph_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::write(
+            root.join("package.json"),
+            r#"{"packageManager": "npm@8.19.2", "workspaces": ["packages/*"]}"#,
+        )
+        .unwrap();
+        std::fs::create_dir_all(root.join("packages/a")).unwrap();
+        std::fs::write(root.join("packages/a/package.json"), r#"{"name": "a"}"#).unwrap();
+
+        let (tx, rx) = tokio::sync::broadcast::channel(16);
+        let mut cache = PackageGraphCache::new(AbsoluteSystemPathBuf::new(root).unwrap(), rx);
+        cache.set_watching();
+        assert_eq!(cache.package_graph().unwrap().workspaces().count(), 2);
+
+        std::fs::create_dir_all(root.join("packages/b")).unwrap();
+        std::fs::write(
+            root.join("packages/b/package.json"),
+            r#"{"name": "b", "dependencies": {"a": "*"}}"#,
+        )
+        .unwrap();
+        tx.send(Arc::new(vec![PathBuf::from("packages/b/package.json")]))
+            .unwrap();
+
+        let response = proto::GetPackageGraphResponse::from(cache.package_graph().unwrap());
+        assert_eq!(response.package_manager, "npm");
+        assert_eq!(
+            response.packages,
+            vec![
+                proto::PackageNode {
+                    name: "".to_string(),
+                    path: "".to_string(),
+                    dependencies: vec![],
+                },
+                proto::PackageNode {
+                    name: "a".to_string(),
+                    path: "packages/a".to_string(),
+                    dependencies: vec![],
+                },
+                proto::PackageNode {
+                    name: "b".to_string(),
+                    path: "packages/b".to_string(),
+                    dependencies: vec!["packages/a".to_string()],
+                },
+            ]
+        );
+
+        // an unparseable package.json is retried on the next request
+        std::fs::write(root.join("packages/b/package.json"), "{").unwrap();
+        tx.send(Arc::new(vec![PathBuf::from("packages/b/package.json")]))
+            .unwrap();
+        assert!(cache.package_graph().is_err());
+        std::fs::write(root.join("packages/b/package.json"), r#"{"name": "b"}"#).unwrap();
+        assert_eq!(cache.package_graph().unwrap().workspaces().count(), 3);
+    }
+}
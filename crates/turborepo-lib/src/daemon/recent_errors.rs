@@ -0,0 +1,73 @@
+use std::{
+    collections::VecDeque,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use super::proto;
+
+/// A bounded log of the most recent errors in the daemon, so that they can be
+/// reported by `turbo daemon status` without digging through the log file.
+#[derive(Debug)]
+pub struct RecentErrors {
+    capacity: usize,
+    errors: Mutex<VecDeque<proto::DaemonErrorRecord>>,
+}
+
+impl RecentErrors {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            errors: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    /// Records an error, dropping the oldest one if there are too many.
+    pub fn push(&self, message: String) {
+        let mut errors = self.errors.lock().expect("only fails if poisoned");
+        if errors.len() == self.capacity {
+            errors.pop_back();
+        }
+        errors.push_front(proto::DaemonErrorRecord {
+            unix_msec: unix_millis(SystemTime::now()),
+            message,
+        });
+    }
+
+    /// Returns the recorded errors, most recent first.
+    pub fn to_vec(&self) -> Vec<proto::DaemonErrorRecord> {
+        self.errors
+            .lock()
+            .expect("only fails if poisoned")
+            .iter()
+            .cloned()
+            .collect()
+    }
+}
+
+pub fn unix_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use super::RecentErrors;
+
+    #[test]
+    fn test_keeps_most_recent() {
+        let errors = RecentErrors::new(2);
+        for message in ["first", "second", "third"] {
+            errors.push(message.to_string());
+        }
+        assert_eq!(
+            errors
+                .to_vec()
+                .into_iter()
+                .map(|error| error.message)
+                .collect::<Vec<_>>(),
+            vec!["third", "second"]
+        );
+    }
+}
@@ -13,7 +13,8 @@
 //! globs, and to query for changes for those globs.
 
 use std::{
-    collections::HashSet,
+    collections::{BTreeMap, HashSet},
+    path::{Path, PathBuf},
     sync::{
         atomic::{AtomicBool, Ordering},
         Arc,
@@ -21,24 +22,31 @@ use std::{
     time::{Duration, Instant},
 };
 
-use globwatch::{StopSource, Watcher};
+use globwatch::{Backend, BackendWatcher, StopSource, Watcher};
+use sysinfo::{ProcessExt, ProcessRefreshKind, System, SystemExt};
 use tokio::{
     select,
     signal::ctrl_c,
     sync::{
+        broadcast::error::RecvError,
+        mpsc,
         oneshot::{Receiver, Sender},
         Mutex,
     },
 };
+use tokio_stream::wrappers::ReceiverStream;
 use tonic::transport::{NamedService, Server};
 use tower::ServiceBuilder;
-use tracing::error;
+use tracing::{error, warn};
 use turbopath::{AbsoluteSystemPathBuf, RelativeSystemPathBuf};
 
 use super::{
     bump_timeout::BumpTimeout,
     endpoint::SocketOpenError,
+    file_hashes::FileHashCache,
+    package_graph::PackageGraphCache,
     proto::{self},
+    recent_errors::{unix_millis, RecentErrors},
     DaemonError,
 };
 use crate::{
@@ -46,6 +54,22 @@ use crate::{
     globwatcher::HashGlobWatcher,
 };
 
+/// number of package change events buffered for each subscriber
+const PACKAGE_CHANGES_BUFFER: usize = 64;
+
+/// where the watcher state is kept between daemon restarts, relative to the
+/// daemon root
+const WATCHER_STATE_FILE: &str = "glob-state.json";
+
+/// where the watcher writes the files it uses to flush events, relative to
+/// the repo root. this has to be on the same filesystem as the repo, so that
+/// a watcher that doesn't receive events for the repo (e.g. on a network
+/// mount) also doesn't see its flushes and falls back to polling.
+const FLUSH_DIR: [&str; 3] = [".turbo", "daemon", "flush"];
+
+/// number of errors reported by `turbo daemon status`
+const RECENT_ERRORS_CAPACITY: usize = 20;
+
 pub struct DaemonServer<T: Watcher> {
     daemon_root: AbsoluteSystemPathBuf,
     log_file: AbsoluteSystemPathBuf,
@@ -54,6 +78,9 @@ pub struct DaemonServer<T: Watcher> {
     timeout: Arc<BumpTimeout>,
 
     watcher: Arc<HashGlobWatcher<T>>,
+    file_hashes: Arc<FileHashCache>,
+    package_graph: Arc<tokio::sync::Mutex<PackageGraphCache>>,
+    recent_errors: Arc<RecentErrors>,
     shutdown: Mutex<Option<Sender<()>>>,
     shutdown_rx: Option<Receiver<()>>,
 
@@ -70,7 +97,7 @@ pub enum CloseReason {
     SocketOpenError(SocketOpenError),
 }
 
-impl DaemonServer<notify::RecommendedWatcher> {
+impl DaemonServer<BackendWatcher> {
     #[tracing::instrument(skip(base), fields(repo_root = %base.repo_root))]
     pub fn new(
         base: &CommandBase,
@@ -79,13 +106,58 @@ impl DaemonServer<notify::RecommendedWatcher> {
     ) -> Result<Self, DaemonError> {
         let daemon_root = base.daemon_file_root();
 
+        let repo_root =
+            AbsoluteSystemPathBuf::new(base.repo_root.clone()).expect("valid repo root");
+        let backend = match base
+            .repo_config()
+            .ok()
+            .and_then(|config| config.watcher_backend())
+            .map(str::parse)
+        {
+            Some(Ok(backend)) => backend,
+            Some(Err(e)) => {
+                warn!("{}, using the default", e);
+                Backend::default()
+            }
+            None => Backend::default(),
+        };
+        let poll_interval = match base
+            .repo_config()
+            .ok()
+            .and_then(|config| config.watcher_poll_interval())
+            .map(str::parse::<u64>)
+        {
+            Some(Ok(millis)) if millis > 0 => Some(Duration::from_millis(millis)),
+            Some(_) => {
+                warn!("invalid watcher poll interval, using the default");
+                None
+            }
+            None => None,
+        };
         let watcher = Arc::new(HashGlobWatcher::new(
-            AbsoluteSystemPathBuf::new(base.repo_root.clone()).expect("valid repo root"),
-            daemon_root
-                .join_relative(RelativeSystemPathBuf::new("flush").expect("valid forward path"))
+            repo_root.clone(),
+            repo_root
+                .join_relative(
+                    RelativeSystemPathBuf::new(FLUSH_DIR.iter().collect::<PathBuf>())
+                        .expect("valid relative path"),
+                )
                 .as_path()
                 .to_owned(),
+            backend,
+            poll_interval,
         )?);
+        if let Err(e) = watcher.restore_state(
+            daemon_root
+                .join_relative(RelativeSystemPathBuf::new(WATCHER_STATE_FILE).expect("valid path"))
+                .as_path(),
+        ) {
+            warn!("failed to restore watcher state: {}", e);
+        }
+        let file_hashes = Arc::new(FileHashCache::new(repo_root.clone(), watcher.changes()));
+        let package_graph = Arc::new(tokio::sync::Mutex::new(PackageGraphCache::new(
+            repo_root,
+            watcher.changes(),
+        )));
 
         let (send_shutdown, recv_shutdown) = tokio::sync::oneshot::channel::<()>();
 
@@ -97,6 +169,9 @@ impl DaemonServer<notify::RecommendedWatcher> {
             timeout: Arc::new(BumpTimeout::new(timeout)),
 
             watcher,
+            file_hashes,
+            package_graph,
+            recent_errors: Arc::new(RecentErrors::new(RECENT_ERRORS_CAPACITY)),
             shutdown: Mutex::new(Some(send_shutdown)),
             shutdown_rx: Some(recv_shutdown),
 
@@ -111,6 +186,14 @@ impl<T: Watcher> Drop for DaemonServer<T> {
     }
 }
 
+impl<T: Watcher> DaemonServer<T> {
+    /// Logs an error and keeps it to be reported by `turbo daemon status`
+    fn record_error(&self, message: String) {
+        error!("{}", message);
+        self.recent_errors.push(message);
+    }
+}
+
 impl<T: Watcher + Send + 'static> DaemonServer<T> {
     /// Serve the daemon server, while also watching for filesystem changes.
     #[tracing::instrument(skip(self))]
@@ -118,6 +201,9 @@ impl<T: Watcher + Send + 'static> DaemonServer<T> {
         let stop = StopSource::new();
         let watcher = self.watcher.clone();
         let watcher_fut = watcher.watch(stop.token());
+        let watcher_state_file = self
+            .daemon_root
+            .join_relative(RelativeSystemPathBuf::new(WATCHER_STATE_FILE).expect("valid path"));
 
         let timer = self.timeout.clone();
         let timeout_fut = timer.wait();
@@ -188,7 +274,7 @@ impl<T: Watcher + Send + 'static> DaemonServer<T> {
             )
         };
 
-        select! {
+        let reason = select! {
             _ = server_fut => {
                 match shutdown_reason {
                     Some(reason) => reason,
@@ -196,8 +282,18 @@ impl<T: Watcher + Send + 'static> DaemonServer<T> {
                 }
             },
             _ = watcher_fut => CloseReason::WatcherClosed,
+        };
+
+        // if the watcher stopped early it may have missed changes, so its
+        // state can't be trusted by the next daemon
+        if !matches!(reason, CloseReason::WatcherClosed) {
+            if let Err(e) = watcher.save_state(watcher_state_file.as_path()) {
+                warn!("failed to save watcher state: {}", e);
+            }
         }
 
+        reason
+
         // here the stop token is dropped, and the pid lock is dropped
         // causing them to be cleaned up
     }
@@ -205,6 +301,9 @@ impl<T: Watcher + Send + 'static> DaemonServer<T> {
 
 #[tonic::async_trait]
 impl<T: Watcher + Send + 'static> proto::turbod_server::Turbod for DaemonServer<T> {
+    type WatchPackageChangesStream =
+        ReceiverStream<Result<proto::PackageChangeEvent, tonic::Status>>;
+
     async fn hello(
         &self,
         request: tonic::Request<proto::HelloRequest>,
@@ -232,10 +331,28 @@ impl<T: Watcher + Send + 'static> proto::turbod_server::Turbod for DaemonServer<
         &self,
         _request: tonic::Request<proto::StatusRequest>,
     ) -> Result<tonic::Response<proto::StatusResponse>, tonic::Status> {
+        let watcher = self.watcher.status();
         Ok(tonic::Response::new(proto::StatusResponse {
             daemon_status: Some(proto::DaemonStatus {
                 uptime_msec: self.start_time.elapsed().as_millis() as u64,
                 log_file: self.log_file.to_str().unwrap().to_string(),
+                watcher: Some(proto::WatcherStatus {
+                    backend: match watcher.backend {
+                        Backend::Native => format!("native ({:?})", BackendWatcher::kind()),
+                        backend => backend.to_string(),
+                    },
+                    watched_globs: watcher.watched_globs as u64,
+                    registered_hashes: watcher.registered_hashes as u64,
+                    pending_flushes: watcher.pending_flushes as u64,
+                    last_event_unix_msec: watcher.last_event.map(unix_millis).unwrap_or_default(),
+                }),
+                timeout_msec: self
+                    .timeout
+                    .as_instant()
+                    .saturating_duration_since(tokio::time::Instant::now())
+                    .as_millis() as u64,
+                memory_bytes: memory_usage().unwrap_or_default(),
+                recent_errors: self.recent_errors.to_vec(),
             }),
         }))
     }
@@ -257,7 +374,7 @@ impl<T: Watcher + Send + 'static> proto::turbod_server::Turbod for DaemonServer<
         {
             Ok(_) => Ok(tonic::Response::new(proto::NotifyOutputsWrittenResponse {})),
             Err(e) => {
-                error!("failed to watch globs: {:?}", e);
+                self.record_error(format!("failed to watch globs: {:?}", e));
                 Err(tonic::Status::internal("failed to watch globs"))
             }
         }
@@ -280,6 +397,227 @@ impl<T: Watcher + Send + 'static> proto::turbod_server::Turbod for DaemonServer<
             changed_output_globs: changed.into_iter().collect(),
         }))
     }
+
+    async fn watch_package_changes(
+        &self,
+        request: tonic::Request<proto::WatchPackageChangesRequest>,
+    ) -> Result<tonic::Response<Self::WatchPackageChangesStream>, tonic::Status> {
+        let workspaces = request.into_inner().workspaces;
+        let globs = workspaces
+            .iter()
+            .map(|workspace| match workspace.path.trim_end_matches('/') {
+                "" => "**".to_string(),
+                path => format!("{}/**", path),
+            })
+            .collect::<Vec<_>>();
+
+        let mut changes = match self.watcher.subscribe(&globs).await {
+            Ok(changes) => changes,
+            Err(e) => {
+                self.record_error(format!("failed to watch workspaces: {:?}", e));
+                return Err(tonic::Status::internal("failed to watch workspaces"));
+            }
+        };
+
+        let (tx, rx) = mpsc::channel(PACKAGE_CHANGES_BUFFER);
+        let watcher = self.watcher.clone();
+        let timeout = self.timeout.clone();
+        tokio::spawn(async move {
+            'events: loop {
+                let paths = select! {
+                    _ = tx.closed() => break,
+                    paths = changes.recv() => paths,
+                };
+                let paths = match paths {
+                    Ok(paths) => paths,
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!(
+                            "package change subscriber lagged, skipped {} events",
+                            skipped
+                        );
+                        // we don't know which workspaces the skipped events
+                        // touched, so the client has to assume all of them
+                        timeout.reset();
+                        for event in resync_events(&workspaces) {
+                            if tx.send(Ok(event)).await.is_err() {
+                                break 'events;
+                            }
+                        }
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                };
+
+                // an active subscriber counts as activity
+                timeout.reset();
+                for event in package_change_events(&workspaces, &paths) {
+                    if tx.send(Ok(event)).await.is_err() {
+                        break 'events;
+                    }
+                }
+            }
+
+            watcher.unsubscribe(globs).await;
+        });
+
+        Ok(tonic::Response::new(ReceiverStream::new(rx)))
+    }
+
+    async fn get_file_hashes(
+        &self,
+        request: tonic::Request<proto::GetFileHashesRequest>,
+    ) -> Result<tonic::Response<proto::GetFileHashesResponse>, tonic::Status> {
+        let inner = request.into_inner();
+        let package_path = normalize_package_path(&inner.package_path).ok_or_else(|| {
+            tonic::Status::invalid_argument("package path must be inside the repository")
+        })?;
+
+        if self.file_hashes.start_watching(&package_path) {
+            // the package stays watched for the lifetime of the daemon, so the
+            // receiver isn't needed. the cache listens to all changes.
+            let globs = FileHashCache::watch_globs(&package_path);
+            if let Err(e) = self.watcher.subscribe(&globs).await {
+                self.file_hashes.stop_watching(&package_path);
+                self.record_error(format!("failed to watch package: {:?}", e));
+                return Err(tonic::Status::internal("failed to watch package"));
+            }
+        }
+
+        // make sure that every write made before the request has invalidated
+        // the cache
+        self.watcher.flush().await;
+
+        let file_hashes = self.file_hashes.clone();
+        let hashes = tokio::task::spawn_blocking(move || {
+            file_hashes.package_input_hashes(&package_path, &inner.inputs)
+        })
+        .await
+        .map_err(|e| {
+            self.record_error(format!("file hashing task failed: {:?}", e));
+            tonic::Status::internal("failed to hash package")
+        })?
+        .map_err(|e| {
+            self.record_error(format!("failed to hash package: {:?}", e));
+            tonic::Status::internal("failed to hash package")
+        })?;
+
+        Ok(tonic::Response::new(proto::GetFileHashesResponse {
+            file_hashes: hashes,
+        }))
+    }
+
+    async fn get_package_graph(
+        &self,
+        _request: tonic::Request<proto::GetPackageGraphRequest>,
+    ) -> Result<tonic::Response<proto::GetPackageGraphResponse>, tonic::Status> {
+        let mut package_graph = self.package_graph.clone().lock_owned().await;
+
+        if !package_graph.is_watching() {
+            // the globs stay watched for the lifetime of the daemon
+            match self
+                .watcher
+                .subscribe(&PackageGraphCache::watch_globs())
+                .await
+            {
+                Ok(_) => package_graph.set_watching(),
+                Err(e) => self.record_error(format!("failed to watch package graph: {:?}", e)),
+            }
+        }
+
+        self.watcher.flush().await;
+
+        let response = tokio::task::spawn_blocking(move || {
+            package_graph
+                .package_graph()
+                .map(proto::GetPackageGraphResponse::from)
+        })
+        .await
+        .map_err(|e| {
+            self.record_error(format!("package graph task failed: {:?}", e));
+            tonic::Status::internal("failed to build package graph")
+        })?
+        .map_err(|e| tonic::Status::internal(format!("failed to build package graph: {:#}", e)))?;
+
+        Ok(tonic::Response::new(response))
+    }
+}
+
+/// Returns the resident memory of the current process in bytes
+fn memory_usage() -> Option<u64> {
+    let pid = sysinfo::get_current_pid().ok()?;
+    let mut system = System::new();
+    system.refresh_process_specifics(pid, ProcessRefreshKind::new());
+    system.process(pid).map(|process| process.memory())
+}
+
+/// Converts a package path sent by a client to a unix path relative to the
+/// repo root, returning None if it could point outside of the repo.
+fn normalize_package_path(package_path: &str) -> Option<String> {
+    let package_path = package_path.replace('\\', "/");
+    let package_path = package_path.trim_end_matches('/');
+    let escapes_root = package_path.starts_with('/')
+        || Path::new(package_path).is_absolute()
+        || package_path
+            .split('/')
+            .any(|component| component == ".." || component.contains(':'));
+    (!escapes_root).then(|| package_path.to_string())
+}
+
+/// Groups changed paths by the workspace that contains them. Paths that are in
+/// nested workspaces belong to the innermost workspace, and paths outside of
+/// every workspace are dropped.
+fn package_change_events(
+    workspaces: &[proto::WorkspaceDirectory],
+    paths: &[PathBuf],
+) -> Vec<proto::PackageChangeEvent> {
+    let mut changed_files: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for path in paths {
+        // invalid utf8 paths can't be sent over grpc
+        let Some(path) = path.to_str() else {
+            continue;
+        };
+        let path = path.replace(std::path::MAIN_SEPARATOR, "/");
+        let workspace = workspaces
+            .iter()
+            .filter(|workspace| {
+                let workspace_path = workspace.path.trim_end_matches('/');
+                workspace_path.is_empty()
+                    || path == workspace_path
+                    || path
+                        .strip_prefix(workspace_path)
+                        .map_or(false, |rest| rest.starts_with('/'))
+            })
+            .max_by_key(|workspace| workspace.path.trim_end_matches('/').len());
+
+        if let Some(workspace) = workspace {
+            changed_files
+                .entry(workspace.name.as_str())
+                .or_default()
+                .push(path);
+        }
+    }
+
+    changed_files
+        .into_iter()
+        .map(|(workspace, changed_files)| proto::PackageChangeEvent {
+            workspace: workspace.to_string(),
+            changed_files,
+            resync: false,
+        })
+        .collect()
+}
+
+/// Returns an event for every workspace that tells the client to treat all of
+/// its files as changed
+fn resync_events(workspaces: &[proto::WorkspaceDirectory]) -> Vec<proto::PackageChangeEvent> {
+    workspaces
+        .iter()
+        .map(|workspace| proto::PackageChangeEvent {
+            workspace: workspace.name.clone(),
+            changed_files: Vec::new(),
+            resync: true,
+        })
+        .collect()
 }
 
 impl<T: Watcher> NamedService for DaemonServer<T> {
@@ -296,9 +634,95 @@ mod test {
     use tokio::select;
     use turbopath::{AbsoluteSystemPathBuf, RelativeSystemPathBuf};
 
-    use super::DaemonServer;
+    use super::{
+        normalize_package_path, package_change_events, proto, resync_events, DaemonServer,
+    };
     use crate::{commands::CommandBase, ui::UI, Args};
 
+    #[test]
+    fn test_package_change_events() {
+        let workspaces = [("//", ""), ("web", "apps/web"), ("web-e2e", "apps/web/e2e")]
+            .into_iter()
+            .map(|(name, path)| proto::WorkspaceDirectory {
+                name: name.to_string(),
+                path: path.to_string(),
+            })
+            .collect::<Vec<_>>();
+        let paths = [
+            "package.json",
+            "apps/web/src/index.ts",
+            "apps/web/e2e/test.ts",
+            "apps/website/index.ts",
+        ]
+        .into_iter()
+        .map(std::path::PathBuf::from)
+        .collect::<Vec<_>>();
+
+        let events = package_change_events(&workspaces, &paths);
+        let events = events
+            .iter()
+            .map(|event| (event.workspace.as_str(), event.changed_files.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            vec![
+                (
+                    "//",
+                    vec![
+                        "package.json".to_string(),
+                        "apps/website/index.ts".to_string()
+                    ]
+                ),
+                ("web", vec!["apps/web/src/index.ts".to_string()]),
+                ("web-e2e", vec!["apps/web/e2e/test.ts".to_string()]),
+            ]
+        );
+
+        // without the root or nested workspaces, files are attributed to the
+        // closest subscribed workspace and the rest are dropped
+        let events = package_change_events(&workspaces[1..2], &paths);
+        assert_eq!(events.len(), 1);
+        assert_eq!(
+            events[0].changed_files,
+            vec!["apps/web/src/index.ts", "apps/web/e2e/test.ts"]
+        );
+        assert!(!events[0].resync);
+
+        // after dropping changes every subscribed workspace has to be resynced
+        let events = resync_events(&workspaces);
+        assert_eq!(
+            events
+                .iter()
+                .map(|event| event.workspace.as_str())
+                .collect::<Vec<_>>(),
+            vec!["//", "web", "web-e2e"]
+        );
+        assert!(events
+            .iter()
+            .all(|event| event.resync && event.changed_files.is_empty()));
+    }
+
+    #[test]
+    fn test_normalize_package_path() {
+        let cases = [
+            ("apps/web", Some("apps/web")),
+            ("apps\\web\\", Some("apps/web")),
+            ("", Some("")),
+            ("apps/../../etc", None),
+            ("..", None),
+            ("/etc", None),
+            ("C:\\Windows", None),
+        ];
+        for (package_path, expected) in cases {
+            assert_eq!(
+                normalize_package_path(package_path).as_deref(),
+                expected,
+                "{}",
+                package_path
+            );
+        }
+    }
+
     // the windows runner starts a new thread to accept uds requests,
     // so we need a multi-threaded runtime
     #[tokio::test(flavor = "multi_thread")]
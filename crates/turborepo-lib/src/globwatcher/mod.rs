@@ -1,18 +1,27 @@
+mod snapshot;
+
 use std::{
     collections::{hash_map::Entry, HashMap, HashSet},
+    io,
     path::{Path, PathBuf},
-    sync::{Arc, Mutex, MutexGuard},
-    time::Duration,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+    time::{Duration, SystemTime},
 };
 
 use futures::{stream::iter, StreamExt};
-use globwatch::{ConfigError, GlobWatcher, StopToken, WatchConfig, Watcher};
+use globwatch::{
+    Backend, BackendWatcher, ConfigError, GlobWatcher, StopToken, WatchConfig, Watcher,
+};
 use itertools::Itertools;
-use notify::RecommendedWatcher;
-use tokio::time::timeout;
+use tokio::sync::broadcast;
 use tracing::{trace, warn};
 use turbopath::AbsoluteSystemPathBuf;
 
+use self::snapshot::Snapshot;
+
 // these aliases are for readability, but they're just strings. it may make
 // sense to use a newtype wrapper for these types in the future.
 type Glob = Arc<String>;
@@ -21,6 +30,12 @@ type Hash = Arc<String>;
 /// timeout for flushing the watcher
 const FLUSH_TIMEOUT: Duration = Duration::from_millis(500);
 
+/// number of change events buffered for each subscriber before it lags
+const CHANGES_CAPACITY: usize = 1024;
+
+/// A batch of changed paths, relative to the watched root
+pub type Changes = Arc<Vec<PathBuf>>;
+
 /// Tracks changes for a given hash. A hash is a unique identifier for a set of
 /// files. Given a hash and a set of globs to track, this will watch for file
 /// changes and allow the user to query for changes. Once all globs for a
@@ -35,27 +50,56 @@ pub struct HashGlobWatcher<T: Watcher> {
     /// maps a glob to the hashes for which this glob hasn't changed
     glob_statuses: Arc<Mutex<HashMap<Glob, HashSet<Hash>>>>,
 
+    /// maps a subscribed glob to the number of subscribers watching it
+    subscribed_globs: Arc<Mutex<HashMap<Glob, usize>>>,
+    changes: broadcast::Sender<Changes>,
+
+    /// number of flushes that are waiting on the watcher
+    pending_flushes: Arc<AtomicUsize>,
+    /// when the last filesystem event was received
+    last_event: Arc<Mutex<Option<SystemTime>>>,
+    /// state from a previous watcher that is validated once its globs are
+    /// being watched
+    restored: Arc<Mutex<Option<Snapshot>>>,
+
     watcher: Arc<Mutex<Option<GlobWatcher>>>,
     config: WatchConfig<T>,
 }
 
+/// a snapshot of the state of the watcher, for diagnostics
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WatcherStatus {
+    pub backend: Backend,
+    pub watched_globs: usize,
+    pub registered_hashes: usize,
+    pub pending_flushes: usize,
+    pub last_event: Option<SystemTime>,
+}
+
 #[derive(Clone, Debug)]
 pub struct GlobSet {
     include: HashSet<Glob>,
     exclude: HashSet<Glob>,
 }
 
-impl HashGlobWatcher<RecommendedWatcher> {
+impl HashGlobWatcher<BackendWatcher> {
     #[tracing::instrument]
     pub fn new(
         relative_to: AbsoluteSystemPathBuf,
         flush_folder: PathBuf,
+        backend: Backend,
+        poll_interval: Option<Duration>,
     ) -> Result<Self, globwatch::Error> {
-        let (watcher, config) = GlobWatcher::new(flush_folder)?;
+        let (watcher, config) = GlobWatcher::with_backend(flush_folder, backend, poll_interval)?;
         Ok(Self {
             relative_to: relative_to.as_path().canonicalize()?,
             hash_globs: Default::default(),
             glob_statuses: Default::default(),
+            subscribed_globs: Default::default(),
+            changes: broadcast::channel(CHANGES_CAPACITY).0,
+            pending_flushes: Default::default(),
+            last_event: Default::default(),
+            restored: Default::default(),
             watcher: Arc::new(Mutex::new(Some(watcher))),
             config,
         })
@@ -67,11 +111,19 @@ impl<T: Watcher> HashGlobWatcher<T> {
     /// make sure that file events are handled in the appropriate order.
     #[tracing::instrument(skip(self, token))]
     pub async fn watch(&self, token: StopToken) {
+        let restored = self.restored.lock().expect("only fails if poisoned").take();
         let start_globs = {
             let lock = self.hash_globs.lock().expect("only fails if poisoned");
             lock.iter()
                 .flat_map(|(_, g)| &g.include)
                 .cloned()
+                .chain(
+                    restored
+                        .iter()
+                        .flat_map(|snapshot| snapshot.include_globs())
+                        .map(|glob| Arc::new(glob.to_string())),
+                )
+                .unique()
                 .collect::<Vec<_>>()
         };
 
@@ -84,18 +136,31 @@ impl<T: Watcher> HashGlobWatcher<T> {
         };
 
         // watch all the globs currently in the map
-        for glob in start_globs {
-            self.config.include(&self.relative_to, &glob).await.ok();
+        for glob in &start_globs {
+            self.config.include(&self.relative_to, glob).await.ok();
+        }
+
+        // the restored globs are only checked for changes once they are
+        // watched, so that changes made while checking aren't missed
+        if let Some(snapshot) = restored {
+            self.apply_snapshot(snapshot, start_globs).await;
         }
 
         while let Some(Ok(event)) = stream.next().await {
             trace!("processing event: {:?}", event);
+            *self.last_event.lock().expect("only fails if poisoned") = Some(SystemTime::now());
 
             let repo_relative_paths = event
                 .paths
                 .iter()
                 .filter_map(|path| path.strip_prefix(&self.relative_to).ok());
 
+            if self.changes.receiver_count() > 0 {
+                let changes = repo_relative_paths.clone().map(Path::to_path_buf).collect();
+                // this only fails if every subscriber has gone away
+                self.changes.send(Arc::new(changes)).ok();
+            }
+
             // put these in a block so we can drop the locks before we await
             let globs_to_exclude = {
                 let glob_statuses = self.glob_statuses.lock().expect("only fails if poisoned");
@@ -111,10 +176,241 @@ impl<T: Watcher> HashGlobWatcher<T> {
                 globs_to_exclude
             };
 
+            if globs_to_exclude.is_empty() {
+                continue;
+            }
+
+            let subscribed_globs = self.subscribed_globs();
             for glob in globs_to_exclude {
+                if !subscribed_globs.contains(&glob) {
+                    self.config.exclude(&self.relative_to, &glob).await;
+                }
+            }
+            // unwatching a directory also unwatches everything inside it, so
+            // make sure subscribers keep receiving events
+            for glob in subscribed_globs {
+                self.config.include(&self.relative_to, &glob).await.ok();
+            }
+        }
+    }
+
+    /// waits for the watcher to flush its events. that will ensure
+    /// that we have seen all filesystem writes *by the calling client*. Other
+    /// tasks _could_ write to the same output directories, however we are
+    /// relying on task execution dependencies to prevent that.
+    ///
+    /// this is a best effort, and times out after 500ms, plus the poll
+    /// interval when polling, in case there is a lot of activity on the
+    /// filesystem. if flushes keep timing out, the watcher may switch to
+    /// polling the filesystem.
+    pub async fn flush(&self) {
+        // the request may be cancelled while waiting, so the count is
+        // decremented when the guard is dropped
+        let _pending = PendingFlush::new(&self.pending_flushes);
+        match self.config.flush_or_fall_back(FLUSH_TIMEOUT).await {
+            Ok(false) => {}
+            Ok(true) => self.forget_changes(),
+            Err(ConfigError::FlushTimeout) => {
+                trace!("timed out waiting for flush");
+            }
+            Err(e) => {
+                trace!("failed to flush: {:?}", e);
+            }
+        }
+    }
+
+    /// called when events may have been missed. every hash is dropped, so
+    /// that its outputs are reported as changed, and subscribers are told
+    /// that the root changed, which means anything may have changed.
+    fn forget_changes(&self) {
+        {
+            let mut glob_statuses = self.glob_statuses.lock().expect("only fails if poisoned");
+            let mut hash_globs = self.hash_globs.lock().expect("only fails if poisoned");
+            glob_statuses.clear();
+            hash_globs.clear();
+        }
+        // this only fails if there are no subscribers
+        self.changes.send(Arc::new(vec![PathBuf::new()])).ok();
+    }
+
+    /// reports the state of the watcher
+    pub fn status(&self) -> WatcherStatus {
+        let watched_globs = {
+            // same lock order as `unsubscribe`
+            let subscribed_globs = self
+                .subscribed_globs
+                .lock()
+                .expect("only fails if poisoned");
+            let glob_statuses = self.glob_statuses.lock().expect("only fails if poisoned");
+            glob_statuses
+                .keys()
+                .chain(subscribed_globs.keys())
+                .unique()
+                .count()
+        };
+
+        WatcherStatus {
+            backend: self.config.backend(),
+            watched_globs,
+            registered_hashes: self
+                .hash_globs
+                .lock()
+                .expect("only fails if poisoned")
+                .len(),
+            pending_flushes: self.pending_flushes.load(Ordering::SeqCst),
+            last_event: *self.last_event.lock().expect("only fails if poisoned"),
+        }
+    }
+
+    /// returns a receiver for every batch of changed files. only paths that
+    /// are being watched are reported. an empty path is the root itself, and
+    /// means that anything may have changed. events are sent before `flush`
+    /// returns, so after flushing every prior change can be received.
+    pub fn changes(&self) -> broadcast::Receiver<Changes> {
+        self.changes.subscribe()
+    }
+
+    /// watches all files matching the given globs and returns a receiver for
+    /// every batch of changed files. the receiver gets changes for any path
+    /// being watched, not just those matching the globs. once the receiver
+    /// is no longer needed, the globs must be released with `unsubscribe`.
+    pub async fn subscribe(
+        &self,
+        globs: &[String],
+    ) -> Result<broadcast::Receiver<Changes>, ConfigError> {
+        let receiver = self.changes();
+        let globs: Vec<Glob> = globs.iter().cloned().map(Arc::new).collect();
+
+        {
+            let mut subscribed_globs = self
+                .subscribed_globs
+                .lock()
+                .expect("only fails if poisoned");
+            for glob in &globs {
+                *subscribed_globs.entry(glob.clone()).or_default() += 1;
+            }
+        }
+
+        for glob in &globs {
+            if let Err(e) = self.config.include(&self.relative_to, glob).await {
+                self.unsubscribe(globs.iter().map(|glob| glob.to_string()))
+                    .await;
+                return Err(e);
+            }
+        }
+
+        Ok(receiver)
+    }
+
+    /// releases globs registered with `subscribe`, unwatching any that are no
+    /// longer needed
+    pub async fn unsubscribe<Iter: IntoIterator<Item = String>>(&self, globs: Iter) {
+        let globs_to_exclude = {
+            let mut subscribed_globs = self
+                .subscribed_globs
+                .lock()
+                .expect("only fails if poisoned");
+            let glob_statuses = self.glob_statuses.lock().expect("only fails if poisoned");
+            globs
+                .into_iter()
+                .map(Arc::new)
+                .filter(|glob| match subscribed_globs.entry(glob.clone()) {
+                    Entry::Occupied(mut o) => {
+                        *o.get_mut() -= 1;
+                        if *o.get() == 0 {
+                            o.remove();
+                            // outputs may still be tracking this glob
+                            !glob_statuses.contains_key(glob)
+                        } else {
+                            false
+                        }
+                    }
+                    Entry::Vacant(_) => false,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        for glob in globs_to_exclude {
+            self.config.exclude(&self.relative_to, &glob).await;
+        }
+    }
+
+    fn subscribed_globs(&self) -> Vec<Glob> {
+        self.subscribed_globs
+            .lock()
+            .expect("only fails if poisoned")
+            .keys()
+            .cloned()
+            .collect()
+    }
+
+    /// saves the hashes and globs being tracked so that a future watcher can
+    /// pick up where this one left off with `restore_state`
+    pub fn save_state(&self, path: &Path) -> Result<(), io::Error> {
+        let hash_globs = self.hash_globs.lock().expect("only fails if poisoned");
+        Snapshot::new(&hash_globs, SystemTime::now()).write(path)
+    }
+
+    /// restores the state saved by `save_state`. this must be called before
+    /// `watch`, which drops globs whose files may have changed since the
+    /// state was saved once they are being watched. the saved state is
+    /// removed, as it is only valid for one restart.
+    pub fn restore_state(&self, path: &Path) -> Result<(), io::Error> {
+        let snapshot = match Snapshot::read(path) {
+            Ok(snapshot) => snapshot,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        std::fs::remove_file(path)?;
+        *self.restored.lock().expect("only fails if poisoned") = snapshot;
+        Ok(())
+    }
+
+    /// registers the hashes of a restored snapshot whose globs haven't
+    /// changed, and unwatches the globs that are no longer needed
+    async fn apply_snapshot(&self, snapshot: Snapshot, watched_globs: Vec<Glob>) {
+        let root = self.relative_to.clone();
+        let restored = match tokio::task::spawn_blocking(move || {
+            snapshot.into_valid_hash_globs(&root)
+        })
+        .await
+        {
+            Ok(restored) => restored,
+            Err(e) => {
+                warn!("failed to check restored globs: {:?}", e);
+                HashMap::new()
+            }
+        };
+        trace!("restored {} hashes", restored.len());
+
+        let globs_to_exclude = {
+            let mut glob_statuses = self.glob_statuses.lock().expect("only fails if poisoned");
+            let mut hash_globs = self.hash_globs.lock().expect("only fails if poisoned");
+            for (hash, globs) in restored {
+                for glob in &globs.include {
+                    glob_statuses
+                        .entry(glob.clone())
+                        .or_default()
+                        .insert(hash.clone());
+                }
+                hash_globs.insert(hash, globs);
+            }
+            watched_globs
+                .into_iter()
+                .filter(|glob| !glob_statuses.contains_key(glob))
+                .collect::<Vec<_>>()
+        };
+
+        let subscribed_globs = self.subscribed_globs();
+        for glob in globs_to_exclude {
+            if !subscribed_globs.contains(&glob) {
                 self.config.exclude(&self.relative_to, &glob).await;
             }
         }
+        // unwatching a directory also unwatches everything inside it
+        for glob in subscribed_globs {
+            self.config.include(&self.relative_to, &glob).await.ok();
+        }
     }
 
     /// registers a hash with a set of globs to watch for changes
@@ -127,20 +423,7 @@ impl<T: Watcher> HashGlobWatcher<T> {
         include: Iter,
         exclude: Iter2,
     ) -> Result<(), ConfigError> {
-        // wait for a the watcher to flush its events
-        // that will ensure that we have seen all filesystem writes
-        // *by the calling client*. Other tasks _could_ write to the
-        // same output directories, however we are relying on task
-        // execution dependencies to prevent that.
-        //
-        // this is a best effort, and times out after 500ms in
-        // case there is a lot of activity on the filesystem
-        match timeout(FLUSH_TIMEOUT, self.config.flush()).await {
-            Ok(_) => {}
-            Err(_) => {
-                trace!("timed out waiting for flush");
-            }
-        }
+        self.flush().await;
 
         let include: HashSet<_> = include.into_iter().map(Arc::new).collect();
         let exclude = exclude.into_iter().map(Arc::new).collect();
@@ -214,20 +497,7 @@ impl<T: Watcher> HashGlobWatcher<T> {
         hash: &Hash,
         mut candidates: HashSet<String>,
     ) -> HashSet<String> {
-        // wait for a the watcher to flush its events
-        // that will ensure that we have seen all filesystem writes
-        // *by the calling client*. Other tasks _could_ write to the
-        // same output directories, however we are relying on task
-        // execution dependencies to prevent that.
-        //
-        // this is a best effort, and times out after 500ms in
-        // case there is a lot of activity on the filesystem
-        match timeout(FLUSH_TIMEOUT, self.config.flush()).await {
-            Ok(_) => {}
-            Err(_) => {
-                trace!("timed out waiting for flush");
-            }
-        }
+        self.flush().await;
 
         // hash_globs tracks all unchanged globs for a given hash.
         // if a hash is not in globs, then either everything has changed
@@ -252,6 +522,22 @@ impl<T: Watcher> HashGlobWatcher<T> {
 ///
 /// note: we take a mutex guard to make sure that the mutex is dropped
 ///       when the function returns
+/// counts a flush as pending for as long as it is alive
+struct PendingFlush<'a>(&'a AtomicUsize);
+
+impl<'a> PendingFlush<'a> {
+    fn new(pending_flushes: &'a AtomicUsize) -> Self {
+        pending_flushes.fetch_add(1, Ordering::SeqCst);
+        Self(pending_flushes)
+    }
+}
+
+impl Drop for PendingFlush<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
 fn populate_hash_globs<'a>(
     glob_statuses: &MutexGuard<HashMap<Glob, HashSet<Hash>>>,
     repo_relative_paths: impl Iterator<Item = &'a Path> + Clone,
@@ -335,7 +621,13 @@ fn clear_hash_globs(
 
 #[cfg(test)]
 mod test {
-    use std::{fs::File, sync::Arc};
+    use std::{
+        fs::File,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
 
     use globwatch::StopSource;
     use turbopath::AbsoluteSystemPathBuf;
@@ -363,6 +655,17 @@ mod test {
         tmp
     }
 
+    #[test]
+    fn pending_flushes_are_released_on_drop() {
+        let pending_flushes = AtomicUsize::new(0);
+        {
+            let _first = super::PendingFlush::new(&pending_flushes);
+            let _second = super::PendingFlush::new(&pending_flushes);
+            assert_eq!(pending_flushes.load(Ordering::SeqCst), 2);
+        }
+        assert_eq!(pending_flushes.load(Ordering::SeqCst), 0);
+    }
+
     #[tokio::test]
     #[tracing_test::traced_test]
     async fn track_outputs() {
@@ -372,6 +675,8 @@ mod test {
             super::HashGlobWatcher::new(
                 AbsoluteSystemPathBuf::new(dir.path()).unwrap(),
                 flush.path().to_path_buf(),
+                globwatch::Backend::Auto,
+                None,
             )
             .unwrap(),
         );
@@ -483,6 +788,8 @@ mod test {
             super::HashGlobWatcher::new(
                 AbsoluteSystemPathBuf::new(dir.path()).unwrap(),
                 flush.path().to_path_buf(),
+                globwatch::Backend::Auto,
+                None,
             )
             .unwrap(),
         );
@@ -600,6 +907,8 @@ mod test {
             super::HashGlobWatcher::new(
                 AbsoluteSystemPathBuf::new(dir.path()).unwrap(),
                 flush.path().to_path_buf(),
+                globwatch::Backend::Auto,
+                None,
             )
             .unwrap(),
         );
@@ -624,6 +933,11 @@ mod test {
             .await
             .unwrap();
 
+        let status = watcher.status();
+        assert_eq!(status.watched_globs, 1);
+        assert_eq!(status.registered_hashes, 1);
+        assert_eq!(status.pending_flushes, 0);
+
         File::create(dir.path().join("my-pkg/.next/irrelevant")).unwrap();
         let changed = watcher
             .changed_globs(&hash, inclusions.clone().into_iter().collect())
@@ -656,5 +970,10 @@ mod test {
             "we should no longer be watching any globs: {:?}",
             watcher.glob_statuses.lock().unwrap()
         );
+
+        let status = watcher.status();
+        assert_eq!(status.watched_globs, 0);
+        assert_eq!(status.registered_hashes, 0);
+        assert!(status.last_event.is_some());
     }
 }
@@ -0,0 +1,260 @@
+//! Persistence for the hash / glob state of a `HashGlobWatcher`, so that a
+//! restarted daemon doesn't report every output as changed.
+//!
+//! the snapshot is taken when the daemon shuts down. filesystem events that
+//! happen while no daemon is running are missed, so on load every glob is
+//! checked against file modification times and dropped if anything that it
+//! could match has been modified since the snapshot was taken.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::Path,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+use walkdir::WalkDir;
+
+use super::{GlobSet, Hash};
+
+/// bump this when the snapshot format changes, older snapshots are discarded
+const SNAPSHOT_VERSION: u32 = 1;
+
+/// the coarsest modification time resolution of common filesystems (FAT).
+/// a file written just after the snapshot may have its modification time
+/// rounded down to before it, so anything modified within this long before
+/// the snapshot is treated as changed.
+const MTIME_GRANULARITY: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+    version: u32,
+    saved_at_unix_ms: u64,
+    hashes: Vec<HashEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct HashEntry {
+    hash: String,
+    /// globs that haven't changed for this hash
+    include: Vec<String>,
+    exclude: Vec<String>,
+}
+
+impl Snapshot {
+    pub fn new(hash_globs: &HashMap<Hash, GlobSet>, saved_at: SystemTime) -> Self {
+        Self {
+            version: SNAPSHOT_VERSION,
+            saved_at_unix_ms: saved_at
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_millis() as u64)
+                .unwrap_or_default(),
+            hashes: hash_globs
+                .iter()
+                .map(|(hash, globs)| HashEntry {
+                    hash: hash.to_string(),
+                    include: globs.include.iter().map(|glob| glob.to_string()).collect(),
+                    exclude: globs.exclude.iter().map(|glob| glob.to_string()).collect(),
+                })
+                .collect(),
+        }
+    }
+
+    /// reads a snapshot, returning None if it was written by an incompatible
+    /// version of the daemon
+    pub fn read(path: &Path) -> Result<Option<Self>, std::io::Error> {
+        let contents = fs::read(path)?;
+        match serde_json::from_slice::<Snapshot>(&contents) {
+            Ok(snapshot) if snapshot.version == SNAPSHOT_VERSION => Ok(Some(snapshot)),
+            Ok(_) | Err(_) => {
+                debug!("discarding incompatible glob snapshot");
+                Ok(None)
+            }
+        }
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), std::io::Error> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // write to a temporary file first so that a partial snapshot is never
+        // read
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, serde_json::to_vec(self)?)?;
+        fs::rename(tmp_path, path)
+    }
+
+    /// every glob included by a hash in the snapshot
+    pub fn include_globs(&self) -> impl Iterator<Item = &str> {
+        self.hashes
+            .iter()
+            .flat_map(|entry| entry.include.iter().map(String::as_str))
+    }
+
+    /// returns the hash globs whose files haven't been modified since the
+    /// snapshot was taken
+    pub fn into_valid_hash_globs(self, root: &Path) -> HashMap<Hash, GlobSet> {
+        let saved_at = (UNIX_EPOCH + Duration::from_millis(self.saved_at_unix_ms))
+            .checked_sub(MTIME_GRANULARITY)
+            .unwrap_or(UNIX_EPOCH);
+        // globs are often shared between hashes, only check each one once
+        let mut changed: HashMap<String, bool> = HashMap::new();
+
+        self.hashes
+            .into_iter()
+            .filter_map(|entry| {
+                let include = entry
+                    .include
+                    .into_iter()
+                    .filter(|glob| {
+                        !*changed
+                            .entry(glob.clone())
+                            .or_insert_with(|| glob_changed_since(root, glob, saved_at))
+                    })
+                    .map(Arc::new)
+                    .collect::<HashSet<_>>();
+                if include.is_empty() {
+                    return None;
+                }
+                let exclude = entry.exclude.into_iter().map(Arc::new).collect();
+                Some((Arc::new(entry.hash), GlobSet { include, exclude }))
+            })
+            .collect()
+    }
+}
+
+/// whether anything matching the glob may have changed since the given time.
+/// this errs on the side of reporting a change: every directory below the
+/// static prefix of the glob is checked, since adding or removing a file
+/// updates the modification time of its directory.
+fn glob_changed_since(root: &Path, glob: &str, since: SystemTime) -> bool {
+    let prefix = glob
+        .split('/')
+        .take_while(|component| !component.contains(['*', '?', '[', '{', '!']))
+        .collect::<Vec<_>>()
+        .join("/");
+    let base = root.join(&prefix);
+
+    // the prefix itself being created or removed shows up in its parent
+    if !prefix.is_empty()
+        && base
+            .parent()
+            .map_or(true, |parent| modified_since(parent, since))
+    {
+        return true;
+    }
+    if !base.exists() {
+        return true;
+    }
+
+    for entry in WalkDir::new(&base) {
+        let Ok(entry) = entry else {
+            return true;
+        };
+        let matches = entry.file_type().is_dir()
+            || entry
+                .path()
+                .strip_prefix(root)
+                .ok()
+                .and_then(|path| path.to_str())
+                .map_or(false, |path| glob_match::glob_match(glob, path));
+        if matches && modified_since(entry.path(), since) {
+            return true;
+        }
+    }
+
+    false
+}
+
+fn modified_since(path: &Path, since: SystemTime) -> bool {
+    fs::symlink_metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .map_or(true, |modified| modified >= since)
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        collections::{HashMap, HashSet},
+        sync::Arc,
+        time::{Duration, SystemTime},
+    };
+
+    use super::{glob_changed_since, Snapshot};
+    use crate::globwatcher::GlobSet;
+
+    #[test]
+    fn test_glob_changed_since() {
+        let dir = tempdir::TempDir::new("globwatch-snapshot").unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("my-pkg/dist/nested")).unwrap();
+        std::fs::write(root.join("my-pkg/dist/nested/file"), "").unwrap();
+        std::fs::create_dir_all(root.join("my-pkg/.next")).unwrap();
+
+        let later = SystemTime::now() + Duration::from_secs(60);
+        assert!(!glob_changed_since(root, "my-pkg/dist/**", later));
+        // a glob whose directory doesn't exist can't be trusted
+        assert!(glob_changed_since(root, "my-pkg/build/**", later));
+
+        let earlier = SystemTime::now() - Duration::from_secs(60);
+        assert!(glob_changed_since(root, "my-pkg/dist/**", earlier));
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let dir = tempdir::TempDir::new("globwatch-snapshot").unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("my-pkg/dist")).unwrap();
+        std::fs::create_dir_all(root.join("my-pkg/.next")).unwrap();
+
+        let hash = Arc::new("the-hash".to_string());
+        let hash_globs = HashMap::from([(
+            hash.clone(),
+            GlobSet {
+                include: HashSet::from([
+                    Arc::new("my-pkg/dist/**".to_string()),
+                    Arc::new("my-pkg/.next/**".to_string()),
+                ]),
+                exclude: HashSet::from([Arc::new("my-pkg/.next/cache/**".to_string())]),
+            },
+        )]);
+
+        let path = root.join("state/glob-state.json");
+        Snapshot::new(&hash_globs, SystemTime::now() + Duration::from_secs(60))
+            .write(&path)
+            .unwrap();
+        let restored = Snapshot::read(&path)
+            .unwrap()
+            .unwrap()
+            .into_valid_hash_globs(root);
+        assert_eq!(restored[&hash].include, hash_globs[&hash].include);
+        assert_eq!(restored[&hash].exclude, hash_globs[&hash].exclude);
+
+        // the directory of one glob changed after the snapshot was taken
+        Snapshot::new(&hash_globs, SystemTime::now() - Duration::from_secs(60))
+            .write(&path)
+            .unwrap();
+        let restored = Snapshot::read(&path)
+            .unwrap()
+            .unwrap()
+            .into_valid_hash_globs(root);
+        assert!(restored.is_empty());
+
+        // a modification time just before the snapshot may have been rounded
+        // down from after it
+        Snapshot::new(&hash_globs, SystemTime::now() + Duration::from_secs(1))
+            .write(&path)
+            .unwrap();
+        let restored = Snapshot::read(&path)
+            .unwrap()
+            .unwrap()
+            .into_valid_hash_globs(root);
+        assert!(restored.is_empty());
+
+        std::fs::write(&path, r#"{"version": 0}"#).unwrap();
+        assert!(Snapshot::read(&path).unwrap().is_none());
+    }
+}
@@ -7,6 +7,7 @@ mod config;
 mod daemon;
 mod execution_state;
 pub(crate) mod globwatcher;
+mod package_graph;
 mod package_json;
 mod package_manager;
 mod shim;
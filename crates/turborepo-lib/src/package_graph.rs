@@ -0,0 +1,284 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    path::Path,
+};
+
+use anyhow::Result;
+use turbopath::{AbsoluteSystemPathBuf, RelativeSystemPathBuf};
+
+use crate::{
+    package_json::PackageJson,
+    package_manager::{Globs, PackageManager, LOCKFILES},
+    ui::UI,
+};
+
+/// The workspaces of a repository and the dependencies between them.
+#[derive(Debug)]
+pub struct PackageGraph {
+    repo_root: AbsoluteSystemPathBuf,
+    package_manager: PackageManager,
+    globs: Option<Globs>,
+    /// the package.json of each workspace keyed by the unix path of the
+    /// workspace relative to the repo root. the root workspace has an empty
+    /// path.
+    workspaces: BTreeMap<String, PackageJson>,
+}
+
+impl PackageGraph {
+    /// Discovers every workspace in the repository
+    pub fn build(repo_root: &AbsoluteSystemPathBuf, ui: &UI) -> Result<Self> {
+        let root_package_json = PackageJson::load(
+            &repo_root.join_relative(RelativeSystemPathBuf::new("package.json")?),
+        )?;
+        let package_manager =
+            PackageManager::get_repo_package_manager(repo_root, ui, Some(&root_package_json))?;
+        let globs = package_manager.get_workspace_globs(repo_root.as_path())?;
+
+        let mut workspaces = package_manager
+            .get_workspace_package_jsons(repo_root)?
+            .into_iter()
+            .collect::<BTreeMap<_, _>>();
+        workspaces.insert(String::new(), root_package_json);
+
+        Ok(Self {
+            repo_root: repo_root.clone(),
+            package_manager,
+            globs,
+            workspaces,
+        })
+    }
+
+    pub fn package_manager(&self) -> &PackageManager {
+        &self.package_manager
+    }
+
+    /// Iterates over the path and package.json of every workspace, including
+    /// the root workspace.
+    pub fn workspaces(&self) -> impl Iterator<Item = (&str, &PackageJson)> {
+        self.workspaces
+            .iter()
+            .map(|(path, package_json)| (path.as_str(), package_json))
+    }
+
+    /// Returns the paths of the workspaces that each workspace depends on.
+    /// Dependencies are matched to workspaces by package name.
+    pub fn dependencies(&self) -> BTreeMap<&str, BTreeSet<&str>> {
+        let workspaces_by_name = self
+            .workspaces()
+            .filter_map(|(path, package_json)| Some((package_json.name.as_deref()?, path)))
+            .collect::<HashMap<_, _>>();
+
+        self.workspaces()
+            .map(|(path, package_json)| {
+                let dependencies = package_json
+                    .all_dependencies()
+                    .filter_map(|(name, _)| workspaces_by_name.get(name.as_str()).copied())
+                    .filter(|dependency| *dependency != path)
+                    .collect();
+                (path, dependencies)
+            })
+            .collect()
+    }
+
+    /// Updates the graph after a change to `path`, which is relative to the
+    /// repo root. Returns false if the change affects how workspaces are
+    /// discovered, in which case the graph needs to be rebuilt.
+    pub fn apply_change(&mut self, path: &Path) -> Result<bool> {
+        let Some(unix_path) = path.to_str().map(|path| path.replace('\\', "/")) else {
+            return Ok(true);
+        };
+
+        // the root itself changing means that anything may have changed
+        if unix_path.is_empty()
+            || unix_path == "package.json"
+            || unix_path == "pnpm-workspace.yaml"
+            || LOCKFILES.contains(&unix_path.as_str())
+        {
+            return Ok(false);
+        }
+
+        if unix_path
+            .split('/')
+            .any(|component| component == "node_modules")
+        {
+            return Ok(true);
+        }
+        let Some(workspace) = unix_path.strip_suffix("/package.json") else {
+            // moving or deleting a directory doesn't always produce events for
+            // the package.json files inside of it
+            return Ok(!self.may_contain_workspaces(&unix_path, path));
+        };
+
+        let package_json_path = self
+            .repo_root
+            .join_relative(RelativeSystemPathBuf::new(path)?);
+        let is_workspace = match &self.globs {
+            Some(globs) => globs.test(
+                self.repo_root.as_path().to_owned(),
+                package_json_path
+                    .as_path()
+                    .parent()
+                    .expect("package.json has a parent")
+                    .to_owned(),
+            )?,
+            None => false,
+        };
+
+        if is_workspace && package_json_path.exists() {
+            self.workspaces.insert(
+                workspace.to_string(),
+                PackageJson::load(&package_json_path)?,
+            );
+        } else {
+            self.workspaces.remove(workspace);
+        }
+
+        Ok(true)
+    }
+
+    /// Whether a change to the directory at `unix_path` may have added or
+    /// removed workspaces
+    fn may_contain_workspaces(&self, unix_path: &str, path: &Path) -> bool {
+        // a workspace inside of it was moved or deleted
+        let workspace_removed = self.workspaces.keys().any(|workspace| {
+            !workspace.is_empty()
+                && is_within(workspace, unix_path)
+                && !self
+                    .repo_root
+                    .as_path()
+                    .join(workspace)
+                    .join("package.json")
+                    .exists()
+        });
+        if workspace_removed {
+            return true;
+        }
+
+        // a directory was moved in that may contain workspaces
+        let Some(globs) = &self.globs else {
+            return false;
+        };
+        self.repo_root.as_path().join(path).is_dir()
+            && globs
+                .inclusions
+                .iter()
+                .any(|glob| glob_may_match_within(glob, unix_path))
+    }
+}
+
+fn is_within(path: &str, dir: &str) -> bool {
+    path == dir
+        || path
+            .strip_prefix(dir)
+            .map_or(false, |rest| rest.starts_with('/'))
+}
+
+/// Whether `glob` can match `dir` or a path inside of it
+fn glob_may_match_within(glob: &str, dir: &str) -> bool {
+    let mut glob_segments = glob.trim_start_matches("./").split('/');
+    for dir_segment in dir.split('/') {
+        match glob_segments.next() {
+            Some("**") => return true,
+            Some(glob_segment) if glob_match::glob_match(glob_segment, dir_segment) => {}
+            _ => return false,
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod test {
+    use std::{collections::BTreeSet, path::Path};
+
+    use turbopath::AbsoluteSystemPathBuf;
+
+    use super::PackageGraph;
+    use crate::{package_manager::PackageManager, ui::UI};
+
+    fn write(root: &Path, path: &str, contents: &str) {
+        let path = root.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+
+    #[test]
+    fn test_build_and_update() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        write(
+            root,
+            "package.json",
+            r#"{"name": "root", "packageManager": "npm@8.19.2", "workspaces": ["packages/*"]}"#,
+        );
+        write(
+            root,
+            "packages/ui/package.json",
+            r#"{"name": "ui", "dependencies": {"react": "^18.0.0"}}"#,
+        );
+        write(
+            root,
+            "packages/web/package.json",
+            r#"{"name": "web", "dependencies": {"ui": "*"}, "devDependencies": {"web": "*"}}"#,
+        );
+        write(root, "other/package.json", r#"{"name": "other"}"#);
+
+        let repo_root = AbsoluteSystemPathBuf::new(root).unwrap();
+        let mut graph = PackageGraph::build(&repo_root, &UI::new(true)).unwrap();
+        assert_eq!(graph.package_manager(), &PackageManager::Npm);
+        assert_eq!(
+            graph.workspaces().map(|(path, _)| path).collect::<Vec<_>>(),
+            vec!["", "packages/ui", "packages/web"]
+        );
+        let dependencies = graph.dependencies();
+        assert_eq!(
+            dependencies["packages/web"],
+            BTreeSet::from(["packages/ui"])
+        );
+        assert!(dependencies["packages/ui"].is_empty());
+
+        // a new workspace is picked up
+        write(
+            root,
+            "packages/docs/package.json",
+            r#"{"name": "docs", "dependencies": {"web": "*"}}"#,
+        );
+        assert!(graph
+            .apply_change(Path::new("packages/docs/package.json"))
+            .unwrap());
+        assert_eq!(
+            graph.dependencies()["packages/docs"],
+            BTreeSet::from(["packages/web"])
+        );
+
+        // a removed workspace is dropped
+        std::fs::remove_file(root.join("packages/ui/package.json")).unwrap();
+        assert!(graph
+            .apply_change(Path::new("packages/ui/package.json"))
+            .unwrap());
+        assert!(graph.dependencies()["packages/web"].is_empty());
+
+        // package.json files outside of the workspace globs are ignored
+        assert!(graph.apply_change(Path::new("other/package.json")).unwrap());
+        assert!(!graph.workspaces().any(|(path, _)| path == "other"));
+
+        // changes to files in a workspace don't affect the graph
+        write(root, "packages/web/src/index.ts", "");
+        assert!(graph
+            .apply_change(Path::new("packages/web/src/index.ts"))
+            .unwrap());
+        assert!(graph.apply_change(Path::new("packages/web/src")).unwrap());
+
+        // moving a workspace directory only reports the directories
+        std::fs::rename(root.join("packages/web"), root.join("packages/app")).unwrap();
+        assert!(!graph.apply_change(Path::new("packages/web")).unwrap());
+        assert!(!graph.apply_change(Path::new("packages/app")).unwrap());
+        assert!(!graph.apply_change(Path::new("packages")).unwrap());
+        std::fs::create_dir_all(root.join("other/nested")).unwrap();
+        assert!(graph.apply_change(Path::new("other/nested")).unwrap());
+
+        // changing the workspace globs needs a rebuild
+        assert!(!graph.apply_change(Path::new("package.json")).unwrap());
+        assert!(!graph.apply_change(Path::new("package-lock.json")).unwrap());
+        assert!(!graph.apply_change(Path::new("")).unwrap());
+    }
+}
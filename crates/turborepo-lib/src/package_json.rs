@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use turbopath::AbsoluteSystemPathBuf;
@@ -5,7 +7,12 @@ use turbopath::AbsoluteSystemPathBuf;
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct PackageJson {
+    pub name: Option<String>,
     pub package_manager: Option<String>,
+    pub dependencies: Option<BTreeMap<String, String>>,
+    pub dev_dependencies: Option<BTreeMap<String, String>>,
+    pub optional_dependencies: Option<BTreeMap<String, String>>,
+    pub resolutions: Option<BTreeMap<String, String>>,
 }
 
 impl PackageJson {
@@ -14,4 +21,14 @@ impl PackageJson {
         let package_json: PackageJson = serde_json::from_str(&contents)?;
         Ok(package_json)
     }
+
+    // Returns the dependencies of the package in the order of precedence the
+    // package manager uses, later entries override earlier ones
+    pub fn all_dependencies(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.dev_dependencies
+            .iter()
+            .flatten()
+            .chain(self.optional_dependencies.iter().flatten())
+            .chain(self.dependencies.iter().flatten())
+    }
 }
@@ -3,6 +3,7 @@ mod pnpm;
 mod yarn;
 
 use std::{
+    collections::HashMap,
     fmt, fs,
     path::{Path, PathBuf},
 };
@@ -11,15 +12,23 @@ use anyhow::{anyhow, Result};
 use itertools::Itertools;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use turbopath::AbsoluteSystemPathBuf;
+use turbopath::{AbsoluteSystemPathBuf, RelativeSystemPathBuf};
+use turborepo_globwalk::{globwalk, WalkType};
+use turborepo_lockfiles::{
+    berry_to_npm, check_lockfile, npm_to_berry, BerryLockfile, BerryManifest, LockfileData,
+    LockfileReport, NpmLockfile, PnpmLockfile, Yarn1Lockfile,
+};
 
 use crate::{
     commands::CommandBase,
     package_json::PackageJson,
     package_manager::{npm::NpmDetector, pnpm::PnpmDetector, yarn::YarnDetector},
-    ui::UNDERLINE,
+    ui::{UI, UNDERLINE},
 };
 
+/// The lockfiles of every supported package manager
+pub const LOCKFILES: [&str; 3] = [npm::LOCKFILE, pnpm::LOCKFILE, yarn::LOCKFILE];
+
 #[derive(Debug, Deserialize)]
 struct PnpmWorkspace {
     pub packages: Vec<String>,
@@ -162,18 +171,178 @@ impl PackageManager {
         }))
     }
 
+    pub fn lockfile_name(&self) -> &'static str {
+        match self {
+            PackageManager::Npm => npm::LOCKFILE,
+            PackageManager::Pnpm | PackageManager::Pnpm6 => pnpm::LOCKFILE,
+            PackageManager::Berry | PackageManager::Yarn => yarn::LOCKFILE,
+        }
+    }
+
+    /// Returns the package.json of every workspace matched by the workspace
+    /// globs keyed by the unix path of the workspace relative to the repo
+    /// root. The root package.json is not included.
+    pub fn get_workspace_package_jsons(
+        &self,
+        repo_root: &AbsoluteSystemPathBuf,
+    ) -> Result<HashMap<String, PackageJson>> {
+        let Some(globs) = self.get_workspace_globs(repo_root.as_path())? else {
+            return Ok(HashMap::new());
+        };
+
+        // Expand the globs to package.json files the same way `GetWorkspaces`
+        // does on the Go side so only the matching directories are walked
+        let inclusions = globs
+            .inclusions
+            .iter()
+            .map(|glob| format!("{}/package.json", glob.trim_end_matches('/')))
+            .collect::<Vec<_>>();
+        let exclusions = globs
+            .exclusions
+            .iter()
+            .cloned()
+            .chain(Some("**/node_modules/**".to_string()))
+            .collect::<Vec<_>>();
+        let matches = globwalk(
+            repo_root.as_absolute_path(),
+            &inclusions,
+            &exclusions,
+            WalkType::Files,
+        )?;
+
+        let mut package_jsons = HashMap::new();
+        for package_json_path in matches {
+            let package_json_path = repo_root.resolve(&package_json_path);
+            let Some(workspace_dir) = package_json_path.parent() else {
+                continue;
+            };
+            if &workspace_dir == repo_root {
+                continue;
+            }
+            let workspace_path = repo_root.anchor(&workspace_dir)?.to_unix()?;
+            package_jsons.insert(
+                workspace_path.as_str()?.to_string(),
+                PackageJson::load(&package_json_path)?,
+            );
+        }
+
+        Ok(package_jsons)
+    }
+
+    /// Reads the lockfile at the repo root and verifies it against the
+    /// external dependencies of each workspace
+    pub fn check_lockfile(
+        &self,
+        repo_root: &AbsoluteSystemPathBuf,
+        workspaces: HashMap<String, HashMap<String, String>>,
+    ) -> Result<LockfileReport> {
+        let lockfile_path =
+            repo_root.join_relative(RelativeSystemPathBuf::new(self.lockfile_name())?);
+        let contents = fs::read(lockfile_path.as_path())
+            .map_err(|e| anyhow!("unable to read {}: {e}", self.lockfile_name()))?;
+
+        let report = match self {
+            PackageManager::Npm => {
+                let lockfile = NpmLockfile::load(&contents)?;
+                check_lockfile(&lockfile, workspaces)?
+            }
+            PackageManager::Pnpm | PackageManager::Pnpm6 => {
+                let lockfile = PnpmLockfile::load(&contents)?;
+                check_lockfile(&lockfile, workspaces)?
+            }
+            PackageManager::Yarn => {
+                let lockfile = Yarn1Lockfile::from_bytes(&contents)?;
+                check_lockfile(&lockfile, workspaces)?
+            }
+            PackageManager::Berry => {
+                // Berry needs the resolutions from the root package.json in
+                // order to resolve dependencies
+                let manifest: BerryManifest = serde_json::from_str(&fs::read_to_string(
+                    repo_root
+                        .join_relative(RelativeSystemPathBuf::new("package.json")?)
+                        .as_path(),
+                )?)?;
+                let data = LockfileData::from_bytes(&contents)?;
+                let lockfile = BerryLockfile::new(&data, Some(&manifest))?;
+                check_lockfile(&lockfile, workspaces)?
+            }
+        };
+
+        Ok(report)
+    }
+
+    /// Converts the lockfile at the repo root into the lockfile format used by
+    /// `target` and writes it to the repo root. If a lockfile for `target`
+    /// already exists it is used as a source of integrity hashes before being
+    /// overwritten. Fails if any hashes would be dropped unless `allow_lossy`
+    /// is set. Only conversions between npm and berry are supported.
+    pub fn convert_lockfile(
+        &self,
+        repo_root: &AbsoluteSystemPathBuf,
+        root_package_json: &PackageJson,
+        target: &PackageManager,
+        allow_lossy: bool,
+    ) -> Result<AbsoluteSystemPathBuf> {
+        let lockfile_path =
+            repo_root.join_relative(RelativeSystemPathBuf::new(self.lockfile_name())?);
+        let target_path =
+            repo_root.join_relative(RelativeSystemPathBuf::new(target.lockfile_name())?);
+        let contents = fs::read(lockfile_path.as_path())
+            .map_err(|e| anyhow!("unable to read {}: {e}", self.lockfile_name()))?;
+        let previous = fs::read(target_path.as_path()).ok();
+
+        let converted = match (self, target) {
+            (PackageManager::Npm, PackageManager::Berry) => {
+                // A yarn.lock left over from yarn 1 can't be used for checksums
+                let previous =
+                    previous.filter(|previous| LockfileData::from_bytes(previous).is_ok());
+                npm_to_berry(&contents, previous.as_deref(), allow_lossy)?
+            }
+            (PackageManager::Berry, PackageManager::Npm) => {
+                let resolutions = root_package_json
+                    .resolutions
+                    .clone()
+                    .map(|resolutions| resolutions.into_iter().collect());
+                berry_to_npm(&contents, resolutions, previous.as_deref(), allow_lossy)?
+            }
+            (from, to) if from == to => {
+                return Err(anyhow!(
+                    "{} is already a {to} lockfile",
+                    self.lockfile_name()
+                ));
+            }
+            (from, to) => {
+                return Err(anyhow!(
+                    "unable to convert {from} lockfile to {to}, only npm and berry lockfiles can \
+                     be converted"
+                ));
+            }
+        };
+
+        fs::write(target_path.as_path(), converted)?;
+        Ok(target_path)
+    }
+
     pub fn get_package_manager(base: &CommandBase, pkg: Option<&PackageJson>) -> Result<Self> {
+        Self::get_repo_package_manager(&base.repo_root, &base.ui, pkg)
+    }
+
+    /// Same as `get_package_manager` for when there isn't a `CommandBase`,
+    /// e.g. in the daemon
+    pub fn get_repo_package_manager(
+        repo_root: &AbsoluteSystemPathBuf,
+        ui: &UI,
+        pkg: Option<&PackageJson>,
+    ) -> Result<Self> {
         // We don't surface errors for `read_package_manager` as we can fall back to
         // `detect_package_manager`
         if let Some(package_json) = pkg {
-            if let Ok(Some(package_manager)) =
-                Self::read_package_manager(&base.repo_root, package_json)
-            {
+            if let Ok(Some(package_manager)) = Self::read_package_manager(repo_root, package_json) {
                 return Ok(package_manager);
             }
         }
 
-        Self::detect_package_manager(base)
+        Self::detect_repo_package_manager(repo_root, ui)
     }
 
     // Attempts to read the package manager from the package.json
@@ -197,15 +366,18 @@ impl PackageManager {
         Ok(manager)
     }
 
-    fn detect_package_manager(base: &CommandBase) -> Result<PackageManager> {
-        let mut detected_package_managers = PnpmDetector::new(&base.repo_root)
-            .chain(NpmDetector::new(&base.repo_root))
-            .chain(YarnDetector::new(&base.repo_root))
+    fn detect_repo_package_manager(
+        repo_root: &AbsoluteSystemPathBuf,
+        ui: &UI,
+    ) -> Result<PackageManager> {
+        let mut detected_package_managers = PnpmDetector::new(repo_root)
+            .chain(NpmDetector::new(repo_root))
+            .chain(YarnDetector::new(repo_root))
             .collect::<Result<Vec<_>>>()?;
 
         match detected_package_managers.len() {
             0 => {
-                let url = base.ui.apply(
+                let url = ui.apply(
                     UNDERLINE.apply_to("https://nodejs.org/api/packages.html#packagemanager"),
                 );
                 Err(anyhow!(
@@ -248,7 +420,7 @@ mod tests {
     use tempfile::tempdir;
 
     use super::*;
-    use crate::{get_version, package_manager::yarn::YARN_RC, ui::UI, Args};
+    use crate::{package_manager::yarn::YARN_RC, ui::UI};
 
     struct TestCase {
         name: String,
@@ -375,19 +547,14 @@ mod tests {
     fn test_detect_multiple_package_managers() -> Result<()> {
         let repo_root = tempdir()?;
         let repo_root_path = AbsoluteSystemPathBuf::new(repo_root.path())?;
-        let base = CommandBase::new(
-            Args::default(),
-            repo_root_path,
-            get_version(),
-            UI::new(true),
-        )?;
+        let ui = UI::new(true);
 
         let package_lock_json_path = repo_root.path().join(npm::LOCKFILE);
         File::create(&package_lock_json_path)?;
         let pnpm_lock_path = repo_root.path().join(pnpm::LOCKFILE);
         File::create(&pnpm_lock_path)?;
 
-        let error = PackageManager::detect_package_manager(&base).unwrap_err();
+        let error = PackageManager::detect_repo_package_manager(&repo_root_path, &ui).unwrap_err();
         assert_eq!(
             error.to_string(),
             "We detected multiple package managers in your repository: pnpm, npm. Please remove \
@@ -396,7 +563,7 @@ mod tests {
 
         fs::remove_file(&package_lock_json_path)?;
 
-        let package_manager = PackageManager::detect_package_manager(&base)?;
+        let package_manager = PackageManager::detect_repo_package_manager(&repo_root_path, &ui)?;
         assert_eq!(package_manager, PackageManager::Pnpm);
 
         Ok(())
@@ -413,6 +580,39 @@ mod tests {
         assert_eq!(globs.inclusions, vec!["apps/*", "packages/*"]);
     }
 
+    #[test]
+    fn test_get_workspace_package_jsons() -> Result<()> {
+        let repo_root = tempdir()?;
+        let repo_root_path = AbsoluteSystemPathBuf::new(repo_root.path())?;
+        fs::write(
+            repo_root.path().join("package.json"),
+            r#"{"workspaces": ["apps/*", "packages/**", "!packages/ignored"]}"#,
+        )?;
+        for workspace in [
+            "apps/web",
+            "apps/web/node_modules/dep",
+            "packages/ui",
+            "packages/nested/lib",
+            "packages/ignored",
+            "tools/script",
+        ] {
+            let dir = repo_root.path().join(workspace);
+            fs::create_dir_all(&dir)?;
+            fs::write(dir.join("package.json"), "{}")?;
+        }
+        fs::create_dir_all(repo_root.path().join("apps/docs"))?;
+
+        let package_jsons = PackageManager::Npm.get_workspace_package_jsons(&repo_root_path)?;
+        let mut workspaces = package_jsons.keys().map(|s| s.as_str()).collect::<Vec<_>>();
+        workspaces.sort();
+        assert_eq!(
+            workspaces,
+            vec!["apps/web", "packages/nested/lib", "packages/ui"]
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_globs_test() {
         struct TestCase {
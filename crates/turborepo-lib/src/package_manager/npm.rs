@@ -49,24 +49,17 @@ mod tests {
     use turbopath::AbsoluteSystemPathBuf;
 
     use super::LOCKFILE;
-    use crate::{
-        commands::CommandBase, get_version, package_manager::PackageManager, ui::UI, Args,
-    };
+    use crate::{package_manager::PackageManager, ui::UI};
 
     #[test]
     fn test_detect_npm() -> Result<()> {
         let repo_root = tempdir()?;
         let repo_root_path = AbsoluteSystemPathBuf::new(repo_root.path())?;
-        let mut base = CommandBase::new(
-            Args::default(),
-            repo_root_path,
-            get_version(),
-            UI::new(true),
-        )?;
 
         let lockfile_path = repo_root.path().join(LOCKFILE);
         File::create(&lockfile_path)?;
-        let package_manager = PackageManager::detect_package_manager(&mut base)?;
+        let package_manager =
+            PackageManager::detect_repo_package_manager(&repo_root_path, &UI::new(true))?;
         assert_eq!(package_manager, PackageManager::Npm);
 
         Ok(())
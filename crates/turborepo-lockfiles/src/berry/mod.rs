@@ -10,10 +10,11 @@ use std::{
     path::Path,
 };
 
-use de::SemverString;
-use identifiers::{Descriptor, Locator};
+pub(crate) use de::SemverString;
+pub(crate) use identifiers::{Descriptor, Locator};
 use protocol_resolver::DescriptorResolver;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use thiserror::Error;
 
 use self::resolution::{parse_resolution, Resolution};
@@ -57,39 +58,39 @@ pub struct BerryLockfile<'a> {
 #[derive(Debug, Deserialize, Serialize)]
 pub struct LockfileData {
     #[serde(rename = "__metadata")]
-    metadata: Metadata,
+    pub(crate) metadata: Metadata,
     #[serde(flatten)]
-    packages: Map<String, BerryPackage>,
+    pub(crate) packages: Map<String, BerryPackage>,
 }
 
 #[derive(Debug, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
-struct Metadata {
-    version: u64,
-    cache_key: Option<String>,
+pub(crate) struct Metadata {
+    pub(crate) version: u64,
+    pub(crate) cache_key: Option<String>,
 }
 
 #[derive(Debug, Deserialize, PartialEq, Eq, Serialize, Default, Clone)]
 #[serde(rename_all = "camelCase")]
-struct BerryPackage {
-    version: SemverString,
-    language_name: Option<String>,
-    dependencies: Option<Map<String, SemverString>>,
-    peer_dependencies: Option<Map<String, SemverString>>,
-    dependencies_meta: Option<Map<String, DependencyMeta>>,
-    peer_dependencies_meta: Option<Map<String, DependencyMeta>>,
+pub(crate) struct BerryPackage {
+    pub(crate) version: SemverString,
+    pub(crate) language_name: Option<String>,
+    pub(crate) dependencies: Option<Map<String, SemverString>>,
+    pub(crate) peer_dependencies: Option<Map<String, SemverString>>,
+    pub(crate) dependencies_meta: Option<Map<String, DependencyMeta>>,
+    pub(crate) peer_dependencies_meta: Option<Map<String, DependencyMeta>>,
     // Structured metadata we need to persist
-    bin: Option<Map<String, SemverString>>,
-    link_type: Option<String>,
-    resolution: String,
-    checksum: Option<String>,
-    conditions: Option<String>,
+    pub(crate) bin: Option<Map<String, SemverString>>,
+    pub(crate) link_type: Option<String>,
+    pub(crate) resolution: String,
+    pub(crate) checksum: Option<String>,
+    pub(crate) conditions: Option<String>,
 }
 
 #[derive(Debug, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Clone, Copy)]
-struct DependencyMeta {
-    optional: Option<bool>,
-    unplugged: Option<bool>,
+pub(crate) struct DependencyMeta {
+    pub(crate) optional: Option<bool>,
+    pub(crate) unplugged: Option<bool>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -183,6 +184,18 @@ impl<'a> BerryLockfile<'a> {
             .collect()
     }
 
+    /// All entries in the lockfile keyed by their locator
+    pub(crate) fn packages(&self) -> impl Iterator<Item = (&Locator<'a>, &'a BerryPackage)> {
+        self.locator_package
+            .iter()
+            .map(|(locator, package)| (locator, *package))
+    }
+
+    /// The entry for a locator
+    pub(crate) fn package(&self, locator: &Locator) -> Option<&'a BerryPackage> {
+        self.locator_package.get(locator).copied()
+    }
+
     // Helper function for inverting the resolution map
     fn locator_to_descriptors(&self) -> HashMap<&Locator<'a>, HashSet<&Descriptor<'a>>> {
         let mut reverse_lookup: HashMap<&Locator, HashSet<&Descriptor>> =
@@ -432,6 +445,58 @@ impl<'a> Lockfile for BerryLockfile<'a> {
         // For each dependency we need to check if there's an override
         Ok(Some(map))
     }
+
+    fn package_keys(&self) -> Vec<String> {
+        self.locator_package
+            .keys()
+            .filter(|locator| !locator.reference.starts_with("workspace:"))
+            .map(|locator| locator.to_string())
+            .collect()
+    }
+
+    fn missing_integrity(&self, key: &str) -> bool {
+        let Ok(locator) = Locator::try_from(key) else {
+            return false;
+        };
+        self.locator_package.get(&locator).map_or(false, |package| {
+            // Linked packages are never added to the cache so they don't have checksums
+            let is_link = package.link_type.as_deref() == Some("soft");
+            !is_link && package.checksum.is_none()
+        })
+    }
+
+    fn workspace_scoped_resolution(&self) -> bool {
+        // Targeted resolutions are applied relative to the workspace that is
+        // resolving the dependency
+        self.overrides
+            .keys()
+            .any(|resolution| resolution.is_targeted())
+    }
+
+    fn package_name(&self, key: &str) -> Option<String> {
+        Locator::try_from(key)
+            .ok()
+            .map(|locator| locator.ident.to_string())
+    }
+
+    fn global_settings(&self) -> Map<&'static str, Value> {
+        let mut settings = Map::new();
+        settings.insert("version", self.data.metadata.version.into());
+        if let Some(cache_key) = &self.data.metadata.cache_key {
+            settings.insert("cacheKey", cache_key.clone().into());
+        }
+        if !self.overrides.is_empty() {
+            let resolutions = self
+                .overrides
+                .iter()
+                .map(|(resolution, reference)| {
+                    (resolution.to_string(), Value::from(reference.to_string()))
+                })
+                .collect();
+            settings.insert("resolutions", Value::Object(resolutions));
+        }
+        settings
+    }
 }
 
 impl LockfileData {
@@ -0,0 +1,313 @@
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+
+use serde::Serialize;
+
+use super::{Error, Lockfile};
+
+/// A dependency that couldn't be resolved to an entry in the lockfile
+#[derive(Debug, PartialEq, Eq, Clone, PartialOrd, Ord, Serialize)]
+pub struct UnresolvedDependency {
+    // Either a workspace path or the lockfile key of the package that
+    // declared the dependency
+    pub dependent: String,
+    pub name: String,
+    pub specifier: String,
+}
+
+/// A dependency whose lookup in the lockfile failed with an error, as opposed
+/// to a dependency that has no entry
+#[derive(Debug, PartialEq, Eq, Clone, PartialOrd, Ord, Serialize)]
+pub struct LookupError {
+    pub dependent: String,
+    pub name: String,
+    pub specifier: String,
+    pub error: String,
+}
+
+/// Problems found while verifying a lockfile against the workspaces of a repo
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize)]
+pub struct LockfileReport {
+    /// Workspaces that don't appear in the lockfile at all
+    pub missing_workspaces: Vec<String>,
+    /// Workspace dependencies that don't resolve to a lockfile entry
+    pub unresolved: Vec<UnresolvedDependency>,
+    /// Dependencies of lockfile entries that don't resolve to another entry
+    pub dangling: Vec<UnresolvedDependency>,
+    /// Entries that aren't reachable from any workspace
+    pub unreachable: Vec<String>,
+    /// Entries that should have an integrity hash, but don't
+    pub missing_integrity: Vec<String>,
+    /// Dependencies that couldn't be looked up because of an error
+    pub errors: Vec<LookupError>,
+}
+
+impl LockfileReport {
+    pub fn is_ok(&self) -> bool {
+        self.missing_workspaces.is_empty()
+            && self.unresolved.is_empty()
+            && self.dangling.is_empty()
+            && self.unreachable.is_empty()
+            && self.missing_integrity.is_empty()
+            && self.errors.is_empty()
+    }
+}
+
+/// Verifies that every workspace dependency resolves through the lockfile and
+/// that every lockfile entry is consistent.
+///
+/// Unlike `transitive_closure` this doesn't stop at the first dependency that
+/// fails to resolve, all problems are collected into the report. An error is
+/// only returned if the lockfile itself can't be traversed.
+pub fn check_lockfile<L: Lockfile + ?Sized>(
+    lockfile: &L,
+    workspaces: HashMap<String, HashMap<String, String>>,
+) -> Result<LockfileReport, Error> {
+    let mut report = LockfileReport::default();
+    let mut reached = HashSet::new();
+    // Packages whose dependencies still need to be checked along with the
+    // workspace that reached them
+    let mut queue = VecDeque::with_capacity(workspaces.len());
+
+    for (workspace, unresolved_deps) in workspaces {
+        for (name, specifier) in unresolved_deps {
+            match lockfile.resolve_package(&workspace, &name, &specifier) {
+                Ok(Some(pkg)) => {
+                    if reached.insert(pkg.key.clone()) {
+                        queue.push_back((workspace.clone(), pkg.key));
+                    }
+                }
+                Err(Error::MissingWorkspace(_)) => {
+                    report.missing_workspaces.push(workspace.clone());
+                    break;
+                }
+                Ok(None) => report.unresolved.push(UnresolvedDependency {
+                    dependent: workspace.clone(),
+                    name,
+                    specifier,
+                }),
+                Err(err) => report.errors.push(LookupError {
+                    dependent: workspace.clone(),
+                    name,
+                    specifier,
+                    error: err.to_string(),
+                }),
+            }
+        }
+    }
+
+    while let Some((workspace, key)) = queue.pop_front() {
+        let Some(deps) = lockfile.all_dependencies(&key)? else {
+            continue;
+        };
+        // Dependencies that don't resolve to an entry are dropped by some
+        // lockfiles, these are found by comparing against the declared ones.
+        if let Some(declared) = lockfile.declared_dependencies(&key) {
+            let listed = deps
+                .keys()
+                .filter_map(|dep| lockfile.package_name(dep))
+                .collect::<HashSet<_>>();
+            report.dangling.extend(
+                declared
+                    .into_iter()
+                    .filter(|(name, _)| !listed.contains(name))
+                    .map(|(name, specifier)| UnresolvedDependency {
+                        dependent: key.clone(),
+                        name,
+                        specifier,
+                    }),
+            );
+        }
+        for (name, specifier) in deps {
+            match lockfile.resolve_package(&workspace, &name, &specifier) {
+                Ok(Some(pkg)) => {
+                    if reached.insert(pkg.key.clone()) {
+                        queue.push_back((workspace.clone(), pkg.key));
+                    }
+                }
+                Ok(None) => report.dangling.push(UnresolvedDependency {
+                    dependent: key.clone(),
+                    name,
+                    specifier,
+                }),
+                Err(err) => report.errors.push(LookupError {
+                    dependent: key.clone(),
+                    name,
+                    specifier,
+                    error: err.to_string(),
+                }),
+            }
+        }
+    }
+
+    let keys = lockfile.package_keys().into_iter().collect::<BTreeSet<_>>();
+    report.missing_integrity = keys
+        .iter()
+        .filter(|key| reached.contains(*key) && lockfile.missing_integrity(key))
+        .cloned()
+        .collect();
+    report.unreachable = keys
+        .into_iter()
+        .filter(|key| !reached.contains(key))
+        .collect();
+
+    report.missing_workspaces.sort();
+    report.unresolved.sort();
+    report.dangling.sort();
+    report.errors.sort();
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::Yarn1Lockfile;
+
+    const LOCKFILE: &str = r#"foo@^1.0.0:
+  version "1.0.0"
+  resolved "https://registry.yarnpkg.com/foo/-/foo-1.0.0.tgz"
+  integrity sha512-foo
+  dependencies:
+    bar "^1.0.0"
+    missing "^1.0.0"
+
+bar@^1.0.0:
+  version "1.0.0"
+  resolved "https://registry.yarnpkg.com/bar/-/bar-1.0.0.tgz"
+
+unused@^1.0.0:
+  version "1.0.0"
+  resolved "https://registry.yarnpkg.com/unused/-/unused-1.0.0.tgz"
+  integrity sha512-unused
+"#;
+
+    #[test]
+    fn test_check_lockfile() {
+        let lockfile = Yarn1Lockfile::from_bytes(LOCKFILE.as_bytes()).unwrap();
+        let workspaces = [(
+            "apps/web".to_string(),
+            [
+                ("foo".to_string(), "^1.0.0".to_string()),
+                ("baz".to_string(), "^2.0.0".to_string()),
+            ]
+            .into_iter()
+            .collect(),
+        )]
+        .into_iter()
+        .collect();
+
+        let report = check_lockfile(&lockfile, workspaces).unwrap();
+        assert!(!report.is_ok());
+        assert_eq!(
+            report,
+            LockfileReport {
+                missing_workspaces: vec![],
+                unresolved: vec![UnresolvedDependency {
+                    dependent: "apps/web".into(),
+                    name: "baz".into(),
+                    specifier: "^2.0.0".into(),
+                }],
+                dangling: vec![UnresolvedDependency {
+                    dependent: "foo@^1.0.0".into(),
+                    name: "missing".into(),
+                    specifier: "^1.0.0".into(),
+                }],
+                unreachable: vec!["unused@^1.0.0".into()],
+                missing_integrity: vec!["bar@^1.0.0".into()],
+                errors: vec![],
+            }
+        );
+    }
+
+    #[test]
+    fn test_check_missing_workspace() {
+        let lockfile =
+            crate::NpmLockfile::load(include_bytes!("../fixtures/npm-lock.json")).unwrap();
+        let workspaces = [(
+            "apps/missing".to_string(),
+            [("lodash".to_string(), "^4.17.21".to_string())]
+                .into_iter()
+                .collect(),
+        )]
+        .into_iter()
+        .collect();
+
+        let report = check_lockfile(&lockfile, workspaces).unwrap();
+        assert_eq!(report.missing_workspaces, vec!["apps/missing".to_string()]);
+        assert!(report.unresolved.is_empty());
+    }
+
+    #[test]
+    fn test_check_npm_dangling() {
+        let lockfile = crate::NpmLockfile::load(
+            br#"{
+  "lockfileVersion": 3,
+  "packages": {
+    "": {"workspaces": ["apps/web"]},
+    "apps/web": {"dependencies": {"foo": "^1.0.0"}},
+    "node_modules/web": {"resolved": "apps/web", "link": true},
+    "node_modules/foo": {
+      "version": "1.0.0",
+      "resolved": "https://registry.npmjs.org/foo/-/foo-1.0.0.tgz",
+      "integrity": "sha512-foo",
+      "dependencies": {"missing": "^2.0.0"}
+    }
+  }
+}"#,
+        )
+        .unwrap();
+        let workspaces = [(
+            "apps/web".to_string(),
+            [("foo".to_string(), "^1.0.0".to_string())].into(),
+        )]
+        .into_iter()
+        .collect();
+
+        let report = check_lockfile(&lockfile, workspaces).unwrap();
+        assert_eq!(
+            report.dangling,
+            vec![UnresolvedDependency {
+                dependent: "node_modules/foo".into(),
+                name: "missing".into(),
+                specifier: "^2.0.0".into(),
+            }]
+        );
+        assert!(report.errors.is_empty());
+    }
+
+    #[test]
+    fn test_check_lookup_error() {
+        let lockfile = crate::PnpmLockfile::load(
+            br#"lockfileVersion: 5.4
+
+importers:
+  apps/web:
+    specifiers:
+      foo: ^1.0.0
+    dependencies:
+      foo: 1.0.0
+
+packages:
+  /foo/1.0.0:
+    resolution: {integrity: sha512-foo}
+    dev: false
+"#,
+        )
+        .unwrap();
+        // pnpm errors if a workspace dependency isn't in its importer
+        let workspaces = [(
+            "apps/web".to_string(),
+            [("bar".to_string(), "^1.0.0".to_string())].into(),
+        )]
+        .into_iter()
+        .collect();
+
+        let report = check_lockfile(&lockfile, workspaces).unwrap();
+        assert!(report.unresolved.is_empty());
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(report.errors[0].name, "bar");
+        assert!(!report.is_ok());
+    }
+}
@@ -0,0 +1,1094 @@
+//! Conversion between npm (`package-lock.json` v2/v3) and Berry (`yarn.lock`)
+//! lockfiles.
+//!
+//! Only packages that come from the npm registry and workspaces can be
+//! converted, any other source (git, file, user patches) results in an error.
+//!
+//! npm and Berry record different integrity hashes: npm hashes the tarball
+//! downloaded from the registry while Berry hashes the zip it stores in its
+//! cache. Neither can be derived from the other, and Berry doesn't record the
+//! tarball URL npm needs for `resolved`. This data is only carried over from a
+//! previous lockfile of the target format. If a previous lockfile doesn't
+//! cover every package the conversion fails with `Error::Lossy` unless a lossy
+//! conversion is allowed, in which case the fields are left out and the
+//! package manager fills them in on the next install.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::{
+    berry::{
+        self, BerryLockfile, BerryManifest, BerryPackage, DependencyMeta, Locator, LockfileData,
+        Metadata, SemverString,
+    },
+    npm::{NpmLockfile, NpmPackage},
+    Lockfile,
+};
+
+type Map<K, V> = BTreeMap<K, V>;
+
+const BERRY_LOCKFILE_VERSION: u64 = 6;
+const NPM_LOCKFILE_VERSION: i32 = 3;
+const WORKSPACE_PROTOCOL: &str = "workspace:";
+const WORKSPACE_VERSION: &str = "0.0.0-use.local";
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error(transparent)]
+    Lockfile(#[from] crate::Error),
+    #[error(transparent)]
+    Berry(#[from] berry::Error),
+    #[error("unable to serialize npm lockfile: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("unable to convert {0}: only registry packages and workspaces are supported")]
+    UnsupportedPackage(String),
+    #[error("unable to convert lockfile with patch {0}: npm doesn't support patches")]
+    Patch(String),
+    #[error("unable to determine the name of the workspace at '{0}'")]
+    MissingWorkspaceName(String),
+    #[error("unable to find a location for {0} in node_modules")]
+    Placement(String),
+    #[error(
+        "converting would drop the {field} of {}, provide an existing lockfile in the target \
+         format or allow a lossy conversion",
+        summarize(.packages)
+    )]
+    Lossy {
+        field: &'static str,
+        packages: Vec<String>,
+    },
+}
+
+fn summarize(packages: &[String]) -> String {
+    const SHOWN: usize = 3;
+    let shown = packages
+        .iter()
+        .take(SHOWN)
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .join(", ");
+    match packages.len().checked_sub(SHOWN) {
+        Some(rest) if rest > 0 => format!("{shown} and {rest} more"),
+        _ => shown,
+    }
+}
+
+/// Converts the contents of a `package-lock.json` into a Berry `yarn.lock`.
+///
+/// Checksums are taken from `previous`, an existing `yarn.lock`, for packages
+/// present in both lockfiles. Any package without a checksum results in
+/// `Error::Lossy` unless `allow_lossy` is set.
+pub fn npm_to_berry(
+    contents: &[u8],
+    previous: Option<&[u8]>,
+    allow_lossy: bool,
+) -> Result<Vec<u8>, Error> {
+    let lockfile = NpmLockfile::load(contents)?;
+    let previous = previous.map(LockfileData::from_bytes).transpose()?;
+    let data = berry_data_from_npm(&lockfile, previous.as_ref(), allow_lossy)?;
+    Ok(data.to_string().into_bytes())
+}
+
+/// Converts the contents of a Berry `yarn.lock` into a `package-lock.json`.
+///
+/// `resolutions` should be the `resolutions` field of the root package.json.
+/// Integrity hashes and resolved URLs are taken from `previous`, an existing
+/// `package-lock.json`, for packages present in both lockfiles. Any package
+/// without them results in `Error::Lossy` unless `allow_lossy` is set.
+pub fn berry_to_npm(
+    contents: &[u8],
+    resolutions: Option<HashMap<String, String>>,
+    previous: Option<&[u8]>,
+    allow_lossy: bool,
+) -> Result<Vec<u8>, Error> {
+    let manifest = resolutions.map(BerryManifest::with_resolutions);
+    let data = LockfileData::from_bytes(contents)?;
+    let lockfile = BerryLockfile::new(&data, manifest.as_ref())?;
+    let previous = previous.map(NpmLockfile::load).transpose()?;
+    let npm_lockfile = npm_lockfile_from_berry(&lockfile, previous.as_ref(), allow_lossy)?;
+    Ok(serde_json::to_vec_pretty(&npm_lockfile)?)
+}
+
+fn berry_data_from_npm(
+    lockfile: &NpmLockfile,
+    previous: Option<&LockfileData>,
+    allow_lossy: bool,
+) -> Result<LockfileData, Error> {
+    let locators = npm_locators(lockfile)?;
+    let checksums = previous
+        .iter()
+        .flat_map(|data| data.packages.values())
+        .filter_map(|pkg| Some((pkg.resolution.as_str(), pkg.checksum.as_deref()?)))
+        .collect::<HashMap<_, _>>();
+
+    let mut packages: Map<&str, BerryPackage> = Map::new();
+    let mut descriptors: Map<&str, BTreeSet<String>> = Map::new();
+    // Berry requires each descriptor to resolve to exactly one locator. If
+    // copies of a package in different node_modules directories resolve the
+    // same range differently, the first resolution is used for all of them.
+    let mut bound_descriptors = HashMap::new();
+
+    for (key, pkg) in &lockfile.packages {
+        // Links share the locator of the workspace they point to
+        let Some(locator) = locators.get(key.as_str()).filter(|_| !pkg.is_link()) else {
+            continue;
+        };
+        if packages.contains_key(locator.as_str()) {
+            continue;
+        }
+
+        let is_workspace = is_npm_workspace(key);
+        let optional_deps = pkg.optional_dependencies.iter();
+        let deps = match is_workspace {
+            // Berry doesn't distinguish between dev and prod dependencies
+            true => pkg
+                .dependencies
+                .iter()
+                .chain(pkg.dev_dependencies.iter())
+                .map(|dep| (dep, false))
+                .chain(optional_deps.map(|dep| (dep, true)))
+                .collect::<Vec<_>>(),
+            false => pkg
+                .dependencies
+                .iter()
+                .map(|dep| (dep, false))
+                .chain(optional_deps.map(|dep| (dep, true)))
+                .collect(),
+        };
+
+        let mut dependencies = Map::new();
+        let mut dependencies_meta = Map::new();
+        for ((name, range), optional) in deps {
+            let dep_locator = NpmLockfile::possible_npm_deps(key, name)
+                .into_iter()
+                .find_map(|possible_key| locators.get(possible_key.as_str()));
+            let Some(dep_locator) = dep_locator else {
+                // npm omits optional dependencies that can't be installed
+                if optional {
+                    continue;
+                }
+                return Err(crate::Error::MissingPackage(format!("{name}@{range}")).into());
+            };
+
+            let descriptor = match has_protocol(range) || is_berry_workspace(dep_locator) {
+                true => format!("{name}@{range}"),
+                false => format!("{name}@npm:{range}"),
+            };
+            let bound_locator = bound_descriptors
+                .entry(descriptor.clone())
+                .or_insert(dep_locator.as_str());
+            descriptors
+                .entry(*bound_locator)
+                .or_default()
+                .insert(descriptor);
+
+            dependencies.insert(name.clone(), SemverString(range.clone()));
+            if optional {
+                dependencies_meta.insert(
+                    name.clone(),
+                    DependencyMeta {
+                        optional: Some(true),
+                        unplugged: None,
+                    },
+                );
+            }
+        }
+
+        let peer_dependencies = pkg
+            .peer_dependencies
+            .iter()
+            .map(|(name, range)| (name.clone(), SemverString(range.clone())))
+            .collect::<Map<_, _>>();
+        let peer_dependencies_meta = pkg
+            .other
+            .get("peerDependenciesMeta")
+            .and_then(Value::as_object)
+            .map(|meta| {
+                meta.iter()
+                    .map(|(name, meta)| {
+                        let optional = meta.get("optional").and_then(Value::as_bool);
+                        (
+                            name.clone(),
+                            DependencyMeta {
+                                optional,
+                                unplugged: None,
+                            },
+                        )
+                    })
+                    .collect::<Map<_, _>>()
+            })
+            .filter(|meta| !meta.is_empty());
+        let bin = pkg
+            .other
+            .get("bin")
+            .and_then(Value::as_object)
+            .map(|bin| {
+                bin.iter()
+                    .filter_map(|(name, path)| {
+                        Some((name.clone(), SemverString(path.as_str()?.to_string())))
+                    })
+                    .collect::<Map<_, _>>()
+            })
+            .filter(|bin| !bin.is_empty());
+
+        let (version, language_name, link_type) = match is_workspace {
+            true => (WORKSPACE_VERSION.to_string(), "unknown", "soft"),
+            false => (
+                pkg.version
+                    .clone()
+                    .ok_or_else(|| crate::Error::MissingVersion(key.clone()))?,
+                "node",
+                "hard",
+            ),
+        };
+
+        packages.insert(
+            locator.as_str(),
+            BerryPackage {
+                version: SemverString(version),
+                language_name: Some(language_name.to_string()),
+                dependencies: Some(dependencies).filter(|deps| !deps.is_empty()),
+                peer_dependencies: Some(peer_dependencies).filter(|deps| !deps.is_empty()),
+                dependencies_meta: Some(dependencies_meta).filter(|meta| !meta.is_empty()),
+                peer_dependencies_meta,
+                bin,
+                link_type: Some(link_type.to_string()),
+                resolution: locator.clone(),
+                checksum: checksums
+                    .get(locator.as_str())
+                    .map(|checksum| checksum.to_string()),
+                conditions: None,
+            },
+        );
+    }
+
+    let missing_checksums = packages
+        .iter()
+        .filter(|(locator, package)| !is_berry_workspace(locator) && package.checksum.is_none())
+        .map(|(locator, _)| locator.to_string())
+        .collect::<Vec<_>>();
+    if !allow_lossy && !missing_checksums.is_empty() {
+        return Err(Error::Lossy {
+            field: "checksums",
+            packages: missing_checksums,
+        });
+    }
+
+    let packages = packages
+        .into_iter()
+        .map(|(locator, package)| {
+            let mut keys = descriptors.remove(locator).unwrap_or_default();
+            // Workspaces are always referenced by their own locator and any
+            // package nothing depends on still needs a key
+            if is_berry_workspace(locator) || keys.is_empty() {
+                keys.insert(locator.to_string());
+            }
+            let key = keys.into_iter().collect::<Vec<_>>().join(", ");
+            (key, package)
+        })
+        .collect::<Map<_, _>>();
+
+    // Checksums are only valid for the cache key they were computed with
+    let cache_key = previous
+        .filter(|_| packages.values().any(|pkg| pkg.checksum.is_some()))
+        .and_then(|data| data.metadata.cache_key.clone());
+
+    Ok(LockfileData {
+        metadata: Metadata {
+            version: BERRY_LOCKFILE_VERSION,
+            cache_key,
+        },
+        packages,
+    })
+}
+
+// Computes the Berry locator for every entry in an npm lockfile
+fn npm_locators(lockfile: &NpmLockfile) -> Result<HashMap<&str, String>, Error> {
+    // Workspace entries don't always include a name, but the name can be
+    // found from the link npm creates for the workspace in node_modules
+    let linked_names = lockfile
+        .packages
+        .iter()
+        .filter(|(_, pkg)| pkg.is_link())
+        .filter_map(|(key, pkg)| Some((pkg.resolved.as_deref()?, npm_package_name(key))))
+        .collect::<HashMap<_, _>>();
+
+    let mut locators = HashMap::with_capacity(lockfile.packages.len());
+    for (key, pkg) in &lockfile.packages {
+        if pkg.is_link() {
+            continue;
+        }
+        let name = pkg.other.get("name").and_then(Value::as_str);
+        let locator = if is_npm_workspace(key) {
+            let name = name
+                .or_else(|| linked_names.get(key.as_str()).copied())
+                .or_else(|| match key.is_empty() {
+                    true => lockfile.other.get("name").and_then(Value::as_str),
+                    false => None,
+                })
+                .ok_or_else(|| Error::MissingWorkspaceName(key.clone()))?;
+            let path = match key.as_str() {
+                "" => ".",
+                path => path,
+            };
+            format!("{name}@{WORKSPACE_PROTOCOL}{path}")
+        } else {
+            let from_registry = pkg
+                .resolved
+                .as_deref()
+                // Bundled dependencies don't have a resolved field
+                .map_or(true, |resolved| resolved.starts_with("http"));
+            if !from_registry {
+                return Err(Error::UnsupportedPackage(key.clone()));
+            }
+            let version = pkg
+                .version
+                .as_deref()
+                .ok_or_else(|| crate::Error::MissingVersion(key.clone()))?;
+            let install_name = npm_package_name(key);
+            match name {
+                // Aliased packages are installed under a different name
+                Some(name) if name != install_name => {
+                    format!("{install_name}@npm:{name}@{version}")
+                }
+                _ => format!("{install_name}@npm:{version}"),
+            }
+        };
+        locators.insert(key.as_str(), locator);
+    }
+
+    for (key, pkg) in &lockfile.packages {
+        if !pkg.is_link() {
+            continue;
+        }
+        let target = pkg
+            .resolved
+            .as_deref()
+            .and_then(|path| locators.get(path))
+            .cloned()
+            .ok_or_else(|| Error::UnsupportedPackage(key.clone()))?;
+        locators.insert(key.as_str(), target);
+    }
+
+    Ok(locators)
+}
+
+fn npm_lockfile_from_berry(
+    lockfile: &BerryLockfile,
+    previous: Option<&NpmLockfile>,
+    allow_lossy: bool,
+) -> Result<NpmLockfile, Error> {
+    if let Some(patch) = lockfile.patches().first() {
+        return Err(Error::Patch(patch.display().to_string()));
+    }
+
+    // Registry metadata from a previous npm lockfile keyed by name and version
+    let registry_info = previous
+        .iter()
+        .flat_map(|lockfile| lockfile.packages.iter())
+        .filter(|(key, pkg)| !is_npm_workspace(key) && !pkg.is_link())
+        .filter_map(|(key, pkg)| {
+            let name = match pkg.other.get("name").and_then(Value::as_str) {
+                Some(name) => name,
+                None => npm_package_name(key),
+            };
+            Some(((name, pkg.version.as_deref()?), pkg))
+        })
+        .collect::<HashMap<_, _>>();
+
+    let mut workspaces = lockfile
+        .packages()
+        .filter_map(|(locator, package)| {
+            let path = locator.reference.strip_prefix(WORKSPACE_PROTOCOL)?;
+            Some((path, locator, package))
+        })
+        .collect::<Vec<_>>();
+    // The root workspace must be processed first so its dependencies get
+    // hoisted before any other workspace's
+    workspaces.sort_by_key(|(path, ..)| (*path != ".", *path));
+
+    let mut packages = Map::new();
+    let mut tree = NodeModules::default();
+    let mut root_name = None;
+    // Packages that aren't in the previous lockfile so they have no integrity
+    // or resolved URL
+    let mut missing_registry_info = BTreeSet::new();
+    let mut queue = VecDeque::with_capacity(workspaces.len());
+    for (path, locator, package) in &workspaces {
+        let name = locator.ident.to_string();
+        let (key, entry) = if *path == "." {
+            root_name = Some(name.clone());
+            let mut entry = npm_workspace_package(package);
+            entry.other.insert("name".into(), name.into());
+            let workspace_paths = workspaces
+                .iter()
+                .filter(|(path, ..)| *path != ".")
+                .map(|(path, ..)| Value::from(*path))
+                .collect::<Vec<_>>();
+            entry
+                .other
+                .insert("workspaces".into(), workspace_paths.into());
+            (String::new(), entry)
+        } else {
+            let mut entry = npm_workspace_package(package);
+            entry.other.insert("name".into(), name.clone().into());
+            let link = format!("node_modules/{name}");
+            tree.occupy(&link, locator.to_string());
+            packages.insert(link, npm_link(path));
+            (path.to_string(), entry)
+        };
+        packages.insert(key.clone(), entry);
+        queue.push_back((key, *path, locator.to_string()));
+    }
+
+    while let Some((key, workspace, locator)) = queue.pop_front() {
+        let Some(deps) = lockfile.all_dependencies(&locator)? else {
+            continue;
+        };
+        for (name, range) in deps.into_iter().collect::<Map<_, _>>() {
+            let Some(dep) = lockfile.resolve_package(workspace, &name, &range)? else {
+                continue;
+            };
+            let dep_locator = unpatched_locator(&dep.key)?;
+            let Some(slot) = tree.place(&key, &name, &dep_locator)? else {
+                continue;
+            };
+            let entry = match dep_locator.strip_prefix(&format!("{name}@{WORKSPACE_PROTOCOL}")) {
+                Some(path) => npm_link(path),
+                None => {
+                    let (entry, found) =
+                        npm_package(lockfile, &name, &dep_locator, &registry_info)?;
+                    if !found {
+                        missing_registry_info.insert(dep_locator.clone());
+                    }
+                    entry
+                }
+            };
+            packages.insert(slot.clone(), entry);
+            queue.push_back((slot, workspace, dep_locator));
+        }
+    }
+
+    if !allow_lossy && !missing_registry_info.is_empty() {
+        return Err(Error::Lossy {
+            field: "integrity hashes and resolved URLs",
+            packages: missing_registry_info.into_iter().collect(),
+        });
+    }
+
+    let mut other = Map::new();
+    if let Some(name) = root_name {
+        other.insert("name".into(), name.into());
+    }
+    other.insert("requires".into(), true.into());
+
+    Ok(NpmLockfile {
+        lockfile_version: NPM_LOCKFILE_VERSION,
+        packages,
+        dependencies: Map::new(),
+        other,
+    })
+}
+
+fn npm_workspace_package(package: &BerryPackage) -> NpmPackage {
+    let mut entry = NpmPackage::default();
+    let (optional, required): (Vec<_>, Vec<_>) = package
+        .dependencies
+        .iter()
+        .flatten()
+        .map(|(name, range)| (name.clone(), range.0.clone()))
+        .partition(|(name, _)| is_optional(package, name));
+    entry.dependencies = required.into_iter().collect();
+    entry.optional_dependencies = optional.into_iter().collect();
+    entry.peer_dependencies = package
+        .peer_dependencies
+        .iter()
+        .flatten()
+        .map(|(name, range)| (name.clone(), range.0.clone()))
+        .collect();
+    if let Some(meta) = &package.peer_dependencies_meta {
+        let meta = meta
+            .iter()
+            .filter(|(_, meta)| meta.optional == Some(true))
+            .map(|(name, _)| (name.clone(), serde_json::json!({ "optional": true })))
+            .collect::<serde_json::Map<_, _>>();
+        if !meta.is_empty() {
+            entry
+                .other
+                .insert("peerDependenciesMeta".into(), meta.into());
+        }
+    }
+    entry
+}
+
+// Builds the npm entry for a registry package, also returns if the package
+// was found in the registry info of the previous lockfile
+fn npm_package(
+    lockfile: &BerryLockfile,
+    name: &str,
+    locator: &str,
+    registry_info: &HashMap<(&str, &str), &NpmPackage>,
+) -> Result<(NpmPackage, bool), Error> {
+    let parsed = Locator::try_from(locator).map_err(berry::Error::from)?;
+    let package = lockfile
+        .package(&parsed)
+        .ok_or_else(|| crate::Error::MissingPackage(locator.to_string()))?;
+    let reference = parsed
+        .reference
+        .strip_prefix("npm:")
+        .ok_or_else(|| Error::UnsupportedPackage(locator.to_string()))?;
+    // Aliased packages reference the real package name e.g. npm:foo@1.0.0
+    let (real_name, version) = match reference.rsplit_once('@') {
+        Some((real_name, version)) if !real_name.is_empty() => (real_name, version),
+        _ => (name, reference),
+    };
+
+    let mut entry = npm_workspace_package(package);
+    entry.version = Some(version.to_string());
+    if real_name != name {
+        entry.other.insert("name".into(), real_name.into());
+    }
+    let previous = registry_info.get(&(real_name, version));
+    if let Some(previous) = previous {
+        entry.resolved = previous.resolved.clone();
+        if let Some(integrity) = previous.other.get("integrity") {
+            entry.other.insert("integrity".into(), integrity.clone());
+        }
+    }
+    if let Some(bin) = &package.bin {
+        let bin = bin
+            .iter()
+            .map(|(name, path)| (name.clone(), Value::from(path.0.clone())))
+            .collect::<serde_json::Map<_, _>>();
+        entry.other.insert("bin".into(), bin.into());
+    }
+    Ok((entry, previous.is_some()))
+}
+
+fn npm_link(path: &str) -> NpmPackage {
+    let mut link = NpmPackage {
+        resolved: Some(path.to_string()),
+        ..Default::default()
+    };
+    link.other.insert("link".into(), true.into());
+    link
+}
+
+fn is_optional(package: &BerryPackage, name: &str) -> bool {
+    package
+        .dependencies_meta
+        .as_ref()
+        .and_then(|meta| meta.get(name))
+        .map_or(false, |meta| meta.optional == Some(true))
+}
+
+// Packages with builtin patches e.g. typescript are installed unpatched by npm
+fn unpatched_locator(key: &str) -> Result<String, Error> {
+    let locator = Locator::try_from(key).map_err(berry::Error::from)?;
+    if locator.patch_file().is_none() {
+        return Ok(key.to_string());
+    }
+    locator
+        .patched_locator()
+        .map(|original| original.to_string())
+        .ok_or_else(|| Error::UnsupportedPackage(key.to_string()))
+}
+
+fn is_npm_workspace(key: &str) -> bool {
+    !key.contains("node_modules/")
+}
+
+fn is_berry_workspace(locator: &str) -> bool {
+    locator.contains(&format!("@{WORKSPACE_PROTOCOL}"))
+}
+
+fn npm_package_name(key: &str) -> &str {
+    key.rsplit_once("node_modules/")
+        .map_or(key, |(_, name)| name)
+}
+
+fn has_protocol(range: &str) -> bool {
+    range.split_once(':').map_or(false, |(protocol, _)| {
+        !protocol.is_empty()
+            && protocol
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '+')
+    })
+}
+
+/// Tracks the layout of `node_modules` directories while hoisting packages
+/// the way npm does. Each package is placed in the highest directory where it
+/// doesn't conflict with a different version of the package that is already
+/// there or change what an already placed package resolves to.
+#[derive(Debug, Default)]
+struct NodeModules {
+    // npm lockfile key to the locator installed there
+    slots: HashMap<String, String>,
+    // Package name to the packages that depend on it along with the locator
+    // they need it to resolve to
+    dependents: HashMap<String, Vec<(String, String)>>,
+}
+
+impl NodeModules {
+    fn occupy(&mut self, slot: &str, locator: String) {
+        self.slots.insert(slot.to_string(), locator);
+    }
+
+    /// Places the package `name` at `locator` so it can be resolved from
+    /// `dependent`. Returns the key of the new entry if the package needed to
+    /// be installed, or `None` if an existing entry is reused.
+    fn place(
+        &mut self,
+        dependent: &str,
+        name: &str,
+        locator: &str,
+    ) -> Result<Option<String>, Error> {
+        let directories = Self::directories(dependent);
+
+        // Anything above the closest existing install of the package is
+        // shadowed by it and can't be used
+        let mut highest = 0;
+        for (i, directory) in directories.iter().enumerate().rev() {
+            if let Some(existing) = self.slots.get(&Self::slot(directory, name)) {
+                if existing == locator {
+                    self.add_dependent(dependent, name, locator);
+                    return Ok(None);
+                }
+                highest = i + 1;
+                break;
+            }
+        }
+
+        for directory in &directories[highest..] {
+            if self.would_shadow(directory, name, locator) {
+                continue;
+            }
+            let slot = Self::slot(directory, name);
+            self.slots.insert(slot.clone(), locator.to_string());
+            self.add_dependent(dependent, name, locator);
+            return Ok(Some(slot));
+        }
+
+        Err(Error::Placement(locator.to_string()))
+    }
+
+    fn add_dependent(&mut self, dependent: &str, name: &str, locator: &str) {
+        self.dependents
+            .entry(name.to_string())
+            .or_default()
+            .push((dependent.to_string(), locator.to_string()));
+    }
+
+    // Checks if installing a package in a directory would change what an
+    // existing dependent below that directory resolves to
+    fn would_shadow(&self, directory: &str, name: &str, locator: &str) -> bool {
+        let Some(dependents) = self.dependents.get(name) else {
+            return false;
+        };
+        dependents
+            .iter()
+            .filter(|(dependent, required)| {
+                required != locator && Self::is_within(dependent, directory)
+            })
+            .any(|(dependent, _)| {
+                // The package isn't installed in `directory` yet so any
+                // dependent within it resolves from above it
+                Self::directories(dependent)
+                    .iter()
+                    .rev()
+                    .find(|dir| self.slots.contains_key(&Self::slot(dir, name)))
+                    .map_or(false, |resolved_from| {
+                        Self::is_within(directory, resolved_from)
+                    })
+            })
+    }
+
+    // All directories a package at `key` searches for dependencies in,
+    // ordered from the repository root to the package itself
+    fn directories(key: &str) -> Vec<&str> {
+        let mut directories = vec![key];
+        let mut curr = key;
+        while !curr.is_empty() {
+            curr = match curr.rsplit_once("/node_modules/") {
+                Some((parent, _)) => parent,
+                // Top level packages and workspaces resolve from the root
+                None => "",
+            };
+            directories.push(curr);
+        }
+        directories.reverse();
+        directories
+    }
+
+    fn slot(directory: &str, name: &str) -> String {
+        match directory {
+            "" => format!("node_modules/{name}"),
+            directory => format!("{directory}/node_modules/{name}"),
+        }
+    }
+
+    fn is_within(key: &str, directory: &str) -> bool {
+        directory.is_empty()
+            || key == directory
+            || key
+                .strip_prefix(directory)
+                .map_or(false, |rest| rest.starts_with('/'))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::transitive_closure;
+
+    // External dependencies of each workspace in the npm fixture
+    fn npm_workspaces(lockfile: &NpmLockfile) -> HashMap<String, HashMap<String, String>> {
+        let workspace_names = lockfile
+            .packages
+            .iter()
+            .filter(|(_, pkg)| pkg.is_link())
+            .map(|(key, _)| npm_package_name(key))
+            .collect::<HashSet<_>>();
+        ["", "apps/docs", "apps/web"]
+            .into_iter()
+            .map(|workspace| {
+                let pkg = &lockfile.packages[workspace];
+                let deps = pkg
+                    .dependencies
+                    .iter()
+                    .chain(pkg.dev_dependencies.iter())
+                    .filter(|(name, _)| !workspace_names.contains(name.as_str()))
+                    .map(|(name, range)| (name.clone(), range.clone()))
+                    .collect();
+                (workspace.to_string(), deps)
+            })
+            .collect()
+    }
+
+    // Resolved packages of a workspace as name@version pairs so they can be
+    // compared across lockfile formats
+    fn resolved_versions<L: Lockfile>(
+        lockfile: &L,
+        workspace: &str,
+        deps: HashMap<String, String>,
+    ) -> BTreeSet<String> {
+        transitive_closure(lockfile, workspace, deps)
+            .unwrap()
+            .into_iter()
+            .map(|pkg| {
+                let name = match is_npm_workspace(&pkg.key) {
+                    true => Locator::try_from(pkg.key.as_str())
+                        .unwrap()
+                        .ident
+                        .to_string(),
+                    false => npm_package_name(&pkg.key).to_string(),
+                };
+                format!("{name}@{}", pkg.version)
+            })
+            .collect()
+    }
+
+    fn berry_workspace(workspace: &str) -> &str {
+        match workspace {
+            "" => ".",
+            workspace => workspace,
+        }
+    }
+
+    #[test]
+    fn test_npm_to_berry() {
+        let contents = include_bytes!("../fixtures/npm-lock.json");
+        let npm = NpmLockfile::load(contents).unwrap();
+        let berry_contents = npm_to_berry(contents, None, true).unwrap();
+        let data = LockfileData::from_bytes(&berry_contents).unwrap();
+        let berry = BerryLockfile::new(&data, None).unwrap();
+
+        assert!(data.packages.contains_key("ui@*, ui@workspace:packages/ui"));
+        assert!(data.packages.contains_key("npm-prune@workspace:."));
+        assert_eq!(
+            data.packages["lodash@npm:^3.0.0"].resolution,
+            "lodash@npm:3.10.1"
+        );
+        assert_eq!(data.metadata.cache_key, None);
+
+        for (workspace, deps) in npm_workspaces(&npm) {
+            assert_eq!(
+                resolved_versions(&berry, berry_workspace(&workspace), deps.clone()),
+                resolved_versions(&npm, &workspace, deps),
+                "{workspace}"
+            );
+        }
+        // Serialization is stable
+        assert_eq!(data.to_string().as_bytes(), berry_contents.as_slice());
+    }
+
+    #[test]
+    fn test_berry_to_npm() {
+        let contents = include_bytes!("../fixtures/minimal-berry.lock");
+        let npm_contents = berry_to_npm(contents, None, None, true).unwrap();
+        let npm = NpmLockfile::load(&npm_contents).unwrap();
+
+        let link = &npm.packages["node_modules/c"];
+        assert!(link.is_link());
+        assert_eq!(link.resolved.as_deref(), Some("packages/c"));
+        let lodash = &npm.packages["node_modules/lodash"];
+        assert_eq!(lodash.version.as_deref(), Some("4.17.21"));
+        // Without a previous npm lockfile there's no source for these
+        assert_eq!(lodash.resolved, None);
+        assert!(!lodash.other.contains_key("integrity"));
+        assert_eq!(
+            npm.packages["packages/a"].dependencies,
+            [("c", "*"), ("lodash", "^4.17.0")]
+                .into_iter()
+                .map(|(name, range)| (name.to_string(), range.to_string()))
+                .collect()
+        );
+        assert_eq!(
+            npm.packages[""].other["workspaces"],
+            serde_json::json!(["packages/a", "packages/b", "packages/c"])
+        );
+    }
+
+    #[test]
+    fn test_round_trip_preserves_integrity() {
+        let contents = include_bytes!("../fixtures/npm-lock.json");
+        let original = NpmLockfile::load(contents).unwrap();
+        let berry_contents = npm_to_berry(contents, None, true).unwrap();
+        // Every package is in the previous lockfile so nothing is lost
+        let npm_contents = berry_to_npm(&berry_contents, None, Some(contents), false).unwrap();
+        let converted = NpmLockfile::load(&npm_contents).unwrap();
+
+        for (workspace, deps) in npm_workspaces(&original) {
+            assert_eq!(
+                resolved_versions(&converted, &workspace, deps.clone()),
+                resolved_versions(&original, &workspace, deps),
+                "{workspace}"
+            );
+        }
+        for (key, pkg) in &converted.packages {
+            if is_npm_workspace(key) || pkg.is_link() {
+                continue;
+            }
+            assert!(pkg.other.contains_key("integrity"), "{key}");
+        }
+        for workspace in ["ui", "tsconfig", "eslint-config-custom"] {
+            assert!(converted.packages[&format!("node_modules/{workspace}")].is_link());
+        }
+    }
+
+    #[test]
+    fn test_berry_patches_unsupported() {
+        let contents = include_bytes!("../fixtures/berry.lock");
+        assert!(matches!(
+            berry_to_npm(contents, None, None, true),
+            Err(Error::Patch(_))
+        ));
+    }
+
+    #[test]
+    fn test_lossy_conversion_errors() {
+        let npm_contents = include_bytes!("../fixtures/npm-lock.json");
+        let err = npm_to_berry(npm_contents, None, false).unwrap_err();
+        assert!(
+            matches!(&err, Error::Lossy { field: "checksums", packages } if !packages.is_empty())
+        );
+        assert!(err.to_string().contains("more"), "{err}");
+
+        let berry_contents = include_bytes!("../fixtures/minimal-berry.lock");
+        let err = berry_to_npm(berry_contents, None, None, false).unwrap_err();
+        let Error::Lossy { packages, .. } = err else {
+            panic!("expected lossy conversion error, found {err}");
+        };
+        assert!(packages.contains(&"lodash@npm:4.17.21".to_string()));
+    }
+
+    #[test]
+    fn test_round_trip_preserves_checksums() {
+        let contents = include_bytes!("../fixtures/minimal-berry.lock");
+        let original = LockfileData::from_bytes(contents).unwrap();
+        let npm_contents = berry_to_npm(contents, None, None, true).unwrap();
+        let berry_contents = npm_to_berry(&npm_contents, Some(contents), false).unwrap();
+        let converted = LockfileData::from_bytes(&berry_contents).unwrap();
+
+        assert_eq!(converted.metadata.cache_key, original.metadata.cache_key);
+        for package in original.packages.values() {
+            if package.checksum.is_none() {
+                continue;
+            }
+            let converted = converted
+                .packages
+                .values()
+                .find(|converted| converted.resolution == package.resolution)
+                .unwrap();
+            assert_eq!(
+                converted.checksum, package.checksum,
+                "{}",
+                package.resolution
+            );
+        }
+    }
+
+    const CONFLICTING_BERRY: &str = r#"__metadata:
+  version: 6
+  cacheKey: 8
+
+"a@workspace:packages/a":
+  version: 0.0.0-use.local
+  resolution: "a@workspace:packages/a"
+  dependencies:
+    foo: ^1.0.0
+  languageName: unknown
+  linkType: soft
+
+"b@workspace:packages/b":
+  version: 0.0.0-use.local
+  resolution: "b@workspace:packages/b"
+  dependencies:
+    foo: ^2.0.0
+  languageName: unknown
+  linkType: soft
+
+"bar@npm:^1.0.0":
+  version: 1.0.0
+  resolution: "bar@npm:1.0.0"
+  checksum: bar1
+  languageName: node
+  linkType: hard
+
+"bar@npm:^2.0.0":
+  version: 2.0.0
+  resolution: "bar@npm:2.0.0"
+  checksum: bar2
+  languageName: node
+  linkType: hard
+
+"foo@npm:^1.0.0":
+  version: 1.0.0
+  resolution: "foo@npm:1.0.0"
+  dependencies:
+    bar: ^1.0.0
+  checksum: foo1
+  languageName: node
+  linkType: hard
+
+"foo@npm:^2.0.0":
+  version: 2.0.0
+  resolution: "foo@npm:2.0.0"
+  dependencies:
+    bar: ^2.0.0
+  checksum: foo2
+  languageName: node
+  linkType: hard
+
+"root@workspace:.":
+  version: 0.0.0-use.local
+  resolution: "root@workspace:."
+  languageName: unknown
+  linkType: soft
+"#;
+
+    #[test]
+    fn test_hoisting_conflicting_versions() {
+        let npm_contents = berry_to_npm(CONFLICTING_BERRY.as_bytes(), None, None, true).unwrap();
+        let npm = NpmLockfile::load(&npm_contents).unwrap();
+
+        let installed = npm
+            .packages
+            .iter()
+            .filter(|(key, pkg)| !is_npm_workspace(key) && !pkg.is_link())
+            .map(|(key, pkg)| (key.as_str(), pkg.version.as_deref().unwrap()))
+            .collect::<Vec<_>>();
+        // The first workspace's versions are hoisted, the conflicting versions
+        // are nested under the workspace that needs them
+        assert_eq!(
+            installed,
+            vec![
+                ("node_modules/bar", "1.0.0"),
+                ("node_modules/foo", "1.0.0"),
+                ("packages/b/node_modules/bar", "2.0.0"),
+                ("packages/b/node_modules/foo", "2.0.0"),
+            ]
+        );
+
+        let data = LockfileData::from_bytes(CONFLICTING_BERRY.as_bytes()).unwrap();
+        let berry = BerryLockfile::new(&data, None).unwrap();
+        for (workspace, dep) in [("packages/a", "^1.0.0"), ("packages/b", "^2.0.0")] {
+            let deps: HashMap<_, _> = [("foo".to_string(), dep.to_string())].into();
+            assert_eq!(
+                resolved_versions(&npm, workspace, deps.clone()),
+                resolved_versions(&berry, workspace, deps),
+                "{workspace}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_hoisting_nested_dependencies() {
+        let mut tree = NodeModules::default();
+        assert_eq!(
+            tree.place("", "foo", "foo@npm:1.0.0").unwrap(),
+            Some("node_modules/foo".into())
+        );
+        // x needs foo@1 and resolves it from the root
+        assert_eq!(
+            tree.place("", "x", "x@npm:1.0.0").unwrap(),
+            Some("node_modules/x".into())
+        );
+        assert_eq!(
+            tree.place("node_modules/x", "foo", "foo@npm:1.0.0")
+                .unwrap(),
+            None
+        );
+        // The root workspace depends on z@2 so x's z@1 gets nested under x
+        assert_eq!(
+            tree.place("", "z", "z@npm:2.0.0").unwrap(),
+            Some("node_modules/z".into())
+        );
+        assert_eq!(
+            tree.place("node_modules/x", "z", "z@npm:1.0.0").unwrap(),
+            Some("node_modules/x/node_modules/z".into())
+        );
+        // z needs foo@2, it can't go at the root because foo@1 is there and it
+        // can't go under x because x would then resolve foo@2
+        assert_eq!(
+            tree.place("node_modules/x/node_modules/z", "foo", "foo@npm:2.0.0")
+                .unwrap(),
+            Some("node_modules/x/node_modules/z/node_modules/foo".into())
+        );
+        // A dependency of a nested package is hoisted as far as possible
+        assert_eq!(
+            tree.place(
+                "node_modules/x/node_modules/z/node_modules/foo",
+                "leaf",
+                "leaf@npm:1.0.0"
+            )
+            .unwrap(),
+            Some("node_modules/leaf".into())
+        );
+    }
+
+    #[test]
+    fn test_hoisting_avoids_conflicts() {
+        let mut tree = NodeModules::default();
+        assert_eq!(
+            tree.place("apps/a", "foo", "foo@npm:1.0.0").unwrap(),
+            Some("node_modules/foo".into())
+        );
+        assert_eq!(
+            tree.place("apps/b", "foo", "foo@npm:2.0.0").unwrap(),
+            Some("apps/b/node_modules/foo".into())
+        );
+        assert_eq!(tree.place("apps/c", "foo", "foo@npm:1.0.0").unwrap(), None);
+        assert_eq!(
+            tree.place("node_modules/bar", "foo", "foo@npm:3.0.0")
+                .unwrap(),
+            Some("node_modules/bar/node_modules/foo".into())
+        );
+    }
+}
@@ -0,0 +1,374 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use super::{Error, Lockfile, LockfileGraph, Package};
+
+/// A package that resolves to a different lockfile entry in the current
+/// lockfile e.g. a version bump
+#[derive(Debug, PartialEq, Eq, Clone, PartialOrd, Ord, Hash, Serialize)]
+pub struct PackageChange {
+    pub name: String,
+    pub previous: Package,
+    pub current: Package,
+}
+
+/// A lockfile setting that applies to every package e.g. overrides or patches
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct GlobalChange {
+    pub setting: String,
+    pub previous: Option<Value>,
+    pub current: Option<Value>,
+}
+
+/// Package level differences between two lockfiles along with the workspaces
+/// whose external dependencies are affected by them
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize)]
+pub struct LockfileDiff {
+    pub added: Vec<Package>,
+    pub removed: Vec<Package>,
+    pub changed: Vec<PackageChange>,
+    pub global_changes: Vec<GlobalChange>,
+    pub affected_workspaces: BTreeSet<String>,
+}
+
+impl LockfileDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.changed.is_empty()
+            && self.global_changes.is_empty()
+    }
+}
+
+/// Computes which packages were added, removed, or changed between two
+/// lockfiles.
+///
+/// Only packages that are reachable from one of the given workspaces are
+/// considered. Packages are matched by name, so a version bump is reported
+/// as a change even for lockfile formats that include the version in the
+/// key (pnpm, berry). If a package has multiple versions, the versions that
+/// aren't present in both lockfiles are paired up in order.
+///
+/// A workspace is affected if a package in its transitive closure in either
+/// lockfile was added, removed, or changed. A change to a global setting
+/// affects every workspace.
+pub fn lockfile_diff<P: Lockfile + ?Sized, C: Lockfile + ?Sized>(
+    prev: &P,
+    curr: &C,
+    workspaces: HashMap<String, HashMap<String, String>>,
+) -> Result<LockfileDiff, Error> {
+    let workspace_names = workspaces.keys().cloned().collect::<BTreeSet<_>>();
+    let prev_graph = LockfileGraph::new(prev, workspaces.clone())?;
+    let curr_graph = LockfileGraph::new(curr, workspaces)?;
+
+    let prev_packages = prev_graph
+        .packages()
+        .map(|pkg| (pkg.key.as_str(), pkg))
+        .collect::<HashMap<_, _>>();
+    let curr_packages = curr_graph
+        .packages()
+        .map(|pkg| (pkg.key.as_str(), pkg))
+        .collect::<HashMap<_, _>>();
+
+    let mut diff = LockfileDiff::default();
+    // Packages whose key only appears in one of the lockfiles grouped by
+    // package name so they can be paired up as changes
+    let mut removed_by_name: BTreeMap<String, Vec<&Package>> = BTreeMap::new();
+    let mut added_by_name: BTreeMap<String, Vec<&Package>> = BTreeMap::new();
+    for (key, curr_pkg) in &curr_packages {
+        match prev_packages.get(key) {
+            None => added_by_name
+                .entry(curr.package_name(key).unwrap_or_else(|| key.to_string()))
+                .or_default()
+                .push(curr_pkg),
+            Some(prev_pkg) if prev_pkg.version != curr_pkg.version => {
+                diff.changed.push(PackageChange {
+                    name: curr.package_name(key).unwrap_or_else(|| key.to_string()),
+                    previous: (*prev_pkg).clone(),
+                    current: (*curr_pkg).clone(),
+                })
+            }
+            Some(_) => (),
+        }
+    }
+    for (key, prev_pkg) in &prev_packages {
+        if !curr_packages.contains_key(key) {
+            removed_by_name
+                .entry(prev.package_name(key).unwrap_or_else(|| key.to_string()))
+                .or_default()
+                .push(prev_pkg);
+        }
+    }
+
+    for (name, mut removed) in removed_by_name {
+        let mut added = added_by_name.remove(&name).unwrap_or_default();
+        removed.sort();
+        added.sort();
+        let mut removed = removed.into_iter();
+        let mut added = added.into_iter();
+        loop {
+            match (removed.next(), added.next()) {
+                (Some(previous), Some(current)) => diff.changed.push(PackageChange {
+                    name: name.clone(),
+                    previous: previous.clone(),
+                    current: current.clone(),
+                }),
+                (Some(previous), None) => diff.removed.push(previous.clone()),
+                (None, Some(current)) => diff.added.push(current.clone()),
+                (None, None) => break,
+            }
+        }
+    }
+    diff.added
+        .extend(added_by_name.into_values().flatten().cloned());
+
+    let prev_settings = prev.global_settings();
+    let curr_settings = curr.global_settings();
+    let settings = prev_settings
+        .keys()
+        .chain(curr_settings.keys())
+        .collect::<BTreeSet<_>>();
+    for setting in settings {
+        let previous = prev_settings.get(setting);
+        let current = curr_settings.get(setting);
+        if previous != current {
+            diff.global_changes.push(GlobalChange {
+                setting: setting.to_string(),
+                previous: previous.cloned(),
+                current: current.cloned(),
+            });
+        }
+    }
+
+    diff.affected_workspaces = if diff.global_changes.is_empty() {
+        let prev_affected = diff
+            .removed
+            .iter()
+            .chain(diff.changed.iter().map(|change| &change.previous))
+            .filter_map(|pkg| prev_graph.workspaces_depending_on(&pkg.key))
+            .flatten();
+        let curr_affected = diff
+            .added
+            .iter()
+            .chain(diff.changed.iter().map(|change| &change.current))
+            .filter_map(|pkg| curr_graph.workspaces_depending_on(&pkg.key))
+            .flatten();
+        prev_affected
+            .chain(curr_affected)
+            .map(|workspace| workspace.to_string())
+            .collect()
+    } else {
+        workspace_names
+    };
+
+    diff.added.sort();
+    diff.removed.sort();
+    diff.changed.sort();
+
+    Ok(diff)
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::{PnpmLockfile, Yarn1Lockfile};
+
+    const PREV: &str = r#"foo@^1.0.0:
+  version "1.0.0"
+  dependencies:
+    bar "^1.0.0"
+    old "^1.0.0"
+
+bar@^1.0.0:
+  version "1.0.0"
+
+baz@^1.0.0:
+  version "1.0.0"
+
+old@^1.0.0:
+  version "1.0.0"
+"#;
+
+    const CURR: &str = r#"foo@^1.0.0:
+  version "1.0.0"
+  dependencies:
+    bar "^1.0.0"
+    new "^1.0.0"
+
+bar@^1.0.0:
+  version "1.1.0"
+
+baz@^1.0.0:
+  version "1.0.0"
+
+new@^1.0.0:
+  version "1.0.0"
+"#;
+
+    fn workspaces() -> HashMap<String, HashMap<String, String>> {
+        [("apps/a", "foo"), ("apps/b", "baz")]
+            .into_iter()
+            .map(|(workspace, dep)| {
+                (
+                    workspace.to_string(),
+                    [(dep.to_string(), "^1.0.0".to_string())]
+                        .into_iter()
+                        .collect(),
+                )
+            })
+            .collect()
+    }
+
+    fn package(key: &str, version: &str) -> Package {
+        Package {
+            key: key.into(),
+            version: version.into(),
+        }
+    }
+
+    #[test]
+    fn test_lockfile_diff() {
+        let prev = Yarn1Lockfile::from_bytes(PREV.as_bytes()).unwrap();
+        let curr = Yarn1Lockfile::from_bytes(CURR.as_bytes()).unwrap();
+        let diff = lockfile_diff(&prev, &curr, workspaces()).unwrap();
+        assert_eq!(
+            diff,
+            LockfileDiff {
+                added: vec![package("new@^1.0.0", "1.0.0")],
+                removed: vec![package("old@^1.0.0", "1.0.0")],
+                changed: vec![PackageChange {
+                    name: "bar".into(),
+                    previous: package("bar@^1.0.0", "1.0.0"),
+                    current: package("bar@^1.0.0", "1.1.0"),
+                }],
+                global_changes: vec![],
+                affected_workspaces: ["apps/a".to_string()].into_iter().collect(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_lockfile_diff_unchanged() {
+        let prev = Yarn1Lockfile::from_bytes(PREV.as_bytes()).unwrap();
+        let diff = lockfile_diff(&prev, &prev, workspaces()).unwrap();
+        assert!(diff.is_empty());
+        assert!(diff.affected_workspaces.is_empty());
+    }
+
+    #[test]
+    fn test_lockfile_diff_range_change() {
+        let prev = Yarn1Lockfile::from_bytes(PREV.as_bytes()).unwrap();
+        let curr = Yarn1Lockfile::from_bytes(
+            PREV.replace(
+                "bar@^1.0.0:\n  version \"1.0.0\"",
+                "bar@^2.0.0:\n  version \"2.0.0\"",
+            )
+            .replace("bar \"^1.0.0\"", "bar \"^2.0.0\"")
+            .as_bytes(),
+        )
+        .unwrap();
+        let diff = lockfile_diff(&prev, &curr, workspaces()).unwrap();
+        assert_eq!(
+            diff.changed,
+            vec![PackageChange {
+                name: "bar".into(),
+                previous: package("bar@^1.0.0", "1.0.0"),
+                current: package("bar@^2.0.0", "2.0.0"),
+            }]
+        );
+        assert!(diff.added.is_empty());
+        assert!(diff.removed.is_empty());
+    }
+
+    const PNPM_PREV: &str = r#"lockfileVersion: 5.4
+
+importers:
+  apps/a:
+    specifiers:
+      foo: ^1.0.0
+    dependencies:
+      foo: 1.0.0
+  apps/b:
+    specifiers:
+      baz: ^1.0.0
+    dependencies:
+      baz: 1.0.0
+
+packages:
+  /baz/1.0.0:
+    resolution: {integrity: sha512-baz}
+    dev: false
+
+  /foo/1.0.0:
+    resolution: {integrity: sha512-foo}
+    dev: false
+"#;
+
+    fn pnpm_workspaces() -> HashMap<String, HashMap<String, String>> {
+        [("apps/a", "foo"), ("apps/b", "baz")]
+            .into_iter()
+            .map(|(workspace, dep)| {
+                (
+                    workspace.to_string(),
+                    [(dep.to_string(), "^1.0.0".to_string())].into(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_pnpm_version_bump() {
+        let prev = PnpmLockfile::load(PNPM_PREV.as_bytes()).unwrap();
+        let curr = PnpmLockfile::load(
+            PNPM_PREV
+                .replace("foo/1.0.0", "foo/1.1.0")
+                .replace("foo: 1.0.0", "foo: 1.1.0")
+                .as_bytes(),
+        )
+        .unwrap();
+        let diff = lockfile_diff(&prev, &curr, pnpm_workspaces()).unwrap();
+        assert_eq!(
+            diff,
+            LockfileDiff {
+                changed: vec![PackageChange {
+                    name: "foo".into(),
+                    previous: package("/foo/1.0.0", "1.0.0"),
+                    current: package("/foo/1.1.0", "1.1.0"),
+                }],
+                affected_workspaces: ["apps/a".to_string()].into_iter().collect(),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_pnpm_global_change() {
+        let prev = PnpmLockfile::load(PNPM_PREV.as_bytes()).unwrap();
+        let curr = PnpmLockfile::load(
+            PNPM_PREV
+                .replace("\nimporters:", "\noverrides:\n  foo: 1.0.0\n\nimporters:")
+                .as_bytes(),
+        )
+        .unwrap();
+        let diff = lockfile_diff(&prev, &curr, pnpm_workspaces()).unwrap();
+        assert!(diff.added.is_empty() && diff.removed.is_empty() && diff.changed.is_empty());
+        assert_eq!(
+            diff.global_changes,
+            vec![GlobalChange {
+                setting: "overrides".into(),
+                previous: None,
+                current: Some(serde_json::json!({"foo": "1.0.0"})),
+            }]
+        );
+        assert_eq!(
+            diff.affected_workspaces,
+            ["apps/a".to_string(), "apps/b".to_string()]
+                .into_iter()
+                .collect()
+        );
+    }
+}
@@ -12,4 +12,6 @@ pub enum Error {
     JsonError(#[from] serde_json::Error),
     #[error("Turbo doesn't support npm lockfiles without a 'packages' field")]
     UnsupportedNpmVersion,
+    #[error(transparent)]
+    Pnpm(#[from] crate::pnpm::Error),
 }
@@ -0,0 +1,561 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use fixedbitset::FixedBitSet;
+use petgraph::{
+    algo::{astar, tarjan_scc},
+    graph::{DiGraph, NodeIndex},
+    visit::{Bfs, Reversed},
+    Direction,
+};
+
+use super::{Error, Lockfile, Package};
+
+/// A node in the lockfile graph, either a workspace or an external package
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum LockfileNode {
+    Workspace(String),
+    Package(Package),
+}
+
+/// Dependency graph of all workspaces and the external packages they pull in.
+///
+/// The graph is built once from any `Lockfile` implementation and can then
+/// answer closure, reverse dependency, and path queries without going back to
+/// the lockfile.
+///
+/// Packages are identified by their lockfile key. For most lockfile formats
+/// the dependencies of an external package resolve the same way no matter
+/// which workspace reached it, so each package gets a single node shared by
+/// all workspaces. If the lockfile reports workspace scoped resolution, e.g.
+/// Berry with targeted resolutions or pnpm importers that pin a different
+/// version of a transitive dependency, each workspace gets its own nodes so
+/// dependencies are always resolved in the context of that workspace.
+#[derive(Debug)]
+pub struct LockfileGraph {
+    graph: DiGraph<LockfileNode, ()>,
+    workspaces: HashMap<String, NodeIndex>,
+    // Lockfile key to every node for that package, there is more than one
+    // node if the package was resolved in multiple workspace scopes.
+    packages: HashMap<String, Vec<NodeIndex>>,
+    // (workspace scope, lockfile key) to the node for that package
+    nodes: HashMap<(Option<String>, String), NodeIndex>,
+}
+
+impl LockfileGraph {
+    /// Constructs the graph given a lockfile and a map from workspace paths to
+    /// the unresolved dependencies listed in that workspace's package.json
+    pub fn new<L: Lockfile + ?Sized>(
+        lockfile: &L,
+        workspaces: HashMap<String, HashMap<String, String>>,
+    ) -> Result<Self, Error> {
+        let mut this = Self {
+            graph: DiGraph::new(),
+            workspaces: HashMap::with_capacity(workspaces.len()),
+            packages: HashMap::new(),
+            nodes: HashMap::new(),
+        };
+        let scoped = lockfile.workspace_scoped_resolution();
+
+        // Workspaces and dependencies are visited in sorted order so the
+        // graph is the same regardless of hash map ordering.
+        let mut workspaces = workspaces.into_iter().collect::<Vec<_>>();
+        workspaces.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        // Packages that have been added to the graph, but whose dependencies
+        // haven't been resolved yet along with the workspace that reached them.
+        let mut queue = VecDeque::with_capacity(workspaces.len());
+        for (workspace, unresolved_deps) in workspaces {
+            let workspace_node = this
+                .graph
+                .add_node(LockfileNode::Workspace(workspace.clone()));
+            this.workspaces.insert(workspace.clone(), workspace_node);
+            let scope = scoped.then(|| workspace.clone());
+            for (name, specifier) in sorted(unresolved_deps) {
+                if let Some(pkg) = lockfile.resolve_package(&workspace, &name, &specifier)? {
+                    let (pkg_node, is_new) = this.add_package(scope.clone(), pkg);
+                    this.graph.update_edge(workspace_node, pkg_node, ());
+                    if is_new {
+                        queue.push_back((workspace.clone(), pkg_node));
+                    }
+                }
+            }
+        }
+
+        while let Some((workspace, node)) = queue.pop_front() {
+            let LockfileNode::Package(pkg) = &this.graph[node] else {
+                unreachable!("only packages are queued for resolution");
+            };
+            let Some(deps) = lockfile.all_dependencies(&pkg.key)? else {
+                continue;
+            };
+            let scope = scoped.then(|| workspace.clone());
+            for (name, specifier) in sorted(deps) {
+                if let Some(dep) = lockfile.resolve_package(&workspace, &name, &specifier)? {
+                    let (dep_node, is_new) = this.add_package(scope.clone(), dep);
+                    this.graph.update_edge(node, dep_node, ());
+                    if is_new {
+                        queue.push_back((workspace.clone(), dep_node));
+                    }
+                }
+            }
+        }
+
+        Ok(this)
+    }
+
+    fn add_package(&mut self, scope: Option<String>, pkg: Package) -> (NodeIndex, bool) {
+        let id = (scope, pkg.key.clone());
+        if let Some(node) = self.nodes.get(&id) {
+            return (*node, false);
+        }
+        let node = self.graph.add_node(LockfileNode::Package(pkg));
+        self.packages.entry(id.1.clone()).or_default().push(node);
+        self.nodes.insert(id, node);
+        (node, true)
+    }
+
+    fn package(&self, node: NodeIndex) -> Option<&Package> {
+        match &self.graph[node] {
+            LockfileNode::Package(pkg) => Some(pkg),
+            LockfileNode::Workspace(_) => None,
+        }
+    }
+
+    /// All external packages in the graph
+    pub fn packages(&self) -> impl Iterator<Item = &Package> {
+        self.packages
+            .values()
+            .filter_map(|nodes| self.package(*nodes.first()?))
+    }
+
+    /// All external packages a workspace depends on either directly or
+    /// transitively. Returns `None` if the workspace isn't in the graph.
+    pub fn closure(&self, workspace: &str) -> Option<HashSet<Package>> {
+        let start = *self.workspaces.get(workspace)?;
+        let mut bfs = Bfs::new(&self.graph, start);
+        let mut closure = HashSet::new();
+        while let Some(node) = bfs.next(&self.graph) {
+            if let Some(pkg) = self.package(node) {
+                closure.insert(pkg.clone());
+            }
+        }
+        Some(closure)
+    }
+
+    /// Transitive closures for every workspace in the graph.
+    ///
+    /// Closures of shared packages are computed once and reused across
+    /// workspaces. We walk the strongly connected components of the graph in
+    /// reverse topological order so each component's closure only needs to be
+    /// merged with the closures of the components it depends on. A
+    /// component's closure is dropped as soon as every component depending on
+    /// it has merged it in so only the frontier of the walk is kept in memory.
+    pub fn all_closures(&self) -> HashMap<String, HashSet<Package>> {
+        let node_count = self.graph.node_count();
+        let components = tarjan_scc(&self.graph);
+        let mut component_of = vec![0; node_count];
+        for (component, nodes) in components.iter().enumerate() {
+            for node in nodes {
+                component_of[node.index()] = component;
+            }
+        }
+        // Number of edges from other components that still need to merge in
+        // each component's closure
+        let mut pending_dependents = vec![0usize; components.len()];
+        for edge in self.graph.raw_edges() {
+            let (source, target) = (
+                component_of[edge.source().index()],
+                component_of[edge.target().index()],
+            );
+            if source != target {
+                pending_dependents[target] += 1;
+            }
+        }
+
+        let mut closures: Vec<Option<FixedBitSet>> = Vec::with_capacity(components.len());
+        let mut workspace_closures = HashMap::with_capacity(self.workspaces.len());
+        // tarjan_scc returns components in reverse topological order, so all
+        // dependencies of a component have been visited before the component.
+        for (component, nodes) in components.iter().enumerate() {
+            let mut closure = FixedBitSet::with_capacity(node_count);
+            for node in nodes {
+                for dep in self.graph.neighbors_directed(*node, Direction::Outgoing) {
+                    closure.insert(dep.index());
+                    let dep_component = component_of[dep.index()];
+                    if dep_component == component {
+                        continue;
+                    }
+                    let dep_closure = closures[dep_component]
+                        .as_ref()
+                        .expect("closure is kept until all dependents have merged it");
+                    closure.union_with(dep_closure);
+                    pending_dependents[dep_component] -= 1;
+                    if pending_dependents[dep_component] == 0 {
+                        closures[dep_component] = None;
+                    }
+                }
+            }
+
+            for node in nodes {
+                if let LockfileNode::Workspace(workspace) = &self.graph[*node] {
+                    let packages = closure
+                        .ones()
+                        .filter_map(|i| self.package(NodeIndex::new(i)))
+                        .cloned()
+                        .collect();
+                    workspace_closures.insert(workspace.clone(), packages);
+                }
+            }
+            closures.push((pending_dependents[component] > 0).then_some(closure));
+        }
+
+        workspace_closures
+    }
+
+    /// Workspaces and packages that directly depend on the package with the
+    /// given key. Returns `None` if the package isn't in the graph.
+    pub fn dependents(&self, key: &str) -> Option<Vec<&LockfileNode>> {
+        let nodes = self.packages.get(key)?;
+        let mut seen = HashSet::new();
+        Some(
+            nodes
+                .iter()
+                .flat_map(|node| self.graph.neighbors_directed(*node, Direction::Incoming))
+                .filter(|dependent| seen.insert(*dependent))
+                .map(|dependent| &self.graph[dependent])
+                .collect(),
+        )
+    }
+
+    /// Workspaces that pull in the package with the given key either directly
+    /// or transitively. Returns `None` if the package isn't in the graph.
+    pub fn workspaces_depending_on(&self, key: &str) -> Option<HashSet<&str>> {
+        let nodes = self.packages.get(key)?;
+        let reversed = Reversed(&self.graph);
+        let mut workspaces = HashSet::new();
+        for node in nodes {
+            let mut bfs = Bfs::new(reversed, *node);
+            while let Some(node) = bfs.next(reversed) {
+                if let LockfileNode::Workspace(workspace) = &self.graph[node] {
+                    workspaces.insert(workspace.as_str());
+                }
+            }
+        }
+        Some(workspaces)
+    }
+
+    /// Shortest chain of packages that causes a workspace to depend on the
+    /// package with the given key. The chain starts with a direct dependency
+    /// of the workspace and ends with the package.
+    pub fn shortest_path(&self, workspace: &str, key: &str) -> Option<Vec<&Package>> {
+        let start = *self.workspaces.get(workspace)?;
+        let goals = self.packages.get(key)?;
+        let (_, path) = astar(
+            &self.graph,
+            start,
+            |node| goals.contains(&node),
+            |_| 1,
+            |_| 0,
+        )?;
+        Some(
+            path.into_iter()
+                .filter_map(|node| self.package(node))
+                .collect(),
+        )
+    }
+}
+
+fn sorted(deps: HashMap<String, String>) -> Vec<(String, String)> {
+    let mut deps = deps.into_iter().collect::<Vec<_>>();
+    deps.sort();
+    deps
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::{NpmLockfile, PnpmLockfile};
+
+    fn npm_graph() -> (NpmLockfile, HashMap<String, HashMap<String, String>>) {
+        let lockfile = NpmLockfile::load(include_bytes!("../fixtures/npm-lock.json")).unwrap();
+        let workspaces = [
+            ("apps/docs", vec![("lodash", "^3.0.0"), ("next", "12.3.0")]),
+            ("apps/web", vec![("lodash", "^4.17.21"), ("next", "12.3.0")]),
+            ("", vec![("turbo", "latest")]),
+        ]
+        .into_iter()
+        .map(|(workspace, deps)| {
+            (
+                workspace.to_string(),
+                deps.into_iter()
+                    .map(|(name, version)| (name.to_string(), version.to_string()))
+                    .collect(),
+            )
+        })
+        .collect();
+        (lockfile, workspaces)
+    }
+
+    #[test]
+    fn test_memoized_closures_match_traversal() {
+        let (lockfile, workspaces) = npm_graph();
+        let graph = LockfileGraph::new(&lockfile, workspaces.clone()).unwrap();
+        let closures = graph.all_closures();
+        assert_eq!(closures.len(), workspaces.len());
+        for workspace in workspaces.keys() {
+            let expected = graph.closure(workspace).unwrap();
+            assert!(!expected.is_empty());
+            assert_eq!(closures.get(workspace), Some(&expected), "{workspace}");
+        }
+        assert!(closures["apps/docs"].contains(&Package {
+            key: "node_modules/lodash".into(),
+            version: "3.10.1".into(),
+        }));
+        assert!(closures["apps/web"].contains(&Package {
+            key: "apps/web/node_modules/lodash".into(),
+            version: "4.17.21".into(),
+        }));
+        assert_eq!(graph.closure("apps/missing"), None);
+    }
+
+    #[test]
+    fn test_reverse_dependencies() {
+        let (lockfile, workspaces) = npm_graph();
+        let graph = LockfileGraph::new(&lockfile, workspaces).unwrap();
+
+        let mut dependents = graph.workspaces_depending_on("node_modules/next").unwrap();
+        assert_eq!(
+            dependents.drain().collect::<HashSet<_>>(),
+            ["apps/docs", "apps/web"].into_iter().collect()
+        );
+        assert_eq!(
+            graph.workspaces_depending_on("node_modules/turbo").unwrap(),
+            [""].into_iter().collect()
+        );
+        assert!(graph
+            .dependents("node_modules/turbo")
+            .unwrap()
+            .contains(&&LockfileNode::Workspace("".into())));
+        assert_eq!(graph.workspaces_depending_on("node_modules/missing"), None);
+    }
+
+    #[test]
+    fn test_shortest_path() {
+        let (lockfile, workspaces) = npm_graph();
+        let graph = LockfileGraph::new(&lockfile, workspaces).unwrap();
+
+        let path = graph
+            .shortest_path("", "node_modules/turbo-darwin-64")
+            .unwrap();
+        assert_eq!(
+            path.iter().map(|pkg| pkg.key.as_str()).collect::<Vec<_>>(),
+            vec!["node_modules/turbo", "node_modules/turbo-darwin-64"]
+        );
+        assert_eq!(graph.shortest_path("apps/docs", "node_modules/turbo"), None);
+    }
+
+    #[test]
+    fn test_deterministic_construction() {
+        let nodes = || {
+            let (lockfile, workspaces) = npm_graph();
+            let graph = LockfileGraph::new(&lockfile, workspaces).unwrap();
+            graph.graph.node_weights().cloned().collect::<Vec<_>>()
+        };
+        let expected = nodes();
+        for _ in 0..5 {
+            assert_eq!(nodes(), expected);
+        }
+    }
+
+    // Every workspace depends on `shared` which depends on `leaf`, but `leaf`
+    // resolves to a different entry in each workspace.
+    struct ScopedLockfile;
+
+    impl Lockfile for ScopedLockfile {
+        fn resolve_package(
+            &self,
+            workspace_path: &str,
+            name: &str,
+            _version: &str,
+        ) -> Result<Option<Package>, Error> {
+            let key = match name {
+                "leaf" => format!("leaf@{workspace_path}"),
+                name => format!("{name}@1.0.0"),
+            };
+            Ok(Some(Package {
+                key,
+                version: "1.0.0".into(),
+            }))
+        }
+
+        fn all_dependencies(&self, key: &str) -> Result<Option<HashMap<String, String>>, Error> {
+            Ok(match key {
+                "shared@1.0.0" => Some([("leaf".to_string(), "*".to_string())].into()),
+                _ => None,
+            })
+        }
+
+        fn package_keys(&self) -> Vec<String> {
+            Vec::new()
+        }
+
+        fn missing_integrity(&self, _key: &str) -> bool {
+            false
+        }
+
+        fn workspace_scoped_resolution(&self) -> bool {
+            true
+        }
+
+        fn package_name(&self, key: &str) -> Option<String> {
+            key.split_once('@').map(|(name, _)| name.to_string())
+        }
+    }
+
+    #[test]
+    fn test_workspace_scoped_resolution() {
+        let workspaces = ["a", "b"]
+            .into_iter()
+            .map(|workspace| {
+                (
+                    workspace.to_string(),
+                    [("shared".to_string(), "^1.0.0".to_string())].into(),
+                )
+            })
+            .collect();
+        let graph = LockfileGraph::new(&ScopedLockfile, workspaces).unwrap();
+        let closures = graph.all_closures();
+        for workspace in ["a", "b"] {
+            let mut keys = closures[workspace]
+                .iter()
+                .map(|pkg| pkg.key.as_str())
+                .collect::<Vec<_>>();
+            keys.sort();
+            assert_eq!(
+                keys,
+                vec![format!("leaf@{workspace}").as_str(), "shared@1.0.0"]
+            );
+            assert_eq!(graph.closure(workspace).unwrap(), closures[workspace]);
+        }
+        assert_eq!(
+            graph.workspaces_depending_on("shared@1.0.0").unwrap(),
+            ["a", "b"].into_iter().collect()
+        );
+        assert_eq!(graph.dependents("shared@1.0.0").unwrap().len(), 2);
+        assert_eq!(
+            graph
+                .shortest_path("b", "leaf@b")
+                .unwrap()
+                .iter()
+                .map(|pkg| pkg.key.as_str())
+                .collect::<Vec<_>>(),
+            vec!["shared@1.0.0", "leaf@b"]
+        );
+        assert_eq!(graph.packages().count(), 3);
+    }
+
+    // `bar` is shared by both workspaces, but its dependency on `foo` is
+    // resolved through the importer which pins a different peer of `foo`.
+    const PNPM_IMPORTER_VERSIONS: &str = r#"lockfileVersion: 5.4
+
+importers:
+
+  apps/a:
+    specifiers:
+      bar: ^1.0.0
+      foo: 1.0.0
+      react: ^18.0.0
+    dependencies:
+      bar: 1.0.0
+      foo: 1.0.0_react@18.0.0
+      react: 18.0.0
+
+  apps/b:
+    specifiers:
+      bar: ^1.0.0
+      foo: 1.0.0
+      react: ^17.0.0
+    dependencies:
+      bar: 1.0.0
+      foo: 1.0.0_react@17.0.0
+      react: 17.0.0
+
+packages:
+
+  /bar/1.0.0:
+    resolution: {integrity: sha512-bar}
+    dependencies:
+      foo: 1.0.0
+    dev: false
+
+  /foo/1.0.0_react@17.0.0:
+    resolution: {integrity: sha512-foo}
+    peerDependencies:
+      react: '*'
+    dependencies:
+      react: 17.0.0
+    dev: false
+
+  /foo/1.0.0_react@18.0.0:
+    resolution: {integrity: sha512-foo}
+    peerDependencies:
+      react: '*'
+    dependencies:
+      react: 18.0.0
+    dev: false
+
+  /react/17.0.0:
+    resolution: {integrity: sha512-react17}
+    dev: false
+
+  /react/18.0.0:
+    resolution: {integrity: sha512-react18}
+    dev: false
+"#;
+
+    #[test]
+    fn test_pnpm_importer_scoped_resolution() {
+        let lockfile = PnpmLockfile::load(PNPM_IMPORTER_VERSIONS.as_bytes()).unwrap();
+        assert!(lockfile.workspace_scoped_resolution());
+        let workspaces = [("apps/a", "^18.0.0"), ("apps/b", "^17.0.0")]
+            .into_iter()
+            .map(|(workspace, react)| {
+                (
+                    workspace.to_string(),
+                    [("bar", "^1.0.0"), ("react", react)]
+                        .into_iter()
+                        .map(|(name, version)| (name.to_string(), version.to_string()))
+                        .collect(),
+                )
+            })
+            .collect();
+        let graph = LockfileGraph::new(&lockfile, workspaces).unwrap();
+        let closures = graph.all_closures();
+        for (workspace, react) in [("apps/a", "18.0.0"), ("apps/b", "17.0.0")] {
+            let mut keys = closures[workspace]
+                .iter()
+                .map(|pkg| pkg.key.as_str())
+                .collect::<Vec<_>>();
+            keys.sort();
+            assert_eq!(
+                keys,
+                vec![
+                    "/bar/1.0.0",
+                    format!("/foo/1.0.0_react@{react}").as_str(),
+                    format!("/react/{react}").as_str(),
+                ],
+                "{workspace}"
+            );
+            assert_eq!(graph.closure(workspace).unwrap(), closures[workspace]);
+        }
+    }
+
+    #[test]
+    fn test_pnpm_shared_resolution() {
+        let lockfile =
+            PnpmLockfile::load(include_bytes!("../fixtures/pnpm7-workspace.yaml")).unwrap();
+        assert!(!lockfile.workspace_scoped_resolution());
+    }
+}
@@ -1,15 +1,28 @@
 #![feature(once_cell)]
 
 mod berry;
+mod check;
+mod convert;
+mod diff;
 mod error;
+mod graph;
 mod npm;
+mod pnpm;
+mod yarn1;
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 pub use berry::{Error as BerryError, *};
+pub use check::{check_lockfile, LockfileReport, LookupError, UnresolvedDependency};
+pub use convert::{berry_to_npm, npm_to_berry, Error as ConvertError};
+pub use diff::{lockfile_diff, GlobalChange, LockfileDiff, PackageChange};
 pub use error::Error;
+pub use graph::{LockfileGraph, LockfileNode};
 pub use npm::*;
+pub use pnpm::{pnpm_global_change, pnpm_subgraph, Error as PnpmError, PnpmLockfile};
 use serde::Serialize;
+use serde_json::Value;
+pub use yarn1::{yarn_subgraph, Error as Yarn1Error, Yarn1Lockfile};
 
 #[derive(Debug, PartialEq, Eq, Clone, PartialOrd, Ord, Hash, Serialize)]
 pub struct Package {
@@ -17,9 +30,8 @@ pub struct Package {
     pub version: String,
 }
 
-// This trait will only be used when migrating the Go lockfile implementations
-// to Rust. Once the migration is complete we will leverage petgraph for doing
-// our graph calculations.
+// Lockfile implementations only need to answer resolution queries, all graph
+// calculations are done on a `LockfileGraph` built from these.
 pub trait Lockfile {
     // Given a workspace, a package it imports and version returns the key, resolved
     // version, and if it was found
@@ -32,63 +44,52 @@ pub trait Lockfile {
     // Given a lockfile key return all (prod/dev/optional) dependencies of that
     // package
     fn all_dependencies(&self, key: &str) -> Result<Option<HashMap<String, String>>, Error>;
+    // Given a lockfile key return the dependencies as declared by that package.
+    // Only needed if `all_dependencies` drops dependencies that don't have an
+    // entry in the lockfile.
+    fn declared_dependencies(&self, _key: &str) -> Option<HashMap<String, String>> {
+        None
+    }
+    // Returns the keys of all external packages in the lockfile
+    fn package_keys(&self) -> Vec<String>;
+    // Given a lockfile key return if the entry should have an integrity hash,
+    // but doesn't. Entries that are never expected to have a hash e.g. links or
+    // git dependencies are never considered missing.
+    fn missing_integrity(&self, key: &str) -> bool;
+    // Returns true if the dependencies of an external package can resolve to
+    // different entries depending on the workspace that pulled the package in
+    fn workspace_scoped_resolution(&self) -> bool {
+        false
+    }
+    // Given a lockfile key return the name of the package it refers to
+    fn package_name(&self, key: &str) -> Option<String>;
+    // Returns settings that apply to every package in the lockfile e.g.
+    // overrides or patches keyed by their name in the lockfile
+    fn global_settings(&self) -> BTreeMap<&'static str, Value> {
+        BTreeMap::new()
+    }
 }
 
-pub fn all_transitive_closures<L: Lockfile + Sync>(
+pub fn all_transitive_closures<L: Lockfile + ?Sized>(
     lockfile: &L,
     workspaces: HashMap<String, HashMap<String, String>>,
 ) -> Result<HashMap<String, HashSet<Package>>, Error> {
-    workspaces
-        .into_iter()
-        .map(|(workspace, unresolved_deps)| {
-            let closure = transitive_closure(lockfile, &workspace, unresolved_deps)?;
-            Ok((workspace, closure))
-        })
-        .collect()
+    let graph = LockfileGraph::new(lockfile, workspaces)?;
+    Ok(graph.all_closures())
 }
 
-// this should get replaced by petgraph in the future :)
-pub fn transitive_closure<L: Lockfile>(
+pub fn transitive_closure<L: Lockfile + ?Sized>(
     lockfile: &L,
     workspace_path: &str,
     unresolved_deps: HashMap<String, String>,
 ) -> Result<HashSet<Package>, Error> {
-    let mut transitive_deps = HashSet::new();
-    transitive_closure_helper(
+    let graph = LockfileGraph::new(
         lockfile,
-        workspace_path,
-        unresolved_deps,
-        &mut transitive_deps,
+        [(workspace_path.to_string(), unresolved_deps)]
+            .into_iter()
+            .collect(),
     )?;
-
-    Ok(transitive_deps)
-}
-
-fn transitive_closure_helper<L: Lockfile>(
-    lockfile: &L,
-    workspace_path: &str,
-    unresolved_deps: HashMap<String, impl AsRef<str>>,
-    resolved_deps: &mut HashSet<Package>,
-) -> Result<(), Error> {
-    for (name, specifier) in unresolved_deps {
-        let pkg = lockfile.resolve_package(workspace_path, &name, specifier.as_ref())?;
-
-        match pkg {
-            None => {
-                continue;
-            }
-            Some(pkg) if resolved_deps.contains(&pkg) => {
-                continue;
-            }
-            Some(pkg) => {
-                let all_deps = lockfile.all_dependencies(&pkg.key)?;
-                resolved_deps.insert(pkg);
-                if let Some(deps) = all_deps {
-                    transitive_closure_helper(lockfile, workspace_path, deps, resolved_deps)?;
-                }
-            }
-        }
-    }
-
-    Ok(())
+    Ok(graph
+        .closure(workspace_path)
+        .expect("workspace was used to construct graph"))
 }
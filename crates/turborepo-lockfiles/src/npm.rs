@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
@@ -13,35 +13,35 @@ type Map<K, V> = std::collections::BTreeMap<K, V>;
 #[derive(Debug, Serialize, Deserialize)]
 pub struct NpmLockfile {
     #[serde(rename = "lockfileVersion")]
-    lockfile_version: i32,
-    packages: Map<String, NpmPackage>,
+    pub(crate) lockfile_version: i32,
+    pub(crate) packages: Map<String, NpmPackage>,
     // We parse this so it doesn't end up in 'other' and we don't need to worry
     // about accidentally serializing it.
     #[serde(skip_serializing, default)]
-    dependencies: Map<String, Value>,
+    pub(crate) dependencies: Map<String, Value>,
     // We want to reserialize any additional fields, but we don't use them
     // we keep them as raw values to avoid describing the correct schema.
     #[serde(flatten)]
-    other: Map<String, Value>,
+    pub(crate) other: Map<String, Value>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
-struct NpmPackage {
-    version: Option<String>,
-    resolved: Option<String>,
+pub(crate) struct NpmPackage {
+    pub(crate) version: Option<String>,
+    pub(crate) resolved: Option<String>,
     #[serde(default)]
-    dependencies: Map<String, String>,
+    pub(crate) dependencies: Map<String, String>,
     #[serde(default)]
-    dev_dependencies: Map<String, String>,
+    pub(crate) dev_dependencies: Map<String, String>,
     #[serde(default)]
-    peer_dependencies: Map<String, String>,
+    pub(crate) peer_dependencies: Map<String, String>,
     #[serde(default)]
-    optional_dependencies: Map<String, String>,
+    pub(crate) optional_dependencies: Map<String, String>,
     // We want to reserialize any additional fields, but we don't use them
     // we keep them as raw values to avoid describing the correct schema.
     #[serde(flatten)]
-    other: Map<String, Value>,
+    pub(crate) other: Map<String, Value>,
 }
 
 impl Lockfile for NpmLockfile {
@@ -99,6 +99,47 @@ impl Lockfile for NpmLockfile {
             })
             .transpose()
     }
+
+    fn declared_dependencies(&self, key: &str) -> Option<HashMap<String, String>> {
+        self.packages.get(key).map(|pkg| {
+            pkg.dep_entries()
+                .map(|(name, range)| (name.clone(), range.clone()))
+                .collect()
+        })
+    }
+
+    fn package_keys(&self) -> Vec<String> {
+        self.packages
+            .iter()
+            .filter(|(key, pkg)| key.contains("node_modules/") && !pkg.is_link())
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+
+    fn missing_integrity(&self, key: &str) -> bool {
+        let Some(pkg) = self.packages.get(key) else {
+            return false;
+        };
+        let from_registry = pkg
+            .resolved
+            .as_deref()
+            .map_or(false, |resolved| resolved.starts_with("http"));
+        from_registry && !pkg.is_link() && !pkg.other.contains_key("integrity")
+    }
+
+    fn package_name(&self, key: &str) -> Option<String> {
+        key.rsplit_once("node_modules/")
+            .map(|(_, name)| name.to_string())
+    }
+
+    fn global_settings(&self) -> BTreeMap<&'static str, Value> {
+        let mut settings = BTreeMap::new();
+        settings.insert("lockfileVersion", self.lockfile_version.into());
+        if let Some(requires) = self.other.get("requires") {
+            settings.insert("requires", requires.clone());
+        }
+        settings
+    }
 }
 
 impl NpmLockfile {
@@ -157,7 +198,7 @@ impl NpmLockfile {
         })
     }
 
-    fn possible_npm_deps(key: &str, dep: &str) -> Vec<String> {
+    pub(crate) fn possible_npm_deps(key: &str, dep: &str) -> Vec<String> {
         let mut possible_deps = vec![format!("{key}/node_modules/{dep}")];
 
         let mut curr = Some(key);
@@ -184,12 +225,23 @@ impl NpmLockfile {
 }
 
 impl NpmPackage {
+    pub(crate) fn is_link(&self) -> bool {
+        matches!(self.other.get("link"), Some(Value::Bool(true)))
+    }
+
     pub fn dep_keys(&self) -> impl Iterator<Item = &String> {
         self.dependencies
             .keys()
             .chain(self.dev_dependencies.keys())
             .chain(self.optional_dependencies.keys())
     }
+
+    pub fn dep_entries(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.dependencies
+            .iter()
+            .chain(self.dev_dependencies.iter())
+            .chain(self.optional_dependencies.iter())
+    }
 }
 
 pub fn npm_subgraph(
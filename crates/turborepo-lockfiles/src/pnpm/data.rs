@@ -0,0 +1,320 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{
+    dep_path::{convert_new_to_old_dep_path, DepPath},
+    Error,
+};
+
+// We depend on BTree iteration being sorted for stable serialization
+type Map<K, V> = std::collections::BTreeMap<K, V>;
+
+// Reference https://github.com/pnpm/pnpm/blob/main/packages/lockfile-types/src/index.ts
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PnpmLockfile {
+    lockfile_version: LockfileVersion,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    never_built_dependencies: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    only_built_dependencies: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) overrides: Option<Map<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    package_extensions_checksum: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) patched_dependencies: Option<Map<String, PatchFile>>,
+    #[serde(default)]
+    pub(super) importers: Map<String, ProjectSnapshot>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) packages: Option<Map<String, PackageSnapshot>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    time: Option<Map<String, String>>,
+}
+
+// Before 6.0 the version was stored as a float, but as of 6.0+ it's a string
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+enum LockfileVersion {
+    Float(f64),
+    String(String),
+}
+
+// The only floats we encounter are 5.x versions, none of which are NaN
+impl Eq for LockfileVersion {}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone)]
+pub struct PatchFile {
+    pub(super) path: String,
+    pub(super) hash: String,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectSnapshot {
+    // Only present in pre-6.0 lockfiles, 6.0+ inlines the specifiers into the
+    // dependency entries.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    specifiers: Option<Map<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dependencies: Option<Map<String, Dependency>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    optional_dependencies: Option<Map<String, Dependency>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dev_dependencies: Option<Map<String, Dependency>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) dependencies_meta: Option<Map<String, DependenciesMeta>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    publish_directory: Option<String>,
+}
+
+// The importer dependency entries are a plain version string if the lockfile
+// version is <6.0 and a specifier/version pair if 6.0+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum Dependency {
+    Version(String),
+    Resolution { specifier: String, version: String },
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DependenciesMeta {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) injected: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    node: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    patch: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageSnapshot {
+    pub(super) resolution: PackageResolution,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+
+    // only needed for packages that aren't in npm
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) version: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    engines: Option<Map<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cpu: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    os: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    libc: Option<Vec<String>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    deprecated: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    has_bin: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    prepare: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    requires_build: Option<bool>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bundled_dependencies: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    peer_dependencies: Option<Map<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    peer_dependencies_meta: Option<Map<String, PeerDependencyMeta>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) dependencies: Option<Map<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) optional_dependencies: Option<Map<String, String>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    transitive_peer_dependencies: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dev: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    optional: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    patched: Option<bool>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Default)]
+pub struct PeerDependencyMeta {
+    optional: bool,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Default)]
+pub struct PackageResolution {
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    type_field: Option<String>,
+    // For npm or tarball
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) integrity: Option<String>,
+    // For tarball
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tarball: Option<String>,
+    // For local directory
+    #[serde(skip_serializing_if = "Option::is_none")]
+    directory: Option<String>,
+    // For git repo
+    #[serde(skip_serializing_if = "Option::is_none")]
+    repo: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    commit: Option<String>,
+}
+
+impl PackageResolution {
+    pub(super) fn missing_integrity(&self) -> bool {
+        self.type_field.is_none() && self.tarball.is_none() && self.integrity.is_none()
+    }
+}
+
+impl PnpmLockfile {
+    pub fn from_bytes(s: &[u8]) -> Result<Self, Error> {
+        serde_yaml::from_slice(s).map_err(Error::from)
+    }
+
+    pub(super) fn is_v6(&self) -> bool {
+        matches!(self.lockfile_version, LockfileVersion::String(_))
+    }
+
+    pub(super) fn is_supported_version(&self) -> bool {
+        match &self.lockfile_version {
+            LockfileVersion::Float(version) => *version == 5.3 || *version == 5.4,
+            LockfileVersion::String(version) => version == "6.0",
+        }
+    }
+
+    pub(super) fn version(&self) -> String {
+        match &self.lockfile_version {
+            LockfileVersion::Float(version) => version.to_string(),
+            LockfileVersion::String(version) => version.clone(),
+        }
+    }
+
+    pub(super) fn get_package(&self, key: &str) -> Option<&PackageSnapshot> {
+        self.packages
+            .as_ref()
+            .and_then(|packages| packages.get(key))
+    }
+
+    pub(super) fn has_package(&self, key: &str) -> bool {
+        self.get_package(key).is_some()
+    }
+
+    /// Formats a lockfile key given a package name and version
+    pub(super) fn format_key(&self, name: &str, version: &str) -> String {
+        match self.is_v6() {
+            true => format!("/{name}@{version}"),
+            false => format!("/{name}/{version}"),
+        }
+    }
+
+    /// Extracts the version from a lockfile key
+    pub(super) fn extract_version(&self, key: &str) -> String {
+        let key = match self.is_v6() {
+            // Internally pnpm partially converts the new path format to the old
+            // format in order for existing parsing logic to work.
+            true => convert_new_to_old_dep_path(key),
+            false => key.into(),
+        };
+        let dp = DepPath::parse(&key);
+        match dp.peer_suffix {
+            Some(suffix) if self.is_v6() => format!("{}{}", dp.version, suffix),
+            Some(suffix) => format!("{}_{}", dp.version, suffix),
+            None => dp.version.to_string(),
+        }
+    }
+
+    /// Extracts the package name from a lockfile key
+    pub(super) fn extract_name(&self, key: &str) -> Option<String> {
+        let key = match self.is_v6() {
+            true => convert_new_to_old_dep_path(key),
+            false => key.into(),
+        };
+        let dp = DepPath::parse(&key);
+        (!dp.name.is_empty()).then(|| dp.name.into_owned())
+    }
+
+    /// Top level settings that apply to every package in the lockfile
+    pub(super) fn settings(&self) -> Map<&'static str, Value> {
+        let mut settings = Map::new();
+        let mut insert = |name, value: Result<Value, _>| {
+            if let Ok(value) = value {
+                if !value.is_null() {
+                    settings.insert(name, value);
+                }
+            }
+        };
+        insert(
+            "lockfileVersion",
+            serde_json::to_value(&self.lockfile_version),
+        );
+        insert("overrides", serde_json::to_value(&self.overrides));
+        insert(
+            "packageExtensionsChecksum",
+            serde_json::to_value(&self.package_extensions_checksum),
+        );
+        insert(
+            "patchedDependencies",
+            serde_json::to_value(&self.patched_dependencies),
+        );
+        settings
+    }
+
+    /// Constructs a new lockfile that shares all top level settings with self,
+    /// but only contains the given importers and packages
+    pub(super) fn with_entries(
+        &self,
+        importers: Map<String, ProjectSnapshot>,
+        packages: Map<String, PackageSnapshot>,
+        patched_dependencies: Option<Map<String, PatchFile>>,
+    ) -> Self {
+        Self {
+            lockfile_version: self.lockfile_version.clone(),
+            never_built_dependencies: self.never_built_dependencies.clone(),
+            only_built_dependencies: self.only_built_dependencies.clone(),
+            overrides: self.overrides.clone(),
+            package_extensions_checksum: self.package_extensions_checksum.clone(),
+            patched_dependencies,
+            importers,
+            packages: Some(packages),
+            time: None,
+        }
+    }
+
+    /// Checks if there are any differences between lockfiles that would
+    /// completely invalidate the cache
+    pub(super) fn global_change(&self, other: &Self) -> bool {
+        self.lockfile_version != other.lockfile_version
+            || self.package_extensions_checksum != other.package_extensions_checksum
+            || self.overrides != other.overrides
+            || self.patched_dependencies != other.patched_dependencies
+    }
+}
+
+impl ProjectSnapshot {
+    /// Finds the resolution of a dependency in any of the importer's dependency
+    /// fields
+    pub(super) fn find_resolution(&self, dependency: &str) -> Option<(&str, &str)> {
+        let entry = [
+            &self.dependencies,
+            &self.dev_dependencies,
+            &self.optional_dependencies,
+        ]
+        .into_iter()
+        .flatten()
+        .find_map(|deps| deps.get(dependency))?;
+
+        match entry {
+            Dependency::Version(version) => {
+                let specifier = self.specifiers.as_ref()?.get(dependency)?;
+                Some((specifier, version))
+            }
+            Dependency::Resolution { specifier, version } => Some((specifier, version)),
+        }
+    }
+}
@@ -0,0 +1,220 @@
+use std::borrow::Cow;
+
+const PATCH_HASH_KEY: &str = "patch_hash=";
+
+/// Parsed representation of a pnpm lockfile key
+#[derive(Debug, PartialEq, Eq, Default, Clone)]
+pub struct DepPath<'a> {
+    pub host: Option<&'a str>,
+    pub name: Cow<'a, str>,
+    pub version: &'a str,
+    pub peer_suffix: Option<&'a str>,
+}
+
+impl<'a> DepPath<'a> {
+    // See https://github.com/pnpm/pnpm/blob/185ab01adfc927ea23d2db08a14723bf51d0025f/packages/dependency-path/src/index.ts#L96
+    pub fn parse(dependency: &'a str) -> Self {
+        let mut dp = DepPath::default();
+        let (host, path) = match dependency.strip_prefix('/') {
+            Some(path) => (None, path),
+            None => match dependency.split_once('/') {
+                Some((host, path)) => (Some(host), path),
+                None => (Some(dependency), ""),
+            },
+        };
+        dp.host = host;
+
+        if path.is_empty() {
+            return dp;
+        }
+
+        let (name, version) = match path.split_once('/') {
+            // Scoped packages have their scope and name separated by a '/'
+            Some((scope, rest)) if scope.starts_with('@') => {
+                let (name, version) = rest.split_once('/').unwrap_or((rest, ""));
+                (Cow::Owned(format!("{scope}/{name}")), version)
+            }
+            Some((name, version)) => (Cow::Borrowed(name), version),
+            None => (Cow::Borrowed(path), ""),
+        };
+        dp.name = name;
+
+        if version.is_empty() {
+            return dp;
+        }
+
+        if let (Some(index), true) = (version.find('('), version.ends_with(')')) {
+            // v6 encodes peers deps using (peer=version)
+            // also used to encode patches using (path_hash=hash)
+            dp.version = &version[..index];
+            dp.peer_suffix = Some(&version[index..]);
+        } else if let Some((version, suffix)) = version.split_once('_') {
+            // pre v6 uses _ to separate version from peer dependencies
+            // if a dependency is patched and has peer dependencies its version will
+            // be encoded as version_patchHash_peerDepsHash
+            dp.version = version;
+            dp.peer_suffix = Some(suffix);
+        } else {
+            dp.version = version;
+        }
+
+        dp
+    }
+
+    pub fn patch_hash(&self) -> Option<&'a str> {
+        let suffix = self.peer_suffix?;
+        if suffix.starts_with('(') && suffix.ends_with(')') {
+            return suffix
+                .split('(')
+                .find_map(|part| part.strip_prefix(PATCH_HASH_KEY))
+                // drop the enclosing ')'
+                .map(|hash| &hash[..hash.len() - 1]);
+        }
+
+        match suffix.split_once('_') {
+            Some((patch_hash, _)) => Some(patch_hash),
+            // if a dependency just has a single suffix we can't tell if it's a patch or
+            // peer hash, return it in case it's a patch hash
+            None => Some(suffix),
+        }
+    }
+}
+
+// Used to convert v6's dep path of /name@version to v5's /name/version
+// See https://github.com/pnpm/pnpm/blob/185ab01adfc927ea23d2db08a14723bf51d0025f/lockfile/lockfile-file/src/experiments/inlineSpecifiersLockfileConverters.ts#L162
+pub fn convert_new_to_old_dep_path(new_path: &str) -> Cow<str> {
+    // Step over characters rather than bytes so a multi-byte leading character
+    // can't land a slice inside of it
+    let mut chars = new_path.chars();
+    chars.next();
+    let after_first = chars.as_str();
+    chars.next();
+    let after_second = chars.as_str();
+    if !after_second.is_empty() && !after_second.contains('@') {
+        return new_path.into();
+    }
+    let search_start = new_path
+        .find("/@")
+        .map_or(new_path.len() - after_first.len(), |index| index + 2);
+    let Some(index) = new_path
+        .get(search_start..)
+        .and_then(|path| path.find('@'))
+        .map(|index| index + search_start)
+    else {
+        return new_path.into();
+    };
+    if matches!(new_path.find('('), Some(paren_index) if index > paren_index) {
+        return new_path.into();
+    }
+    format!("{}/{}", &new_path[..index], &new_path[index + 1..]).into()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_dep_path_parsing() {
+        let tests = [
+            (
+                "/foo/1.0.0",
+                DepPath {
+                    name: "foo".into(),
+                    version: "1.0.0",
+                    ..Default::default()
+                },
+            ),
+            (
+                "/@foo/bar/1.0.0",
+                DepPath {
+                    name: "@foo/bar".into(),
+                    version: "1.0.0",
+                    ..Default::default()
+                },
+            ),
+            (
+                "example.org/foo/1.0.0",
+                DepPath {
+                    host: Some("example.org"),
+                    name: "foo".into(),
+                    version: "1.0.0",
+                    ..Default::default()
+                },
+            ),
+            (
+                "/foo/1.0.0_bar@1.0.0",
+                DepPath {
+                    name: "foo".into(),
+                    version: "1.0.0",
+                    peer_suffix: Some("bar@1.0.0"),
+                    ..Default::default()
+                },
+            ),
+            (
+                "/foo/1.0.0(bar@1.0.0)",
+                DepPath {
+                    name: "foo".into(),
+                    version: "1.0.0",
+                    peer_suffix: Some("(bar@1.0.0)"),
+                    ..Default::default()
+                },
+            ),
+            (
+                "/foo/1.0.0_patchHash_peerHash",
+                DepPath {
+                    name: "foo".into(),
+                    version: "1.0.0",
+                    peer_suffix: Some("patchHash_peerHash"),
+                    ..Default::default()
+                },
+            ),
+            (
+                "/@babel/helper-string-parser/7.19.4(patch_hash=wjhgmpzh47qmycrzgpeyoyh3ce)(@\
+                 babel/core@7.21.0)",
+                DepPath {
+                    name: "@babel/helper-string-parser".into(),
+                    version: "7.19.4",
+                    peer_suffix: Some(
+                        "(patch_hash=wjhgmpzh47qmycrzgpeyoyh3ce)(@babel/core@7.21.0)",
+                    ),
+                    ..Default::default()
+                },
+            ),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(DepPath::parse(input), expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn test_convert_new_to_old() {
+        let tests = [
+            ("/foo@1.0.0", "/foo/1.0.0"),
+            ("/@foo/bar@1.0.0", "/@foo/bar/1.0.0"),
+            ("/foo@1.0.0(bar@1.0.0)", "/foo/1.0.0(bar@1.0.0)"),
+            ("/foo/1.0.0", "/foo/1.0.0"),
+            ("日本@1.0.0", "日本/1.0.0"),
+            ("é", "é"),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(convert_new_to_old_dep_path(input), expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn test_patch_hash() {
+        let tests = [
+            ("/foo/1.0.0_patchHash_peerHash", Some("patchHash")),
+            ("/foo/1.0.0_hash", Some("hash")),
+            ("/foo/1.0.0(patch_hash=abc)(bar@1.0.0)", Some("abc")),
+            ("/foo/1.0.0(bar@1.0.0)", None),
+            ("/foo/1.0.0", None),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(DepPath::parse(input).patch_hash(), expected, "{input}");
+        }
+    }
+}
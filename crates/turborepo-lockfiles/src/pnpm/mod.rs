@@ -0,0 +1,664 @@
+mod data;
+mod dep_path;
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+pub use data::PnpmLockfile;
+use serde_json::Value;
+use thiserror::Error;
+
+use self::dep_path::{convert_new_to_old_dep_path, DepPath};
+use super::{Lockfile, Package};
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("unable to parse yaml: {0}")]
+    Parse(#[from] serde_yaml::Error),
+    #[error("Unable to find resolved version for {name}@{specifier} in {workspace}")]
+    MissingResolvedVersion {
+        name: String,
+        specifier: String,
+        workspace: String,
+    },
+    #[error("Unable to find lockfile entry for {0}")]
+    MissingPackage(String),
+    #[error("Unable to find {0} other than reference in dependenciesMeta")]
+    MissingInjectedPackage(String),
+    #[error(
+        "Unable to generate pnpm-lock.yaml with lockfileVersion: {0}. Supported lockfile versions \
+         are 5.3, 5.4, and 6.0"
+    )]
+    UnsupportedVersion(String),
+}
+
+impl PnpmLockfile {
+    pub fn load(content: &[u8]) -> Result<Self, Error> {
+        Self::from_bytes(content)
+    }
+
+    /// All patch files referenced in the lockfile
+    pub fn patches(&self) -> Vec<&str> {
+        let mut patches = self
+            .patched_dependencies
+            .iter()
+            .flatten()
+            .map(|(_, patch)| patch.path.as_str())
+            .collect::<Vec<_>>();
+        patches.sort();
+        patches
+    }
+
+    /// Produces a new lockfile containing only the given workspaces and
+    /// packages
+    pub fn subgraph(
+        &self,
+        workspace_packages: &[String],
+        packages: &[String],
+    ) -> Result<Self, Error> {
+        let mut pruned_packages = BTreeMap::new();
+        for key in packages {
+            let entry = self
+                .get_package(key)
+                .ok_or_else(|| Error::MissingPackage(key.clone()))?;
+            pruned_packages.insert(key.clone(), entry.clone());
+        }
+
+        let mut pruned_importers = BTreeMap::new();
+        // Copy over root level importer
+        if let Some(root) = self.importers.get(".") {
+            pruned_importers.insert(".".to_string(), root.clone());
+        }
+        for workspace in workspace_packages {
+            // pnpm always uses unix separators for importer keys
+            let workspace = workspace.replace('\\', "/");
+            // If a workspace has no dependencies *and* it is only depended on by the
+            // workspace root it will not show up as an importer.
+            if let Some(importer) = self.importers.get(&workspace) {
+                pruned_importers.insert(workspace, importer.clone());
+            }
+        }
+
+        for importer in pruned_importers.values() {
+            for (dependency, meta) in importer.dependencies_meta.iter().flatten() {
+                if meta.injected != Some(true) {
+                    continue;
+                }
+                let (_, version) = importer
+                    .find_resolution(dependency)
+                    .ok_or_else(|| Error::MissingInjectedPackage(dependency.clone()))?;
+                let entry = self
+                    .get_package(version)
+                    .ok_or_else(|| Error::MissingPackage(version.to_string()))?;
+                pruned_packages.insert(version.to_string(), entry.clone());
+            }
+        }
+
+        let patches = self.prune_patches(&pruned_packages);
+        Ok(self.with_entries(pruned_importers, pruned_packages, patches))
+    }
+
+    fn prune_patches(
+        &self,
+        packages: &BTreeMap<String, data::PackageSnapshot>,
+    ) -> Option<BTreeMap<String, data::PatchFile>> {
+        let patches = self.patched_dependencies.as_ref()?;
+        if patches.is_empty() {
+            return None;
+        }
+
+        let mut pruned_patches = BTreeMap::new();
+        for dependency in packages.keys() {
+            let dependency = match self.is_v6() {
+                // Internally pnpm partially converts the new path format to the old
+                // format in order for existing parsing logic to work.
+                true => convert_new_to_old_dep_path(dependency),
+                false => dependency.into(),
+            };
+            let dp = DepPath::parse(&dependency);
+            let patch_key = format!("{}@{}", dp.name, dp.version);
+
+            if let Some(patch) = patches
+                .get(&patch_key)
+                .filter(|patch| Some(patch.hash.as_str()) == dp.patch_hash())
+            {
+                pruned_patches.insert(patch_key, patch.clone());
+            }
+        }
+
+        Some(pruned_patches)
+    }
+
+    /// Serializes the lockfile, checking that the version is one we know how
+    /// to write.
+    pub fn encode(&self) -> Result<Vec<u8>, Error> {
+        if !self.is_supported_version() {
+            return Err(Error::UnsupportedVersion(self.version()));
+        }
+        Ok(serde_yaml::to_string(self)?.into_bytes())
+    }
+
+    fn resolve_specifier(
+        &self,
+        workspace_path: &str,
+        name: &str,
+        specifier: &str,
+    ) -> Result<Option<String>, crate::Error> {
+        // For pnpm, the root is named "."
+        let pnpm_workspace_path = match workspace_path {
+            "" => ".",
+            path => path,
+        };
+        let importer = self
+            .importers
+            .get(pnpm_workspace_path)
+            .ok_or_else(|| crate::Error::MissingWorkspace(workspace_path.to_string()))?;
+
+        let Some((resolved_specifier, resolved_version)) = importer.find_resolution(name) else {
+            // Check if the specifier is already a resolved version
+            if self.has_package(&self.format_key(name, specifier)) {
+                return Ok(Some(specifier.to_string()));
+            }
+            return Err(Error::MissingResolvedVersion {
+                name: name.to_string(),
+                specifier: specifier.to_string(),
+                workspace: workspace_path.to_string(),
+            }
+            .into());
+        };
+
+        // Verify that the specifier in the importer matches the one given
+        let override_specifier = self.apply_overrides(name, specifier);
+        if resolved_specifier != override_specifier {
+            if self.has_package(&self.format_key(name, override_specifier)) {
+                return Ok(Some(override_specifier.to_string()));
+            }
+            return Ok(None);
+        }
+
+        Ok(Some(resolved_version.to_string()))
+    }
+
+    // Apply pnpm overrides to specifier, see https://pnpm.io/package_json#pnpmoverrides
+    // Note this is barebones support and will only supports global overrides
+    // future work will support semver ranges and selector filtering.
+    fn apply_overrides<'a>(&'a self, name: &str, specifier: &'a str) -> &'a str {
+        self.overrides
+            .as_ref()
+            .and_then(|overrides| overrides.get(name))
+            .map(|s| s.as_str())
+            .unwrap_or(specifier)
+    }
+}
+
+impl Lockfile for PnpmLockfile {
+    fn resolve_package(
+        &self,
+        workspace_path: &str,
+        name: &str,
+        version: &str,
+    ) -> Result<Option<Package>, crate::Error> {
+        // Check if version is a key
+        if self.has_package(version) {
+            return Ok(Some(Package {
+                key: version.to_string(),
+                version: self.extract_version(version),
+            }));
+        }
+
+        let Some(resolved_version) = self.resolve_specifier(workspace_path, name, version)? else {
+            return Ok(None);
+        };
+
+        let key = self.format_key(name, &resolved_version);
+        if let Some(entry) = self.get_package(&key) {
+            let version = entry.version.clone().unwrap_or(resolved_version);
+            return Ok(Some(Package { key, version }));
+        }
+
+        if let Some(entry) = self.get_package(&resolved_version) {
+            // If there isn't a version field in the entry then the version is
+            // encoded in the key and we can omit the name from the version.
+            let version = entry
+                .version
+                .clone()
+                .unwrap_or_else(|| self.extract_version(&resolved_version));
+            return Ok(Some(Package {
+                key: resolved_version,
+                version,
+            }));
+        }
+
+        Ok(None)
+    }
+
+    fn package_keys(&self) -> Vec<String> {
+        self.packages
+            .iter()
+            .flatten()
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+
+    fn missing_integrity(&self, key: &str) -> bool {
+        // Only registry packages are expected to have an integrity, tarballs,
+        // directories, and git repos are resolved by other means.
+        self.get_package(key)
+            .map_or(false, |entry| entry.resolution.missing_integrity())
+    }
+
+    fn all_dependencies(&self, key: &str) -> Result<Option<HashMap<String, String>>, crate::Error> {
+        let Some(entry) = self.get_package(key) else {
+            return Ok(None);
+        };
+
+        // Peer dependencies appear in the dependencies map already resolved
+        Ok(Some(
+            entry
+                .dependencies
+                .iter()
+                .flatten()
+                .chain(entry.optional_dependencies.iter().flatten())
+                .map(|(name, version)| (name.clone(), version.clone()))
+                .collect(),
+        ))
+    }
+
+    fn workspace_scoped_resolution(&self) -> bool {
+        // Dependencies of external packages whose version isn't a lockfile key
+        // are resolved through the importer of the workspace. An importer that
+        // lists the same package with the same specifier resolves it to its
+        // own version which can differ between importers.
+        let unkeyed_deps = self
+            .packages
+            .iter()
+            .flatten()
+            .flat_map(|(_, entry)| {
+                entry
+                    .dependencies
+                    .iter()
+                    .flatten()
+                    .chain(entry.optional_dependencies.iter().flatten())
+            })
+            .filter(|(_, version)| !self.has_package(version))
+            .collect::<HashSet<_>>();
+        unkeyed_deps.into_iter().any(|(name, version)| {
+            let specifier = self.apply_overrides(name, version);
+            specifier != version
+                || self.importers.values().any(|importer| {
+                    matches!(
+                        importer.find_resolution(name),
+                        Some((resolved_specifier, resolved_version))
+                            if resolved_specifier == specifier && resolved_version != specifier
+                    )
+                })
+        })
+    }
+
+    fn package_name(&self, key: &str) -> Option<String> {
+        self.extract_name(key)
+    }
+
+    fn global_settings(&self) -> BTreeMap<&'static str, Value> {
+        self.settings()
+    }
+}
+
+pub fn pnpm_subgraph(
+    contents: &[u8],
+    workspace_packages: &[String],
+    packages: &[String],
+) -> Result<Vec<u8>, Error> {
+    let lockfile = PnpmLockfile::load(contents)?;
+    let pruned_lockfile = lockfile.subgraph(workspace_packages, packages)?;
+    pruned_lockfile.encode()
+}
+
+pub fn pnpm_global_change(prev_contents: &[u8], curr_contents: &[u8]) -> Result<bool, Error> {
+    let prev_lockfile = PnpmLockfile::load(prev_contents)?;
+    let curr_lockfile = PnpmLockfile::load(curr_contents)?;
+    Ok(prev_lockfile.global_change(&curr_lockfile))
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::transitive_closure;
+
+    const PNPM6: &[u8] = include_bytes!("../../fixtures/pnpm6-workspace.yaml");
+    const PNPM7: &[u8] = include_bytes!("../../fixtures/pnpm7-workspace.yaml");
+    const PNPM8: &[u8] = include_bytes!("../../fixtures/pnpm8.yaml");
+    const PNPM_ABSOLUTE: &[u8] = include_bytes!("../../fixtures/pnpm-absolute.yaml");
+    const PNPM_ABSOLUTE_V6: &[u8] = include_bytes!("../../fixtures/pnpm-absolute-v6.yaml");
+    const PNPM_PEER: &[u8] = include_bytes!("../../fixtures/pnpm-peer-v6.yaml");
+    const PNPM_TOP_LEVEL_OVERRIDE: &[u8] =
+        include_bytes!("../../fixtures/pnpm-top-level-dupe.yaml");
+    const PNPM_OVERRIDE: &[u8] = include_bytes!("../../fixtures/pnpm_override.yaml");
+    const PNPM_PATCH: &[u8] = include_bytes!("../../fixtures/pnpm-patch.yaml");
+    const PNPM_PATCH_V6: &[u8] = include_bytes!("../../fixtures/pnpm-patch-v6.yaml");
+
+    #[test]
+    fn test_roundtrip() {
+        for fixture in [PNPM6, PNPM7, PNPM8] {
+            let lockfile = PnpmLockfile::load(fixture).unwrap();
+            let serialized_lockfile = lockfile.encode().unwrap();
+            let lockfile_from_serialized = PnpmLockfile::load(&serialized_lockfile).unwrap();
+            assert_eq!(lockfile, lockfile_from_serialized);
+        }
+    }
+
+    #[test]
+    fn test_version_format() {
+        let v5 = PnpmLockfile::load(PNPM7).unwrap();
+        assert!(!v5.is_v6());
+        assert_eq!(v5.version(), "5.4");
+        let v6 = PnpmLockfile::load(PNPM8).unwrap();
+        assert!(v6.is_v6());
+        assert_eq!(v6.version(), "6.0");
+        let serialized = String::from_utf8(v6.encode().unwrap()).unwrap();
+        assert!(serialized.starts_with("lockfileVersion: '6.0'"));
+    }
+
+    #[test]
+    fn test_package_name() {
+        let v5 = PnpmLockfile::load(PNPM7).unwrap();
+        assert_eq!(v5.package_name("/is-odd/3.0.1").as_deref(), Some("is-odd"));
+        assert_eq!(
+            v5.package_name("/@babel/core/7.20.0_react@18.2.0")
+                .as_deref(),
+            Some("@babel/core")
+        );
+        let v6 = PnpmLockfile::load(PNPM8).unwrap();
+        assert_eq!(
+            v6.package_name("/@babel/core@7.20.0(react@18.2.0)")
+                .as_deref(),
+            Some("@babel/core")
+        );
+    }
+
+    #[test]
+    fn test_specifier_resolution() {
+        let lockfile = PnpmLockfile::load(PNPM7).unwrap();
+
+        let tests = [
+            (
+                "apps/docs",
+                "next",
+                "12.2.5",
+                Some("12.2.5_ir3quccc6i62x6qn6jjhyjjiey"),
+            ),
+            (
+                "apps/web",
+                "next",
+                "12.2.5",
+                Some("12.2.5_ir3quccc6i62x6qn6jjhyjjiey"),
+            ),
+            ("apps/web", "typescript", "^4.5.3", Some("4.8.3")),
+            ("apps/web", "lodash", "bad-tag", None),
+            (
+                "apps/web",
+                "lodash",
+                "^4.17.21",
+                Some("4.17.21_ehchni3mpmovsvjxesffg2i5a4"),
+            ),
+            (
+                "apps/docs",
+                "dashboard-icons",
+                "github:peerigon/dashboard-icons",
+                Some(
+                    "github.com/peerigon/dashboard-icons/ce27ef933144e09cef3911025f3649040a8571b6",
+                ),
+            ),
+            ("", "turbo", "latest", Some("1.4.6")),
+        ];
+
+        for (workspace, name, specifier, expected) in tests {
+            let actual = lockfile
+                .resolve_specifier(workspace, name, specifier)
+                .unwrap();
+            assert_eq!(actual.as_deref(), expected, "{name}@{specifier}");
+        }
+
+        let err = lockfile
+            .resolve_specifier("apps/bad_workspace", "turbo", "latest")
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Workspace 'apps/bad_workspace' not found in lockfile"
+        );
+    }
+
+    #[test]
+    fn test_specifier_resolution_v6() {
+        let lockfile = PnpmLockfile::load(PNPM8).unwrap();
+
+        let tests = [
+            ("packages/a", "c", "workspace:*", Some("link:../c")),
+            ("packages/a", "is-odd", "^3.0.1", Some("3.0.1")),
+        ];
+        for (workspace, name, specifier, expected) in tests {
+            let actual = lockfile
+                .resolve_specifier(workspace, name, specifier)
+                .unwrap();
+            assert_eq!(actual.as_deref(), expected, "{name}@{specifier}");
+        }
+
+        let err = lockfile
+            .resolve_specifier("packages/b", "is-odd", "^3.0.1")
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unable to find resolved version for is-odd@^3.0.1 in packages/b"
+        );
+    }
+
+    #[test]
+    fn test_subgraph_injected_packages() {
+        let lockfile = PnpmLockfile::load(PNPM7).unwrap();
+        let pruned = lockfile.subgraph(&["apps/docs".into()], &[]).unwrap();
+        assert!(
+            pruned.has_package("file:packages/ui"),
+            "pruned lockfile is missing injected package"
+        );
+    }
+
+    #[test]
+    fn test_git_package() {
+        let lockfile = PnpmLockfile::load(PNPM7).unwrap();
+        let pkg = lockfile
+            .resolve_package(
+                "apps/docs",
+                "dashboard-icons",
+                "github:peerigon/dashboard-icons",
+            )
+            .unwrap();
+        assert_eq!(
+            pkg,
+            Some(Package {
+                key: "github.com/peerigon/dashboard-icons/ce27ef933144e09cef3911025f3649040a8571b6"
+                    .into(),
+                version: "1.0.0".into(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_unquoted_url_resolution() {
+        let resolution: data::PackageResolution =
+            serde_yaml::from_str("{integrity: sha512-deadbeef, tarball: path/to/tarball?foo=bar}")
+                .unwrap();
+        let serialized = serde_yaml::to_string(&resolution).unwrap();
+        assert!(serialized.contains("tarball: path/to/tarball?foo=bar"));
+    }
+
+    #[test]
+    fn test_patches() {
+        let lockfile = PnpmLockfile::load(PNPM_PATCH).unwrap();
+        let patches = lockfile.patches();
+        assert_eq!(
+            patches,
+            vec![
+                "patches/@babel__core@7.20.12.patch",
+                "patches/is-odd@3.0.1.patch",
+                "patches/moleculer@0.14.28.patch",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_prune_patches() {
+        let lockfile = PnpmLockfile::load(PNPM_PATCH).unwrap();
+        let pruned = lockfile
+            .subgraph(
+                &["packages/dependency".into()],
+                &[
+                    "/is-odd/3.0.1_nrrwwz7lemethtlvvm75r5bmhq".into(),
+                    "/is-number/6.0.0".into(),
+                    "/@babel/core/7.20.12_3hyn7hbvzkemudbydlwjmrb65y".into(),
+                    "/moleculer/0.14.28_5pk7ojv7qbqha75ozglk4y4f74_kumip57h7zlinbhp4gz3jrbqry"
+                        .into(),
+                ],
+            )
+            .unwrap();
+        assert_eq!(pruned.patches().len(), 3);
+    }
+
+    #[test]
+    fn test_prune_patches_v6() {
+        let lockfile = PnpmLockfile::load(PNPM_PATCH_V6).unwrap();
+        let pruned = lockfile
+            .subgraph(
+                &["packages/a".into()],
+                &["/lodash@4.17.21(patch_hash=lgum37zgng4nfkynzh3cs7wdeq)".into()],
+            )
+            .unwrap();
+        assert_eq!(pruned.patches(), vec!["patches/lodash@4.17.21.patch"]);
+
+        let pruned =
+            lockfile
+                .subgraph(
+                    &["packages/b".into()],
+                    &["/@babel/helper-string-parser@7.19.\
+                       4(patch_hash=wjhgmpzh47qmycrzgpeyoyh3ce)(@babel/core@7.21.0)"
+                        .into()],
+                )
+                .unwrap();
+        assert_eq!(
+            pruned.patches(),
+            vec!["patches/@babel__helper-string-parser@7.19.4.patch"]
+        );
+    }
+
+    #[test]
+    fn test_absolute_dependency() {
+        let tests = [
+            (PNPM_ABSOLUTE, "/@scope/child/1.0.0"),
+            (PNPM_ABSOLUTE_V6, "/@scope/child@1.0.0"),
+        ];
+        for (fixture, key) in tests {
+            let lockfile = PnpmLockfile::load(fixture).unwrap();
+            let pkg = lockfile
+                .resolve_package("packages/a", "child", key)
+                .unwrap();
+            assert_eq!(
+                pkg,
+                Some(Package {
+                    key: key.into(),
+                    version: "1.0.0".into()
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn test_peer_suffix_key() {
+        let lockfile = PnpmLockfile::load(PNPM_PEER).unwrap();
+        let pkg = lockfile
+            .resolve_package("apps/web", "next", "13.0.4")
+            .unwrap();
+        assert_eq!(
+            pkg,
+            Some(Package {
+                key: "/next@13.0.4(react-dom@18.2.0)(react@18.2.0)".into(),
+                version: "13.0.4(react-dom@18.2.0)(react@18.2.0)".into(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_top_level_dupe() {
+        let lockfile = PnpmLockfile::load(PNPM_TOP_LEVEL_OVERRIDE).unwrap();
+        let pkg = lockfile
+            .resolve_package("packages/a", "ci-info", "3.7.1")
+            .unwrap();
+        assert_eq!(
+            pkg,
+            Some(Package {
+                key: "/ci-info/3.7.1".into(),
+                version: "3.7.1".into(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_override() {
+        let lockfile = PnpmLockfile::load(PNPM_OVERRIDE).unwrap();
+        let pkg = lockfile
+            .resolve_package(
+                "config/hardhat",
+                "@nomiclabs/hardhat-ethers",
+                "npm:hardhat-deploy-ethers@0.3.0-beta.13",
+            )
+            .unwrap();
+        assert_eq!(
+            pkg,
+            Some(Package {
+                key: "/hardhat-deploy-ethers/0.3.0-beta.13_yab2ug5tvye2kp6e24l5x3z7uy".into(),
+                version: "0.3.0-beta.13_yab2ug5tvye2kp6e24l5x3z7uy".into(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_aliases_overlap() {
+        let lockfile = PnpmLockfile::load(PNPM_ABSOLUTE).unwrap();
+        let closure = transitive_closure(
+            &lockfile,
+            "packages/a",
+            [
+                ("@scope/parent", "^1.0.0"),
+                ("another", "^1.0.0"),
+                ("special", "npm:Special@1.2.3"),
+            ]
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect(),
+        )
+        .unwrap();
+
+        let expected = [
+            ("/@scope/child/1.0.0", "1.0.0"),
+            ("/@scope/parent/1.0.0", "1.0.0"),
+            ("/Special/1.2.3", "1.2.3"),
+            ("/another/1.0.0", "1.0.0"),
+            ("/foo/1.0.0", "1.0.0"),
+        ]
+        .iter()
+        .map(|(key, version)| Package {
+            key: key.to_string(),
+            version: version.to_string(),
+        })
+        .collect::<HashSet<_>>();
+        assert_eq!(closure, expected);
+    }
+
+    #[test]
+    fn test_global_change() {
+        assert!(!pnpm_global_change(PNPM7, PNPM7).unwrap());
+        assert!(pnpm_global_change(PNPM7, PNPM8).unwrap());
+        assert!(pnpm_global_change(PNPM_PATCH, PNPM7).unwrap());
+    }
+}
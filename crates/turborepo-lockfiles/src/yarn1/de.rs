@@ -0,0 +1,248 @@
+use serde_json::{Map, Value};
+
+use super::Error;
+
+// A minimal port of the Yarn 1 lockfile tokenizer and parser.
+// See https://github.com/yarnpkg/yarn/blob/master/src/lockfile/parse.js
+#[derive(Debug, PartialEq, Clone)]
+enum Token {
+    String(String),
+    Boolean(bool),
+    Number(u64),
+    Colon,
+    Comma,
+}
+
+struct Line {
+    number: usize,
+    indent: usize,
+    tokens: Vec<Token>,
+}
+
+/// Parses the contents of a yarn.lock into a list of top level entries.
+/// Keys that are listed together share the same entry.
+pub fn parse(contents: &str) -> Result<Vec<(Vec<String>, Value)>, Error> {
+    let lines = tokenize(contents)?;
+    let mut index = 0;
+    parse_object(&lines, &mut index, 0)
+}
+
+fn tokenize(contents: &str) -> Result<Vec<Line>, Error> {
+    let mut lines = Vec::new();
+    for (number, line) in contents.lines().enumerate() {
+        // Line numbers are 1-indexed for error messages
+        let number = number + 1;
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        let content = line.trim_start_matches(' ');
+        if content.is_empty() || content.starts_with('#') {
+            continue;
+        }
+        let spaces = line.len() - content.len();
+        if spaces % 2 != 0 {
+            return Err(Error::Indentation(number));
+        }
+        let tokens = tokenize_line(content, number)?;
+        if tokens.is_empty() {
+            continue;
+        }
+        lines.push(Line {
+            number,
+            indent: spaces / 2,
+            tokens,
+        });
+    }
+    Ok(lines)
+}
+
+fn tokenize_line(mut input: &str, line: usize) -> Result<Vec<Token>, Error> {
+    let mut tokens = Vec::new();
+    while let Some(c) = input.chars().next() {
+        match c {
+            ' ' | '\t' => {
+                input = &input[1..];
+            }
+            '#' => break,
+            ':' => {
+                tokens.push(Token::Colon);
+                input = &input[1..];
+            }
+            ',' => {
+                tokens.push(Token::Comma);
+                input = &input[1..];
+            }
+            '"' => {
+                let end = closing_quote(input).ok_or(Error::UnterminatedString(line))?;
+                let value: String =
+                    serde_json::from_str(&input[..=end]).map_err(|_| Error::InvalidString(line))?;
+                tokens.push(Token::String(value));
+                input = &input[end + 1..];
+            }
+            _ if input.starts_with("true") => {
+                tokens.push(Token::Boolean(true));
+                input = &input[4..];
+            }
+            _ if input.starts_with("false") => {
+                tokens.push(Token::Boolean(false));
+                input = &input[5..];
+            }
+            '0'..='9' => {
+                let end = input
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(input.len());
+                let value = input[..end]
+                    .parse()
+                    .map_err(|_| Error::UnexpectedCharacter(c, line))?;
+                tokens.push(Token::Number(value));
+                input = &input[end..];
+            }
+            'a'..='z' | 'A'..='Z' | '/' | '.' | '-' => {
+                let end = input
+                    .find(|c| matches!(c, ':' | ' ' | ','))
+                    .unwrap_or(input.len());
+                tokens.push(Token::String(input[..end].to_string()));
+                input = &input[end..];
+            }
+            c => return Err(Error::UnexpectedCharacter(c, line)),
+        }
+    }
+    Ok(tokens)
+}
+
+// Finds the index of the quote that closes the string starting at the
+// beginning of input
+fn closing_quote(input: &str) -> Option<usize> {
+    let mut escaped = false;
+    for (i, c) in input.char_indices().skip(1) {
+        match c {
+            '\\' if !escaped => escaped = true,
+            '"' if !escaped => return Some(i),
+            _ => escaped = false,
+        }
+    }
+    None
+}
+
+fn parse_object(
+    lines: &[Line],
+    index: &mut usize,
+    indent: usize,
+) -> Result<Vec<(Vec<String>, Value)>, Error> {
+    let mut entries = Vec::new();
+    while let Some(line) = lines.get(*index) {
+        if line.indent < indent {
+            break;
+        }
+        if line.indent > indent {
+            return Err(Error::Indentation(line.number));
+        }
+        *index += 1;
+
+        let mut tokens = line.tokens.iter();
+        let mut keys = Vec::new();
+        let value = loop {
+            match tokens.next() {
+                Some(Token::String(key)) => keys.push(key.clone()),
+                _ => return Err(Error::ExpectedKey(line.number)),
+            }
+            match tokens.next() {
+                Some(Token::Comma) => continue,
+                Some(Token::Colon) => {
+                    let nested = parse_object(lines, index, indent + 1)?;
+                    break Value::Object(into_map(nested));
+                }
+                Some(Token::String(value)) => break Value::String(value.clone()),
+                Some(Token::Boolean(value)) => break Value::Bool(*value),
+                Some(Token::Number(value)) => break Value::Number((*value).into()),
+                None => return Err(Error::ExpectedValue(line.number)),
+            }
+        };
+        if tokens.next().is_some() {
+            return Err(Error::TrailingTokens(line.number));
+        }
+        entries.push((keys, value));
+    }
+    Ok(entries)
+}
+
+fn into_map(entries: Vec<(Vec<String>, Value)>) -> Map<String, Value> {
+    let mut map = Map::new();
+    for (keys, value) in entries {
+        for key in keys {
+            map.insert(key, value.clone());
+        }
+    }
+    map
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_tokenize_line() {
+        let tests = [
+            (
+                "\"@babel/types@^7.18.10\", \"@babel/types@^7.18.6\":",
+                vec![
+                    Token::String("@babel/types@^7.18.10".into()),
+                    Token::Comma,
+                    Token::String("@babel/types@^7.18.6".into()),
+                    Token::Colon,
+                ],
+            ),
+            (
+                "integrity sha512-abc/+==",
+                vec![
+                    Token::String("integrity".into()),
+                    Token::String("sha512-abc/+==".into()),
+                ],
+            ),
+            (
+                "optional true",
+                vec![Token::String("optional".into()), Token::Boolean(true)],
+            ),
+            (
+                "\"a\\\"b\" 12",
+                vec![Token::String("a\"b".into()), Token::Number(12)],
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(tokenize_line(input, 1).unwrap(), expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn test_parse_nested() {
+        let input = "# yarn lockfile v1\n\nfoo@^1.0.0, foo@^1.1.0:\n  version \"1.1.0\"\n  \
+                     dependencies:\n    bar \"^2.0.0\"\n\nbar@^2.0.0:\n  version \"2.0.0\"\n";
+        let entries = parse(input).unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                (
+                    vec!["foo@^1.0.0".to_string(), "foo@^1.1.0".to_string()],
+                    json!({"version": "1.1.0", "dependencies": {"bar": "^2.0.0"}})
+                ),
+                (vec!["bar@^2.0.0".to_string()], json!({"version": "2.0.0"})),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(matches!(
+            parse("foo@^1.0.0:\n   version \"1.0.0\"\n"),
+            Err(Error::Indentation(2))
+        ));
+        assert!(matches!(
+            parse("foo@^1.0.0:\n  version \"1.0.0\n"),
+            Err(Error::UnterminatedString(2))
+        ));
+        assert!(matches!(
+            parse("foo@^1.0.0:\n  version\n"),
+            Err(Error::ExpectedValue(2))
+        ));
+    }
+}
@@ -0,0 +1,318 @@
+mod de;
+mod ser;
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+use super::{Lockfile, Package};
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("yarn.lock is not valid utf8: {0}")]
+    Utf8(#[from] std::str::Utf8Error),
+    #[error("invalid number of spaces on line {0}")]
+    Indentation(usize),
+    #[error("unterminated string on line {0}")]
+    UnterminatedString(usize),
+    #[error("invalid string on line {0}")]
+    InvalidString(usize),
+    #[error("unexpected character '{0}' on line {1}")]
+    UnexpectedCharacter(char, usize),
+    #[error("expected a key on line {0}")]
+    ExpectedKey(usize),
+    #[error("expected a value on line {0}")]
+    ExpectedValue(usize),
+    #[error("unexpected tokens after value on line {0}")]
+    TrailingTokens(usize),
+    #[error("invalid lockfile entry: {0}")]
+    Entry(#[from] serde_json::Error),
+    #[error("missing lockfile entry for {0}")]
+    MissingPackage(String),
+}
+
+// We depend on BTree iteration being sorted for correct serialization
+type Map<K, V> = BTreeMap<K, V>;
+
+pub struct Yarn1Lockfile {
+    // Maps each lockfile key to an index in entries. Keys that share an entry
+    // are written out together on the same line.
+    keys: Map<String, usize>,
+    entries: Vec<Entry>,
+    has_crlf: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+struct Entry {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    version: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    uid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    resolved: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    integrity: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    registry: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dependencies: Option<Map<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    optional_dependencies: Option<Map<String, String>>,
+    // We want to reserialize any additional fields, but we don't use them
+    // we keep them as raw values to avoid describing the correct schema.
+    #[serde(flatten)]
+    other: Map<String, Value>,
+}
+
+impl Yarn1Lockfile {
+    pub fn from_bytes(input: &[u8]) -> Result<Self, Error> {
+        let contents = std::str::from_utf8(input)?;
+        let has_crlf = Self::detect_crlf(input);
+
+        let mut keys = Map::new();
+        let mut entries = Vec::new();
+        for (entry_keys, value) in de::parse(contents)? {
+            let index = entries.len();
+            entries.push(serde_json::from_value(value)?);
+            for key in entry_keys {
+                keys.insert(key, index);
+            }
+        }
+
+        Ok(Self {
+            keys,
+            entries,
+            has_crlf,
+        })
+    }
+
+    fn detect_crlf(contents: &[u8]) -> bool {
+        if contents.ends_with(b"\r\n") {
+            return true;
+        }
+        // there's no trailing newline for this file, need to inspect more to see
+        // newline style
+        if !contents.ends_with(b"\n") {
+            if let Some(first_newline) = contents.iter().position(|c| *c == b'\n') {
+                return first_newline > 0 && contents[first_newline - 1] == b'\r';
+            }
+        }
+        false
+    }
+
+    fn get_entry(&self, key: &str) -> Option<&Entry> {
+        self.keys.get(key).map(|index| &self.entries[*index])
+    }
+
+    /// Produces a new lockfile containing only the given packages
+    pub fn subgraph(&self, packages: &[String]) -> Result<Self, Error> {
+        let mut keys = Map::new();
+        let mut entries = Vec::new();
+        // Maps indices of the original entries to the pruned entries
+        let mut reindex = HashMap::new();
+        for key in packages {
+            let index = self
+                .keys
+                .get(key)
+                .ok_or_else(|| Error::MissingPackage(key.clone()))?;
+            let new_index = *reindex.entry(*index).or_insert_with(|| {
+                entries.push(self.entries[*index].clone());
+                entries.len() - 1
+            });
+            keys.insert(key.clone(), new_index);
+        }
+
+        Ok(Self {
+            keys,
+            entries,
+            has_crlf: self.has_crlf,
+        })
+    }
+
+    // Groups keys by the entry they refer to, ordered by the first key of each
+    // group as Yarn does.
+    fn grouped_entries(&self) -> Vec<(Vec<&str>, &Entry)> {
+        let mut groups: Vec<(Vec<&str>, &Entry)> = Vec::new();
+        let mut group_index = HashMap::new();
+        for (key, index) in &self.keys {
+            let group = *group_index.entry(*index).or_insert_with(|| {
+                groups.push((Vec::new(), &self.entries[*index]));
+                groups.len() - 1
+            });
+            groups[group].0.push(key.as_str());
+        }
+        groups
+    }
+
+    fn possible_keys<'a>(name: &'a str, version: &'a str) -> impl Iterator<Item = String> + 'a {
+        ["", "npm:", "file:", "workspace:", "yarn:"]
+            .into_iter()
+            .map(move |protocol| format!("{name}@{protocol}{version}"))
+    }
+}
+
+impl Lockfile for Yarn1Lockfile {
+    fn resolve_package(
+        &self,
+        _workspace_path: &str,
+        name: &str,
+        version: &str,
+    ) -> Result<Option<Package>, crate::Error> {
+        Ok(Self::possible_keys(name, version).find_map(|key| {
+            let entry = self.get_entry(&key)?;
+            Some(Package {
+                version: entry.version.clone(),
+                key,
+            })
+        }))
+    }
+
+    fn all_dependencies(&self, key: &str) -> Result<Option<HashMap<String, String>>, crate::Error> {
+        let Some(entry) = self.get_entry(key) else {
+            return Ok(None);
+        };
+
+        Ok(Some(
+            entry
+                .dependencies
+                .iter()
+                .flatten()
+                .chain(entry.optional_dependencies.iter().flatten())
+                .map(|(name, version)| (name.clone(), version.clone()))
+                .collect(),
+        ))
+    }
+
+    fn package_keys(&self) -> Vec<String> {
+        self.keys.keys().cloned().collect()
+    }
+
+    fn missing_integrity(&self, key: &str) -> bool {
+        self.get_entry(key).map_or(false, |entry| {
+            let from_registry = entry
+                .resolved
+                .as_deref()
+                .map_or(false, |resolved| resolved.starts_with("http"));
+            from_registry && entry.integrity.is_none()
+        })
+    }
+
+    fn package_name(&self, key: &str) -> Option<String> {
+        // Scoped packages start with an '@' so we skip the first character
+        // when looking for the separator between the name and the range
+        let separator = key.get(1..)?.find('@')? + 1;
+        Some(key[..separator].to_string())
+    }
+}
+
+pub fn yarn_subgraph(contents: &[u8], packages: &[String]) -> Result<Vec<u8>, Error> {
+    let lockfile = Yarn1Lockfile::from_bytes(contents)?;
+    let pruned_lockfile = lockfile.subgraph(packages)?;
+    Ok(pruned_lockfile.to_string().into_bytes())
+}
+
+#[cfg(test)]
+mod test {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::transitive_closure;
+
+    const YARN_LOCK: &str = include_str!("../../fixtures/yarn.lock");
+
+    #[test]
+    fn test_roundtrip() {
+        let lockfile = Yarn1Lockfile::from_bytes(YARN_LOCK.as_bytes()).unwrap();
+        assert_eq!(lockfile.to_string(), YARN_LOCK);
+    }
+
+    #[test]
+    fn test_roundtrip_crlf() {
+        let contents = YARN_LOCK.replace('\n', "\r\n");
+        let lockfile = Yarn1Lockfile::from_bytes(contents.as_bytes()).unwrap();
+        assert!(lockfile.has_crlf);
+        assert_eq!(lockfile.to_string(), contents);
+    }
+
+    #[test]
+    fn test_key_splitting() {
+        let lockfile = Yarn1Lockfile::from_bytes(YARN_LOCK.as_bytes()).unwrap();
+        // @babel/types has multiple entries, these should all appear in the lockfile
+        for key in [
+            "@babel/types@^7.18.10",
+            "@babel/types@^7.18.6",
+            "@babel/types@^7.19.0",
+        ] {
+            assert!(
+                lockfile.get_entry(key).is_some(),
+                "Unable to find entry for {key} in parsed lockfile"
+            );
+        }
+    }
+
+    #[test]
+    fn test_package_name() {
+        let lockfile = Yarn1Lockfile::from_bytes(YARN_LOCK.as_bytes()).unwrap();
+        assert_eq!(
+            lockfile.package_name("@babel/types@^7.18.6").as_deref(),
+            Some("@babel/types")
+        );
+        assert_eq!(
+            lockfile.package_name("lodash@npm:^4.17.21").as_deref(),
+            Some("lodash")
+        );
+    }
+
+    #[test]
+    fn test_resolve_package() {
+        let lockfile = Yarn1Lockfile::from_bytes(YARN_LOCK.as_bytes()).unwrap();
+        assert_eq!(
+            lockfile
+                .resolve_package("apps/docs", "@babel/types", "^7.18.6")
+                .unwrap(),
+            Some(Package {
+                key: "@babel/types@^7.18.6".into(),
+                version: "7.19.0".into(),
+            })
+        );
+        assert_eq!(
+            lockfile
+                .resolve_package("apps/docs", "@babel/types", "^6.0.0")
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_subgraph() {
+        let lockfile = Yarn1Lockfile::from_bytes(YARN_LOCK.as_bytes()).unwrap();
+        let closure = transitive_closure(
+            &lockfile,
+            "apps/docs",
+            [("@babel/generator".to_string(), "^7.19.0".to_string())]
+                .into_iter()
+                .collect(),
+        )
+        .unwrap();
+        let mut packages = closure.into_iter().map(|pkg| pkg.key).collect::<Vec<_>>();
+        packages.sort();
+        assert!(packages.contains(&"@babel/types@^7.19.0".to_string()));
+
+        let pruned = lockfile.subgraph(&packages).unwrap();
+        let contents = pruned.to_string();
+        let reparsed = Yarn1Lockfile::from_bytes(contents.as_bytes()).unwrap();
+        assert_eq!(reparsed.keys.keys().cloned().collect::<Vec<_>>(), packages);
+        assert!(contents.contains("\n\"@babel/types@^7.19.0\":\n"));
+    }
+
+    #[test]
+    fn test_subgraph_missing_package() {
+        let lockfile = Yarn1Lockfile::from_bytes(YARN_LOCK.as_bytes()).unwrap();
+        let result = lockfile.subgraph(&["not-a-package@^1.0.0".to_string()]);
+        assert!(matches!(result, Err(Error::MissingPackage(key)) if key == "not-a-package@^1.0.0"));
+    }
+}
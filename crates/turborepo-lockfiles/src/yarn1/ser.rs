@@ -0,0 +1,149 @@
+use std::{cmp::Ordering, fmt, fmt::Write};
+
+use serde_json::{Map, Value};
+
+use super::Yarn1Lockfile;
+
+const HEADER: &str = "# THIS IS AN AUTOGENERATED FILE. DO NOT EDIT THIS FILE DIRECTLY.
+# yarn lockfile v1
+
+
+";
+
+// We implement Display in order to produce a `yarn.lock` that is byte for byte
+// identical to what Yarn would write.
+// See https://github.com/yarnpkg/yarn/blob/master/src/lockfile/stringify.js
+impl fmt::Display for Yarn1Lockfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut contents = String::from(HEADER);
+
+        let mut first = true;
+        for (keys, entry) in self.grouped_entries() {
+            if !first {
+                contents.push('\n');
+            }
+            first = false;
+
+            let key_line = keys
+                .iter()
+                .map(|key| maybe_wrap(key))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let value = serde_json::to_value(entry).map_err(|_| fmt::Error)?;
+            let Value::Object(object) = value else {
+                return Err(fmt::Error);
+            };
+            writeln!(contents, "{key_line}:\n{}", stringify(&object, "  "))?;
+        }
+
+        match self.has_crlf {
+            true => f.write_str(&contents.replace('\n', "\r\n")),
+            false => f.write_str(&contents),
+        }
+    }
+}
+
+fn stringify(object: &Map<String, Value>, indent: &str) -> String {
+    let mut keys = object.keys().collect::<Vec<_>>();
+    keys.sort_by(|a, b| priority_then_alpha(a, b));
+
+    let mut lines = Vec::with_capacity(keys.len());
+    for key in keys {
+        let line = match &object[key] {
+            Value::Null => continue,
+            Value::Object(nested) => format!(
+                "{}:\n{}",
+                maybe_wrap(key),
+                stringify(nested, &format!("{indent}  "))
+            ),
+            Value::String(value) => format!("{} {}", maybe_wrap(key), maybe_wrap(value)),
+            // Arrays aren't valid in yarn.lock files so we write them as JSON
+            value => format!("{} {}", maybe_wrap(key), value),
+        };
+        lines.push(line);
+    }
+
+    format!("{indent}{}", lines.join(&format!("\n{indent}")))
+}
+
+fn priority(key: &str) -> Option<u8> {
+    match key {
+        "name" => Some(1),
+        "version" => Some(2),
+        "uid" => Some(3),
+        "resolved" => Some(4),
+        "integrity" => Some(5),
+        "registry" => Some(6),
+        "dependencies" => Some(7),
+        _ => None,
+    }
+}
+
+fn priority_then_alpha(a: &str, b: &str) -> Ordering {
+    match (priority(a), priority(b)) {
+        (None, None) => a.cmp(b),
+        (a, b) => a.unwrap_or(100).cmp(&b.unwrap_or(100)),
+    }
+}
+
+fn should_wrap(s: &str) -> bool {
+    s.starts_with("true")
+        || s.starts_with("false")
+        || s.contains(|c: char| {
+            matches!(c, ':' | '\\' | '"' | ',' | '[' | ']') || c.is_whitespace()
+        })
+        || !s.starts_with(|c: char| c.is_ascii_alphabetic())
+}
+
+fn maybe_wrap(s: &str) -> String {
+    match should_wrap(s) {
+        true => serde_json::to_string(s).expect("strings are always valid json"),
+        false => s.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_maybe_wrap() {
+        let tests = [
+            ("lodash", "lodash"),
+            ("lodash@^4.17.21", "lodash@^4.17.21"),
+            ("@babel/core@7.0.0", "\"@babel/core@7.0.0\""),
+            ("1.0.0", "\"1.0.0\""),
+            ("sha512-abc/+==", "sha512-abc/+=="),
+            ("truex", "\"truex\""),
+            ("a b", "\"a b\""),
+            ("", "\"\""),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(maybe_wrap(input), expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn test_priority_sort() {
+        let mut keys = vec![
+            "optionalDependencies",
+            "dependencies",
+            "integrity",
+            "resolved",
+            "version",
+            "bin",
+        ];
+        keys.sort_by(|a, b| priority_then_alpha(a, b));
+        assert_eq!(
+            keys,
+            vec![
+                "version",
+                "resolved",
+                "integrity",
+                "dependencies",
+                "bin",
+                "optionalDependencies"
+            ]
+        );
+    }
+}
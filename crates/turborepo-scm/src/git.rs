@@ -12,9 +12,10 @@ use crate::Error;
 /// (unstaged changes) and between two commits. Includes untracked files,
 /// i.e. files not yet in git.
 ///
-/// We shell out to git instead of using a git2 library because git2 doesn't
-/// support shallow clones, and therefore errors on repositories that
-/// are shallow cloned.
+/// With the `native-git` feature the repository is read directly, falling
+/// back to running git for anything the native backend doesn't support. We
+/// don't use git2 because it doesn't support shallow clones, and therefore
+/// errors on repositories that are shallow cloned.
 ///
 /// # Arguments
 ///
@@ -38,6 +39,29 @@ pub fn changed_files(
 
     let mut files = HashSet::new();
 
+    #[cfg(feature = "native-git")]
+    if let Ok(changed) = crate::native::changed_files(
+        git_root.as_path(),
+        turbo_root_relative_to_git_root.to_unix()?.as_str()?,
+        from_commit,
+        to_commit,
+    ) {
+        for path in &changed {
+            let path = path.as_str();
+            let path = RelativeUnixPath::new(&path)?;
+            files.insert(
+                reanchor_path_from_git_root_to_turbo_root(
+                    git_root.borrow(),
+                    turbo_root.borrow(),
+                    path,
+                )?
+                .to_str()?
+                .to_string(),
+            );
+        }
+        return Ok(files);
+    }
+
     let output = execute_git_command(
         git_root.borrow(),
         &["diff", "--name-only", to_commit],
@@ -71,6 +95,180 @@ pub fn changed_files(
     Ok(files)
 }
 
+/// How a file changed
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ChangeKind {
+    Added,
+    Modified,
+    Deleted,
+    /// The file was moved from `from`, relative to the turbo root
+    Renamed {
+        from: String,
+    },
+    /// The file was copied from `from`, relative to the turbo root
+    Copied {
+        from: String,
+    },
+}
+
+/// Where a change was found
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ChangeSource {
+    /// Uncommitted changes compared to `to_commit`
+    WorkingTree,
+    /// Changes between the merge base of `from` and `to`
+    CommitRange { from: String, to: String },
+    /// Files that aren't tracked by git
+    Untracked,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ChangedFile {
+    /// The path of the file relative to the turbo root
+    pub path: String,
+    pub kind: ChangeKind,
+    pub source: ChangeSource,
+}
+
+/// Like `changed_files`, but reports how each file changed and which
+/// comparison the change came from. Renames and copies are detected by git so
+/// a moved file is reported once as `Renamed` instead of a deletion and an
+/// unrelated addition. A file can be reported by more than one source.
+pub fn changed_files_detailed(
+    git_root: PathBuf,
+    turbo_root: PathBuf,
+    from_commit: Option<&str>,
+    to_commit: &str,
+) -> Result<Vec<ChangedFile>, Error> {
+    let git_root = AbsoluteSystemPathBuf::new(git_root)?;
+    let turbo_root = AbsoluteSystemPathBuf::new(turbo_root)?;
+    let turbo_root_relative_to_git_root = git_root.anchor(&turbo_root)?;
+    let pathspec = turbo_root_relative_to_git_root.to_str()?;
+
+    let mut files = Vec::new();
+
+    let output = execute_git_command(
+        git_root.borrow(),
+        &["diff", "--name-status", "-z", "-M", "-C", to_commit],
+        pathspec,
+    )?;
+    add_changes_from_name_status(
+        &mut files,
+        git_root.borrow(),
+        turbo_root.borrow(),
+        ChangeSource::WorkingTree,
+        output,
+    )?;
+
+    if let Some(from_commit) = from_commit {
+        let output = execute_git_command(
+            git_root.borrow(),
+            &[
+                "diff",
+                "--name-status",
+                "-z",
+                "-M",
+                "-C",
+                &format!("{}...{}", from_commit, to_commit),
+            ],
+            pathspec,
+        )?;
+        let source = ChangeSource::CommitRange {
+            from: from_commit.to_string(),
+            to: to_commit.to_string(),
+        };
+        add_changes_from_name_status(
+            &mut files,
+            git_root.borrow(),
+            turbo_root.borrow(),
+            source,
+            output,
+        )?;
+    }
+
+    let output = execute_git_command(
+        git_root.borrow(),
+        &["ls-files", "--others", "--exclude-standard", "-z"],
+        pathspec,
+    )?;
+    for path in output.split(|b| *b == 0).filter(|path| !path.is_empty()) {
+        files.push(ChangedFile {
+            path: reanchor_git_output(git_root.borrow(), turbo_root.borrow(), path)?,
+            kind: ChangeKind::Added,
+            source: ChangeSource::Untracked,
+        });
+    }
+
+    Ok(files)
+}
+
+// Parses the output of `git diff --name-status -z`. Each entry is a status
+// followed by a path, or two paths for renames and copies. Renames and copies
+// include a similarity score after the status letter e.g. `R100`.
+fn add_changes_from_name_status(
+    files: &mut Vec<ChangedFile>,
+    git_root: &AbsoluteSystemPath,
+    turbo_root: &AbsoluteSystemPath,
+    source: ChangeSource,
+    stdout: Vec<u8>,
+) -> Result<(), Error> {
+    let mut fields = stdout.split(|b| *b == 0).filter(|field| !field.is_empty());
+    while let Some(status) = fields.next() {
+        let mut next_path = || {
+            fields
+                .next()
+                .ok_or_else(|| {
+                    Error::git_error(format!(
+                        "missing path for status {}",
+                        String::from_utf8_lossy(status)
+                    ))
+                })
+                .and_then(|path| reanchor_git_output(git_root, turbo_root, path))
+        };
+        let (path, kind) = match status[0] {
+            b'A' => (next_path()?, ChangeKind::Added),
+            b'D' => (next_path()?, ChangeKind::Deleted),
+            // Type changes and unmerged files are treated as modifications
+            b'M' | b'T' | b'U' => (next_path()?, ChangeKind::Modified),
+            b'R' => {
+                let from = next_path()?;
+                (next_path()?, ChangeKind::Renamed { from })
+            }
+            b'C' => {
+                let from = next_path()?;
+                (next_path()?, ChangeKind::Copied { from })
+            }
+            _ => {
+                return Err(Error::git_error(format!(
+                    "unknown git diff status {}",
+                    String::from_utf8_lossy(status)
+                )))
+            }
+        };
+        files.push(ChangedFile {
+            path,
+            kind,
+            source: source.clone(),
+        });
+    }
+    Ok(())
+}
+
+fn reanchor_git_output(
+    git_root: &AbsoluteSystemPath,
+    turbo_root: &AbsoluteSystemPath,
+    path: &[u8],
+) -> Result<String, Error> {
+    let path = String::from_utf8(path.to_vec())?;
+    let path = path.as_str();
+    let path = RelativeUnixPath::new(&path)?;
+    Ok(
+        reanchor_path_from_git_root_to_turbo_root(git_root, turbo_root, path)?
+            .to_str()?
+            .to_string(),
+    )
+}
+
 fn execute_git_command(
     git_root: &AbsoluteSystemPath,
     args: &[&str],
@@ -156,6 +354,15 @@ pub fn previous_content(
         file_path.as_path().try_into()?
     };
 
+    #[cfg(feature = "native-git")]
+    if let Ok(contents) = crate::native::previous_content(
+        git_root.as_path(),
+        from_commit,
+        &anchored_file_path.to_unix()?,
+    ) {
+        return Ok(contents);
+    }
+
     let mut command = Command::new("git");
     let command = command
         .arg("show")
@@ -192,7 +399,10 @@ mod tests {
     use turbopath::{PathError, PathValidationError};
 
     use super::previous_content;
-    use crate::{git::changed_files, Error};
+    use crate::{
+        git::{changed_files, changed_files_detailed, ChangeKind, ChangeSource, ChangedFile},
+        Error,
+    };
 
     fn setup_repository() -> Result<(TempDir, Repository), Error> {
         let repo_root = tempfile::tempdir()?;
@@ -416,6 +626,75 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_changed_files_detailed() -> Result<(), Error> {
+        let (repo_root, repo) = setup_repository()?;
+        let root = repo_root.path();
+        let git = |args: &[&str]| {
+            let output = Command::new("git")
+                .args(args)
+                .current_dir(root)
+                .output()
+                .unwrap();
+            assert!(output.status.success(), "{:?}", output);
+        };
+        fs::create_dir_all(root.join("packages").join("a"))?;
+        fs::write(
+            root.join("packages").join("a").join("index.js"),
+            "export const a = 'a long enough line for rename detection';",
+        )?;
+        fs::write(root.join("foo.js"), "let z = 0;")?;
+        let first_commit_oid = commit_file(&repo, Path::new("foo.js"), None)?;
+        let first_commit_oid = commit_file(
+            &repo,
+            Path::new("packages/a/index.js"),
+            Some(first_commit_oid),
+        )?;
+
+        // Move the package in a commit
+        git(&["mv", "packages/a", "packages/b"]);
+        git(&["commit", "-m", "move"]);
+
+        // Modify a tracked file and add an untracked one
+        fs::write(root.join("foo.js"), "let z = 1;")?;
+        fs::write(root.join("bar.js"), "let y = 1;")?;
+
+        let files = changed_files_detailed(
+            root.to_path_buf(),
+            root.to_path_buf(),
+            Some(&first_commit_oid.to_string()),
+            "HEAD",
+        )?;
+        let head = "HEAD".to_string();
+        assert_eq!(
+            files.into_iter().collect::<HashSet<_>>(),
+            HashSet::from([
+                ChangedFile {
+                    path: "foo.js".to_string(),
+                    kind: ChangeKind::Modified,
+                    source: ChangeSource::WorkingTree,
+                },
+                ChangedFile {
+                    path: "packages/b/index.js".to_string(),
+                    kind: ChangeKind::Renamed {
+                        from: "packages/a/index.js".to_string()
+                    },
+                    source: ChangeSource::CommitRange {
+                        from: first_commit_oid.to_string(),
+                        to: head,
+                    },
+                },
+                ChangedFile {
+                    path: "bar.js".to_string(),
+                    kind: ChangeKind::Added,
+                    source: ChangeSource::Untracked,
+                },
+            ])
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_changed_files_with_root_as_relative() -> Result<(), Error> {
         let (repo_root, repo) = setup_repository()?;
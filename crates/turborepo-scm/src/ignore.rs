@@ -0,0 +1,170 @@
+use std::{fs, path::Path};
+
+use globset::{GlobBuilder, GlobMatcher};
+
+use crate::Error;
+
+/// A single pattern from a gitignore file
+#[derive(Debug, Clone)]
+struct Pattern {
+    matcher: GlobMatcher,
+    // Directory containing the gitignore file relative to the repository root
+    // with a trailing slash, empty for the root.
+    base: String,
+    negated: bool,
+    dir_only: bool,
+    // Patterns without a slash match against the file name at any depth
+    match_name: bool,
+}
+
+/// Ignore rules in order of increasing precedence. Later patterns override
+/// earlier ones which matches how git prioritizes `core.excludesFile`,
+/// `.git/info/exclude`, and then `.gitignore` files from the root down.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct IgnoreRules {
+    patterns: Vec<Pattern>,
+}
+
+impl IgnoreRules {
+    /// Adds the patterns of an ignore file if it exists. `base` is the
+    /// directory of the file relative to the repository root.
+    pub(crate) fn add_file(&mut self, path: &Path, base: &str) -> Result<(), Error> {
+        match fs::read(path) {
+            Ok(contents) => {
+                self.add_patterns(&contents, base);
+                Ok(())
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            // A gitignore that is a directory or unreadable is skipped by git
+            Err(e) if path.is_dir() || e.kind() == std::io::ErrorKind::PermissionDenied => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub(crate) fn add_patterns(&mut self, contents: &[u8], base: &str) {
+        let base = match base {
+            "" => String::new(),
+            base => format!("{}/", base.trim_end_matches('/')),
+        };
+        let contents = String::from_utf8_lossy(contents);
+        self.patterns.extend(
+            contents
+                .lines()
+                .filter_map(|line| Pattern::parse(line, &base)),
+        );
+    }
+
+    /// Checks if a path relative to the repository root is ignored
+    pub(crate) fn is_ignored(&self, path: &str, is_dir: bool) -> bool {
+        self.patterns
+            .iter()
+            .rev()
+            .find(|pattern| pattern.matches(path, is_dir))
+            .map_or(false, |pattern| !pattern.negated)
+    }
+}
+
+impl Pattern {
+    fn parse(line: &str, base: &str) -> Option<Self> {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let mut pattern = trim_trailing_spaces(line);
+        let negated = pattern.starts_with('!');
+        if negated {
+            pattern = &pattern[1..];
+        }
+        // A leading backslash escapes a literal # or !
+        if pattern.starts_with("\\#") || pattern.starts_with("\\!") {
+            pattern = &pattern[1..];
+        }
+        let dir_only = pattern.ends_with('/');
+        let pattern = pattern.trim_end_matches('/');
+        if pattern.is_empty() {
+            return None;
+        }
+
+        let match_name = !pattern.contains('/');
+        let pattern = pattern.strip_prefix('/').unwrap_or(pattern);
+        let matcher = GlobBuilder::new(pattern)
+            .literal_separator(true)
+            .backslash_escape(true)
+            .build()
+            .ok()?
+            .compile_matcher();
+
+        Some(Self {
+            matcher,
+            base: base.to_string(),
+            negated,
+            dir_only,
+            match_name,
+        })
+    }
+
+    fn matches(&self, path: &str, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        let Some(relative) = path.strip_prefix(&self.base) else {
+            return false;
+        };
+        match self.match_name {
+            true => {
+                let name = relative.rsplit('/').next().unwrap_or(relative);
+                self.matcher.is_match(name)
+            }
+            false => self.matcher.is_match(relative),
+        }
+    }
+}
+
+// Trailing spaces are ignored unless they are escaped with a backslash
+fn trim_trailing_spaces(line: &str) -> &str {
+    let mut end = line.len();
+    while line[..end].ends_with(' ') && !line[..end - 1].ends_with('\\') {
+        end -= 1;
+    }
+    &line[..end]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_ignore_rules() {
+        let mut rules = IgnoreRules::default();
+        rules.add_patterns(
+            b"# comment\nnode_modules/\n*.log\n!keep.log\n/dist\nbuild/**/*.js\n",
+            "",
+        );
+        rules.add_patterns(b"*.tmp\n!/root.log\n", "apps/web");
+
+        let cases = [
+            ("node_modules", true, true),
+            ("apps/web/node_modules", true, true),
+            ("node_modules", false, false),
+            ("error.log", false, true),
+            ("apps/keep.log", false, false),
+            ("dist", true, true),
+            ("apps/dist", true, false),
+            ("build/a/b/c.js", false, true),
+            ("build/c.ts", false, false),
+            ("apps/web/x.tmp", false, true),
+            ("apps/docs/x.tmp", false, false),
+            ("apps/web/root.log", false, false),
+            ("apps/web/other.log", false, true),
+        ];
+        for (path, is_dir, expected) in cases {
+            assert_eq!(rules.is_ignored(path, is_dir), expected, "{path}");
+        }
+    }
+
+    #[test]
+    fn test_trailing_spaces() {
+        assert_eq!(trim_trailing_spaces("foo  "), "foo");
+        assert_eq!(trim_trailing_spaces("foo\\ "), "foo\\ ");
+    }
+}
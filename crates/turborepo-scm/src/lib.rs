@@ -6,10 +6,15 @@ use std::backtrace::{self, Backtrace};
 
 use thiserror::Error;
 use turbopath::PathError;
+use turborepo_globwalk::WalkError;
 
 pub mod git;
 mod hash_object;
+mod ignore;
 mod ls_tree;
+mod manual;
+#[cfg(feature = "native-git")]
+mod native;
 pub mod package_deps;
 mod status;
 
@@ -23,6 +28,8 @@ pub enum Error {
     Io(#[from] std::io::Error, #[backtrace] backtrace::Backtrace),
     #[error("path error: {0}")]
     Path(#[from] PathError, #[backtrace] backtrace::Backtrace),
+    #[error("glob error: {0}")]
+    Glob(#[from] WalkError, #[backtrace] backtrace::Backtrace),
     #[error("encoding error: {0}")]
     Encoding(
         #[from] std::string::FromUtf8Error,
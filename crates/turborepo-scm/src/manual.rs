@@ -0,0 +1,155 @@
+//! Hashing for repositories that aren't git checkouts, e.g. a source tarball
+//! or a Docker build context without `.git`. Files are hashed the same way git
+//! hashes blobs so that hashes match those computed in a git checkout.
+
+use std::{fs, io, path::Path};
+
+use sha1::{Digest, Sha1};
+use turbopath::{AbsoluteSystemPathBuf, AnchoredSystemPathBuf, RelativeUnixPathBuf};
+
+use crate::{ignore::IgnoreRules, package_deps::GitHashes, Error};
+
+/// Hashes every file in a package that isn't ignored by a `.gitignore` file
+/// between `turbo_root` and the file. Paths are relative to the package.
+pub fn get_package_file_hashes_from_processing_gitignore(
+    turbo_root: &AbsoluteSystemPathBuf,
+    package_path: &AnchoredSystemPathBuf,
+) -> Result<GitHashes, Error> {
+    let pkg_prefix = package_path.to_unix()?;
+    let pkg_prefix = pkg_prefix.as_str()?.trim_end_matches('/');
+
+    // Collect rules from the root down to the package
+    let mut rules = IgnoreRules::default();
+    let mut base = String::new();
+    for component in pkg_prefix.split('/').filter(|c| !c.is_empty()) {
+        rules.add_file(&turbo_root.as_path().join(&base).join(".gitignore"), &base)?;
+        if !base.is_empty() {
+            base.push('/');
+        }
+        base.push_str(component);
+        if rules.is_ignored(&base, true) {
+            return Ok(GitHashes::new());
+        }
+    }
+
+    let mut hashes = GitHashes::new();
+    let mut stack = vec![(pkg_prefix.to_string(), rules)];
+    while let Some((dir, mut rules)) = stack.pop() {
+        let full_dir = turbo_root.as_path().join(&dir);
+        rules.add_file(&full_dir.join(".gitignore"), &dir)?;
+
+        for entry in fs::read_dir(&full_dir)? {
+            let entry = entry?;
+            let name = entry.file_name();
+            let name = name
+                .to_str()
+                .ok_or_else(|| Error::git_error("non UTF-8 paths are not supported"))?;
+            if name == ".git" {
+                continue;
+            }
+            let path = match dir.as_str() {
+                "" => name.to_string(),
+                dir => format!("{dir}/{name}"),
+            };
+            // Symlinks aren't followed, git stores the link itself
+            let file_type = entry.file_type()?;
+            if rules.is_ignored(&path, file_type.is_dir()) {
+                continue;
+            }
+            if file_type.is_dir() {
+                stack.push((path, rules.clone()));
+            } else {
+                let hash = git_like_hash_file(&entry.path())?;
+                let relative = match pkg_prefix {
+                    "" => path.as_str(),
+                    prefix => &path[prefix.len() + 1..],
+                };
+                hashes.insert(RelativeUnixPathBuf::new(relative)?, hash);
+            }
+        }
+    }
+    Ok(hashes)
+}
+
+/// Computes the hash git would give a file if it was added to a repository
+pub(crate) fn git_like_hash_file(path: &Path) -> Result<String, Error> {
+    let metadata = fs::symlink_metadata(path)?;
+    let mut hasher = Sha1::new();
+    if metadata.file_type().is_symlink() {
+        // Symlinks are stored as the path they point to
+        let target = fs::read_link(path)?;
+        let target = target
+            .to_str()
+            .ok_or_else(|| Error::git_error("non UTF-8 paths are not supported"))?
+            .replace('\\', "/");
+        hasher.update(format!("blob {}\0", target.len()));
+        hasher.update(target);
+    } else {
+        hasher.update(format!("blob {}\0", metadata.len()));
+        io::copy(&mut fs::File::open(path)?, &mut hasher)?;
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+#[cfg(test)]
+mod test {
+    use std::process::Command;
+
+    use super::*;
+    use crate::package_deps::{get_package_file_hashes, get_package_file_hashes_from_git_index};
+
+    #[test]
+    fn test_matches_git_hashes() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let turbo_root = AbsoluteSystemPathBuf::new(tmp_dir.path().to_path_buf())
+            .unwrap()
+            .to_realpath()
+            .unwrap();
+        for (path, contents) in [
+            (".gitignore", "*.log\n/my-pkg/dist\n"),
+            ("my-pkg/package.json", "{}"),
+            ("my-pkg/.gitignore", "generated/\n"),
+            ("my-pkg/src/index.js", "export {}"),
+            ("my-pkg/src/debug.log", "ignored"),
+            ("my-pkg/dist/index.js", "ignored"),
+            ("my-pkg/generated/types.d.ts", "ignored"),
+            ("other-pkg/package.json", "{}"),
+        ] {
+            let path = turbo_root.join_literal(path);
+            path.ensure_dir().unwrap();
+            path.create_with_contents(contents).unwrap();
+        }
+
+        let package_path = AnchoredSystemPathBuf::from_raw("my-pkg").unwrap();
+        let manual =
+            get_package_file_hashes_from_processing_gitignore(&turbo_root, &package_path).unwrap();
+        let mut paths = manual
+            .keys()
+            .map(|path| path.as_str().unwrap())
+            .collect::<Vec<_>>();
+        paths.sort();
+        assert_eq!(paths, vec![".gitignore", "package.json", "src/index.js"]);
+        assert_eq!(
+            get_package_file_hashes(&turbo_root, &package_path).unwrap(),
+            manual
+        );
+
+        // The same files once the directory becomes a git repository
+        for args in [
+            &["init", "."][..],
+            &["config", "--local", "user.name", "test"],
+            &["config", "--local", "user.email", "test@example.com"],
+            &["add", "."],
+            &["commit", "-m", "foo"],
+        ] {
+            let output = Command::new("git")
+                .args(args)
+                .current_dir(&turbo_root)
+                .output()
+                .unwrap();
+            assert!(output.status.success());
+        }
+        let from_git = get_package_file_hashes_from_git_index(&turbo_root, &package_path).unwrap();
+        assert_eq!(manual, from_git);
+    }
+}
@@ -0,0 +1,264 @@
+use std::fs::Metadata;
+
+use super::{object::ObjectId, pack::read_offset_varint};
+use crate::Error;
+
+const SIGNATURE: &[u8] = b"DIRC";
+const HEADER_LEN: usize = 12;
+// ctime, mtime, dev, ino, mode, uid, gid, and size followed by the id and flags
+const ENTRY_FIXED_LEN: usize = 40 + ObjectId::LEN + 2;
+
+const FLAG_EXTENDED: u16 = 0x4000;
+const FLAG_STAGE_MASK: u16 = 0x3000;
+const FLAG_NAME_MASK: u16 = 0x0fff;
+const EXTENDED_FLAG_SKIP_WORKTREE: u16 = 0x4000;
+const EXTENDED_FLAG_INTENT_TO_ADD: u16 = 0x2000;
+
+/// A single entry of the git index
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct IndexEntry {
+    pub(crate) path: Vec<u8>,
+    pub(crate) id: ObjectId,
+    pub(crate) mode: u32,
+    pub(crate) stage: u8,
+    pub(crate) skip_worktree: bool,
+    pub(crate) intent_to_add: bool,
+    ctime: (u32, u32),
+    mtime: (u32, u32),
+    ino: u32,
+    size: u32,
+}
+
+/// The git index (`.git/index`) which tracks the contents of the next commit
+/// along with stat information used to quickly detect modified files.
+#[derive(Debug, Default)]
+pub(crate) struct Index {
+    pub(crate) entries: Vec<IndexEntry>,
+    // Modification time of the index file itself in seconds and nanoseconds
+    mtime: (u32, u32),
+}
+
+impl Index {
+    /// Parses an index file. Split indexes and sparse indexes aren't
+    /// supported and result in an error.
+    pub(crate) fn parse(data: &[u8], mtime: (u32, u32)) -> Result<Self, Error> {
+        if data.len() < HEADER_LEN || &data[..4] != SIGNATURE {
+            return Err(Error::git_error("invalid index signature"));
+        }
+        let version = read_u32(data, 4)?;
+        if !(2..=4).contains(&version) {
+            return Err(Error::git_error(format!(
+                "unsupported index version {version}"
+            )));
+        }
+        let count = read_u32(data, 8)? as usize;
+        // The index ends with a checksum of its contents
+        let end = data
+            .len()
+            .checked_sub(ObjectId::LEN)
+            .ok_or_else(|| Error::git_error("truncated index"))?;
+        let data = &data[..end];
+
+        // Every entry takes at least ENTRY_FIXED_LEN bytes, so a larger count
+        // can only come from a corrupt index
+        let mut entries = Vec::with_capacity(count.min(data.len() / ENTRY_FIXED_LEN));
+        let mut pos = HEADER_LEN;
+        let mut previous_path: Vec<u8> = Vec::new();
+        for _ in 0..count {
+            let entry_start = pos;
+            let fixed = data
+                .get(pos..pos + ENTRY_FIXED_LEN)
+                .ok_or_else(|| Error::git_error("truncated index entry"))?;
+            let field = |i: usize| u32::from_be_bytes(fixed[i * 4..i * 4 + 4].try_into().unwrap());
+            let id = ObjectId::from_bytes(&fixed[40..40 + ObjectId::LEN])?;
+            let flags = u16::from_be_bytes(fixed[60..62].try_into().unwrap());
+            pos += ENTRY_FIXED_LEN;
+
+            let mut extended_flags = 0;
+            if flags & FLAG_EXTENDED != 0 {
+                if version < 3 {
+                    return Err(Error::git_error("extended flags in version 2 index"));
+                }
+                let bytes = data
+                    .get(pos..pos + 2)
+                    .ok_or_else(|| Error::git_error("truncated index entry"))?;
+                extended_flags = u16::from_be_bytes(bytes.try_into().unwrap());
+                pos += 2;
+            }
+
+            let path = if version == 4 {
+                // Paths are prefix compressed against the previous entry
+                let (strip, read) = read_offset_varint(&data[pos..])?;
+                pos += read;
+                let suffix_len = nul_position(&data[pos..])?;
+                let keep = previous_path
+                    .len()
+                    .checked_sub(strip)
+                    .ok_or_else(|| Error::git_error("invalid index path compression"))?;
+                let mut path = previous_path[..keep].to_vec();
+                path.extend_from_slice(&data[pos..pos + suffix_len]);
+                pos += suffix_len + 1;
+                path
+            } else {
+                let mut name_len = (flags & FLAG_NAME_MASK) as usize;
+                // Longer names are NUL terminated
+                if name_len == FLAG_NAME_MASK as usize {
+                    name_len = nul_position(&data[pos..])?;
+                }
+                let path = data
+                    .get(pos..pos + name_len)
+                    .ok_or_else(|| Error::git_error("truncated index entry"))?
+                    .to_vec();
+                // Entries are padded with 1-8 NUL bytes to a multiple of 8
+                let entry_len = pos + name_len - entry_start;
+                pos = entry_start + (entry_len + 8) / 8 * 8;
+                path
+            };
+
+            entries.push(IndexEntry {
+                id,
+                mode: field(6),
+                stage: ((flags & FLAG_STAGE_MASK) >> 12) as u8,
+                skip_worktree: extended_flags & EXTENDED_FLAG_SKIP_WORKTREE != 0,
+                intent_to_add: extended_flags & EXTENDED_FLAG_INTENT_TO_ADD != 0,
+                ctime: (field(0), field(1)),
+                mtime: (field(2), field(3)),
+                ino: field(5),
+                size: field(9),
+                path: path.clone(),
+            });
+            previous_path = path;
+        }
+
+        // Extensions with an uppercase signature are optional caches that are
+        // safe to skip. Any other extension e.g. split or sparse indexes
+        // changes what the entries mean.
+        while pos + 8 <= data.len() {
+            let signature = &data[pos..pos + 4];
+            if !signature[0].is_ascii_uppercase() {
+                return Err(Error::git_error(format!(
+                    "unsupported index extension {}",
+                    String::from_utf8_lossy(signature)
+                )));
+            }
+            let size = read_u32(data, pos + 4)? as usize;
+            pos += 8 + size;
+        }
+
+        Ok(Self { entries, mtime })
+    }
+
+    /// Checks if the file in the working tree is unchanged since it was
+    /// added to the index based on its stat information.
+    ///
+    /// Files modified in the same second the index was written are
+    /// considered changed as their modification can't be detected
+    /// ("racy git").
+    pub(crate) fn is_unchanged(&self, entry: &IndexEntry, metadata: &Metadata) -> bool {
+        let Some(mtime) = file_time(metadata.modified().ok()) else {
+            return false;
+        };
+        if entry.mtime != mtime || entry.size != metadata.len() as u32 {
+            return false;
+        }
+        if entry.mtime >= self.mtime {
+            return false;
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            let ctime = (metadata.ctime() as u32, metadata.ctime_nsec() as u32);
+            if entry.ctime != ctime || entry.ino != metadata.ino() as u32 {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Converts a file time to the seconds and nanoseconds stored in the index
+pub(crate) fn file_time(time: Option<std::time::SystemTime>) -> Option<(u32, u32)> {
+    let duration = time?.duration_since(std::time::UNIX_EPOCH).ok()?;
+    Some((duration.as_secs() as u32, duration.subsec_nanos()))
+}
+
+fn nul_position(data: &[u8]) -> Result<usize, Error> {
+    data.iter()
+        .position(|b| *b == 0)
+        .ok_or_else(|| Error::git_error("unterminated index path"))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, Error> {
+    data.get(offset..offset + 4)
+        .map(|bytes| u32::from_be_bytes(bytes.try_into().unwrap()))
+        .ok_or_else(|| Error::git_error("truncated index"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn entry_bytes(path: &[u8], previous: &[u8], id: ObjectId, version: u32) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for field in [1, 0, 2, 0, 0, 3, 0o100644, 0, 0, 4] {
+            bytes.extend_from_slice(&u32::to_be_bytes(field));
+        }
+        bytes.extend_from_slice(id.as_bytes());
+        bytes.extend_from_slice(&(path.len() as u16).to_be_bytes());
+        if version == 4 {
+            // Strip the entire previous path, all lengths used are below 128
+            bytes.push(previous.len() as u8);
+            bytes.extend_from_slice(path);
+            bytes.push(0);
+        } else {
+            bytes.extend_from_slice(path);
+            let len = ENTRY_FIXED_LEN + path.len();
+            bytes.resize(bytes.len() + (len + 8) / 8 * 8 - len, 0);
+        }
+        bytes
+    }
+
+    fn index_bytes(version: u32, entries: &[&[u8]], extension: Option<&[u8]>) -> Vec<u8> {
+        let id = ObjectId::for_blob(b"");
+        let mut bytes = SIGNATURE.to_vec();
+        bytes.extend_from_slice(&version.to_be_bytes());
+        bytes.extend_from_slice(&(entries.len() as u32).to_be_bytes());
+        let mut previous: &[u8] = b"";
+        for path in entries {
+            bytes.extend(entry_bytes(path, previous, id, version));
+            previous = path;
+        }
+        if let Some(signature) = extension {
+            bytes.extend_from_slice(signature);
+            bytes.extend_from_slice(&0u32.to_be_bytes());
+        }
+        bytes.extend_from_slice(&[0; ObjectId::LEN]);
+        bytes
+    }
+
+    #[test]
+    fn test_parse_index() {
+        for version in [2, 3, 4] {
+            let bytes = index_bytes(version, &[b"a", b"my-pkg/package.json"], Some(b"TREE"));
+            let index = Index::parse(&bytes, (10, 0)).unwrap();
+            let paths = index
+                .entries
+                .iter()
+                .map(|entry| entry.path.as_slice())
+                .collect::<Vec<_>>();
+            assert_eq!(paths, vec![b"a".as_slice(), b"my-pkg/package.json"]);
+            assert_eq!(index.entries[1].mode, 0o100644);
+            assert_eq!(index.entries[1].size, 4);
+            assert_eq!(index.entries[1].stage, 0);
+        }
+    }
+
+    #[test]
+    fn test_unsupported_index() {
+        let bytes = index_bytes(2, &[b"a"], Some(b"link"));
+        assert!(Index::parse(&bytes, (0, 0)).is_err());
+        let mut bytes = index_bytes(2, &[b"a"], None);
+        bytes[7] = 5;
+        assert!(Index::parse(&bytes, (0, 0)).is_err());
+    }
+}
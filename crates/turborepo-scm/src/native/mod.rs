@@ -0,0 +1,1149 @@
+//! A git backend that reads the index, loose objects, and packfiles directly
+//! instead of spawning git processes.
+//!
+//! Only the subset of git needed for hashing and change detection is
+//! implemented. Anything this backend doesn't understand e.g. SHA-256
+//! repositories, split indexes, content filters, or ambiguous revisions,
+//! results in an error and callers fall back to running git. Shallow clones
+//! are supported as only objects that are present on disk are read.
+
+mod index;
+mod object;
+mod pack;
+
+use std::{
+    collections::{BTreeMap, BinaryHeap, HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use turbopath::{AbsoluteSystemPathBuf, AnchoredSystemPathBuf, RelativeUnixPathBuf};
+
+use self::{
+    index::{file_time, Index, IndexEntry},
+    object::{
+        parse_tag_target, tree_entries, Commit, Kind, Object, ObjectId, GITLINK_MODE, SYMLINK_MODE,
+    },
+    pack::Pack,
+};
+use crate::{ignore::IgnoreRules, package_deps::GitHashes, Error};
+
+// Environment variables that change where git looks for the repository
+const GIT_ENV_OVERRIDES: &[&str] = &[
+    "GIT_DIR",
+    "GIT_WORK_TREE",
+    "GIT_INDEX_FILE",
+    "GIT_OBJECT_DIRECTORY",
+    "GIT_ALTERNATE_OBJECT_DIRECTORIES",
+    "GIT_COMMON_DIR",
+];
+const MAX_SYMREF_DEPTH: usize = 5;
+
+struct CachedIndex {
+    path: PathBuf,
+    mtime: (u32, u32),
+    len: u64,
+    index: Arc<Index>,
+}
+
+pub(crate) struct Repository {
+    // The repository root that files are checked out to
+    work_dir: PathBuf,
+    // The git directory of the worktree, contains HEAD and the index
+    git_dir: PathBuf,
+    // The git directory shared by all worktrees, contains objects and refs
+    common_dir: PathBuf,
+    packs: Vec<Pack>,
+    object_dirs: Vec<PathBuf>,
+    shallow: HashSet<ObjectId>,
+    excludes_file: Option<PathBuf>,
+}
+
+impl Repository {
+    /// Finds the repository containing `path`
+    pub(crate) fn discover(path: &Path) -> Result<Self, Error> {
+        if let Some(var) = GIT_ENV_OVERRIDES
+            .iter()
+            .find(|var| std::env::var_os(var).is_some())
+        {
+            return Err(Error::git_error(format!("{var} is not supported")));
+        }
+
+        let path = dunce::canonicalize(path)?;
+        for dir in path.ancestors() {
+            let dot_git = dir.join(".git");
+            let git_dir = if dot_git.is_dir() {
+                dot_git
+            } else if dot_git.is_file() {
+                // Worktrees and submodules use a file pointing to the git dir
+                let contents = fs::read_to_string(&dot_git)?;
+                let git_dir = contents
+                    .trim_end()
+                    .strip_prefix("gitdir: ")
+                    .ok_or_else(|| Error::git_error("invalid .git file"))?;
+                dir.join(git_dir)
+            } else {
+                continue;
+            };
+            return Self::open(dir.to_path_buf(), git_dir);
+        }
+        Err(Error::git_error(format!(
+            "{} is not in a git repository",
+            path.display()
+        )))
+    }
+
+    fn open(work_dir: PathBuf, git_dir: PathBuf) -> Result<Self, Error> {
+        let common_dir = match fs::read_to_string(git_dir.join("commondir")) {
+            Ok(common_dir) => git_dir.join(common_dir.trim_end()),
+            Err(_) => git_dir.clone(),
+        };
+
+        let config = Config::load(&common_dir.join("config"))?;
+        if config.get("core", "repositoryformatversion").unwrap_or("0") > "1"
+            || config
+                .get("extensions", "objectformat")
+                .map_or(false, |format| !format.eq_ignore_ascii_case("sha1"))
+        {
+            return Err(Error::git_error("unsupported repository format"));
+        }
+        let mut excludes_file = None;
+        for config in Config::global().iter().chain(std::iter::once(&config)) {
+            // Line ending conversion changes the contents that git hashes
+            if config
+                .get("core", "autocrlf")
+                .map_or(false, |value| !value.eq_ignore_ascii_case("false"))
+            {
+                return Err(Error::git_error("core.autocrlf is not supported"));
+            }
+            if let Some(path) = config.get("core", "excludesfile") {
+                excludes_file = Some(expand_home(path));
+            }
+        }
+        let excludes_file = excludes_file.or_else(|| {
+            std::env::var_os("XDG_CONFIG_HOME")
+                .map(PathBuf::from)
+                .or_else(|| home_dir().map(|home| home.join(".config")))
+                .map(|config| config.join("git").join("ignore"))
+        });
+
+        let objects_dir = common_dir.join("objects");
+        let mut object_dirs = vec![objects_dir.clone()];
+        if let Ok(alternates) = fs::read_to_string(objects_dir.join("info").join("alternates")) {
+            object_dirs.extend(
+                alternates
+                    .lines()
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .map(|line| objects_dir.join(line)),
+            );
+        }
+
+        let mut packs = Vec::new();
+        for dir in &object_dirs {
+            let Ok(entries) = fs::read_dir(dir.join("pack")) else {
+                continue;
+            };
+            for entry in entries {
+                let path = entry?.path();
+                if path.extension().map_or(false, |ext| ext == "idx") {
+                    packs.push(Pack::open(&path)?);
+                }
+            }
+        }
+
+        let shallow = match fs::read_to_string(common_dir.join("shallow")) {
+            Ok(shallow) => shallow
+                .lines()
+                .map(|line| ObjectId::from_hex(line.as_bytes()))
+                .collect::<Result<_, _>>()?,
+            Err(_) => HashSet::new(),
+        };
+
+        Ok(Self {
+            work_dir,
+            git_dir,
+            common_dir,
+            packs,
+            object_dirs,
+            shallow,
+            excludes_file,
+        })
+    }
+
+    pub(crate) fn work_dir(&self) -> &Path {
+        &self.work_dir
+    }
+
+    pub(crate) fn read_object(&self, id: &ObjectId) -> Result<Object, Error> {
+        for pack in &self.packs {
+            if let Some(offset) = pack.find(id)? {
+                return pack.read_at(offset, &|base| self.read_object(base));
+            }
+        }
+        let hex = id.to_hex();
+        for dir in &self.object_dirs {
+            match fs::read(dir.join(&hex[..2]).join(&hex[2..])) {
+                Ok(compressed) => return parse_loose_object(&compressed),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Err(Error::git_error(format!("object {id} not found")))
+    }
+
+    fn read_commit(&self, id: &ObjectId) -> Result<Commit, Error> {
+        let object = self.read_object(id)?.expect(Kind::Commit, *id)?;
+        Commit::parse(&object.data)
+    }
+
+    /// Resolves a revision to a commit. Supports full object ids, `HEAD`,
+    /// branch, tag, and remote names, along with `~n` and `^n` suffixes.
+    pub(crate) fn rev_parse(&self, revision: &str) -> Result<ObjectId, Error> {
+        let base_end = revision
+            .find(|c| c == '~' || c == '^')
+            .unwrap_or(revision.len());
+        let (base, mut suffix) = revision.split_at(base_end);
+
+        let mut id = self.resolve_name(base)?;
+        id = self.peel_to_commit(id)?;
+        while !suffix.is_empty() {
+            let operator = suffix.as_bytes()[0];
+            let digits_end = suffix[1..]
+                .find(|c: char| !c.is_ascii_digit())
+                .map_or(suffix.len(), |end| end + 1);
+            let count = match &suffix[1..digits_end] {
+                "" => 1,
+                count => count
+                    .parse::<usize>()
+                    .map_err(|_| Error::git_error(format!("invalid revision {revision}")))?,
+            };
+            suffix = &suffix[digits_end..];
+            id = match operator {
+                b'~' => {
+                    for _ in 0..count {
+                        id = self.parent(&id, 0, revision)?;
+                    }
+                    id
+                }
+                b'^' if count == 0 => id,
+                _ => self.parent(&id, count - 1, revision)?,
+            };
+        }
+        Ok(id)
+    }
+
+    fn parent(&self, id: &ObjectId, n: usize, revision: &str) -> Result<ObjectId, Error> {
+        self.read_commit(id)?
+            .parents
+            .get(n)
+            .copied()
+            .ok_or_else(|| Error::git_error(format!("unable to resolve {revision}")))
+    }
+
+    fn resolve_name(&self, name: &str) -> Result<ObjectId, Error> {
+        if name.len() == ObjectId::LEN * 2 && name.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return ObjectId::from_hex(name.as_bytes());
+        }
+        if name.is_empty() || name.contains("..") || name.contains('@') || name.contains(':') {
+            return Err(Error::git_error(format!("unsupported revision {name}")));
+        }
+        // Same order that git uses to disambiguate ref names
+        let candidates = [
+            name.to_string(),
+            format!("refs/{name}"),
+            format!("refs/tags/{name}"),
+            format!("refs/heads/{name}"),
+            format!("refs/remotes/{name}"),
+            format!("refs/remotes/{name}/HEAD"),
+        ];
+        for candidate in &candidates {
+            if let Some(id) = self.resolve_ref(candidate, 0)? {
+                return Ok(id);
+            }
+        }
+        Err(Error::git_error(format!("unable to resolve {name}")))
+    }
+
+    fn resolve_ref(&self, name: &str, depth: usize) -> Result<Option<ObjectId>, Error> {
+        if depth > MAX_SYMREF_DEPTH {
+            return Err(Error::git_error(format!("symbolic ref loop at {name}")));
+        }
+        // HEAD and other pseudorefs are specific to a worktree
+        let dir = match name.contains('/') {
+            true => &self.common_dir,
+            false => &self.git_dir,
+        };
+        match fs::read_to_string(dir.join(name)) {
+            Ok(contents) => {
+                let contents = contents.trim_end();
+                return match contents.strip_prefix("ref: ") {
+                    Some(target) => self.resolve_ref(target, depth + 1),
+                    None => ObjectId::from_hex(contents.as_bytes()).map(Some),
+                };
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => (),
+            // Directories e.g. refs/heads/feature when asking for
+            // refs/heads aren't refs
+            Err(_) if dir.join(name).is_dir() => (),
+            Err(e) => return Err(e.into()),
+        }
+        if !name.starts_with("refs/") {
+            return Ok(None);
+        }
+        let packed_refs = match fs::read_to_string(self.common_dir.join("packed-refs")) {
+            Ok(packed_refs) => packed_refs,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        packed_refs
+            .lines()
+            .filter(|line| !line.starts_with('#') && !line.starts_with('^'))
+            .filter_map(|line| line.split_once(' '))
+            .find(|(_, ref_name)| *ref_name == name)
+            .map(|(id, _)| ObjectId::from_hex(id.as_bytes()))
+            .transpose()
+    }
+
+    fn peel_to_commit(&self, mut id: ObjectId) -> Result<ObjectId, Error> {
+        loop {
+            let object = self.read_object(&id)?;
+            match object.kind {
+                Kind::Commit => return Ok(id),
+                Kind::Tag => id = parse_tag_target(&object.data)?,
+                kind => {
+                    return Err(Error::git_error(format!(
+                        "{id} is a {kind:?}, not a commit"
+                    )))
+                }
+            }
+        }
+    }
+
+    /// Lists every file under `prefix` in a tree. Paths are relative to
+    /// `prefix` and map to their mode and object id.
+    fn tree_files(
+        &self,
+        tree: ObjectId,
+        prefix: &[u8],
+    ) -> Result<BTreeMap<Vec<u8>, (u32, ObjectId)>, Error> {
+        let mut files = BTreeMap::new();
+        // Walk down to the tree for the prefix
+        let mut tree = tree;
+        for component in prefix.split(|b| *b == b'/').filter(|c| !c.is_empty()) {
+            let object = self.read_object(&tree)?.expect(Kind::Tree, tree)?;
+            let entry = tree_entries(&object.data)
+                .find(|entry| {
+                    entry
+                        .as_ref()
+                        .map_or(true, |entry| entry.name == component && entry.is_tree())
+                })
+                .transpose()?;
+            match entry {
+                Some(entry) => tree = entry.id,
+                None => return Ok(files),
+            }
+        }
+
+        let mut stack = vec![(Vec::new(), tree)];
+        while let Some((dir, tree)) = stack.pop() {
+            let object = self.read_object(&tree)?.expect(Kind::Tree, tree)?;
+            for entry in tree_entries(&object.data) {
+                let entry = entry?;
+                let mut path = dir.clone();
+                if !path.is_empty() {
+                    path.push(b'/');
+                }
+                path.extend_from_slice(entry.name);
+                if entry.is_tree() {
+                    stack.push((path, entry.id));
+                } else {
+                    files.insert(path, (entry.mode, entry.id));
+                }
+            }
+        }
+        Ok(files)
+    }
+
+    /// Reads the contents of a file at `path` in a commit
+    pub(crate) fn file_contents(&self, commit: &ObjectId, path: &[u8]) -> Result<Vec<u8>, Error> {
+        let tree = self.read_commit(commit)?.tree;
+        let (parent, name) = match path.iter().rposition(|b| *b == b'/') {
+            Some(slash) => (&path[..slash], &path[slash + 1..]),
+            None => (&path[..0], path),
+        };
+        let (mode, id) = self.tree_files(tree, parent)?.remove(name).ok_or_else(|| {
+            Error::git_error(format!(
+                "path '{}' does not exist in {commit}",
+                String::from_utf8_lossy(path)
+            ))
+        })?;
+        if mode == GITLINK_MODE {
+            return Err(Error::git_error("unable to read contents of a submodule"));
+        }
+        Ok(self.read_object(&id)?.expect(Kind::Blob, id)?.data)
+    }
+
+    /// Finds the best common ancestor of two commits.
+    ///
+    /// Commits are visited newest first, marking which of the two commits
+    /// they are reachable from. The first commits reachable from both are
+    /// merge base candidates, anything reachable from a candidate is stale.
+    /// Multiple merge bases result in an error.
+    pub(crate) fn merge_base(&self, one: ObjectId, two: ObjectId) -> Result<ObjectId, Error> {
+        const FROM_ONE: u8 = 1;
+        const FROM_TWO: u8 = 2;
+        const STALE: u8 = 4;
+        const RESULT: u8 = 8;
+
+        if one == two {
+            return Ok(one);
+        }
+        let mut flags: HashMap<ObjectId, u8> = HashMap::new();
+        let mut queue = BinaryHeap::new();
+        for (id, flag) in [(one, FROM_ONE), (two, FROM_TWO)] {
+            flags.insert(id, flag);
+            queue.push((self.read_commit(&id)?.committer_time, id));
+        }
+
+        let mut results = Vec::new();
+        while queue
+            .iter()
+            .any(|(_, id)| flags.get(id).map_or(false, |flag| flag & STALE == 0))
+        {
+            let Some((_, id)) = queue.pop() else {
+                break;
+            };
+            let commit_flags = flags[&id];
+            let mut propagate = commit_flags & (FROM_ONE | FROM_TWO | STALE);
+            if propagate == FROM_ONE | FROM_TWO {
+                if commit_flags & RESULT == 0 {
+                    flags.insert(id, commit_flags | RESULT);
+                    results.push(id);
+                }
+                propagate |= STALE;
+            }
+            // History stops at the boundary of a shallow clone
+            if self.shallow.contains(&id) {
+                continue;
+            }
+            for parent in self.read_commit(&id)?.parents {
+                let parent_flags = flags.entry(parent).or_default();
+                if *parent_flags & propagate == propagate {
+                    continue;
+                }
+                *parent_flags |= propagate;
+                queue.push((self.read_commit(&parent)?.committer_time, parent));
+            }
+        }
+
+        // Candidates that became stale are reachable from a better candidate
+        results.retain(|id| flags[id] & STALE == 0);
+        match results.as_slice() {
+            [base] => Ok(*base),
+            [] => Err(Error::git_error(format!(
+                "no merge base for {one} and {two}"
+            ))),
+            _ => Err(Error::git_error(format!(
+                "multiple merge bases for {one} and {two}"
+            ))),
+        }
+    }
+
+    /// Reads the index along with the stat information for the worktree.
+    /// Parsed indexes are cached and reused until the index file changes.
+    pub(crate) fn index(&self) -> Result<Arc<Index>, Error> {
+        static CACHE: Mutex<Option<CachedIndex>> = Mutex::new(None);
+
+        let path = self.git_dir.join("index");
+        let metadata = match fs::metadata(&path) {
+            Ok(metadata) => metadata,
+            // A repository without an index has nothing staged
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(Arc::new(Index::default()));
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let mtime = file_time(metadata.modified().ok())
+            .ok_or_else(|| Error::git_error("unable to read index modification time"))?;
+
+        let mut cache = CACHE.lock().expect("index cache lock poisoned");
+        if let Some(cached) = cache.as_ref() {
+            if cached.path == path && cached.mtime == mtime && cached.len == metadata.len() {
+                return Ok(cached.index.clone());
+            }
+        }
+        let index = Arc::new(Index::parse(&fs::read(&path)?, mtime)?);
+        *cache = Some(CachedIndex {
+            path,
+            mtime,
+            len: metadata.len(),
+            index: index.clone(),
+        });
+        Ok(index)
+    }
+
+    /// Ignore rules that apply to files in `dir` from everything above it.
+    /// Rules from `.gitignore` files in `dir` and below are added while
+    /// walking the directory.
+    fn ignore_rules(&self, dir: &[u8]) -> Result<IgnoreRules, Error> {
+        let mut rules = IgnoreRules::default();
+        if let Some(excludes_file) = &self.excludes_file {
+            rules.add_file(excludes_file, "")?;
+        }
+        rules.add_file(&self.common_dir.join("info").join("exclude"), "")?;
+
+        let dir = utf8(dir)?;
+        let mut base = String::new();
+        rules.add_file(&self.work_dir.join(".gitignore"), "")?;
+        for component in dir.split('/').filter(|c| !c.is_empty()) {
+            if !base.is_empty() {
+                base.push('/');
+            }
+            base.push_str(component);
+            if rules.is_ignored(&base, true) {
+                break;
+            }
+            rules.add_file(&self.work_dir.join(&base).join(".gitignore"), &base)?;
+        }
+        Ok(rules)
+    }
+
+    /// Finds untracked files under `dir` that aren't ignored. Returned paths
+    /// are relative to the repository root.
+    fn untracked_files(&self, dir: &[u8], tracked: &HashSet<&[u8]>) -> Result<Vec<Vec<u8>>, Error> {
+        let rules = self.ignore_rules(dir)?;
+        let mut untracked = Vec::new();
+        if !dir.is_empty() && rules.is_ignored(utf8(dir)?, true) {
+            return Ok(untracked);
+        }
+
+        let mut stack = vec![(dir.to_vec(), rules)];
+        while let Some((dir, mut rules)) = stack.pop() {
+            let dir_str = utf8(&dir)?.to_string();
+            let full_dir = self.work_dir.join(&dir_str);
+            rules.add_file(&full_dir.join(".gitignore"), &dir_str)?;
+            check_attributes(&full_dir.join(".gitattributes"))?;
+
+            let entries = match fs::read_dir(&full_dir) {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            };
+            let mut subdirs = Vec::new();
+            for entry in entries {
+                let entry = entry?;
+                let name = entry.file_name();
+                let name = name
+                    .to_str()
+                    .ok_or_else(|| Error::git_error("non UTF-8 paths are not supported"))?;
+                if name == ".git" {
+                    continue;
+                }
+                let path = match dir_str.as_str() {
+                    "" => name.to_string(),
+                    dir => format!("{dir}/{name}"),
+                };
+                let file_type = entry.file_type()?;
+                if file_type.is_dir() {
+                    // Nested repositories are only tracked as submodules
+                    if tracked.contains(path.as_bytes())
+                        || entry.path().join(".git").exists()
+                        || rules.is_ignored(&path, true)
+                    {
+                        continue;
+                    }
+                    subdirs.push(path);
+                } else if !tracked.contains(path.as_bytes()) && !rules.is_ignored(&path, false) {
+                    untracked.push(path.into_bytes());
+                }
+            }
+            for subdir in subdirs {
+                stack.push((subdir.into_bytes(), rules.clone()));
+            }
+        }
+        Ok(untracked)
+    }
+
+    /// Computes the hash git would assign to a file in the working tree
+    fn hash_file(&self, path: &[u8]) -> Result<ObjectId, Error> {
+        let full_path = self.work_dir.join(utf8(path)?);
+        let metadata = fs::symlink_metadata(&full_path)?;
+        let contents = if metadata.file_type().is_symlink() {
+            // Symlinks are stored as the path they point to
+            let target = fs::read_link(&full_path)?;
+            target
+                .to_str()
+                .ok_or_else(|| Error::git_error("non UTF-8 paths are not supported"))?
+                .replace('\\', "/")
+                .into_bytes()
+        } else {
+            fs::read(&full_path)?
+        };
+        Ok(ObjectId::for_blob(&contents))
+    }
+
+    /// Computes the hash of a tracked file in the working tree, reusing the
+    /// hash in the index if the file hasn't changed. Returns `None` if the
+    /// file was deleted.
+    fn hash_tracked_file(
+        &self,
+        index: &Index,
+        entry: &IndexEntry,
+    ) -> Result<Option<ObjectId>, Error> {
+        if entry.mode == GITLINK_MODE || entry.skip_worktree {
+            return Ok(Some(entry.id));
+        }
+        let full_path = self.work_dir.join(utf8(&entry.path)?);
+        let metadata = match fs::symlink_metadata(full_path) {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        // A file replaced by a directory is deleted as far as git is concerned
+        if metadata.is_dir() {
+            return Ok(None);
+        }
+        if !entry.intent_to_add
+            && entry.mode != SYMLINK_MODE
+            && index.is_unchanged(entry, &metadata)
+        {
+            return Ok(Some(entry.id));
+        }
+        self.hash_file(&entry.path).map(Some)
+    }
+}
+
+/// Computes the hashes of all files in a package the same way
+/// `get_package_file_hashes_from_git_index` does with git subprocesses:
+/// committed files, modified files, and untracked files that aren't ignored.
+pub(crate) fn package_file_hashes(
+    turbo_root: &AbsoluteSystemPathBuf,
+    package_path: &AnchoredSystemPathBuf,
+) -> Result<GitHashes, Error> {
+    let repo = Repository::discover(turbo_root.as_path())?;
+    let work_dir = AbsoluteSystemPathBuf::new(repo.work_dir().to_path_buf())?;
+    let package_dir = AbsoluteSystemPathBuf::new(dunce::canonicalize(
+        turbo_root.resolve(package_path).as_path(),
+    )?)?;
+    let prefix = work_dir.anchor(package_dir)?.to_unix()?;
+    file_hashes_under(&repo, prefix.as_str()?.as_bytes())
+}
+
+/// Computes the hashes of every file in the repository containing
+/// `git_root`. Paths are relative to the repository root.
+pub(crate) fn repo_file_hashes(git_root: &Path) -> Result<GitHashes, Error> {
+    let repo = Repository::discover(git_root)?;
+    file_hashes_under(&repo, b"")
+}
+
+// Hashes of files under `prefix` with paths relative to `prefix`
+fn file_hashes_under(repo: &Repository, prefix: &[u8]) -> Result<GitHashes, Error> {
+    check_attributes_above(repo, prefix)?;
+
+    let index = repo.index()?;
+    let entries = index_entries_under(&index, prefix);
+    let tracked = entries
+        .iter()
+        .map(|entry| entry.path.as_slice())
+        .collect::<HashSet<_>>();
+
+    let mut hashes = GitHashes::new();
+    for entry in &entries {
+        if let Some(id) = repo.hash_tracked_file(&index, entry)? {
+            hashes.insert(package_relative(&entry.path, prefix)?, id.to_hex());
+        }
+    }
+    for path in repo.untracked_files(prefix, &tracked)? {
+        let id = repo.hash_file(&path)?;
+        hashes.insert(package_relative(&path, prefix)?, id.to_hex());
+    }
+    Ok(hashes)
+}
+
+/// Files that differ between the working tree and `to_commit`, between the
+/// merge base of `from_commit` and `to_commit`, and untracked files. Paths are
+/// relative to the repository root and limited to `pathspec`.
+///
+/// Renames are reported by git as only the new path, which requires content
+/// similarity detection. If a diff contains both added and deleted files an
+/// error is returned so the caller can defer to git.
+pub(crate) fn changed_files(
+    git_root: &Path,
+    pathspec: &str,
+    from_commit: Option<&str>,
+    to_commit: &str,
+) -> Result<HashSet<String>, Error> {
+    let repo = Repository::discover(git_root)?;
+    let prefix = pathspec.as_bytes();
+    check_attributes_above(&repo, prefix)?;
+    let mut files = HashSet::new();
+
+    // Working tree compared to `to_commit`
+    let to = repo.rev_parse(to_commit)?;
+    let to_tree = repo.tree_files(repo.read_commit(&to)?.tree, prefix)?;
+    let index = repo.index()?;
+    let entries = index_entries_under(&index, prefix);
+    let mut working_tree = BTreeMap::new();
+    for entry in &entries {
+        if let Some(id) = repo.hash_tracked_file(&index, entry)? {
+            working_tree.insert(
+                package_relative_bytes(&entry.path, prefix),
+                (entry.mode, id),
+            );
+        }
+    }
+    add_diff(&mut files, prefix, &to_tree, &working_tree)?;
+
+    if let Some(from_commit) = from_commit {
+        let from = repo.rev_parse(from_commit)?;
+        let base = repo.merge_base(from, to)?;
+        let base_tree = repo.tree_files(repo.read_commit(&base)?.tree, prefix)?;
+        add_diff(&mut files, prefix, &base_tree, &to_tree)?;
+    }
+
+    let tracked = entries
+        .iter()
+        .map(|entry| entry.path.as_slice())
+        .collect::<HashSet<_>>();
+    for path in repo.untracked_files(prefix, &tracked)? {
+        files.insert(String::from_utf8(path)?);
+    }
+    Ok(files)
+}
+
+/// Reads the contents of a file relative to the repository root at a commit
+pub(crate) fn previous_content(
+    git_root: &Path,
+    from_commit: &str,
+    path: &RelativeUnixPathBuf,
+) -> Result<Vec<u8>, Error> {
+    let repo = Repository::discover(git_root)?;
+    let commit = repo.rev_parse(from_commit)?;
+    repo.file_contents(&commit, path.as_str()?.as_bytes())
+}
+
+// Adds the paths that differ between two sets of files to `files`
+fn add_diff(
+    files: &mut HashSet<String>,
+    prefix: &[u8],
+    old: &BTreeMap<Vec<u8>, (u32, ObjectId)>,
+    new: &BTreeMap<Vec<u8>, (u32, ObjectId)>,
+) -> Result<(), Error> {
+    let added = new.keys().filter(|path| !old.contains_key(*path));
+    let deleted = old.keys().filter(|path| !new.contains_key(*path));
+    if added.clone().next().is_some() && deleted.clone().next().is_some() {
+        return Err(Error::git_error(
+            "diffs with possible renames are not supported",
+        ));
+    }
+    let modified = new
+        .iter()
+        .filter(|(path, file)| old.get(*path).map_or(false, |old| old != *file))
+        .map(|(path, _)| path);
+    for path in added.chain(deleted).chain(modified) {
+        let path = match prefix.is_empty() {
+            true => path.clone(),
+            false => [prefix, b"/", path].concat(),
+        };
+        files.insert(String::from_utf8(path)?);
+    }
+    Ok(())
+}
+
+fn index_entries_under<'a>(index: &'a Index, prefix: &[u8]) -> Vec<&'a IndexEntry> {
+    index
+        .entries
+        .iter()
+        // Only the first entry of a path with merge conflicts is needed
+        .filter(|entry| entry.stage <= 1)
+        .filter(|entry| is_under(&entry.path, prefix))
+        .collect()
+}
+
+fn is_under(path: &[u8], prefix: &[u8]) -> bool {
+    prefix.is_empty() || (path.starts_with(prefix) && path.get(prefix.len()) == Some(&b'/'))
+}
+
+fn package_relative_bytes(path: &[u8], prefix: &[u8]) -> Vec<u8> {
+    match prefix.is_empty() {
+        true => path.to_vec(),
+        false => path[prefix.len() + 1..].to_vec(),
+    }
+}
+
+fn package_relative(path: &[u8], prefix: &[u8]) -> Result<RelativeUnixPathBuf, Error> {
+    Ok(RelativeUnixPathBuf::new(package_relative_bytes(
+        path, prefix,
+    ))?)
+}
+
+fn parse_loose_object(compressed: &[u8]) -> Result<Object, Error> {
+    let mut data = Vec::new();
+    std::io::Read::read_to_end(&mut flate2::read::ZlibDecoder::new(compressed), &mut data)?;
+    // Loose objects start with a `<type> <size>\0` header
+    let header_end = data
+        .iter()
+        .position(|b| *b == 0)
+        .ok_or_else(|| Error::git_error("invalid loose object header"))?;
+    let header = &data[..header_end];
+    let (kind, size) = header
+        .iter()
+        .position(|b| *b == b' ')
+        .map(|space| (&header[..space], &header[space + 1..]))
+        .ok_or_else(|| Error::git_error("invalid loose object header"))?;
+    let kind = Kind::from_name(kind)?;
+    let size = std::str::from_utf8(size)
+        .ok()
+        .and_then(|size| size.parse::<usize>().ok())
+        .ok_or_else(|| Error::git_error("invalid loose object size"))?;
+    let data = data.split_off(header_end + 1);
+    if data.len() != size {
+        return Err(Error::git_error("loose object size mismatch"));
+    }
+    Ok(Object { kind, data })
+}
+
+// Attributes that change the contents git hashes for a file
+const CONTENT_ATTRIBUTES: &[&str] = &[
+    "filter",
+    "text",
+    "eol",
+    "crlf",
+    "ident",
+    "working-tree-encoding",
+];
+
+/// Checks that the attributes file at `path` doesn't use attributes that
+/// change how file contents are hashed
+fn check_attributes(path: &Path) -> Result<(), Error> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+    let uses_content_attributes = contents
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .flat_map(|line| line.split_whitespace().skip(1))
+        .map(|attribute| attribute.trim_start_matches(['-', '!']))
+        .any(|attribute| {
+            CONTENT_ATTRIBUTES
+                .iter()
+                .any(|name| attribute == *name || attribute.starts_with(&format!("{name}=")))
+        });
+    match uses_content_attributes {
+        true => Err(Error::git_error(format!(
+            "content attributes in {} are not supported",
+            path.display()
+        ))),
+        false => Ok(()),
+    }
+}
+
+// Checks the attributes of the repository and every directory down to `dir`
+fn check_attributes_above(repo: &Repository, dir: &[u8]) -> Result<(), Error> {
+    check_attributes(&repo.common_dir.join("info").join("attributes"))?;
+    let mut path = repo.work_dir.clone();
+    check_attributes(&path.join(".gitattributes"))?;
+    for component in utf8(dir)?.split('/').filter(|c| !c.is_empty()) {
+        path.push(component);
+        check_attributes(&path.join(".gitattributes"))?;
+    }
+    Ok(())
+}
+
+fn utf8(bytes: &[u8]) -> Result<&str, Error> {
+    std::str::from_utf8(bytes).map_err(|_| Error::git_error("non UTF-8 paths are not supported"))
+}
+
+fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(PathBuf::from)
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// The handful of git config values the native backend needs. Values are
+/// keyed by lowercase `section.key`, subsections and includes are ignored.
+#[derive(Debug, Default)]
+struct Config {
+    values: HashMap<String, String>,
+}
+
+impl Config {
+    fn load(path: &Path) -> Result<Self, Error> {
+        match fs::read_to_string(path) {
+            Ok(contents) => Ok(Self::parse(&contents)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    // System and user config files in order of increasing precedence
+    fn global() -> Vec<Self> {
+        let mut paths = vec![PathBuf::from("/etc/gitconfig")];
+        if let Some(config) = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| home_dir().map(|home| home.join(".config")))
+        {
+            paths.push(config.join("git").join("config"));
+        }
+        if let Some(home) = home_dir() {
+            paths.push(home.join(".gitconfig"));
+        }
+        paths
+            .iter()
+            .filter_map(|path| Self::load(path).ok())
+            .collect()
+    }
+
+    fn parse(contents: &str) -> Self {
+        let mut values = HashMap::new();
+        let mut section = String::new();
+        for line in contents.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+            if let Some(header) = line.strip_prefix('[') {
+                let header = header.split(']').next().unwrap_or_default();
+                section = header
+                    .split_whitespace()
+                    .next()
+                    .unwrap_or_default()
+                    .to_ascii_lowercase();
+                // Subsections e.g. [remote "origin"] aren't needed
+                if header.contains('"') {
+                    section.clear();
+                }
+                continue;
+            }
+            if section.is_empty() {
+                continue;
+            }
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                // A key without a value is a boolean true
+                None => (line, "true"),
+            };
+            let value = value.trim_matches('"');
+            values.insert(
+                format!("{section}.{}", key.to_ascii_lowercase()),
+                value.to_string(),
+            );
+        }
+        Self { values }
+    }
+
+    fn get(&self, section: &str, key: &str) -> Option<&str> {
+        self.values
+            .get(&format!("{section}.{key}"))
+            .map(|value| value.as_str())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::process::Command;
+
+    use super::*;
+
+    fn git(dir: &Path, args: &[&str]) -> String {
+        let output = Command::new("git")
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "git {args:?} failed: {output:?}");
+        String::from_utf8(output.stdout).unwrap().trim().to_string()
+    }
+
+    fn setup_repository() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for args in [
+            &["init", "."][..],
+            &["config", "--local", "user.name", "test"],
+            &["config", "--local", "user.email", "test@example.com"],
+        ] {
+            git(dir.path(), args);
+        }
+        dir
+    }
+
+    fn commit(dir: &Path, files: &[(&str, &str)], message: &str) -> String {
+        for (path, contents) in files {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+        git(dir, &["add", "."]);
+        git(dir, &["commit", "-m", message]);
+        git(dir, &["rev-parse", "HEAD"])
+    }
+
+    #[test]
+    fn test_read_loose_and_packed_objects() {
+        let dir = setup_repository();
+        let first = commit(dir.path(), &[("a.txt", "a"), ("dir/b.txt", "b")], "first");
+        let second = commit(dir.path(), &[("a.txt", "changed")], "second");
+
+        let repo = Repository::discover(dir.path()).unwrap();
+        assert_eq!(repo.rev_parse("HEAD").unwrap().to_hex(), second);
+        assert_eq!(repo.rev_parse("HEAD~1").unwrap().to_hex(), first);
+        assert_eq!(repo.rev_parse("HEAD^").unwrap().to_hex(), first);
+        assert_eq!(
+            repo.file_contents(&repo.rev_parse(&first).unwrap(), b"dir/b.txt")
+                .unwrap(),
+            b"b"
+        );
+
+        // The same lookups should work once everything is in a packfile
+        git(dir.path(), &["gc", "--aggressive", "--prune=now"]);
+        let repo = Repository::discover(dir.path()).unwrap();
+        assert!(!repo.packs.is_empty());
+        let branch = git(dir.path(), &["branch", "--show-current"]);
+        assert_eq!(repo.rev_parse(&branch).unwrap().to_hex(), second);
+        let head = repo.rev_parse("HEAD").unwrap();
+        assert_eq!(repo.file_contents(&head, b"a.txt").unwrap(), b"changed");
+        assert_eq!(
+            repo.file_contents(&repo.rev_parse(&first).unwrap(), b"a.txt")
+                .unwrap(),
+            b"a"
+        );
+    }
+
+    #[test]
+    fn test_merge_base() {
+        let dir = setup_repository();
+        let base = commit(dir.path(), &[("a.txt", "a")], "base");
+        git(dir.path(), &["checkout", "-b", "feature"]);
+        let feature = commit(dir.path(), &[("b.txt", "b")], "feature");
+        git(dir.path(), &["checkout", "-"]);
+        let main = commit(dir.path(), &[("c.txt", "c")], "main");
+
+        let repo = Repository::discover(dir.path()).unwrap();
+        let main = ObjectId::from_hex(main.as_bytes()).unwrap();
+        let feature = ObjectId::from_hex(feature.as_bytes()).unwrap();
+        assert_eq!(repo.merge_base(main, feature).unwrap().to_hex(), base);
+        assert_eq!(repo.merge_base(feature, main).unwrap().to_hex(), base);
+    }
+
+    #[test]
+    fn test_matches_git_status() {
+        let dir = setup_repository();
+        commit(
+            dir.path(),
+            &[
+                (".gitignore", "*.log\n"),
+                ("pkg/committed", "committed"),
+                ("pkg/modified", "original"),
+                ("pkg/deleted", "deleted"),
+                ("pkg/nested/.gitignore", "ignored-dir/\n"),
+                ("other/file", "other"),
+            ],
+            "first",
+        );
+        fs::write(dir.path().join("pkg/modified"), "modified").unwrap();
+        fs::remove_file(dir.path().join("pkg/deleted")).unwrap();
+        fs::write(dir.path().join("pkg/untracked"), "untracked").unwrap();
+        fs::write(dir.path().join("pkg/debug.log"), "ignored").unwrap();
+        fs::create_dir_all(dir.path().join("pkg/nested/ignored-dir")).unwrap();
+        fs::write(dir.path().join("pkg/nested/ignored-dir/file"), "ignored").unwrap();
+
+        let turbo_root = AbsoluteSystemPathBuf::new(dir.path().to_path_buf()).unwrap();
+        let package_path = AnchoredSystemPathBuf::from_raw("pkg").unwrap();
+        let hashes = package_file_hashes(&turbo_root, &package_path).unwrap();
+        let mut paths = hashes
+            .keys()
+            .map(|path| path.as_str().unwrap())
+            .collect::<Vec<_>>();
+        paths.sort();
+        assert_eq!(
+            paths,
+            vec!["committed", "modified", "nested/.gitignore", "untracked"]
+        );
+        assert_eq!(
+            hashes[&RelativeUnixPathBuf::new("modified").unwrap()],
+            git(dir.path(), &["hash-object", "pkg/modified"])
+        );
+    }
+
+    #[test]
+    fn test_shallow_clone() {
+        let origin = setup_repository();
+        commit(origin.path(), &[("a.txt", "a")], "first");
+        commit(origin.path(), &[("b.txt", "b")], "second");
+        commit(
+            origin.path(),
+            &[("c.txt", "c"), ("a.txt", "changed")],
+            "third",
+        );
+
+        let clone = tempfile::tempdir().unwrap();
+        let url = format!("file://{}", origin.path().display());
+        git(
+            clone.path(),
+            &[
+                "clone",
+                "--depth",
+                "2",
+                &url,
+                clone.path().to_str().unwrap(),
+            ],
+        );
+
+        let changed = changed_files(clone.path(), "", Some("HEAD~1"), "HEAD").unwrap();
+        assert_eq!(
+            changed,
+            HashSet::from(["a.txt".to_string(), "c.txt".to_string()])
+        );
+        // The parent of HEAD~1 isn't part of the clone
+        assert!(changed_files(clone.path(), "", Some("HEAD~2"), "HEAD").is_err());
+    }
+
+    #[test]
+    fn test_content_filters_unsupported() {
+        let dir = setup_repository();
+        commit(
+            dir.path(),
+            &[(".gitattributes", "*.bin filter=lfs\n"), ("pkg/a", "a")],
+            "first",
+        );
+        let turbo_root = AbsoluteSystemPathBuf::new(dir.path().to_path_buf()).unwrap();
+        let package_path = AnchoredSystemPathBuf::from_raw("pkg").unwrap();
+        assert!(package_file_hashes(&turbo_root, &package_path).is_err());
+    }
+
+    #[test]
+    fn test_info_attributes_unsupported() {
+        let dir = setup_repository();
+        commit(dir.path(), &[("pkg/a", "a")], "first");
+        let turbo_root = AbsoluteSystemPathBuf::new(dir.path().to_path_buf()).unwrap();
+        let package_path = AnchoredSystemPathBuf::from_raw("pkg").unwrap();
+        assert!(package_file_hashes(&turbo_root, &package_path).is_ok());
+
+        let info_dir = dir.path().join(".git").join("info");
+        fs::create_dir_all(&info_dir).unwrap();
+        fs::write(info_dir.join("attributes"), "* crlf\n").unwrap();
+        assert!(package_file_hashes(&turbo_root, &package_path).is_err());
+    }
+
+    #[test]
+    fn test_parse_config() {
+        let config = Config::parse(
+            "[core]\n\trepositoryformatversion = 0\n\tbare\n[remote \"origin\"]\n\turl = \
+             x\n[Extensions]\n\tobjectFormat = \"sha256\"\n",
+        );
+        assert_eq!(config.get("core", "repositoryformatversion"), Some("0"));
+        assert_eq!(config.get("core", "bare"), Some("true"));
+        assert_eq!(config.get("remote", "url"), None);
+        assert_eq!(config.get("extensions", "objectformat"), Some("sha256"));
+    }
+}
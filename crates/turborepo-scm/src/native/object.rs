@@ -0,0 +1,267 @@
+use std::fmt;
+
+use sha1::{Digest, Sha1};
+
+use crate::Error;
+
+pub(crate) const TREE_MODE: u32 = 0o040000;
+pub(crate) const SYMLINK_MODE: u32 = 0o120000;
+pub(crate) const GITLINK_MODE: u32 = 0o160000;
+
+/// A SHA-1 object id
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) struct ObjectId([u8; 20]);
+
+impl ObjectId {
+    pub(crate) const LEN: usize = 20;
+
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let bytes = bytes
+            .try_into()
+            .map_err(|_| Error::git_error(format!("invalid object id length {}", bytes.len())))?;
+        Ok(Self(bytes))
+    }
+
+    pub(crate) fn from_hex(hex: &[u8]) -> Result<Self, Error> {
+        let mut bytes = [0; Self::LEN];
+        hex::decode_to_slice(hex, &mut bytes).map_err(|_| {
+            Error::git_error(format!(
+                "invalid object id {}",
+                String::from_utf8_lossy(hex)
+            ))
+        })?;
+        Ok(Self(bytes))
+    }
+
+    pub(crate) fn as_bytes(&self) -> &[u8; Self::LEN] {
+        &self.0
+    }
+
+    pub(crate) fn to_hex(self) -> String {
+        hex::encode(self.0)
+    }
+
+    /// The id git assigns to a blob with the given contents
+    pub(crate) fn for_blob(contents: &[u8]) -> Self {
+        let mut hasher = Sha1::new();
+        hasher.update(format!("blob {}\0", contents.len()).as_bytes());
+        hasher.update(contents);
+        Self(hasher.finalize().into())
+    }
+}
+
+impl fmt::Display for ObjectId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_hex())
+    }
+}
+
+impl fmt::Debug for ObjectId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ObjectId({self})")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Kind {
+    Commit,
+    Tree,
+    Blob,
+    Tag,
+}
+
+impl Kind {
+    pub(crate) fn from_name(name: &[u8]) -> Result<Self, Error> {
+        match name {
+            b"commit" => Ok(Kind::Commit),
+            b"tree" => Ok(Kind::Tree),
+            b"blob" => Ok(Kind::Blob),
+            b"tag" => Ok(Kind::Tag),
+            _ => Err(Error::git_error(format!(
+                "unknown object type {}",
+                String::from_utf8_lossy(name)
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Object {
+    pub(crate) kind: Kind,
+    pub(crate) data: Vec<u8>,
+}
+
+impl Object {
+    pub(crate) fn expect(self, kind: Kind, id: ObjectId) -> Result<Self, Error> {
+        if self.kind != kind {
+            return Err(Error::git_error(format!(
+                "expected {id} to be a {kind:?}, found {:?}",
+                self.kind
+            )));
+        }
+        Ok(self)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Commit {
+    pub(crate) tree: ObjectId,
+    pub(crate) parents: Vec<ObjectId>,
+    pub(crate) committer_time: i64,
+}
+
+impl Commit {
+    pub(crate) fn parse(data: &[u8]) -> Result<Self, Error> {
+        let mut tree = None;
+        let mut parents = Vec::new();
+        let mut committer_time = 0;
+        for line in data.split(|b| *b == b'\n') {
+            // Headers end at the first empty line
+            if line.is_empty() {
+                break;
+            }
+            if let Some(hex) = line.strip_prefix(b"tree ") {
+                tree = Some(ObjectId::from_hex(hex)?);
+            } else if let Some(hex) = line.strip_prefix(b"parent ") {
+                parents.push(ObjectId::from_hex(hex)?);
+            } else if let Some(committer) = line.strip_prefix(b"committer ") {
+                // committer <name> <email> <timestamp> <timezone>
+                committer_time = committer
+                    .rsplitn(3, |b| *b == b' ')
+                    .nth(1)
+                    .and_then(|time| std::str::from_utf8(time).ok())
+                    .and_then(|time| time.parse().ok())
+                    .unwrap_or_default();
+            }
+        }
+        let tree = tree.ok_or_else(|| Error::git_error("commit is missing a tree"))?;
+        Ok(Self {
+            tree,
+            parents,
+            committer_time,
+        })
+    }
+}
+
+/// Returns the id of the object an annotated tag points to
+pub(crate) fn parse_tag_target(data: &[u8]) -> Result<ObjectId, Error> {
+    data.split(|b| *b == b'\n')
+        .find_map(|line| line.strip_prefix(b"object "))
+        .ok_or_else(|| Error::git_error("tag is missing an object"))
+        .and_then(ObjectId::from_hex)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct TreeEntry<'a> {
+    pub(crate) mode: u32,
+    pub(crate) name: &'a [u8],
+    pub(crate) id: ObjectId,
+}
+
+impl<'a> TreeEntry<'a> {
+    pub(crate) fn is_tree(&self) -> bool {
+        self.mode == TREE_MODE
+    }
+}
+
+/// Iterates over the entries of a tree object.
+/// Each entry is stored as `<octal mode> <name>\0<20 byte id>`
+pub(crate) fn tree_entries(data: &[u8]) -> impl Iterator<Item = Result<TreeEntry<'_>, Error>> {
+    let mut rest = data;
+    std::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+        let entry = (|| {
+            let space = rest
+                .iter()
+                .position(|b| *b == b' ')
+                .ok_or_else(|| Error::git_error("malformed tree entry"))?;
+            let mode = std::str::from_utf8(&rest[..space])
+                .ok()
+                .and_then(|mode| u32::from_str_radix(mode, 8).ok())
+                .ok_or_else(|| Error::git_error("malformed tree entry mode"))?;
+            let name_end = rest[space..]
+                .iter()
+                .position(|b| *b == 0)
+                .map(|nul| space + nul)
+                .ok_or_else(|| Error::git_error("malformed tree entry name"))?;
+            let id_end = name_end + 1 + ObjectId::LEN;
+            if rest.len() < id_end {
+                return Err(Error::git_error("truncated tree entry"));
+            }
+            let entry = TreeEntry {
+                mode,
+                name: &rest[space + 1..name_end],
+                id: ObjectId::from_bytes(&rest[name_end + 1..id_end])?,
+            };
+            rest = &rest[id_end..];
+            Ok(entry)
+        })();
+        if entry.is_err() {
+            rest = &[];
+        }
+        Some(entry)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_blob_id() {
+        assert_eq!(
+            ObjectId::for_blob(b"").to_hex(),
+            "e69de29bb2d1d6434b8b29ae775ad8c2e48c5391"
+        );
+        assert_eq!(
+            ObjectId::for_blob(b"committed bytes").to_hex(),
+            "3a29e62ea9ba15c4a4009d1f605d391cdd262033"
+        );
+    }
+
+    #[test]
+    fn test_parse_commit() {
+        let data = b"tree 3a29e62ea9ba15c4a4009d1f605d391cdd262033\nparent \
+                     e69de29bb2d1d6434b8b29ae775ad8c2e48c5391\nauthor test <test@example.com> \
+                     1680000000 +0000\ncommitter test <test@example.com> 1680000001 \
+                     +0000\n\nparent in the message\n";
+        let commit = Commit::parse(data).unwrap();
+        assert_eq!(
+            commit.tree.to_hex(),
+            "3a29e62ea9ba15c4a4009d1f605d391cdd262033"
+        );
+        assert_eq!(commit.parents.len(), 1);
+        assert_eq!(commit.committer_time, 1680000001);
+    }
+
+    #[test]
+    fn test_tree_entries() {
+        let id = ObjectId::for_blob(b"");
+        let mut data = b"100644 package.json\0".to_vec();
+        data.extend_from_slice(id.as_bytes());
+        data.extend_from_slice(b"40000 src\0");
+        data.extend_from_slice(id.as_bytes());
+        let entries = tree_entries(&data).collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                TreeEntry {
+                    mode: 0o100644,
+                    name: b"package.json",
+                    id
+                },
+                TreeEntry {
+                    mode: TREE_MODE,
+                    name: b"src",
+                    id
+                },
+            ]
+        );
+        assert!(tree_entries(b"100644 truncated\0abc")
+            .next()
+            .unwrap()
+            .is_err());
+    }
+}
@@ -0,0 +1,438 @@
+use std::{fs::File, io::Read, path::Path};
+
+use flate2::read::ZlibDecoder;
+use memmap2::Mmap;
+
+use super::object::{Kind, Object, ObjectId};
+use crate::Error;
+
+const IDX_MAGIC: &[u8] = b"\xfftOc";
+const IDX_HEADER_LEN: usize = 8;
+const FANOUT_LEN: usize = 256 * 4;
+const PACK_HEADER_LEN: usize = 12;
+// Each object has an id, a crc32, and a 32 bit offset in the index
+const IDX_ENTRY_LEN: usize = ObjectId::LEN + 4 + 4;
+// The index ends with the checksum of the pack and of the index itself
+const IDX_TRAILER_LEN: usize = 2 * ObjectId::LEN;
+// Deltas chains longer than this are either corrupt or cyclic
+const MAX_DELTA_CHAIN: usize = 10_000;
+// Object sizes come from the pack, so we don't trust them for allocations
+// larger than this and let the buffer grow as data is actually inflated
+const MAX_PREALLOCATION: usize = 16 * 1024 * 1024;
+
+const OBJ_COMMIT: u8 = 1;
+const OBJ_TREE: u8 = 2;
+const OBJ_BLOB: u8 = 3;
+const OBJ_TAG: u8 = 4;
+const OBJ_OFS_DELTA: u8 = 6;
+const OBJ_REF_DELTA: u8 = 7;
+
+/// A packfile along with its version 2 index
+pub(crate) struct Pack {
+    index: Mmap,
+    data: Mmap,
+    count: usize,
+}
+
+enum Entry<'a> {
+    Base(Kind, &'a [u8], usize),
+    OfsDelta(usize, &'a [u8], usize),
+    RefDelta(ObjectId, &'a [u8], usize),
+}
+
+impl Pack {
+    /// Opens the packfile that belongs to the given `.idx` file
+    pub(crate) fn open(index_path: &Path) -> Result<Self, Error> {
+        let index = map(index_path)?;
+        let data = map(&index_path.with_extension("pack"))?;
+
+        if index.len() < IDX_HEADER_LEN + FANOUT_LEN || &index[..4] != IDX_MAGIC {
+            return Err(Error::git_error(format!(
+                "unsupported pack index {}",
+                index_path.display()
+            )));
+        }
+        if read_u32(&index, 4)? != 2 {
+            return Err(Error::git_error(format!(
+                "unsupported pack index version in {}",
+                index_path.display()
+            )));
+        }
+        if data.len() < PACK_HEADER_LEN || &data[..4] != b"PACK" {
+            return Err(Error::git_error(format!(
+                "invalid packfile for {}",
+                index_path.display()
+            )));
+        }
+        let count = read_u32(&index, IDX_HEADER_LEN + 255 * 4)? as usize;
+        let min_len = count
+            .checked_mul(IDX_ENTRY_LEN)
+            .and_then(|entries| entries.checked_add(IDX_HEADER_LEN + FANOUT_LEN + IDX_TRAILER_LEN));
+        if min_len.map_or(true, |min_len| index.len() < min_len) {
+            return Err(Error::git_error(format!(
+                "truncated pack index {}",
+                index_path.display()
+            )));
+        }
+        Ok(Self { index, data, count })
+    }
+
+    /// Returns the offset of the object in the packfile if it's in this pack
+    pub(crate) fn find(&self, id: &ObjectId) -> Result<Option<usize>, Error> {
+        let first = id.as_bytes()[0] as usize;
+        let start = match first {
+            0 => 0,
+            first => read_u32(&self.index, IDX_HEADER_LEN + (first - 1) * 4)? as usize,
+        };
+        let end = read_u32(&self.index, IDX_HEADER_LEN + first * 4)? as usize;
+        if start > end || end > self.count {
+            return Err(Error::git_error("invalid pack index fanout table"));
+        }
+
+        let ids_start = IDX_HEADER_LEN + FANOUT_LEN;
+        let (mut low, mut high) = (start, end);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            let offset = ids_start + mid * ObjectId::LEN;
+            let mid_id = self
+                .index
+                .get(offset..offset + ObjectId::LEN)
+                .ok_or_else(|| Error::git_error("truncated pack index"))?;
+            match mid_id.cmp(id.as_bytes()) {
+                std::cmp::Ordering::Less => low = mid + 1,
+                std::cmp::Ordering::Greater => high = mid,
+                std::cmp::Ordering::Equal => return self.offset(mid).map(Some),
+            }
+        }
+        Ok(None)
+    }
+
+    fn offset(&self, i: usize) -> Result<usize, Error> {
+        let crcs_start = IDX_HEADER_LEN + FANOUT_LEN + self.count * ObjectId::LEN;
+        let offsets_start = crcs_start + self.count * 4;
+        let offset = read_u32(&self.index, offsets_start + i * 4)?;
+        // The most significant bit marks offsets that don't fit in 31 bits,
+        // the rest is an index into the table of 64 bit offsets.
+        if offset & 0x8000_0000 == 0 {
+            return Ok(offset as usize);
+        }
+        let large_offsets_start = offsets_start + self.count * 4;
+        let i = (offset & 0x7fff_ffff) as usize;
+        let offset = read_u64(&self.index, large_offsets_start + i * 8)?;
+        usize::try_from(offset).map_err(|_| Error::git_error("pack offset out of bounds"))
+    }
+
+    /// Reads the object at the given offset. Bases of `REF_DELTA` objects that
+    /// aren't in this pack are looked up with `find_base`.
+    pub(crate) fn read_at(
+        &self,
+        offset: usize,
+        find_base: &dyn Fn(&ObjectId) -> Result<Object, Error>,
+    ) -> Result<Object, Error> {
+        let mut deltas = Vec::new();
+        let mut offset = offset;
+        let mut base = loop {
+            if deltas.len() > MAX_DELTA_CHAIN {
+                return Err(Error::git_error("delta chain is too long"));
+            }
+            match self.entry(offset)? {
+                Entry::Base(kind, compressed, size) => {
+                    break Object {
+                        kind,
+                        data: inflate(compressed, size)?,
+                    };
+                }
+                Entry::OfsDelta(base_offset, compressed, size) => {
+                    deltas.push(inflate(compressed, size)?);
+                    offset = base_offset;
+                }
+                Entry::RefDelta(base_id, compressed, size) => {
+                    deltas.push(inflate(compressed, size)?);
+                    match self.find(&base_id)? {
+                        Some(base_offset) => offset = base_offset,
+                        None => break find_base(&base_id)?,
+                    }
+                }
+            }
+        };
+
+        for delta in deltas.iter().rev() {
+            base.data = apply_delta(&base.data, delta)?;
+        }
+        Ok(base)
+    }
+
+    fn entry(&self, offset: usize) -> Result<Entry<'_>, Error> {
+        let data = &self.data[..];
+        let mut pos = offset;
+        let mut byte = *data
+            .get(pos)
+            .ok_or_else(|| Error::git_error("pack offset out of bounds"))?;
+        pos += 1;
+        let kind = (byte >> 4) & 0b111;
+        let mut size = (byte & 0x0f) as usize;
+        let mut shift = 4;
+        while byte & 0x80 != 0 {
+            byte = *data
+                .get(pos)
+                .ok_or_else(|| Error::git_error("truncated pack entry"))?;
+            pos += 1;
+            if shift >= usize::BITS {
+                return Err(Error::git_error("pack entry size is too large"));
+            }
+            size |= ((byte & 0x7f) as usize) << shift;
+            shift += 7;
+        }
+
+        match kind {
+            OBJ_COMMIT | OBJ_TREE | OBJ_BLOB | OBJ_TAG => {
+                let kind = match kind {
+                    OBJ_COMMIT => Kind::Commit,
+                    OBJ_TREE => Kind::Tree,
+                    OBJ_BLOB => Kind::Blob,
+                    _ => Kind::Tag,
+                };
+                Ok(Entry::Base(kind, &data[pos..], size))
+            }
+            OBJ_OFS_DELTA => {
+                let (distance, read) = read_offset_varint(&data[pos..])?;
+                let base_offset = offset
+                    .checked_sub(distance)
+                    .ok_or_else(|| Error::git_error("invalid delta base offset"))?;
+                Ok(Entry::OfsDelta(base_offset, &data[pos + read..], size))
+            }
+            OBJ_REF_DELTA => {
+                let id_end = pos + ObjectId::LEN;
+                let base_id = ObjectId::from_bytes(
+                    data.get(pos..id_end)
+                        .ok_or_else(|| Error::git_error("truncated pack entry"))?,
+                )?;
+                Ok(Entry::RefDelta(base_id, &data[id_end..], size))
+            }
+            kind => Err(Error::git_error(format!("unknown pack object type {kind}"))),
+        }
+    }
+}
+
+fn map(path: &Path) -> Result<Mmap, Error> {
+    let file = File::open(path)?;
+    // SAFETY: git never modifies pack files in place, new packs are written
+    // to temporary files and renamed into place.
+    Ok(unsafe { Mmap::map(&file)? })
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, Error> {
+    data.get(offset..offset + 4)
+        .map(|bytes| u32::from_be_bytes(bytes.try_into().unwrap()))
+        .ok_or_else(|| Error::git_error("truncated pack index"))
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Result<u64, Error> {
+    data.get(offset..offset + 8)
+        .map(|bytes| u64::from_be_bytes(bytes.try_into().unwrap()))
+        .ok_or_else(|| Error::git_error("truncated pack index"))
+}
+
+/// Inflates zlib compressed data that is expected to be `size` bytes
+pub(crate) fn inflate(compressed: &[u8], size: usize) -> Result<Vec<u8>, Error> {
+    let mut data = Vec::with_capacity(size.min(MAX_PREALLOCATION));
+    // Reading one byte past the expected size is enough to detect a mismatch
+    // without inflating everything a corrupt stream produces
+    ZlibDecoder::new(compressed)
+        .take((size as u64).saturating_add(1))
+        .read_to_end(&mut data)?;
+    if data.len() != size {
+        return Err(Error::git_error(format!(
+            "expected object of size {size}, found {}",
+            data.len()
+        )));
+    }
+    Ok(data)
+}
+
+/// Reads the variable length offset encoding used by `OFS_DELTA` entries and
+/// index v4 path compression. Returns the value and the number of bytes read.
+pub(crate) fn read_offset_varint(data: &[u8]) -> Result<(usize, usize), Error> {
+    let mut bytes = data.iter();
+    let mut byte = *bytes
+        .next()
+        .ok_or_else(|| Error::git_error("truncated varint"))?;
+    let mut value = (byte & 0x7f) as usize;
+    let mut read = 1;
+    while byte & 0x80 != 0 {
+        byte = *bytes
+            .next()
+            .ok_or_else(|| Error::git_error("truncated varint"))?;
+        read += 1;
+        value = value
+            .checked_add(1)
+            .and_then(|value| value.checked_mul(1 << 7))
+            .ok_or_else(|| Error::git_error("varint is too large"))?
+            | (byte & 0x7f) as usize;
+    }
+    Ok((value, read))
+}
+
+// Little endian base 128 varint used for the sizes in delta headers
+fn read_size_varint(data: &[u8], pos: &mut usize) -> Result<usize, Error> {
+    let mut value = 0;
+    let mut shift = 0;
+    loop {
+        let byte = *data
+            .get(*pos)
+            .ok_or_else(|| Error::git_error("truncated delta"))?;
+        *pos += 1;
+        if shift >= usize::BITS {
+            return Err(Error::git_error("delta size is too large"));
+        }
+        value |= ((byte & 0x7f) as usize) << shift;
+        shift += 7;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+}
+
+/// Applies a git delta to its base object.
+///
+/// A delta consists of the size of the base and result followed by a list of
+/// instructions that either copy a range of the base or insert literal data.
+pub(crate) fn apply_delta(base: &[u8], delta: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut pos = 0;
+    let base_size = read_size_varint(delta, &mut pos)?;
+    if base_size != base.len() {
+        return Err(Error::git_error("delta base size mismatch"));
+    }
+    let result_size = read_size_varint(delta, &mut pos)?;
+    let mut result = Vec::with_capacity(result_size.min(MAX_PREALLOCATION));
+
+    while let Some(&instruction) = delta.get(pos) {
+        pos += 1;
+        if instruction & 0x80 != 0 {
+            // Copy from the base, the low 4 bits mark which offset bytes are
+            // present and the next 3 bits which size bytes are present.
+            let mut read_bytes = |bits: u8, count: usize| -> Result<usize, Error> {
+                let mut value = 0;
+                for i in 0..count {
+                    if bits & (1 << i) != 0 {
+                        let byte = *delta
+                            .get(pos)
+                            .ok_or_else(|| Error::git_error("truncated delta"))?;
+                        pos += 1;
+                        value |= (byte as usize) << (i * 8);
+                    }
+                }
+                Ok(value)
+            };
+            let offset = read_bytes(instruction & 0x0f, 4)?;
+            let size = match read_bytes((instruction >> 4) & 0x07, 3)? {
+                0 => 0x10000,
+                size => size,
+            };
+            let copy = offset
+                .checked_add(size)
+                .and_then(|end| base.get(offset..end))
+                .ok_or_else(|| Error::git_error("delta copy out of bounds"))?;
+            result.extend_from_slice(copy);
+        } else if instruction != 0 {
+            let size = instruction as usize;
+            let insert = delta
+                .get(pos..pos + size)
+                .ok_or_else(|| Error::git_error("truncated delta"))?;
+            result.extend_from_slice(insert);
+            pos += size;
+        } else {
+            return Err(Error::git_error("invalid delta instruction"));
+        }
+    }
+
+    if result.len() != result_size {
+        return Err(Error::git_error("delta result size mismatch"));
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_apply_delta() {
+        let base = b"hello world";
+        // base size 11, result size 15
+        let mut delta = vec![11, 15];
+        // copy 6 bytes from offset 0: "hello "
+        delta.extend_from_slice(&[0x80 | 0x10, 6]);
+        // insert "there "
+        delta.push(6);
+        delta.extend_from_slice(b"there ");
+        // copy 3 bytes from offset 6: "wor"
+        delta.extend_from_slice(&[0x80 | 0x10 | 0x01, 6, 3]);
+        assert_eq!(apply_delta(base, &delta).unwrap(), b"hello there wor");
+
+        assert!(apply_delta(b"too short", &delta).is_err());
+    }
+
+    #[test]
+    fn test_offset_varint() {
+        assert_eq!(read_offset_varint(&[0x05]).unwrap(), (5, 1));
+        // Each continuation byte adds one before shifting
+        assert_eq!(read_offset_varint(&[0x80, 0x00]).unwrap(), (128, 2));
+        assert_eq!(read_offset_varint(&[0x81, 0x7f]).unwrap(), (383, 2));
+        assert!(read_offset_varint(&[0x80]).is_err());
+        assert!(read_offset_varint(&[0xff; 16]).is_err());
+    }
+
+    #[test]
+    fn test_size_varint_overflow() {
+        let mut pos = 0;
+        assert!(read_size_varint(&[0xff; 16], &mut pos).is_err());
+        let mut delta = vec![0xff; 16];
+        delta.push(0x01);
+        assert!(apply_delta(b"", &delta).is_err());
+    }
+
+    #[test]
+    fn test_inflate_size_mismatch() {
+        use std::io::Write;
+
+        let mut encoder =
+            flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&[0; 1024]).unwrap();
+        let compressed = encoder.finish().unwrap();
+        assert_eq!(inflate(&compressed, 1024).unwrap().len(), 1024);
+        assert!(inflate(&compressed, 10).is_err());
+        assert!(inflate(&compressed, usize::MAX).is_err());
+    }
+
+    #[test]
+    fn test_corrupt_index() {
+        let dir = tempfile::tempdir().unwrap();
+        let index_path = dir.path().join("pack-corrupt.idx");
+        let mut pack = b"PACK".to_vec();
+        pack.extend_from_slice(&[0, 0, 0, 2, 0, 0, 0, 1]);
+        std::fs::write(index_path.with_extension("pack"), &pack).unwrap();
+
+        let mut index = IDX_MAGIC.to_vec();
+        index.extend_from_slice(&2u32.to_be_bytes());
+        // The fanout table claims there are a million objects, but the
+        // index doesn't have room for them
+        for _ in 0..256 {
+            index.extend_from_slice(&1_000_000u32.to_be_bytes());
+        }
+        std::fs::write(&index_path, &index).unwrap();
+        assert!(Pack::open(&index_path).is_err());
+
+        // A fanout table that isn't sorted points outside of the index
+        let mut index = IDX_MAGIC.to_vec();
+        index.extend_from_slice(&2u32.to_be_bytes());
+        for i in 0..256u32 {
+            let count: u32 = if i == 0 { 5 } else { 0 };
+            index.extend_from_slice(&count.to_be_bytes());
+        }
+        index.extend_from_slice(&[0; IDX_TRAILER_LEN]);
+        std::fs::write(&index_path, &index).unwrap();
+        let pack = Pack::open(&index_path).unwrap();
+        assert!(pack.find(&ObjectId::from_bytes(&[0; 20]).unwrap()).is_err());
+    }
+}
@@ -1,17 +1,55 @@
-use std::{collections::HashMap, process::Command};
+use std::{
+    collections::{BTreeMap, HashMap},
+    process::Command,
+};
 
 use turbopath::{AbsoluteSystemPathBuf, AnchoredSystemPathBuf, RelativeUnixPathBuf};
+use turborepo_globwalk::{globwalk, WalkType};
 
-use crate::{hash_object::hash_objects, ls_tree::git_ls_tree, status::append_git_status, Error};
+use crate::{
+    hash_object::hash_objects,
+    ls_tree::git_ls_tree,
+    manual::{get_package_file_hashes_from_processing_gitignore, git_like_hash_file},
+    status::append_git_status,
+    Error,
+};
 
 pub type GitHashes = HashMap<RelativeUnixPathBuf, String>;
 
+/// Files that are always inputs to a package's tasks as they define them
+const DEFAULT_INPUTS: [&str; 2] = ["package.json", "turbo.json"];
+
+/// Hashes the files of a package using git when `turbo_root` is in a git
+/// repository, and by walking the filesystem otherwise. Both produce the same
+/// hashes for the same files.
+pub fn get_package_file_hashes(
+    turbo_root: &AbsoluteSystemPathBuf,
+    package_path: &AnchoredSystemPathBuf,
+) -> Result<GitHashes, Error> {
+    match find_git_root(turbo_root) {
+        Ok(git_root) => package_file_hashes_from_git_index(turbo_root, &git_root, package_path),
+        Err(_) => get_package_file_hashes_from_processing_gitignore(turbo_root, package_path),
+    }
+}
+
 pub fn get_package_file_hashes_from_git_index(
     turbo_root: &AbsoluteSystemPathBuf,
     package_path: &AnchoredSystemPathBuf,
 ) -> Result<GitHashes, Error> {
     // TODO: memoize git root -> turbo root calculation once we aren't crossing ffi
     let git_root = find_git_root(turbo_root)?;
+    package_file_hashes_from_git_index(turbo_root, &git_root, package_path)
+}
+
+fn package_file_hashes_from_git_index(
+    turbo_root: &AbsoluteSystemPathBuf,
+    git_root: &AbsoluteSystemPathBuf,
+    package_path: &AnchoredSystemPathBuf,
+) -> Result<GitHashes, Error> {
+    #[cfg(feature = "native-git")]
+    if let Ok(hashes) = crate::native::package_file_hashes(turbo_root, package_path) {
+        return Ok(hashes);
+    }
     let full_pkg_path = turbo_root.resolve(package_path);
     let git_to_pkg_path = git_root.anchor(&full_pkg_path)?;
     let pkg_prefix = git_to_pkg_path.to_unix()?;
@@ -22,6 +60,179 @@ pub fn get_package_file_hashes_from_git_index(
     Ok(hashes)
 }
 
+/// Hashes the files of a package matching `inputs`, see
+/// `expand_package_inputs`. Equivalent to `getPackageFileHashesFromInputs` on
+/// the Go side.
+pub fn get_package_file_hashes_from_inputs(
+    turbo_root: &AbsoluteSystemPathBuf,
+    package_path: &AnchoredSystemPathBuf,
+    inputs: &[String],
+) -> Result<GitHashes, Error> {
+    let files = expand_package_inputs(turbo_root, package_path, inputs)?;
+    get_hashes_for_files(turbo_root, package_path, &files)
+}
+
+/// Returns the files matching `inputs`, which are globs relative to the
+/// package. Globs prefixed with `!` exclude files. The globs are expanded on
+/// disk, so files ignored by git are matched as well. `package.json` and
+/// `turbo.json` are always included. Paths are relative to the package and may
+/// point outside of it if an input does.
+pub fn expand_package_inputs(
+    turbo_root: &AbsoluteSystemPathBuf,
+    package_path: &AnchoredSystemPathBuf,
+    inputs: &[String],
+) -> Result<Vec<RelativeUnixPathBuf>, Error> {
+    let pkg_prefix = package_path.to_unix()?;
+    let pkg_prefix = pkg_prefix.as_str()?.trim_end_matches('/');
+    // the globs are relative to the package, but are expanded from the root
+    let reroot = |glob: &str| match pkg_prefix {
+        "" => glob.to_string(),
+        prefix => format!("{prefix}/{glob}"),
+    };
+    let (exclude, include): (Vec<_>, Vec<_>) = inputs
+        .iter()
+        .map(|input| input.as_str())
+        .chain(DEFAULT_INPUTS)
+        .partition(|input| input.starts_with('!'));
+    let include = include.into_iter().map(reroot).collect::<Vec<_>>();
+    let exclude = exclude
+        .into_iter()
+        .map(|input| reroot(&input[1..]))
+        .collect::<Vec<_>>();
+
+    globwalk(
+        turbo_root.as_absolute_path(),
+        &include,
+        &exclude,
+        WalkType::Files,
+    )?
+    .into_iter()
+    .map(|path| {
+        let path = path.to_unix()?;
+        Ok(RelativeUnixPathBuf::new(relative_to_package(
+            pkg_prefix,
+            path.as_str()?,
+        ))?)
+    })
+    .collect()
+}
+
+// Rewrites a path relative to the root to be relative to the package
+fn relative_to_package(pkg_prefix: &str, path: &str) -> String {
+    let package = pkg_prefix
+        .split('/')
+        .filter(|c| !c.is_empty())
+        .collect::<Vec<_>>();
+    let path = path.split('/').collect::<Vec<_>>();
+    let common = package
+        .iter()
+        .zip(&path)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let mut relative = vec![".."; package.len() - common];
+    relative.extend(&path[common..]);
+    relative.join("/")
+}
+
+/// Hashes files from disk the same way git would hash them. Paths are relative
+/// to the package.
+pub fn get_hashes_for_files(
+    turbo_root: &AbsoluteSystemPathBuf,
+    package_path: &AnchoredSystemPathBuf,
+    files: &[RelativeUnixPathBuf],
+) -> Result<GitHashes, Error> {
+    let full_pkg_path = turbo_root.resolve(package_path);
+    files
+        .iter()
+        .map(|file| {
+            let hash = git_like_hash_file(&full_pkg_path.as_path().join(file.as_str()?))?;
+            Ok((file.clone(), hash))
+        })
+        .collect()
+}
+
+/// File hashes for an entire repository, computed once and then sliced into
+/// per-package views. Prefer this over calling
+/// `get_package_file_hashes_from_git_index` for each package as the index and
+/// working tree are only read a single time. Outside of a git repository the
+/// files under `turbo_root` are hashed directly.
+pub struct RepoHashSnapshot {
+    turbo_root: AbsoluteSystemPathBuf,
+    git_root: AbsoluteSystemPathBuf,
+    // Keyed by paths relative to the git root. Ordered so that the files of a
+    // package form a contiguous range.
+    hashes: BTreeMap<String, String>,
+}
+
+impl RepoHashSnapshot {
+    pub fn new(turbo_root: &AbsoluteSystemPathBuf) -> Result<Self, Error> {
+        let Ok(git_root) = find_git_root(turbo_root) else {
+            let root = AnchoredSystemPathBuf::from_raw("")?;
+            let hashes = get_package_file_hashes_from_processing_gitignore(turbo_root, &root)?;
+            return Self::from_hashes(turbo_root, turbo_root.clone(), hashes);
+        };
+        #[cfg(feature = "native-git")]
+        let hashes = match crate::native::repo_file_hashes(git_root.as_path()) {
+            Ok(hashes) => hashes,
+            Err(_) => repo_hashes_from_git(&git_root)?,
+        };
+        #[cfg(not(feature = "native-git"))]
+        let hashes = repo_hashes_from_git(&git_root)?;
+        Self::from_hashes(turbo_root, git_root, hashes)
+    }
+
+    fn from_hashes(
+        turbo_root: &AbsoluteSystemPathBuf,
+        git_root: AbsoluteSystemPathBuf,
+        hashes: GitHashes,
+    ) -> Result<Self, Error> {
+        let hashes = hashes
+            .into_iter()
+            .map(|(path, hash)| Ok((path.as_str()?.to_string(), hash)))
+            .collect::<Result<_, Error>>()?;
+        Ok(Self {
+            turbo_root: turbo_root.clone(),
+            git_root,
+            hashes,
+        })
+    }
+
+    /// The hashes of files in a package, equivalent to
+    /// `get_package_file_hashes_from_git_index`. Paths are relative to the
+    /// package.
+    pub fn package_hashes(&self, package_path: &AnchoredSystemPathBuf) -> Result<GitHashes, Error> {
+        let full_pkg_path = self.turbo_root.resolve(package_path);
+        let pkg_prefix = self.git_root.anchor(full_pkg_path)?.to_unix()?;
+        let pkg_prefix = pkg_prefix.as_str()?;
+        if pkg_prefix.is_empty() {
+            return self
+                .hashes
+                .iter()
+                .map(|(path, hash)| Ok((RelativeUnixPathBuf::new(path.as_str())?, hash.clone())))
+                .collect();
+        }
+
+        let pkg_prefix = format!("{pkg_prefix}/");
+        self.hashes
+            .range(pkg_prefix.clone()..)
+            .take_while(|(path, _)| path.starts_with(&pkg_prefix))
+            .map(|(path, hash)| {
+                let path = RelativeUnixPathBuf::new(&path[pkg_prefix.len()..])?;
+                Ok((path, hash.clone()))
+            })
+            .collect()
+    }
+}
+
+// Runs ls-tree, status, and hash-object once from the git root
+fn repo_hashes_from_git(git_root: &AbsoluteSystemPathBuf) -> Result<GitHashes, Error> {
+    let root_prefix = RelativeUnixPathBuf::new("")?;
+    let mut hashes = git_ls_tree(git_root)?;
+    let to_hash = append_git_status(git_root, &root_prefix, &mut hashes)?;
+    hash_objects(git_root, to_hash, &root_prefix, &mut hashes)?;
+    Ok(hashes)
+}
+
 pub(crate) fn find_git_root(
     turbo_root: &AbsoluteSystemPathBuf,
 ) -> Result<AbsoluteSystemPathBuf, Error> {
@@ -29,6 +240,9 @@ pub(crate) fn find_git_root(
         .args(["rev-parse", "--show-cdup"])
         .current_dir(turbo_root)
         .output()?;
+    if !rev_parse.status.success() {
+        return Err(Error::git_error(String::from_utf8_lossy(&rev_parse.stderr)));
+    }
     let root = String::from_utf8(rev_parse.stdout)?;
     Ok(turbo_root.join_literal(root.trim_end()).to_realpath()?)
 }
@@ -137,6 +351,115 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_repo_hash_snapshot() -> Result<(), Error> {
+        // Directory structure:
+        // <root>/
+        //   root-file
+        //   untracked-root-file <- new file not added to git
+        //   packages/
+        //     a/
+        //       package.json
+        //       modified-file <- modified after commit
+        //     ab/
+        //       package.json
+        //       untracked-file <- new file not added to git
+        let (_repo_root_tmp, repo_root) = tmp_dir();
+        for (path, contents) in [
+            ("root-file", "root"),
+            ("packages/a/package.json", "{}"),
+            ("packages/a/modified-file", "original"),
+            ("packages/ab/package.json", "{}"),
+        ] {
+            let path = repo_root.join_literal(path);
+            path.ensure_dir()?;
+            path.create_with_contents(contents)?;
+        }
+        setup_repository(&repo_root);
+        commit_all(&repo_root);
+        repo_root
+            .join_literal("packages/a/modified-file")
+            .create_with_contents("modified")?;
+        repo_root
+            .join_literal("packages/ab/untracked-file")
+            .create_with_contents("untracked")?;
+        repo_root
+            .join_literal("untracked-root-file")
+            .create_with_contents("untracked")?;
+
+        let snapshot = RepoHashSnapshot::new(&repo_root)?;
+        // Both the native and subprocess backends should agree
+        let from_git = repo_hashes_from_git(&repo_root)?
+            .into_iter()
+            .map(|(path, hash)| (path.as_str().unwrap().to_string(), hash))
+            .collect::<BTreeMap<_, _>>();
+        assert_eq!(snapshot.hashes, from_git);
+
+        for package in ["packages/a", "packages/ab", ""] {
+            let package_path = AnchoredSystemPathBuf::from_raw(package)?;
+            assert_eq!(
+                snapshot.package_hashes(&package_path)?,
+                get_package_file_hashes_from_git_index(&repo_root, &package_path)?,
+                "{package}"
+            );
+        }
+        let package_a = snapshot.package_hashes(&AnchoredSystemPathBuf::from_raw("packages/a")?)?;
+        assert_eq!(package_a.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_package_file_hashes_from_inputs() -> Result<(), Error> {
+        let (_repo_root_tmp, repo_root) = tmp_dir();
+        setup_repository(&repo_root);
+        for (path, contents) in [
+            (".gitignore", "dist/\n.env\n"),
+            ("apps/web/package.json", "{}"),
+            ("apps/web/.env", "SECRET=1"),
+            ("apps/web/src/index.ts", "index"),
+            ("apps/web/src/index.test.ts", "test"),
+            ("apps/web/dist/index.js", "built"),
+            ("apps/shared/config.json", "{}"),
+        ] {
+            let path = repo_root.join_literal(path);
+            path.ensure_dir()?;
+            path.create_with_contents(contents)?;
+        }
+        commit_all(&repo_root);
+
+        let package_path = AnchoredSystemPathBuf::from_raw("apps/web")?;
+        let inputs = [
+            ".env",
+            "src/**",
+            "dist/**",
+            "!**/*.test.ts",
+            "../shared/*.json",
+        ]
+        .map(String::from);
+        let hashes = get_package_file_hashes_from_inputs(&repo_root, &package_path, &inputs)?;
+        let mut files = hashes
+            .keys()
+            .map(|path| path.as_str().unwrap())
+            .collect::<Vec<_>>();
+        files.sort();
+        // gitignored files are matched too
+        assert_eq!(
+            files,
+            vec![
+                "../shared/config.json",
+                ".env",
+                "dist/index.js",
+                "package.json",
+                "src/index.ts"
+            ]
+        );
+
+        let from_git = get_package_file_hashes_from_git_index(&repo_root, &package_path)?;
+        let index = RelativeUnixPathBuf::new("src/index.ts")?;
+        assert_eq!(hashes.get(&index), from_git.get(&index));
+        Ok(())
+    }
+
     fn to_hash_map(pairs: &[(&str, &str)]) -> GitHashes {
         HashMap::from_iter(pairs.into_iter().map(|(path, hash)| {
             (
@@ -1,10 +1,23 @@
-use std::net::SocketAddr;
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
 
 use anyhow::Result;
-use axum::{routing::get, Json, Router};
+use axum::{
+    body::Bytes,
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    routing::{get, post},
+    Json, Router,
+};
 use turborepo_api_client::{
-    CachingStatus, CachingStatusResponse, Membership, Role, Space, SpacesResponse, Team,
-    TeamsResponse, User, UserResponse, VerificationResponse,
+    AnalyticsEvent, CachingStatus, CachingStatusResponse, Membership, Role, Space, SpacesResponse,
+    Team, TeamsResponse, User, UserResponse, VerificationResponse,
 };
 
 pub const EXPECTED_TOKEN: &str = "expected_token";
@@ -24,6 +37,64 @@ pub const EXPECTED_SPACE_NAME: &str = "expected_space_name";
 pub const EXPECTED_SSO_TEAM_ID: &str = "expected_sso_team_id";
 pub const EXPECTED_SSO_TEAM_SLUG: &str = "expected_sso_team_slug";
 
+/// The first request for this artifact fails with a server error, so that
+/// clients have to retry
+pub const FLAKY_ARTIFACT_HASH: &str = "flaky_artifact_hash";
+
+/// The first upload of this artifact fails with a server error, so that
+/// clients have to send the body again
+pub const FLAKY_UPLOAD_HASH: &str = "flaky_upload_hash";
+
+/// Artifacts uploaded to the mock, keyed by hash, with the headers they were
+/// uploaded with
+#[derive(Default)]
+struct ArtifactStore {
+    artifacts: Mutex<HashMap<String, (HeaderMap, Bytes)>>,
+    flaky_request_failed: AtomicBool,
+    flaky_upload_failed: AtomicBool,
+}
+
+const ARTIFACT_HEADERS: [&str; 2] = ["x-artifact-duration", "x-artifact-tag"];
+
+async fn put_artifact(
+    State(store): State<Arc<ArtifactStore>>,
+    Path(hash): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> StatusCode {
+    if hash == FLAKY_UPLOAD_HASH && !store.flaky_upload_failed.swap(true, Ordering::SeqCst) {
+        return StatusCode::SERVICE_UNAVAILABLE;
+    }
+    let mut artifact_headers = HeaderMap::new();
+    for name in ARTIFACT_HEADERS {
+        if let Some(value) = headers.get(name) {
+            artifact_headers.insert(name, value.clone());
+        }
+    }
+    store
+        .artifacts
+        .lock()
+        .unwrap()
+        .insert(hash, (artifact_headers, body));
+    StatusCode::OK
+}
+
+async fn get_artifact(
+    State(store): State<Arc<ArtifactStore>>,
+    Path(hash): Path<String>,
+) -> Result<(HeaderMap, Bytes), StatusCode> {
+    if hash == FLAKY_ARTIFACT_HASH && !store.flaky_request_failed.swap(true, Ordering::SeqCst) {
+        return Err(StatusCode::SERVICE_UNAVAILABLE);
+    }
+    store
+        .artifacts
+        .lock()
+        .unwrap()
+        .get(&hash)
+        .cloned()
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
 pub async fn start_test_server(port: u16) -> Result<()> {
     let app = Router::new()
         .route(
@@ -82,7 +153,14 @@ pub async fn start_test_server(port: u16) -> Result<()> {
                     team_id: Some(EXPECTED_SSO_TEAM_ID.to_string()),
                 })
             }),
-        );
+        )
+        .route(
+            "/v8/artifacts/events",
+            post(|Json(_events): Json<Vec<AnalyticsEvent>>| async { StatusCode::OK }),
+        )
+        // HEAD requests are handled by the GET handler
+        .route("/v8/artifacts/:hash", get(get_artifact).put(put_artifact))
+        .with_state(Arc::new(ArtifactStore::default()));
     let addr = SocketAddr::from(([127, 0, 0, 1], port));
     // We print the port so integration tests can use it
     println!("{}", port);